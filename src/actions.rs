@@ -0,0 +1,383 @@
+use glium::glutin::event::VirtualKeyCode;
+use imgui::{Ui, Io};
+use crate::image_handling::Rating;
+use crate::external_tools::ExternalTool;
+
+  // Things the user can do, independent of the input that triggers them.
+  // The keyboard and other input devices, like gamepads and midi controllers, map their input to these, so they all behave the same
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+  ShowPrevious(usize), // move back this many images
+  ShowNext(usize),
+  Rate(Rating), // the shown image, or all selected images while there is a selection
+  FilterByRating(Rating),
+  ToggleSelected,
+  SelectRange, // everything from the last image toggled up to the shown one
+  ClearSelectionOrFilters, // the selection first, and the filters once nothing is selected
+  ToggleRejected, // strikes the shown image out, or takes that back
+  ToggleStrikeOut, // whether X rejects and hides the shown image, instead of selecting it
+  ToggleSelect, // adds the shown image to the selects, or removes it
+  ToggleSelectsOnly, // shows only the selects, in their order
+  MoveSelect { earlier: bool }, // swaps the shown image with the select before or after it
+  ExportSelects,
+  PresentSelects,
+  ToggleBookmark,
+  ShowBookmark { forward: bool }, // the next or previous bookmarked image, going around at the ends
+  PlayPause, // the shown video clip
+  RotateCcw,
+  RotateCw,
+  Flip,
+  FindDuplicates, // once they are found, jumps between groups of them
+  TogglePinned, // pins the shown image to the compare view, or unpins it
+  UnpinAll,
+  ToggleSharedCompareZoom, // whether zooming an image of the compare view zooms all of them
+  ToggleHotFolder, // watching the folder for new images
+  ToggleFollowArrivals, // whether new images in a watched folder are shown as they arrive
+  OpenRawFile, // or the image itself if there is none
+  ShowOnMap,
+  Share, // the shown image's file, through the platform's share menu
+  CopyImage,
+  CopyFileName,
+  CopyFilePath,
+  RetryOrReveal, // retries a failed load of the shown image, and otherwise reveals it in the file manager
+  RunExternalTool(usize), // index into the configured tools
+  ExportContactSheets,
+  ExportArchive, // the images passing the filters, into a zip file
+  ExportToFolder, // copies of the images passing the filters
+  SaveView { with_ui: bool },
+  CycleDisplayMode, // fit, fill, actual size
+  CycleTransition, // cut, crossfade, slide
+  ResetZoom { all: bool }, // of the shown image, or of every image
+  ToggleHighQualityDownscaling,
+  TogglePowerSaver, // draws fewer frames, see `support::FramePacing`
+  ToggleStatisticsPanel,
+  ToggleFilterPanel,
+  ToggleFilmstrip,
+  ToggleLogConsole,
+  TogglePerfHud,
+  ToggleUi,
+  ToggleUiScaleWindow,
+  ToggleBackgroundWindow,
+  ToggleExportWindow,
+  ToggleVerifyWindow, // for checking a copy of a card against a checksum manifest or the card
+  ToggleLoaderThreadsWindow,
+  ToggleLoadSetWindow,
+  TogglePresentation,
+  ToggleReadOnly, // whether ratings and everything else that writes files is turned off
+  OpenCommandPalette,
+  Quit
+}
+
+  // how many images shift + navigation skips
+pub const SKIP_DISTANCE: usize = 10;
+  // with cmd + shift
+pub const FAR_SKIP_DISTANCE: usize = 100;
+  // the letters, digits and function keys `keyboard_actions` reacts to without cmd. shift only changes what most of them do,
+  // so they are taken with and without it. keep in line with `keyboard_actions`
+pub const BUILT_IN_KEYS: [VirtualKeyCode; 28] = {
+  use VirtualKeyCode::*;
+  [A, B, C, D, E, F, G, H, I, J, L, M, N, O, P, R, S, T, U, V, W, X, Z, Key0, Key1, Key2, Key3, F5]
+};
+
+impl Action {
+    // the action with this name in the preferences, e.g. for midi bindings
+  pub fn from_name(name: &str)->Option<Action> {
+    match name {
+      "previous" => Some(Action::ShowPrevious(1)),
+      "next" => Some(Action::ShowNext(1)),
+      "skip_back" => Some(Action::ShowPrevious(SKIP_DISTANCE)),
+      "skip_forward" => Some(Action::ShowNext(SKIP_DISTANCE)),
+      "rate_1" => Some(Action::Rate(Rating::Low)),
+      "rate_2" => Some(Action::Rate(Rating::Medium)),
+      "rate_3" => Some(Action::Rate(Rating::High)),
+      "select" => Some(Action::ToggleSelected),
+      "select_range" => Some(Action::SelectRange),
+      "play_pause" => Some(Action::PlayPause),
+      "toggle_ui" => Some(Action::ToggleUi),
+      _ => None
+    }
+  }
+
+    // whether the action changes the folder, or something in it. these are turned off in read-only mode
+  pub fn writes_files(&self)->bool {
+    matches!(self, Action::Rate(_) | Action::ToggleRejected | Action::ToggleSelect | Action::MoveSelect { .. } |
+      Action::RotateCcw | Action::RotateCw | Action::Flip | Action::RunExternalTool(_) |
+      Action::ExportSelects | Action::ExportContactSheets | Action::ExportArchive | Action::ExportToFolder | Action::SaveView { .. })
+  }
+}
+
+  // An action as it is listed in the command palette
+pub struct Command {
+  pub action: Action,
+  pub name: String,
+  pub shortcut: String // empty if it has none
+}
+
+impl Command {
+  fn new(action: Action, name: &str, shortcut: &str)->Command {
+    Command {
+      action,
+      name: name.to_string(),
+      shortcut: shortcut.to_string()
+    }
+  }
+}
+
+  // all actions with the keys bound to them, followed by the external tools
+pub fn commands(external_tools: &[ExternalTool])->Vec<Command> {
+  let mut commands = vec![
+    Command::new(Action::ShowPrevious(1), "Previous image", "A"),
+    Command::new(Action::ShowNext(1), "Next image", "D"),
+    Command::new(Action::ShowPrevious(SKIP_DISTANCE), "Skip back 10 images", "Shift+A"),
+    Command::new(Action::ShowNext(SKIP_DISTANCE), "Skip forward 10 images", "Shift+D"),
+    Command::new(Action::ShowPrevious(FAR_SKIP_DISTANCE), "Skip back 100 images", "Cmd+Shift+A"),
+    Command::new(Action::ShowNext(FAR_SKIP_DISTANCE), "Skip forward 100 images", "Cmd+Shift+D"),
+    Command::new(Action::Rate(Rating::Low), "Rate low", "1"),
+    Command::new(Action::Rate(Rating::Medium), "Rate medium", "2"),
+    Command::new(Action::Rate(Rating::High), "Rate high", "3"),
+    Command::new(Action::FilterByRating(Rating::Low), "Only show low ratings", "Cmd+1"),
+    Command::new(Action::FilterByRating(Rating::Medium), "Only show medium ratings", "Cmd+2"),
+    Command::new(Action::FilterByRating(Rating::High), "Only show high ratings", "Cmd+3"),
+    Command::new(Action::ToggleSelected, "Add to or remove from the selection", "X"),
+    Command::new(Action::ToggleRejected, "Reject the image, or take the rejection back", "Backspace"),
+    Command::new(Action::ToggleStrikeOut, "Toggle striking out rejected images with X", ""),
+    Command::new(Action::ToggleSelect, "Add to or remove from the selects", "J"),
+    Command::new(Action::ToggleSelectsOnly, "Show only the selects, or all images", "Shift+J"),
+    Command::new(Action::MoveSelect { earlier: true }, "Move the select earlier", ","),
+    Command::new(Action::MoveSelect { earlier: false }, "Move the select later", "."),
+    Command::new(Action::ExportSelects, "Export the selects", "Shift+P"),
+    Command::new(Action::PresentSelects, "Present the selects", "Shift+F5"),
+    Command::new(Action::ToggleBookmark, "Bookmark the image, or remove its bookmark", "L"),
+    Command::new(Action::ShowBookmark { forward: true }, "Next bookmark", "N"),
+    Command::new(Action::ShowBookmark { forward: false }, "Previous bookmark", "Shift+N"),
+    Command::new(Action::SelectRange, "Select up to the shown image", "Shift+X"),
+    Command::new(Action::ClearSelectionOrFilters, "Clear the selection or filters", "Escape"),
+    Command::new(Action::PlayPause, "Play/pause the video clip", "Space"),
+    Command::new(Action::RotateCcw, "Rotate counterclockwise", "["),
+    Command::new(Action::RotateCw, "Rotate clockwise", "]"),
+    Command::new(Action::Flip, "Flip horizontally", "F"),
+    Command::new(Action::FindDuplicates, "Find duplicates", "G"),
+    Command::new(Action::TogglePinned, "Pin the image to compare it, or unpin it", "I"),
+    Command::new(Action::UnpinAll, "Unpin all images", "Shift+I"),
+    Command::new(Action::ToggleSharedCompareZoom, "Toggle zooming the compared images together", ""),
+    Command::new(Action::ToggleHotFolder, "Watch the folder for new images", "W"),
+    Command::new(Action::ToggleFollowArrivals, "Show new images as they arrive or stay put", "Shift+W"),
+    Command::new(Action::OpenRawFile, "Open the raw file", "O"),
+    Command::new(Action::ShowOnMap, "Show on a map", "M"),
+    Command::new(Action::Share, "Share the image", "Cmd+Shift+S"),
+    Command::new(Action::CopyImage, "Copy the image", "Cmd+C"),
+    Command::new(Action::CopyFileName, "Copy the file name", "C"),
+    Command::new(Action::CopyFilePath, "Copy the file path", "Shift+C"),
+    Command::new(Action::RetryOrReveal, "Retry loading or reveal the file", "R"),
+    Command::new(Action::ExportContactSheets, "Export contact sheets", "P"),
+    Command::new(Action::ExportArchive, "Export the shown images as a zip archive", "Cmd+Shift+P"),
+    Command::new(Action::ExportToFolder, "Copy the shown images to a folder", ""),
+    Command::new(Action::ToggleExportWindow, "Change the size of exported images", ""),
+    Command::new(Action::ToggleVerifyWindow, "Verify the copy against a checksum manifest or another folder", ""),
+    Command::new(Action::SaveView { with_ui: false }, "Save the view", "V"),
+    Command::new(Action::SaveView { with_ui: true }, "Save the view with the UI", "Shift+V"),
+    Command::new(Action::CycleDisplayMode, "Switch between fit, fill and actual size", "Z"),
+    Command::new(Action::CycleTransition, "Switch between cut, crossfade and slide transitions", ""),
+    Command::new(Action::ResetZoom { all: false }, "Reset the zoom", "0"),
+    Command::new(Action::ResetZoom { all: true }, "Reset the zoom of all images", "Shift+0"),
+    Command::new(Action::ToggleHighQualityDownscaling, "Toggle high quality downscaling", "H"),
+    Command::new(Action::TogglePowerSaver, "Toggle power saver", ""),
+    Command::new(Action::ToggleStatisticsPanel, "Show/hide the statistics", "S"),
+    Command::new(Action::ToggleFilterPanel, "Show/hide the filters", "E"),
+    Command::new(Action::ToggleFilmstrip, "Show/hide the filmstrip", "B"),
+    Command::new(Action::ToggleLogConsole, "Show/hide the log", "`"),
+    Command::new(Action::TogglePerfHud, "Show/hide performance stats", "T"),
+    Command::new(Action::ToggleUi, "Show/hide the UI", "U"),
+    Command::new(Action::ToggleUiScaleWindow, "Adjust the UI scale", ""),
+    Command::new(Action::ToggleBackgroundWindow, "Change the background color and margin", ""),
+    Command::new(Action::ToggleLoaderThreadsWindow, "Change how many threads load images", ""),
+    Command::new(Action::ToggleLoadSetWindow, "Change how many images load around the shown one", ""),
+    Command::new(Action::TogglePresentation, "Start/end the presentation", "F5"),
+    Command::new(Action::ToggleReadOnly, "Toggle read-only mode", ""),
+    Command::new(Action::Quit, "Quit", "Cmd+Q")
+  ];
+
+  commands.extend(external_tools.iter().enumerate().map(|(idx, tool)| Command {
+    action: Action::RunExternalTool(idx),
+    name: tool.name.clone(),
+    shortcut: tool.key_name.clone()
+  }));
+  commands
+}
+
+  // how far A/D and the arrow keys move, further with shift and further still with cmd + shift
+fn step_distance(io: &Io)->usize {
+  match (io.key_shift, io.key_super) {
+    (true, true) => FAR_SKIP_DISTANCE,
+    (true, false) => SKIP_DISTANCE,
+    (false, _) => 1
+  }
+}
+
+  // the direction of the navigation key held down, forward for D and the right arrow, and how far one step of it goes
+pub fn held_navigation(ui: &Ui)->Option<(bool, usize)> {
+  let io = ui.io();
+  if io.want_text_input {
+    return None;
+  }
+
+  let down = |key: VirtualKeyCode| ui.is_key_index_down(key as _);
+  let distance = step_distance(io);
+  if down(VirtualKeyCode::A) || down(VirtualKeyCode::Left) {
+    Some((false, distance))
+  } else if down(VirtualKeyCode::D) || down(VirtualKeyCode::Right) {
+    Some((true, distance))
+  } else {
+    None
+  }
+}
+
+  // the actions triggered by the keys pressed this frame. none while text is typed into the ui
+pub fn keyboard_actions(ui: &Ui, external_tools: &[ExternalTool])->Vec<Action> {
+  let io = ui.io();
+  if io.want_text_input {
+    return Vec::new();
+  }
+
+  let pressed = |key: VirtualKeyCode| ui.is_key_index_pressed_no_repeat(key as _);
+  let mut actions = Vec::new();
+
+  let distance = step_distance(io);
+  if pressed(VirtualKeyCode::A) || pressed(VirtualKeyCode::Left) {
+    actions.push(Action::ShowPrevious(distance));
+  } else if pressed(VirtualKeyCode::D) || pressed(VirtualKeyCode::Right) {
+    actions.push(Action::ShowNext(distance));
+  }
+
+  let rating = if pressed(VirtualKeyCode::Key1) {
+    Some(Rating::Low)
+  } else if pressed(VirtualKeyCode::Key2) {
+    Some(Rating::Medium)
+  } else if pressed(VirtualKeyCode::Key3) {
+    Some(Rating::High)
+  } else {
+    None
+  };
+    // cmd + 1/2/3 filters by rating, without cmd the keys rate
+  if let Some(rating) = rating {
+    actions.push(if io.key_super { Action::FilterByRating(rating) } else { Action::Rate(rating) });
+  }
+
+  if pressed(VirtualKeyCode::X) {
+    actions.push(if io.key_shift { Action::SelectRange } else { Action::ToggleSelected });
+  }
+  if pressed(VirtualKeyCode::Escape) {
+    actions.push(Action::ClearSelectionOrFilters);
+  }
+  if pressed(VirtualKeyCode::Back) {
+    actions.push(Action::ToggleRejected);
+  }
+  if pressed(VirtualKeyCode::J) {
+    actions.push(if io.key_shift { Action::ToggleSelectsOnly } else { Action::ToggleSelect });
+  }
+  if pressed(VirtualKeyCode::Comma) {
+    actions.push(Action::MoveSelect { earlier: true });
+  } else if pressed(VirtualKeyCode::Period) {
+    actions.push(Action::MoveSelect { earlier: false });
+  }
+  if pressed(VirtualKeyCode::L) {
+    actions.push(Action::ToggleBookmark);
+  }
+  if pressed(VirtualKeyCode::N) {
+    actions.push(Action::ShowBookmark { forward: !io.key_shift });
+  }
+  if pressed(VirtualKeyCode::Space) {
+    actions.push(Action::PlayPause);
+  }
+
+  if pressed(VirtualKeyCode::LBracket) {
+    actions.push(Action::RotateCcw);
+  } else if pressed(VirtualKeyCode::RBracket) {
+    actions.push(Action::RotateCw);
+  } else if pressed(VirtualKeyCode::F) {
+    actions.push(Action::Flip);
+  }
+
+  if pressed(VirtualKeyCode::G) {
+    actions.push(Action::FindDuplicates);
+  }
+  if pressed(VirtualKeyCode::I) {
+    actions.push(if io.key_shift { Action::UnpinAll } else { Action::TogglePinned });
+  }
+  if pressed(VirtualKeyCode::W) {
+    actions.push(if io.key_shift { Action::ToggleFollowArrivals } else { Action::ToggleHotFolder });
+  }
+  if pressed(VirtualKeyCode::O) {
+    actions.push(Action::OpenRawFile);
+  }
+  if pressed(VirtualKeyCode::M) {
+    actions.push(Action::ShowOnMap);
+  }
+  if pressed(VirtualKeyCode::C) {
+    actions.push(if io.key_super {
+      Action::CopyImage
+    } else if io.key_shift {
+      Action::CopyFilePath
+    } else {
+      Action::CopyFileName
+    });
+  }
+  if pressed(VirtualKeyCode::R) {
+    actions.push(Action::RetryOrReveal);
+  }
+  for (idx, tool) in external_tools.iter().enumerate() {
+    if tool.is_triggered(ui) {
+      actions.push(Action::RunExternalTool(idx));
+    }
+  }
+
+  if pressed(VirtualKeyCode::P) {
+    actions.push(match (io.key_shift, io.key_super) {
+      (true, true) => Action::ExportArchive,
+      (true, false) => Action::ExportSelects,
+      (false, _) => Action::ExportContactSheets
+    });
+  }
+  if pressed(VirtualKeyCode::V) {
+    actions.push(Action::SaveView { with_ui: io.key_shift });
+  }
+
+  if pressed(VirtualKeyCode::Z) {
+    actions.push(Action::CycleDisplayMode);
+  }
+  if pressed(VirtualKeyCode::Key0) {
+    actions.push(Action::ResetZoom { all: io.key_shift });
+  }
+  if pressed(VirtualKeyCode::H) {
+    actions.push(Action::ToggleHighQualityDownscaling);
+  }
+  if pressed(VirtualKeyCode::S) {
+    actions.push(if io.key_super && io.key_shift { Action::Share } else { Action::ToggleStatisticsPanel });
+  }
+  if pressed(VirtualKeyCode::E) {
+    actions.push(Action::ToggleFilterPanel);
+  }
+  if pressed(VirtualKeyCode::B) {
+    actions.push(Action::ToggleFilmstrip);
+  }
+  if pressed(VirtualKeyCode::Grave) {
+    actions.push(Action::ToggleLogConsole);
+  }
+  if pressed(VirtualKeyCode::T) {
+    actions.push(Action::TogglePerfHud);
+  }
+  if pressed(VirtualKeyCode::U) {
+    actions.push(Action::ToggleUi);
+  }
+  if pressed(VirtualKeyCode::F5) {
+    actions.push(if io.key_shift { Action::PresentSelects } else { Action::TogglePresentation });
+  }
+  if pressed(VirtualKeyCode::K) && io.key_super {
+    actions.push(Action::OpenCommandPalette);
+  }
+  if pressed(VirtualKeyCode::Q) && io.key_super {
+    actions.push(Action::Quit);
+  }
+
+  actions
+}
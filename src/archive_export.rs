@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::SystemTime;
+use crate::background_job::BackgroundJob;
+use crate::image::ImageLoadError;
+use crate::image_export::{self, ExportPlan, ExportOptions};
+use crate::image_handling::{loader_pool, ImageSummary};
+use crate::worker_pool::{WorkerPool, Worker, CancelToken, TaskSubmitter};
+use crate::zip_archive::{ZipWriter, ZipError};
+use log::warn;
+
+  // how many files can be submitted to the workers ahead of the one the archive writer waits for,
+  // to bound the memory held by those that are prepared but can't be written yet
+const WRITE_QUEUE_LIMIT: usize = 8;
+
+struct ArchiveTask {
+  idx: usize, // position in the archive
+  summary: ImageSummary,
+  entry_name: String,
+  token: CancelToken // of the export
+}
+
+struct ArchiveEntry {
+  contents: Vec<u8>,
+  modified: SystemTime
+}
+
+struct ArchiveWorker {
+  options: ExportOptions
+}
+
+impl Worker for ArchiveWorker {
+  type Input = ArchiveTask;
+  type Output = Option<ArchiveEntry>; // None if the export was cancelled
+  type Error = ImageLoadError;
+
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    if input.token.is_cancelled() {
+      return Ok(None);
+    }
+    let modified = fs::metadata(&input.summary.path)?.modified().unwrap_or_else(|_| SystemTime::now());
+    let contents = image_export::export_contents(&input.summary, &self.options)?;
+    Ok(Some(ArchiveEntry { contents, modified }))
+  }
+}
+
+type ArchivePool = WorkerPool<ArchiveWorker>;
+type WrittenEntry = (usize, String, Result<ArchiveEntry, ImageLoadError>);
+
+  // Packs images into a single zip file inside the loaded folder, to hand them to a client in one piece. Their files are read,
+  // and scaled down if the options say so, on a pool of workers, while another thread writes them into the archive in order.
+  // The archive is written to a temporary file first, so a failed export leaves nothing behind
+pub struct ArchiveExport {
+  pub out_path: PathBuf,
+  pub image_count: usize,
+  job: BackgroundJob<usize, ArchiveError>, // counts the images written to the archive, results in how many were skipped because they couldn't be read
+  token: CancelToken,
+  _pool: ArchivePool // kept until the export is done, its workers stop with it
+}
+
+impl ArchiveExport {
+    // archives the planned files in their order as the plan's destination
+  pub fn start(plan: ExportPlan)->ArchiveExport {
+    let ExportPlan { destination: out_path, files, options, .. } = plan;
+    let image_count = files.len();
+    let token = CancelToken::new();
+    let (entry_sender, entry_receiver) = mpsc::channel();
+
+    let pool = new_pool(entry_sender, options);
+    let tasks: Vec<_> = files.into_iter().enumerate().map(|(idx, file)| {
+      ArchiveTask { idx, entry_name: file.entry_name(), summary: file.summary, token: token.clone() }
+    }).collect();
+
+    let thread_out_path = out_path.clone();
+    let submitter = pool.submitter();
+    let job = BackgroundJob::spawn("export", move |progress| write_archive(&thread_out_path, tasks, &submitter, entry_receiver, progress));
+
+    ArchiveExport {
+      out_path,
+      image_count,
+      job,
+      token,
+      _pool: pool
+    }
+  }
+
+  pub fn progress(&self)->usize {
+    self.job.progress()
+  }
+
+    // the number of images skipped, or the error message if the export failed, once it is done
+  pub fn poll(&self)->Option<Result<usize, String>> {
+    self.job.poll()
+  }
+}
+
+impl Drop for ArchiveExport {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
+
+fn new_pool(entries: Sender<WrittenEntry>, options: ExportOptions)->ArchivePool {
+  WorkerPool::new(loader_pool::default_decode_thread_count(), move |_| {
+    ArchiveWorker { options }
+  }, move |task: ArchiveTask, result| {
+      // the writer is gone once the export failed or was dropped
+    let result = match result {
+      Ok(Some(entry)) => Ok(entry),
+      Ok(None) => return,
+      Err(error) => Err(error)
+    };
+    let _ = entries.send((task.idx, task.entry_name, result));
+  })
+}
+
+  // writes the entries in the order of their index, while the workers finish them in any order.
+  // the tasks are handed to the workers as the writer gets to them, at most `WRITE_QUEUE_LIMIT` ahead of the next one to write
+fn write_archive(out_path: &Path, tasks: Vec<ArchiveTask>, submitter: &TaskSubmitter<ArchiveTask>, entries: Receiver<WrittenEntry>,
+  progress: &AtomicUsize)->Result<usize, ArchiveError> {
+
+  let folder = out_path.parent().ok_or(ArchiveError::NoFolder)?;
+  let tmp_file = tempfile::NamedTempFile::new_in(folder)?;
+  let mut zip = ZipWriter::new(BufWriter::new(tmp_file));
+
+  let image_count = tasks.len();
+  let mut tasks = tasks.into_iter();
+  let mut submitted_count = 0;
+  let mut waiting = BTreeMap::new();
+  let mut skipped_count = 0;
+  for next_idx in 0..image_count {
+    while submitted_count < next_idx + WRITE_QUEUE_LIMIT {
+      let Some(task) = tasks.next() else {
+        break;
+      };
+      submitter.submit(task, submitted_count);
+      submitted_count += 1;
+    }
+
+    let (entry_name, result) = loop {
+      if let Some(entry) = waiting.remove(&next_idx) {
+        break entry;
+      }
+      let (idx, entry_name, result) = entries.recv().map_err(|_| ArchiveError::WorkersStopped)?;
+      waiting.insert(idx, (entry_name, result));
+    };
+
+    match result {
+      Ok(entry) => zip.add_file(&entry_name, &entry.contents, entry.modified)?,
+      Err(error) => {
+        warn!("Archive export: Couldn't read {}, {}", entry_name, error);
+        skipped_count += 1;
+      }
+    }
+    progress.fetch_add(1, Ordering::Relaxed);
+  }
+
+  let tmp_file = zip.finish()?.into_inner().map_err(|error| error.into_error())?;
+  tmp_file.persist(out_path).map_err(|error| error.error)?;
+  Ok(skipped_count)
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+  NoFolder,
+  Io(io::Error),
+  Zip(ZipError),
+  WorkersStopped // the pool preparing the files stopped before it finished
+}
+
+impl fmt::Display for ArchiveError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::ArchiveError::*;
+    match self {
+      NoFolder => write!(f, "The archive has no folder to be saved in"),
+      Io(error) => write!(f, "Could not save the archive: {}", error),
+      Zip(error) => write!(f, "{}", error),
+      WorkersStopped => write!(f, "The export workers stopped unexpectedly"),
+    }
+  }
+}
+
+impl Error for ArchiveError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::ArchiveError::*;
+    match self {
+      NoFolder => None,
+      Io(error) => Some(error),
+      Zip(error) => Some(error),
+      WorkersStopped => None
+    }
+  }
+}
+
+impl From<io::Error> for ArchiveError {
+  fn from(error: io::Error)->Self {
+    ArchiveError::Io(error)
+  }
+}
+
+impl From<ZipError> for ArchiveError {
+  fn from(error: ZipError)->Self {
+    ArchiveError::Zip(error)
+  }
+}
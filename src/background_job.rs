@@ -0,0 +1,47 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+  // Work that runs on a thread of its own while Fotoleine stays responsive, like exports. It counts how far it got,
+  // e.g. in images, for the overlay to show, and is polled every frame for its result
+pub struct BackgroundJob<T, E> {
+  name: &'static str, // what the thread does, for the message if it stops without a result
+  progress: Arc<AtomicUsize>,
+  result: Receiver<Result<T, E>>
+}
+
+impl<T: Send + 'static, E: Display + Send + 'static> BackgroundJob<T, E> {
+    // runs `job` with the counter it advances as it goes
+  pub fn spawn<F>(name: &'static str, job: F)->BackgroundJob<T, E>
+    where F: FnOnce(&AtomicUsize)->Result<T, E> + Send + 'static {
+
+    let progress = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+    let thread_progress = Arc::clone(&progress);
+    thread::spawn(move || {
+        // the receiver is gone if the job was dropped in the meantime, like a copy replaced by a newer one
+      let _ = sender.send(job(&thread_progress));
+    });
+
+    BackgroundJob {
+      name,
+      progress,
+      result: receiver
+    }
+  }
+
+  pub fn progress(&self)->usize {
+    self.progress.load(Ordering::Relaxed)
+  }
+
+    // the result once the job is done, with errors as their message
+  pub fn poll(&self)->Option<Result<T, String>> {
+    match self.result.try_recv() {
+      Ok(result) => Some(result.map_err(|error| error.to_string())),
+      Err(TryRecvError::Empty) => None,
+      Err(TryRecvError::Disconnected) => Some(Err(format!("The {} thread stopped unexpectedly", self.name)))
+    }
+  }
+}
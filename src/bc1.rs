@@ -0,0 +1,124 @@
+  // BC1 (S3TC DXT1) compression of rgb pixel data, for uploading textures that are already compressed instead of leaving it to the driver.
+  // Each 4x4 block of pixels becomes two 565 colors at the ends of the line through the block's colors and a 2 bit index per pixel
+  // into those and the two colors between them, 8 bytes per block. The line follows the block's main color axis, so gradients keep their shape
+
+  // the compressed blocks of the image, row by row from the top. `depth` is the bytes per pixel, of which the first three are used.
+  // blocks over the right and bottom edges repeat the edge pixels
+pub fn compress(data: &[u8], width: usize, height: usize, depth: usize)->Vec<u8> {
+  if width == 0 || height == 0 {
+    return Vec::new();
+  }
+  let (block_cols, block_rows) = (width.div_ceil(4), height.div_ceil(4));
+  let mut blocks = Vec::with_capacity(block_cols * block_rows * 8);
+  let mut pixels = [[0f32; 3]; 16];
+  for block_row in 0..block_rows {
+    for block_col in 0..block_cols {
+      for (i, pixel) in pixels.iter_mut().enumerate() {
+        let x = (block_col * 4 + i % 4).min(width - 1);
+        let y = (block_row * 4 + i / 4).min(height - 1);
+        let offset = (y * width + x) * depth;
+        *pixel = [data[offset] as f32, data[offset + 1] as f32, data[offset + 2] as f32];
+      }
+      blocks.extend_from_slice(&compress_block(&pixels));
+    }
+  }
+  blocks
+}
+
+  // bytes of the compressed blocks covering `rows` rows of an image `width` pixels wide
+pub fn compressed_len(width: usize, rows: usize)->usize {
+  width.div_ceil(4) * rows.div_ceil(4) * 8
+}
+
+fn compress_block(pixels: &[[f32; 3]; 16])->[u8; 8] {
+  let mean = pixels.iter().fold([0.0; 3], |sum, pixel| add(sum, *pixel)).map(|channel| channel / 16.0);
+  let axis = main_axis(pixels, mean);
+
+    // the ends of the line, inset a little since the extremes are rarely hit exactly by the colors between them
+  let projections = pixels.map(|pixel| dot(sub(pixel, mean), axis));
+  let min = projections.iter().copied().fold(f32::INFINITY, f32::min);
+  let max = projections.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+  let inset = (max - min) / 16.0;
+  let end0 = to_565(add(mean, scale(axis, max - inset)));
+  let end1 = to_565(add(mean, scale(axis, min + inset)));
+
+    // the first color has to be the larger one, the other order means a block with 3 colors and transparency
+  let (color0, color1) = if end0 >= end1 { (end0, end1) } else { (end1, end0) };
+  let mut indices = 0u32;
+  if color0 != color1 {
+    let (c0, c1) = (from_565(color0), from_565(color1));
+    let palette = [c0, c1, lerp(c0, c1, 1.0 / 3.0), lerp(c0, c1, 2.0 / 3.0)];
+    for (i, pixel) in pixels.iter().enumerate() {
+      let nearest = (0..4).min_by(|&a, &b| distance(*pixel, palette[a]).total_cmp(&distance(*pixel, palette[b]))).unwrap();
+      indices |= (nearest as u32) << (i * 2);
+    }
+  }
+
+  let mut block = [0u8; 8];
+  block[0..2].copy_from_slice(&color0.to_le_bytes());
+  block[2..4].copy_from_slice(&color1.to_le_bytes());
+  block[4..8].copy_from_slice(&indices.to_le_bytes());
+  block
+}
+
+  // the direction the colors spread out in the most, found by power iteration on their covariance.
+  // falls back to the gray axis for blocks of a single color
+fn main_axis(pixels: &[[f32; 3]; 16], mean: [f32; 3])->[f32; 3] {
+  let mut covariance = [[0f32; 3]; 3];
+  for pixel in pixels {
+    let d = sub(*pixel, mean);
+    for (row, d_row) in covariance.iter_mut().zip(d) {
+      for (value, d_col) in row.iter_mut().zip(d) {
+        *value += d_row * d_col;
+      }
+    }
+  }
+
+  let mut axis = [1.0, 1.0, 1.0];
+  for _ in 0..8 {
+    let next = covariance.map(|row| dot(row, axis));
+    let length = dot(next, next).sqrt();
+    if length < 1e-6 {
+      break;
+    }
+    axis = scale(next, 1.0 / length);
+  }
+  let length = dot(axis, axis).sqrt();
+  scale(axis, 1.0 / length)
+}
+
+fn to_565(color: [f32; 3])->u16 {
+  let quantize = |value: f32, max: f32| (value.clamp(0.0, 255.0) * max / 255.0).round() as u16;
+  (quantize(color[0], 31.0) << 11) | (quantize(color[1], 63.0) << 5) | quantize(color[2], 31.0)
+}
+
+  // the color the gpu decodes, with the high bits repeated in the low ones
+fn from_565(color: u16)->[f32; 3] {
+  let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+  [((r << 3) | (r >> 2)) as f32, ((g << 2) | (g >> 4)) as f32, ((b << 3) | (b >> 2)) as f32]
+}
+
+fn add(a: [f32; 3], b: [f32; 3])->[f32; 3] {
+  [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3])->[f32; 3] {
+  [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], factor: f32)->[f32; 3] {
+  a.map(|channel| channel * factor)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3])->f32 {
+  a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32)->[f32; 3] {
+  add(a, scale(sub(b, a), t))
+}
+
+fn distance(a: [f32; 3], b: [f32; 3])->f32 {
+  let d = sub(a, b);
+  dot(d, d)
+}
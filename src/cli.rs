@@ -0,0 +1,188 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::image_handling::{read_folder_ratings, Rating, DirLoadError, RatingsFile};
+use crate::preferences::Preferences;
+use crate::external_tools;
+
+  // opens the viewer without rating or changing anything in folders, e.g. for archives on media that must not be modified
+const READ_ONLY_FLAG: &str = "--read-only";
+
+const USAGE: &str = "\
+Usage:
+  fotoleine                        open the viewer
+  fotoleine --read-only            open the viewer without changing anything in folders.
+                                   only for the viewer, not combined with the subcommands
+  fotoleine stats <folder>         print how many images have each rating
+  fotoleine export-rated <folder> <destination> [--min-rating low|medium|high] [--with-raw] [--dry-run]
+                                   copy the images rated at least that high, high by default,
+                                   and with --with-raw their raw files too. with --dry-run,
+                                   only print what would be copied where
+  fotoleine help                   print this
+
+Other arguments are reported as errors.";
+
+  // What the arguments ask for
+pub enum Invocation {
+  Viewer { read_only: bool },
+  Exit(i32) // a subcommand ran, or the arguments were wrong, with the exit code
+}
+
+  // Runs the subcommand in the arguments without opening a window, for scripts working with the ratings of a folder.
+  // Returns the exit code if there was one, or how to open the viewer if the arguments are for it
+pub fn run(args: &[String])->Invocation {
+  let Some((first, rest)) = args.split_first() else {
+    return Invocation::Viewer { read_only: false };
+  };
+  let result = match (first.as_str(), rest) {
+    (READ_ONLY_FLAG, []) => return Invocation::Viewer { read_only: true },
+    (READ_ONLY_FLAG, [other, ..]) => Err(CliError::UsageError(format!("{} only applies to the viewer, it can't be combined with {}", READ_ONLY_FLAG, other))),
+    (subcommand, _) if rest.iter().any(|arg| arg == READ_ONLY_FLAG) => {
+      Err(CliError::UsageError(format!("{} only applies to the viewer, it can't be combined with {}", READ_ONLY_FLAG, subcommand)))
+    },
+    ("stats", _) => stats(rest),
+    ("export-rated", _) => export_rated(rest),
+    ("help" | "--help" | "-h", _) => {
+      println!("{}", USAGE);
+      Ok(())
+    },
+    (option, _) if option.starts_with('-') => Err(CliError::UsageError(format!("Unknown option {}", option))),
+    (subcommand, _) => Err(CliError::UsageError(format!("Unknown subcommand {}", subcommand)))
+  };
+
+  Invocation::Exit(match result {
+    Ok(()) => 0,
+    Err(CliError::UsageError(message)) => {
+      eprintln!("{}\n\n{}", message, USAGE);
+      2
+    },
+    Err(error) => {
+      eprintln!("{}", error);
+      1
+    }
+  })
+}
+
+  // the ratings are found where the viewer saves them, and the folders list the images symbolic links point to if the viewer shows them
+fn folder_preferences()->(RatingsFile, bool) {
+  match Preferences::load() {
+    Ok(preferences) => (preferences.ratings_file(), preferences.follow_symlinks),
+    Err(error) => {
+      eprintln!("Couldn't load preferences, looking for the ratings next to the images and following links: {}", error);
+      (RatingsFile::default(), true)
+    }
+  }
+}
+
+fn stats(args: &[String])->Result<(), CliError> {
+  let [folder] = args else {
+    return Err(CliError::UsageError("stats takes a folder".to_string()));
+  };
+
+  let (ratings_file, follow_symlinks) = folder_preferences();
+  let ratings = read_folder_ratings(Path::new(folder), follow_symlinks, &ratings_file)?;
+  for rating_val in (0..=Rating::max()).rev() {
+    let rating = Rating::from_u8(rating_val);
+    let count = ratings.iter().filter(|(_, image_rating)| *image_rating == rating).count();
+    println!("{:<8}{}", rating.name(), count);
+  }
+  println!("{:<8}{}", "Total", ratings.len());
+  Ok(())
+}
+
+fn export_rated(args: &[String])->Result<(), CliError> {
+  let mut paths = Vec::new();
+  let mut min_rating = Rating::High;
+  let mut with_raw = false;
+  let mut dry_run = false;
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--min-rating" => {
+        let name = args.next()
+          .ok_or_else(|| CliError::UsageError("--min-rating needs a rating".to_string()))?;
+        min_rating = parse_rating(name)?;
+      },
+      "--with-raw" => with_raw = true,
+      "--dry-run" => dry_run = true,
+      _ if arg.starts_with("--") => return Err(CliError::UsageError(format!("Unknown option {}", arg))),
+      _ => paths.push(PathBuf::from(arg))
+    }
+  }
+  let [folder, destination] = paths.as_slice() else {
+    return Err(CliError::UsageError("export-rated takes a folder and a destination".to_string()));
+  };
+
+  let (ratings_file, follow_symlinks) = folder_preferences();
+  let ratings = read_folder_ratings(folder, follow_symlinks, &ratings_file)?;
+  let mut files: Vec<_> = ratings.into_iter()
+    .filter(|(_, rating)| rating.to_u8() >= min_rating.to_u8())
+    .map(|(path, _)| path)
+    .collect();
+  let image_count = files.len();
+  if with_raw {
+    let raw_files: Vec<_> = files.iter().filter_map(|path| external_tools::find_raw_file(path)).collect();
+    files.extend(raw_files);
+  }
+
+  if !dry_run {
+    fs::create_dir_all(destination).map_err(CliError::DestinationFailed)?;
+  }
+
+  let mut copied = 0;
+  for file in &files {
+      // file_is_relevant guarantees the images have file names, and the raw files are named after them
+    let target = destination.join(file.file_name().unwrap());
+    if target.exists() {
+      eprintln!("Skipped {}, it already exists", target.display());
+      continue;
+    }
+    if dry_run {
+      println!("{} -> {}", file.display(), target.display());
+    } else {
+      fs::copy(file, &target).map_err(|error| CliError::CopyFailed(file.clone(), error))?;
+    }
+    copied += 1;
+  }
+
+  let copied_verb = if dry_run { "Would copy" } else { "Copied" };
+  println!("{} {} of {} files, for {} images rated {} or higher", copied_verb, copied, files.len(), image_count, min_rating.name().to_lowercase());
+  Ok(())
+}
+
+fn parse_rating(name: &str)->Result<Rating, CliError> {
+  match name.to_lowercase().as_str() {
+    "low" | "1" => Ok(Rating::Low),
+    "medium" | "2" => Ok(Rating::Medium),
+    "high" | "3" => Ok(Rating::High),
+    _ => Err(CliError::UsageError(format!("Unknown rating {}, expected low, medium or high", name)))
+  }
+}
+
+#[derive(Debug)]
+enum CliError {
+  UsageError(String),
+  DirLoadFailed(DirLoadError),
+  DestinationFailed(io::Error),
+  CopyFailed(PathBuf, io::Error)
+}
+
+impl fmt::Display for CliError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::CliError::*;
+    match self {
+      UsageError(message) => write!(f, "{}", message),
+      DirLoadFailed(error) => write!(f, "Couldn't read the folder: {}", error),
+      DestinationFailed(error) => write!(f, "Couldn't create the destination folder: {}", error),
+      CopyFailed(path, error) => write!(f, "Couldn't copy {}: {}", path.display(), error),
+    }
+  }
+}
+
+impl From<DirLoadError> for CliError {
+  fn from(error: DirLoadError)->CliError {
+    CliError::DirLoadFailed(error)
+  }
+}
@@ -0,0 +1,131 @@
+use imgui::*;
+use glium::glutin::event::VirtualKeyCode;
+use crate::actions::{Action, Command};
+
+const WIDTH: f32 = 480.0; // at a ui scale of 1
+const MAX_LISTED: usize = 12;
+
+  // A searchable list of all commands, to find the ones that are rarely used without remembering their keys.
+  // Typing filters the list by fuzzy matching, up/down pick a command, enter runs it and escape closes the palette
+pub struct CommandPalette {
+  open: bool,
+  query: String,
+  selected: usize, // index into the commands matching the query
+  focus_query: bool // focus the search field in the next frame, to type right away
+}
+
+impl CommandPalette {
+  pub fn new()->CommandPalette {
+    CommandPalette {
+      open: false,
+      query: String::new(),
+      selected: 0,
+      focus_query: false
+    }
+  }
+
+  pub fn is_open(&self)->bool {
+    self.open
+  }
+
+  pub fn open(&mut self) {
+    self.open = true;
+    self.query.clear();
+    self.selected = 0;
+    self.focus_query = true;
+  }
+
+    // returns the action of the command picked this frame, which closes the palette
+  pub fn draw(&mut self, ui: &Ui, commands: &[Command], display_size: [f32; 2], ui_scale: f32)->Option<Action> {
+    if !self.open {
+      return None;
+    }
+
+    let mut matches: Vec<_> = commands.iter()
+      .filter_map(|command| fuzzy_score(&self.query, &command.name).map(|score| (score, command)))
+      .collect();
+    matches.sort_by_key(|(score, _)| -score); // stable, so equal scores keep the order of the commands
+    self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+    if ui.is_key_index_pressed(VirtualKeyCode::Down as _) && self.selected + 1 < matches.len() {
+      self.selected += 1;
+    }
+    if ui.is_key_index_pressed(VirtualKeyCode::Up as _) {
+      self.selected = self.selected.saturating_sub(1);
+    }
+
+    let width = WIDTH * ui_scale;
+    let mut picked = None;
+    let mut close = ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Escape as _);
+
+    ui.window("Commands")
+      .position([display_size[0] / 2.0, display_size[1] * 0.2], Condition::Always)
+      .position_pivot([0.5, 0.0])
+      .size([width, 0.0], Condition::Always)
+      .flags(WindowFlags::NO_DECORATION | WindowFlags::NO_MOVE | WindowFlags::NO_SAVED_SETTINGS)
+      .build(|| {
+        if self.focus_query {
+          ui.set_keyboard_focus_here();
+          self.focus_query = false;
+        }
+        ui.set_next_item_width(-1.0);
+        let entered = ui.input_text("##query", &mut self.query)
+          .hint("Type a command")
+          .enter_returns_true(true)
+          .build();
+        if ui.is_item_edited() {
+          self.selected = 0;
+        }
+        if entered {
+          picked = matches.get(self.selected).map(|(_, command)| command.action);
+          close = true;
+        }
+
+        for (idx, (_, command)) in matches.iter().enumerate().take(MAX_LISTED) {
+          if ui.selectable_config(&command.name).selected(idx == self.selected).build() {
+            picked = Some(command.action);
+            close = true;
+          }
+          if !command.shortcut.is_empty() {
+            ui.same_line_with_pos(width - ui.calc_text_size(&command.shortcut)[0] - 16.0 * ui_scale);
+            ui.text_disabled(&command.shortcut);
+          }
+        }
+        if matches.len() > MAX_LISTED {
+          ui.text_disabled(format!("{} more", matches.len() - MAX_LISTED));
+        }
+      });
+
+    if close {
+      self.open = false;
+    }
+    picked
+  }
+}
+
+  // how well `text` matches `query`, or None if the query's characters don't all appear in it in order, ignoring case.
+  // matches of consecutive characters and at the start of words score higher, so the first letters of the words find a command
+fn fuzzy_score(query: &str, text: &str)->Option<i32> {
+  let text: Vec<char> = text.to_lowercase().chars().collect();
+  let mut score = 0;
+  let mut text_idx = 0;
+  let mut previous_match: Option<usize> = None;
+
+  for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+    let match_idx = text_idx + text[text_idx..].iter().position(|c| *c == query_char)?;
+
+    score += 1;
+    if previous_match.is_some_and(|previous| previous + 1 == match_idx) {
+      score += 4;
+    }
+    if match_idx == 0 || !text[match_idx - 1].is_alphanumeric() {
+      score += 3;
+    }
+    score -= (match_idx - text_idx).min(5) as i32; // skipped characters
+
+    previous_match = Some(match_idx);
+    text_idx = match_idx + 1;
+  }
+
+  Some(score)
+}
@@ -0,0 +1,26 @@
+use glium::glutin::dpi::{LogicalSize, LogicalPosition};
+use crate::image_display::Viewport;
+
+  // logical pixels between the cells of the compare view
+const CELL_GAP: f64 = 4.0;
+
+  // Splits the view into cells for the images of the compare view, in rows from the top left.
+  // Two images are side by side, three and four in a grid of two by two
+pub fn layout(count: usize, view_size: &LogicalSize<f64>)->Vec<Viewport> {
+  if count == 0 {
+    return Vec::new();
+  }
+
+  let columns = (count as f64).sqrt().ceil() as usize;
+  let rows = count.div_ceil(columns);
+  let cell_width = ((view_size.width - CELL_GAP * (columns - 1) as f64) / columns as f64).max(0.0);
+  let cell_height = ((view_size.height - CELL_GAP * (rows - 1) as f64) / rows as f64).max(0.0);
+
+  (0..count).map(|cell| {
+    let (column, row) = (cell % columns, cell / columns);
+    Viewport {
+      origin: LogicalPosition::new(column as f64 * (cell_width + CELL_GAP), row as f64 * (cell_height + CELL_GAP)),
+      size: LogicalSize::new(cell_width, cell_height)
+    }
+  }).collect()
+}
@@ -0,0 +1,265 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use crate::background_job::BackgroundJob;
+use crate::image::{self, ImageData, ImageLoadError};
+use crate::image_handling::ImageSummary;
+use log::warn;
+
+  // a4 landscape at 300 dpi, with a grid of 6 by 4 images
+const SHEET_SIZE: [usize; 2] = [3508, 2480];
+const COLUMNS: usize = 6;
+const ROWS: usize = 4;
+const MARGIN: usize = 100; // between the sheet edge and the content
+const HEADER_HEIGHT: usize = 90; // the folder name and page number above the grid
+const CELL_SPACING: usize = 40;
+const CAPTION_HEIGHT: usize = 60; // the file name and rating below each image
+const HEADER_TEXT_SIZE: f32 = 48.0;
+const CAPTION_TEXT_SIZE: f32 = 32.0;
+const BACKGROUND_COL: [u8; 3] = [26, 26, 26]; // same as the viewer's background
+const TEXT_COL: [u8; 3] = [230, 230, 230];
+const JPEG_QUALITY: f32 = 90.0;
+
+  // Renders the images into a grid of thumbnails with their file names and ratings, on as many sheets as it takes.
+  // Runs on a separate thread, since it decodes every image. The sheets are saved as jpegs in a contact_sheets folder
+  // inside the loaded folder, where they don't get loaded along with the images.
+  // They are drawn on the cpu, with the text rasterized by ab_glyph, rather than into an offscreen framebuffer: the gl context
+  // belongs to the main thread, and drawing and reading back sheets of this size there would stall the viewer for the whole export
+pub struct ContactSheetExport {
+  pub out_dir: PathBuf,
+  pub image_count: usize,
+  job: BackgroundJob<usize, ContactSheetError> // counts the images drawn, results in how many sheets were saved
+}
+
+impl ContactSheetExport {
+  pub fn start(folder: &Path, images: Vec<ImageSummary>, color_management: bool)->ContactSheetExport {
+    let out_dir = folder.join("contact_sheets");
+    let title = folder.file_name().map_or_else(|| folder.to_string_lossy(), |name| name.to_string_lossy()).into_owned();
+    let image_count = images.len();
+    let thread_out_dir = out_dir.clone();
+    let job = BackgroundJob::spawn("export", move |progress| export(&thread_out_dir, &title, &images, color_management, progress));
+
+    ContactSheetExport {
+      out_dir,
+      image_count,
+      job
+    }
+  }
+
+  pub fn progress(&self)->usize {
+    self.job.progress()
+  }
+
+    // the number of sheets saved, or the error message if the export failed, once it is done
+  pub fn poll(&self)->Option<Result<usize, String>> {
+    self.job.poll()
+  }
+}
+
+fn export(out_dir: &Path, title: &str, images: &[ImageSummary], color_management: bool, progress: &AtomicUsize)->Result<usize, ContactSheetError> {
+  let font = FontRef::try_from_slice(crate::UI_FONT_DATA).expect("Couldn't read the bundled font");
+  fs::create_dir_all(out_dir)?;
+
+  let cell_width = (SHEET_SIZE[0] - 2 * MARGIN - (COLUMNS - 1) * CELL_SPACING) / COLUMNS;
+  let cell_height = (SHEET_SIZE[1] - 2 * MARGIN - HEADER_HEIGHT - (ROWS - 1) * CELL_SPACING) / ROWS;
+  let thumbnail_size = [cell_width, cell_height - CAPTION_HEIGHT];
+
+  let per_sheet = COLUMNS * ROWS;
+  let sheet_count = images.len().div_ceil(per_sheet);
+  for (sheet_idx, sheet_images) in images.chunks(per_sheet).enumerate() {
+    let mut sheet = Sheet::new(SHEET_SIZE);
+    let header = format!("{}, page {} of {}", title, sheet_idx + 1, sheet_count);
+    sheet.draw_text(&font, &header, [MARGIN, MARGIN], HEADER_TEXT_SIZE, SHEET_SIZE[0] - 2 * MARGIN);
+
+    for (cell_idx, summary) in sheet_images.iter().enumerate() {
+      let left = MARGIN + (cell_idx % COLUMNS) * (cell_width + CELL_SPACING);
+      let top = MARGIN + HEADER_HEIGHT + (cell_idx / COLUMNS) * (cell_height + CELL_SPACING);
+
+      match load_thumbnail(summary, thumbnail_size, color_management) {
+        Ok((size, rgba)) => sheet.draw_image_to_fit(size, &rgba, [left, top], thumbnail_size),
+        Err(error) => {
+          warn!("Contact sheet: Couldn't load {}, {}", summary.path.display(), error);
+          sheet.draw_text(&font, "Couldn't load the image", [left, top], CAPTION_TEXT_SIZE, cell_width);
+        }
+      }
+
+        // the rating goes to the right, the file name gets the space that is left
+      let caption_top = top + thumbnail_size[1] + (CAPTION_HEIGHT - CAPTION_TEXT_SIZE as usize) / 2;
+      let rating = summary.rating.name();
+      let rating_width = text_width(&font, rating, CAPTION_TEXT_SIZE).ceil() as usize;
+      sheet.draw_text(&font, rating, [left + cell_width - rating_width, caption_top], CAPTION_TEXT_SIZE, rating_width);
+      sheet.draw_text(&font, &summary.file_name, [left, caption_top], CAPTION_TEXT_SIZE, cell_width.saturating_sub(rating_width + CELL_SPACING));
+
+      progress.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let jpeg = image::encode_rgb_jpeg(SHEET_SIZE, &sheet.rgb, JPEG_QUALITY)?;
+    fs::write(out_dir.join(format!("contact_sheet_{}_of_{}.jpg", sheet_idx + 1, sheet_count)), jpeg)?;
+  }
+
+  Ok(sheet_count)
+}
+
+  // the image decoded at a resolution close to `fit_size`, oriented the way it is shown. returns the oriented size and rgba pixels
+fn load_thumbnail(summary: &ImageSummary, fit_size: [usize; 2], color_management: bool)->Result<([usize; 2], Vec<u8>), ImageLoadError> {
+    // the user's orientation might swap width and height, so it's decoded to fit the longer side either way
+  let longest_side = fit_size[0].max(fit_size[1]) as u32;
+  let mut data = ImageData::load_to_fit(&summary.path, [longest_side, longest_side])?;
+  if color_management {
+    data.convert_to_srgb()?;
+  }
+  Ok(data.to_oriented_rgba(summary.orientation.unwrap_or(data.rotation())))
+}
+
+fn text_width(font: &FontRef, text: &str, size: f32)->f32 {
+  let scaled_font = font.as_scaled(PxScale::from(size));
+  let mut width = 0.0;
+  let mut previous = None;
+  for c in text.chars() {
+    let glyph_id = scaled_font.glyph_id(c);
+    if let Some(previous) = previous {
+      width += scaled_font.kern(previous, glyph_id);
+    }
+    width += scaled_font.h_advance(glyph_id);
+    previous = Some(glyph_id);
+  }
+  width
+}
+
+struct Sheet {
+  size: [usize; 2],
+  rgb: Vec<u8>
+}
+
+impl Sheet {
+  fn new(size: [usize; 2])->Sheet {
+    Sheet {
+      size,
+      rgb: BACKGROUND_COL.repeat(size[0] * size[1])
+    }
+  }
+
+    // scales the image down to fit into the rectangle at `top_left` of `fit_size`, centered in it.
+    // each sheet pixel averages the image pixels it covers
+  fn draw_image_to_fit(&mut self, size: [usize; 2], rgba: &[u8], top_left: [usize; 2], fit_size: [usize; 2]) {
+    let scale = (fit_size[0] as f64 / size[0] as f64).min(fit_size[1] as f64 / size[1] as f64).min(1.0);
+    let out_width = ((size[0] as f64 * scale).round() as usize).max(1);
+    let out_height = ((size[1] as f64 * scale).round() as usize).max(1);
+    let left = top_left[0] + (fit_size[0] - out_width) / 2;
+    let top = top_left[1] + (fit_size[1] - out_height) / 2;
+
+      // the range of image pixels covering sheet pixel `out` of `out_len`, at least one pixel wide
+    let source_range = |out: usize, out_len: usize, len: usize| {
+      let start = out * len / out_len;
+      let end = ((out + 1) * len / out_len).max(start + 1);
+      start..end
+    };
+
+    for out_y in 0..out_height {
+      let rows = source_range(out_y, out_height, size[1]);
+      for out_x in 0..out_width {
+        let cols = source_range(out_x, out_width, size[0]);
+
+        let mut sum = [0u32; 3];
+        for y in rows.clone() {
+          for x in cols.clone() {
+            let pixel = &rgba[(y * size[0] + x) * 4..][..3];
+            for (channel_sum, &value) in sum.iter_mut().zip(pixel) {
+              *channel_sum += value as u32;
+            }
+          }
+        }
+
+        let count = (rows.len() * cols.len()) as u32;
+        let out_start = ((top + out_y) * self.size[0] + left + out_x) * 3;
+        for (out, channel_sum) in self.rgb[out_start..out_start + 3].iter_mut().zip(sum) {
+          *out = ((channel_sum + count / 2) / count) as u8;
+        }
+      }
+    }
+  }
+
+    // draws a single line of text with its top left at `top_left`. text beyond `max_width` is cut off with an ellipsis
+  fn draw_text(&mut self, font: &FontRef, text: &str, top_left: [usize; 2], size: f32, max_width: usize) {
+    let mut text = text.to_string();
+    if text_width(font, &text, size) > max_width as f32 {
+      while !text.is_empty() && text_width(font, &format!("{}...", text), size) > max_width as f32 {
+        text.pop();
+      }
+      text.push_str("...");
+    }
+
+    let scaled_font = font.as_scaled(PxScale::from(size));
+    let mut caret = point(top_left[0] as f32, top_left[1] as f32 + scaled_font.ascent());
+    let mut previous = None;
+    for c in text.chars() {
+      let glyph_id = scaled_font.glyph_id(c);
+      if let Some(previous) = previous {
+        caret.x += scaled_font.kern(previous, glyph_id);
+      }
+      let glyph = glyph_id.with_scale_and_position(size, caret);
+      caret.x += scaled_font.h_advance(glyph_id);
+      previous = Some(glyph_id);
+
+      let outline = match font.outline_glyph(glyph) {
+        Some(outline) => outline,
+        None => continue // whitespace
+      };
+      let bounds = outline.px_bounds();
+      outline.draw(|x, y, coverage| {
+        let x = bounds.min.x as i64 + x as i64;
+        let y = bounds.min.y as i64 + y as i64;
+        if x < 0 || y < 0 || x as usize >= self.size[0] || y as usize >= self.size[1] {
+          return;
+        }
+
+        let start = (y as usize * self.size[0] + x as usize) * 3;
+        for (out, text_value) in self.rgb[start..start + 3].iter_mut().zip(TEXT_COL) {
+          *out = (*out as f32 + (text_value as f32 - *out as f32) * coverage.min(1.0)).round() as u8;
+        }
+      });
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum ContactSheetError {
+  IoError(io::Error),
+  EncodeError(ImageLoadError)
+}
+
+impl fmt::Display for ContactSheetError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::ContactSheetError::*;
+    match self {
+      IoError(error) => write!(f, "Couldn't save the contact sheet: {}", error),
+      EncodeError(error) => write!(f, "Couldn't encode the contact sheet: {}", error),
+    }
+  }
+}
+
+impl Error for ContactSheetError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::ContactSheetError::*;
+    match self {
+      IoError(error) => Some(error),
+      EncodeError(error) => Some(error),
+    }
+  }
+}
+
+impl From<io::Error> for ContactSheetError {
+  fn from(error: io::Error)->Self {
+    ContactSheetError::IoError(error)
+  }
+}
+
+impl From<ImageLoadError> for ContactSheetError {
+  fn from(error: ImageLoadError)->Self {
+    ContactSheetError::EncodeError(error)
+  }
+}
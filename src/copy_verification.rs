@@ -0,0 +1,190 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::background_job::BackgroundJob;
+use crate::sha256;
+
+  // file extensions of the checksum manifests looked for in the loaded folder
+const MANIFEST_EXTENSIONS: [&str; 2] = ["sha256", "sha256sum"];
+
+  // Checks that a copy of a memory card is complete before the card is formatted: every file listed in a checksum manifest,
+  // or every file in the card's folder, has to be in the loaded folder with the same SHA-256 digest.
+  // Hashing runs on a separate thread, since it reads every file in full
+pub struct CopyVerification {
+  pub file_count: usize,
+  job: BackgroundJob<VerificationReport, Infallible> // counts the files checked
+}
+
+  // What the copy is compared against
+#[derive(Debug, Clone)]
+pub enum VerificationSource {
+  Manifest(PathBuf), // lines of a digest and a file name, as written by sha256sum, or in the BSD format of shasum --tag
+  Folder(PathBuf) // the original files, compared with the files of the same name
+}
+
+  // the names of the files that didn't match, in the order they were checked
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+  pub matched: usize,
+  pub mismatched: Vec<String>, // the copy differs from the original
+  pub missing: Vec<String>, // not in the copy
+  pub unreadable: Vec<(String, String)> // with the error
+}
+
+impl VerificationReport {
+  pub fn is_complete_copy(&self)->bool {
+    self.mismatched.is_empty() && self.missing.is_empty() && self.unreadable.is_empty()
+  }
+}
+
+struct ExpectedFile {
+  name: String, // relative to the folder of the copy
+  digest: Option<String> // from the manifest. None for the files of a folder, which are hashed alongside their copies
+}
+
+impl CopyVerification {
+    // reads the manifest or lists the folder right away, so a wrong path is reported before anything is hashed
+  pub fn start(copy_folder: &Path, source: &VerificationSource)->Result<CopyVerification, VerificationError> {
+    let expected = match source {
+      VerificationSource::Manifest(path) => read_manifest(path)?,
+      VerificationSource::Folder(path) => list_files(path)?
+    };
+    let file_count = expected.len();
+
+    let copy_folder = copy_folder.to_path_buf();
+    let source_folder = match source {
+      VerificationSource::Manifest(_) => None,
+      VerificationSource::Folder(path) => Some(path.clone())
+    };
+    let job = BackgroundJob::spawn("verification", move |progress| Ok(verify(&copy_folder, source_folder.as_deref(), &expected, progress)));
+
+    Ok(CopyVerification {
+      file_count,
+      job
+    })
+  }
+
+  pub fn progress(&self)->usize {
+    self.job.progress()
+  }
+
+    // the report once every file was checked
+  pub fn poll(&self)->Option<Result<VerificationReport, String>> {
+    self.job.poll()
+  }
+}
+
+  // the first checksum manifest in the folder, by file name, to verify against without looking for it
+pub fn find_manifest(folder: &Path)->Option<PathBuf> {
+  let mut manifests: Vec<_> = fs::read_dir(folder).ok()?
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| path.extension().and_then(|extension| extension.to_str()).is_some_and(|extension| MANIFEST_EXTENSIONS.contains(&extension.to_lowercase().as_str())))
+    .collect();
+  manifests.sort_unstable();
+  manifests.into_iter().next()
+}
+
+fn verify(copy_folder: &Path, source_folder: Option<&Path>, expected: &[ExpectedFile], progress: &AtomicUsize)->VerificationReport {
+  let mut report = VerificationReport::default();
+  for file in expected {
+    let copy_path = copy_folder.join(&file.name);
+    if !copy_path.is_file() {
+      report.missing.push(file.name.clone());
+    } else {
+      let digests = match (&file.digest, source_folder) {
+        (Some(digest), _) => sha256::hash_file(&copy_path).map(|copy_digest| (digest.clone(), copy_digest)),
+        (None, Some(source_folder)) => sha256::hash_file(&source_folder.join(&file.name))
+          .and_then(|digest| sha256::hash_file(&copy_path).map(|copy_digest| (digest, copy_digest))),
+        (None, None) => unreachable!("files from a manifest have their digest")
+      };
+      match digests {
+        Ok((digest, copy_digest)) if digest == copy_digest => report.matched += 1,
+        Ok(_) => report.mismatched.push(file.name.clone()),
+        Err(error) => report.unreadable.push((file.name.clone(), error.to_string()))
+      }
+    }
+    progress.fetch_add(1, Ordering::Relaxed);
+  }
+  report
+}
+
+  // GNU lines look like `<digest>  <name>`, with a * before binary files' names, BSD lines like `SHA256 (<name>) = <digest>`.
+  // empty lines and comments are skipped
+fn read_manifest(path: &Path)->Result<Vec<ExpectedFile>, VerificationError> {
+  let contents = fs::read_to_string(path)?;
+  let mut files = Vec::new();
+  for (line_idx, line) in contents.lines().enumerate() {
+    let line = line.trim_end();
+    if line.trim().is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let (name, digest) = parse_manifest_line(line).ok_or(VerificationError::InvalidLine(line_idx + 1))?;
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+      return Err(VerificationError::NotSha256(line_idx + 1));
+    }
+    files.push(ExpectedFile { name: name.to_string(), digest: Some(digest.to_lowercase()) });
+  }
+  Ok(files)
+}
+
+fn parse_manifest_line(line: &str)->Option<(&str, &str)> {
+  if let Some(rest) = line.strip_prefix("SHA256 (") {
+    let (name, digest) = rest.rsplit_once(") = ")?;
+    return Some((name, digest));
+  }
+  let (digest, name) = line.split_once(' ')?;
+  let name = name.strip_prefix(' ').or_else(|| name.strip_prefix('*')).unwrap_or(name);
+  Some((name, digest))
+}
+
+  // the files of the folder, without the ones inside folders in it, sorted by name. hidden files, like the ones file systems leave on cards, are skipped
+fn list_files(folder: &Path)->Result<Vec<ExpectedFile>, VerificationError> {
+  let mut names = Vec::new();
+  for entry in fs::read_dir(folder)? {
+    let entry = entry?;
+    let name = entry.file_name().to_string_lossy().into_owned();
+    if !name.starts_with('.') && entry.path().is_file() {
+      names.push(name);
+    }
+  }
+  names.sort_unstable();
+  Ok(names.into_iter().map(|name| ExpectedFile { name, digest: None }).collect())
+}
+
+#[derive(Debug)]
+pub enum VerificationError {
+  ReadFailed(io::Error),
+  InvalidLine(usize), // the line number in the manifest, starting at 1
+  NotSha256(usize)
+}
+
+impl fmt::Display for VerificationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::VerificationError::*;
+    match self {
+      ReadFailed(error) => write!(f, "Couldn't read the manifest or folder: {}", error),
+      InvalidLine(line) => write!(f, "Line {} of the manifest isn't a digest and a file name", line),
+      NotSha256(line) => write!(f, "Line {} of the manifest isn't a SHA-256 digest, which is the only kind supported", line),
+    }
+  }
+}
+
+impl Error for VerificationError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::VerificationError::*;
+    match self {
+      ReadFailed(error) => Some(error),
+      _ => None
+    }
+  }
+}
+
+impl From<io::Error> for VerificationError {
+  fn from(error: io::Error)->Self {
+    VerificationError::ReadFailed(error)
+  }
+}
@@ -0,0 +1,94 @@
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::error;
+use crate::{logging, platform};
+
+thread_local! {
+    // set while panics are caught as errors on this thread, so the hook doesn't report them as crashes
+  static CATCHING_PANICS: Cell<bool> = const { Cell::new(false) };
+}
+
+  // set by the first crash that is reported. panics on other threads while its dialog is open don't show another
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+  // Reports panics that aren't caught as crashes: logs them, writes a crash log next to the log file and shows a dialog, then exits.
+  // Without this, panics on worker threads only end up on the console, and the app keeps running without them
+pub fn install_panic_hook() {
+  panic::set_hook(Box::new(|info| {
+    if CATCHING_PANICS.get() {
+      return;
+    }
+
+    let thread = thread::current();
+    let report = format!("Thread '{}' panicked at {}: {}\n\n{}",
+      thread.name().unwrap_or("unnamed"),
+      info.location().map_or_else(|| "an unknown location".to_string(), |location| location.to_string()),
+      panic_message(info),
+      Backtrace::force_capture());
+    report_and_exit(&report, 101);
+  }));
+}
+
+  // for errors the app can't continue after, that aren't panics. reported the same way
+pub fn fatal(message: &str)->! {
+  report_and_exit(message, 1)
+}
+
+  // runs `f`, turning a panic into an error instead of a crash, for libraries that report errors by panicking
+pub fn catch_unwind_quietly<F, T>(f: F)->thread::Result<T>
+  where F: FnOnce()->T {
+
+  let was_catching = CATCHING_PANICS.replace(true);
+  let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+  CATCHING_PANICS.set(was_catching);
+  result
+}
+
+fn panic_message(info: &PanicHookInfo)->String {
+  info.payload_as_str().unwrap_or("Unknown error").to_string()
+}
+
+fn report_and_exit(report: &str, exit_code: i32)->! {
+  if REPORTED.swap(true, Ordering::SeqCst) {
+      // the first report exits the app once its dialog is closed
+    loop {
+      thread::park();
+    }
+  }
+
+  error!("{}", report);
+  log::logger().flush();
+
+  let crash_log = write_crash_log(report);
+  let summary = report.lines().next().unwrap_or(report);
+  let mut message = format!("Fotoleine ran into an error it can't recover from and has to close.\n\n{}", summary);
+  match crash_log {
+    Ok(path) => message.push_str(&format!("\n\nThe details were saved to {}", path.display())),
+    Err(error) => error!("Couldn't write the crash log: {}", error)
+  }
+
+  if let Err(error) = platform::show_error_dialog("Fotoleine crashed", &message) {
+    error!("Couldn't show the crash dialog: {}", error);
+  }
+  process::exit(exit_code);
+}
+
+  // crash logs are kept separately from the rotating log, so they don't get rotated away
+fn write_crash_log(report: &str)->io::Result<PathBuf> {
+  let log_path = logging::path().ok_or_else(|| io::Error::other("The platform has no data directory"))?;
+  let dir = log_path.parent().ok_or_else(|| io::Error::other("The log file has no folder"))?;
+  fs::create_dir_all(dir)?;
+
+  let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs());
+  let path = dir.join(format!("crash_{}.log", timestamp));
+  fs::write(&path, report)?;
+  Ok(path)
+}
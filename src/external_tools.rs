@@ -0,0 +1,149 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use imgui::Ui;
+use glium::glutin::event::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use crate::actions;
+use log::{error, warn};
+
+const RAW_EXTENSIONS: [&str; 2] = ["cr2", "cr3"];
+
+pub fn is_raw_file(path: &Path)->bool {
+  path.extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| RAW_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+  // the raw file with the same name as the image, in either letter case
+pub fn find_raw_file(image_path: &Path)->Option<PathBuf> {
+  RAW_EXTENSIONS.iter()
+    .flat_map(|ext| [ext.to_string(), ext.to_uppercase()])
+    .map(|ext| image_path.with_extension(ext))
+    .find(|raw_path| raw_path.is_file())
+}
+
+  // An external program as configured in the preferences, e.g.
+  //   name: Edit in Capture One
+  //   key: F1
+  //   command: [open, -a, Capture One, "{raw}"]
+  // Placeholders in the command arguments are replaced with details of the shown image:
+  //   {path}: the image's path, {raw}: the raw file shot alongside it, or the image's path if there is none,
+  //   {name}: the image's file name, {folder}: the folder containing it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExternalToolConfig {
+  pub name: String,
+  pub key: String,
+  pub command: Vec<String>
+}
+
+pub struct ExternalTool {
+  pub name: String,
+  pub key_name: String, // as configured, to show it
+  key: VirtualKeyCode,
+  shift: bool,
+  command: Vec<String> // program, then arguments
+}
+
+impl ExternalTool {
+  pub fn from_config(config: &ExternalToolConfig)->Result<ExternalTool, ExternalToolError> {
+    let (shift, key_name) = match config.key.strip_prefix("Shift+") {
+      Some(key_name) => (true, key_name),
+      None => (false, config.key.as_str())
+    };
+    let key = parse_key(key_name).ok_or_else(|| ExternalToolError::InvalidKey(config.key.clone()))?;
+      // the built-in command would run along with the tool
+    if actions::BUILT_IN_KEYS.contains(&key) {
+      return Err(ExternalToolError::BuiltInKey(config.key.clone()));
+    }
+
+    if config.command.is_empty() {
+      return Err(ExternalToolError::EmptyCommand);
+    }
+
+    Ok(ExternalTool {
+      name: config.name.clone(),
+      key_name: config.key.clone(),
+      key,
+      shift,
+      command: config.command.clone()
+    })
+  }
+
+    // the other modifiers must not be held, so that e.g. Cmd+E doesn't also run a tool bound to E
+  pub fn is_triggered(&self, ui: &Ui)->bool {
+    let io = ui.io();
+    ui.is_key_index_pressed_no_repeat(self.key as _) && io.key_shift == self.shift && !io.key_ctrl && !io.key_alt && !io.key_super
+  }
+
+    // starts the program for the image. it keeps running independently, only failing to start it is an error
+  pub fn run(&self, image_path: &Path)->io::Result<()> {
+    let raw_path = find_raw_file(image_path).unwrap_or_else(|| image_path.to_path_buf());
+    let name = image_path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let folder = image_path.parent().unwrap_or(image_path);
+
+    let args: Vec<_> = self.command.iter()
+      .map(|arg| {
+        arg.replace("{path}", &image_path.to_string_lossy())
+          .replace("{raw}", &raw_path.to_string_lossy())
+          .replace("{name}", &name)
+          .replace("{folder}", &folder.to_string_lossy())
+      })
+      .collect();
+
+    let mut child = Command::new(&args[0]).args(&args[1..]).spawn()?;
+
+      // waited on so the process gets cleaned up once it exits
+    let tool_name = self.name.clone();
+    thread::spawn(move || {
+      match child.wait() {
+        Ok(status) if !status.success() => warn!("{} exited with {}", tool_name, status),
+        Ok(_) => {},
+        Err(error) => error!("Couldn't wait for {} to exit: {}", tool_name, error)
+      }
+    });
+
+    Ok(())
+  }
+}
+
+  // letters, digits, and function keys, named like on the keyboard
+fn parse_key(name: &str)->Option<VirtualKeyCode> {
+  use VirtualKeyCode::*;
+  const LETTERS: [VirtualKeyCode; 26] = [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z];
+  const DIGITS: [VirtualKeyCode; 10] = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+  const FUNCTION_KEYS: [VirtualKeyCode; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+  let mut chars = name.chars();
+  match (chars.next(), chars.next()) {
+    (Some(c), None) if c.is_ascii_alphabetic() => Some(LETTERS[(c.to_ascii_uppercase() as u8 - b'A') as usize]),
+    (Some(c), None) if c.is_ascii_digit() => Some(DIGITS[(c as u8 - b'0') as usize]),
+    (Some('F'), Some(_)) => name[1..].parse::<usize>().ok()
+      .filter(|number| (1..=FUNCTION_KEYS.len()).contains(number))
+      .map(|number| FUNCTION_KEYS[number - 1]),
+    _ => None
+  }
+}
+
+#[derive(Debug)]
+pub enum ExternalToolError {
+  InvalidKey(String),
+  BuiltInKey(String), // a key Fotoleine uses itself, see `actions::BUILT_IN_KEYS`
+  EmptyCommand
+}
+
+impl fmt::Display for ExternalToolError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::ExternalToolError::*;
+    match self {
+      InvalidKey(key) => write!(f, "\"{}\" is not a key that tools can be bound to. Use a letter, digit or F1-F12, optionally prefixed with Shift+", key),
+      BuiltInKey(key) => write!(f, "\"{}\" is taken by one of Fotoleine's own commands, with or without Shift. Free are K, Q, Y, 4-9 and the function keys other than F5", key),
+      EmptyCommand => write!(f, "The command is empty"),
+    }
+  }
+}
+
+impl Error for ExternalToolError {}
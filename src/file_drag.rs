@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use glium::glutin::dpi::LogicalPosition;
+use glium::glutin::window::Window;
+use crate::image::{self, ImageData, ImageLoadError, ImageRotation};
+use crate::image_handling::Thumbnail;
+
+  // how far the cursor has to move with the button held down before a drag starts, so that plain clicks don't start one
+const DRAG_DISTANCE: f64 = 5.0;
+  // the longest side of the image shown under the cursor while dragging
+const ICON_SIZE: usize = 256;
+const ICON_QUALITY: f32 = 80.0;
+
+const SUPPORTED: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+
+  // Tracks presses of the left mouse button on the shown image, to drag its file out of the window into other apps
+pub struct FileDrag {
+  press_pos: Option<LogicalPosition<f64>> // where the button was pressed, while it is held down on the image
+}
+
+impl FileDrag {
+  pub fn new()->FileDrag {
+    FileDrag {
+      press_pos: None
+    }
+  }
+
+  pub fn press(&mut self, pos: LogicalPosition<f64>) {
+    self.press_pos = Some(pos);
+  }
+
+  pub fn release(&mut self) {
+    self.press_pos = None;
+  }
+
+    // returns true if the cursor moved far enough away from a press to start a drag. a press only starts one drag
+  pub fn cursor_moved(&mut self, pos: LogicalPosition<f64>)->bool {
+    match self.press_pos {
+      Some(press_pos) if (pos.x - press_pos.x).hypot(pos.y - press_pos.y) >= DRAG_DISTANCE => {
+        self.press_pos = None;
+        true
+      },
+      _ => false
+    }
+  }
+}
+
+  // starts the operating system's drag of the image file out of `window`, together with its raw file if one is given.
+  // the image's thumbnail is shown under the cursor, turned by the rotation it is shown with. returns once the drag started, or on Windows, once it ended
+pub fn start(window: &Window, path: &Path, raw_path: Option<&Path>, thumbnail: Option<(&Thumbnail, ImageRotation)>)->Result<(), FileDragError> {
+    // checked first, to not decode the icon for nothing
+  if !SUPPORTED {
+    return Err(FileDragError::Unsupported);
+  }
+
+    // apps receiving the files need their absolute paths
+  let mut paths = vec![fs::canonicalize(path)?];
+  if let Some(raw_path) = raw_path {
+    paths.push(fs::canonicalize(raw_path)?);
+  }
+  let icon = match thumbnail {
+    Some((thumbnail, rotation)) => thumbnail.to_oriented_jpeg(rotation)?,
+    None => drag_icon(&paths[0])?
+  };
+  start_os_drag(window, paths, icon)
+}
+
+  // a small jpeg of the image, for images whose thumbnail isn't made yet. this decodes the file, so the thumbnail is preferred
+fn drag_icon(path: &Path)->Result<Vec<u8>, ImageLoadError> {
+  let mut data = if image::is_jpeg(path) {
+    ImageData::load_preview(path)?
+  } else {
+    ImageData::load(path)?
+  };
+  data.shrink_to(ICON_SIZE);
+  data.encode_oriented_jpeg(ICON_QUALITY)
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn start_os_drag(window: &Window, paths: Vec<PathBuf>, icon: Vec<u8>)->Result<(), FileDragError> {
+    // the receiving app handles the files, nothing to do here once they're dropped
+  drag::start_drag(window, drag::DragItem::Files(paths), drag::Image::Raw(icon), |_, _| {}, drag::Options::default())
+    .map_err(|error| FileDragError::DragFailed(error.to_string()))
+}
+
+  // the drag crate needs a gtk window on linux, which winit doesn't create
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn start_os_drag(_window: &Window, _paths: Vec<PathBuf>, _icon: Vec<u8>)->Result<(), FileDragError> {
+  Err(FileDragError::Unsupported)
+}
+
+#[derive(Debug)]
+pub enum FileDragError {
+  PathError(io::Error),
+  IconError(ImageLoadError),
+  #[cfg(any(target_os = "macos", target_os = "windows"))]
+  DragFailed(String),
+  Unsupported
+}
+
+impl fmt::Display for FileDragError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::FileDragError::*;
+    match self {
+      PathError(error) => write!(f, "Couldn't find the file: {}", error),
+      IconError(error) => write!(f, "Couldn't load the image to show while dragging: {}", error),
+      #[cfg(any(target_os = "macos", target_os = "windows"))]
+      DragFailed(error) => write!(f, "The drag failed to start: {}", error),
+      Unsupported => write!(f, "Dragging files out of the window isn't supported on this platform"),
+    }
+  }
+}
+
+impl Error for FileDragError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::FileDragError::*;
+    match self {
+      PathError(error) => Some(error),
+      IconError(error) => Some(error),
+      _ => None
+    }
+  }
+}
+
+impl From<io::Error> for FileDragError {
+  fn from(error: io::Error)->Self {
+    FileDragError::PathError(error)
+  }
+}
+
+impl From<ImageLoadError> for FileDragError {
+  fn from(error: ImageLoadError)->Self {
+    FileDragError::IconError(error)
+  }
+}
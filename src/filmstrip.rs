@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use glium::backend::Facade;
+use glium::texture::{Texture2d, RawImage2d};
+use glium::uniforms::{SamplerBehavior, MinifySamplerFilter, MagnifySamplerFilter};
+use imgui::{TextureId, Textures};
+use imgui_glium_renderer::Texture;
+use crate::image::ImageRotation;
+use crate::image_handling::Thumbnail;
+use log::warn;
+
+  // how many images the filmstrip shows on each side of the shown one
+pub const IMAGES_PER_SIDE: usize = 4;
+
+  // The textures of the thumbnails in the filmstrip, registered with the ui renderer so the ui can draw them.
+  // Only the thumbnails in view have one, the others are freed as they scroll out of it
+#[derive(Default)]
+pub struct FilmstripTextures {
+  textures: HashMap<usize, (ImageRotation, bool, TextureId, [usize; 2])> // by collection index: the orientation the texture was made in, whether it is of an exif thumbnail, and its size
+}
+
+impl FilmstripTextures {
+    // the texture of the image's thumbnail and its size, made if there is none yet, or the image was turned or its exif thumbnail replaced since.
+    // None if it couldn't be made, the filmstrip shows an empty cell then
+  pub fn get_or_make<F: Facade>(&mut self, coll_idx: usize, thumbnail: &Thumbnail, rotation: ImageRotation, gl_ctx: &F, textures: &mut Textures<Texture>)->Option<(TextureId, [usize; 2])> {
+    if let Some(&(texture_rotation, texture_embedded, texture_id, size)) = self.textures.get(&coll_idx) {
+      if texture_rotation == rotation && texture_embedded == thumbnail.is_embedded() {
+        return Some((texture_id, size));
+      }
+      textures.remove(texture_id);
+      self.textures.remove(&coll_idx);
+    }
+
+    let (size, rgba) = match thumbnail.to_oriented_rgba(rotation) {
+      Ok(oriented) => oriented,
+      Err(error) => {
+        warn!("Filmstrip: Couldn't decode the thumbnail of image {}, {}", coll_idx, error);
+        return None;
+      }
+    };
+    let texture = match Texture2d::new(gl_ctx, RawImage2d::from_raw_rgba(rgba, (size[0] as u32, size[1] as u32))) {
+      Ok(texture) => texture,
+      Err(error) => {
+        warn!("Filmstrip: Couldn't create the texture of image {}, {}", coll_idx, error);
+        return None;
+      }
+    };
+    let texture_id = textures.insert(Texture {
+      texture: Rc::new(texture),
+      sampler: SamplerBehavior {
+        minify_filter: MinifySamplerFilter::Linear,
+        magnify_filter: MagnifySamplerFilter::Linear,
+        ..Default::default()
+      }
+    });
+    self.textures.insert(coll_idx, (rotation, thumbnail.is_embedded(), texture_id, size));
+    Some((texture_id, size))
+  }
+
+    // frees the textures of the images `keep` returns false for
+  pub fn retain(&mut self, mut keep: impl FnMut(usize)->bool, textures: &mut Textures<Texture>) {
+    self.textures.retain(|&coll_idx, &mut (_, _, texture_id, _)| {
+      let kept = keep(coll_idx);
+      if !kept {
+        textures.remove(texture_id);
+      }
+      kept
+    });
+  }
+
+  pub fn clear(&mut self, textures: &mut Textures<Texture>) {
+    self.retain(|_| false, textures);
+  }
+}
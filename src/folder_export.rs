@@ -0,0 +1,66 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::background_job::BackgroundJob;
+use crate::image_export::{self, ExportPlan, ExportOptions, PlannedFile};
+use log::warn;
+
+  // Copies images into a folder of their own inside the loaded folder, e.g. the selects to hand them to a client, or web-size proofs
+  // of the ones with a rating. Runs on a separate thread, since the files can be large and may be scaled down on the way.
+  // Every export gets a new folder, so files of an earlier export, like selects in a different order, don't mix in
+pub struct FolderExport {
+  pub out_dir: PathBuf,
+  pub image_count: usize,
+  job: BackgroundJob<usize, io::Error> // counts the images copied, results in how many were skipped because they couldn't be read or written
+}
+
+impl FolderExport {
+    // copies the planned files into the plan's new folder
+  pub fn start(plan: ExportPlan)->FolderExport {
+    let ExportPlan { destination: out_dir, files, options, .. } = plan;
+    let image_count = files.len();
+    let thread_out_dir = out_dir.clone();
+    let job = BackgroundJob::spawn("export", move |progress| export(&thread_out_dir, &files, &options, progress));
+
+    FolderExport {
+      out_dir,
+      image_count,
+      job
+    }
+  }
+
+  pub fn progress(&self)->usize {
+    self.job.progress()
+  }
+
+    // the number of images skipped, or the error message if the export failed, once it is done
+  pub fn poll(&self)->Option<Result<usize, String>> {
+    self.job.poll()
+  }
+}
+
+fn export(out_dir: &Path, files: &[PlannedFile], options: &ExportOptions, progress: &AtomicUsize)->Result<usize, io::Error> {
+  fs::create_dir_all(out_dir)?;
+
+  let mut skipped_count = 0;
+  for PlannedFile { summary, target } in files {
+      // a file that can't be written, e.g. because of its name, doesn't keep the others from being exported
+    match image_export::export_contents(summary, options) {
+      Ok(contents) => {
+        let target_path = out_dir.join(target);
+        if let Err(error) = fs::write(&target_path, contents) {
+          warn!("Folder export: Couldn't write {}, {}", target_path.display(), error);
+          skipped_count += 1;
+        }
+      },
+      Err(error) => {
+        warn!("Folder export: Couldn't read {}, {}", summary.path.display(), error);
+        skipped_count += 1;
+      }
+    }
+    progress.fetch_add(1, Ordering::Relaxed);
+  }
+
+  Ok(skipped_count)
+}
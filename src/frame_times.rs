@@ -0,0 +1,47 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+  // how many of the latest frames are kept
+const HISTORY_LENGTH: usize = 120;
+
+  // How long the latest frames took to build and draw, for the performance overlay.
+  // Frames are only drawn when something changes, so these are the durations of the frames that were drawn, not a frame rate
+pub struct FrameTimes {
+  times: VecDeque<f32> // in milliseconds, oldest first
+}
+
+impl FrameTimes {
+  pub fn new()->FrameTimes {
+    FrameTimes {
+      times: VecDeque::with_capacity(HISTORY_LENGTH)
+    }
+  }
+
+  pub fn push(&mut self, frame_time: Duration) {
+    if self.times.len() == HISTORY_LENGTH {
+      self.times.pop_front();
+    }
+    self.times.push_back(frame_time.as_secs_f32() * 1000.0);
+  }
+
+    // oldest first, in milliseconds
+  pub fn times(&mut self)->&[f32] {
+    self.times.make_contiguous()
+  }
+
+  pub fn last(&self)->f32 {
+    self.times.back().copied().unwrap_or(0.0)
+  }
+
+  pub fn average(&self)->f32 {
+    if self.times.is_empty() {
+      0.0
+    } else {
+      self.times.iter().sum::<f32>() / self.times.len() as f32
+    }
+  }
+
+  pub fn max(&self)->f32 {
+    self.times.iter().copied().fold(0.0, f32::max)
+  }
+}
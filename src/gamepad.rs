@@ -0,0 +1,83 @@
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use gilrs::{Button, EventType, Gilrs};
+use glium::glutin::event_loop::{EventLoopProxy, EventLoopClosed};
+use log::{info, warn};
+use crate::actions::{Action, SKIP_DISTANCE};
+use crate::image_handling::Rating;
+use crate::image_handling::loader_pool::LoadNotification;
+
+  // Turns gamepad button presses into actions, to cull from the couch.
+  // The gamepads are read on a thread of their own, which blocks until a button is pressed and wakes up the event loop for it
+pub struct Gamepads {
+  actions: Receiver<Action>
+}
+
+impl Gamepads {
+  pub fn start(event_loop_proxy: EventLoopProxy<LoadNotification>)->Gamepads {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+      let mut gilrs = match Gilrs::new() {
+        Ok(gilrs) => gilrs,
+        Err(error) => {
+          warn!("Gamepads aren't available: {}", error);
+          return;
+        }
+      };
+
+      loop {
+        let event = match gilrs.next_event_blocking(None) {
+          Some(event) => event,
+          None => continue
+        };
+
+        let action = match event.event {
+          EventType::ButtonPressed(button, _) => button_action(button),
+          EventType::Connected => {
+            info!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+            None
+          },
+          _ => None
+        };
+
+        if let Some(action) = action {
+            // the app is shutting down once either end is gone
+          if sender.send(action).is_err() {
+            return;
+          }
+          if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::ActionTriggered) {
+            return;
+          }
+        }
+      }
+    });
+
+    Gamepads {
+      actions: receiver
+    }
+  }
+
+    // the actions of the buttons pressed since the last call, in the order they were pressed
+  pub fn take_actions(&self)->Vec<Action> {
+    self.actions.try_iter().collect()
+  }
+}
+
+  // the d-pad moves between images and the shoulder buttons skip ahead, the face buttons on the left, top and right rate low, medium and high.
+  // uses the positions of the buttons rather than their labels, which differ between controllers
+fn button_action(button: Button)->Option<Action> {
+  match button {
+    Button::DPadLeft => Some(Action::ShowPrevious(1)),
+    Button::DPadRight => Some(Action::ShowNext(1)),
+    Button::LeftTrigger => Some(Action::ShowPrevious(SKIP_DISTANCE)),
+    Button::RightTrigger => Some(Action::ShowNext(SKIP_DISTANCE)),
+    Button::West => Some(Action::Rate(Rating::Low)),
+    Button::North => Some(Action::Rate(Rating::Medium)),
+    Button::East => Some(Action::Rate(Rating::High)),
+    Button::South => Some(Action::ToggleSelected),
+    Button::Start => Some(Action::PlayPause),
+    Button::Select => Some(Action::ToggleUi),
+    _ => None
+  }
+}
@@ -1,108 +1,1417 @@
 use std::error::Error;
 use std::io;
+use std::fs;
+use std::borrow::Cow;
+use std::collections::{VecDeque, HashMap};
+use std::time::{Duration, Instant};
 use std::path::Path;
 use glium::{
+  Rect, CapabilitiesSource,
   backend::Facade,
-  texture::{RawImage2d, CompressedSrgbTexture2d, TextureCreationError},
+  texture::{RawImage2d, ClientFormat, CompressedSrgbTexture2d, CompressedSrgbFormat, CompressedMipmapsOption, SrgbTexture2d, SrgbFormat, MipmapsOption, TextureCreationError},
+  uniforms::{AsUniformValue, UniformValue, SamplerBehavior, MinifySamplerFilter, MagnifySamplerFilter, SamplerWrapFunction},
 };
+use serde::{Deserialize, Serialize};
 use glium::glutin::dpi::{LogicalSize, LogicalPosition};
 use stb_image::image::{Image, LoadResult};
 use exif;
+use ::image::{AnimationDecoder, ImageDecoder};
+use crate::{bc1, crash, video};
+use crate::metadata::{GpsLocation, ExposureSettings};
+use crate::tween::{Tween, Fling};
+use log::warn;
 
   // Rotation that should be applied when displaying an image
   // to make it appear as it was taken.
+  // The mirrored variants flip the image horizontally first, then apply their rotation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ImageRotation { 
   None,
   NinetyCW,
   NinetyCCW,
-  OneEighty
+  OneEighty,
+  Mirrored,
+  MirroredNinetyCW,
+  MirroredNinetyCCW,
+  MirroredOneEighty
 }
 
+impl ImageRotation {
+    // number of 90 degree clockwise steps
+  pub fn rotation_steps(&self)->usize {
+    match self {
+      ImageRotation::None | ImageRotation::Mirrored => 0,
+      ImageRotation::NinetyCW | ImageRotation::MirroredNinetyCW => 1,
+      ImageRotation::OneEighty | ImageRotation::MirroredOneEighty => 2,
+      ImageRotation::NinetyCCW | ImageRotation::MirroredNinetyCCW => 3
+    }
+  }
+
+  pub fn is_mirrored(&self)->bool {
+    matches!(self, ImageRotation::Mirrored | ImageRotation::MirroredNinetyCW | ImageRotation::MirroredNinetyCCW | ImageRotation::MirroredOneEighty)
+  }
+
+  fn from_parts(mirrored: bool, rotation_steps: usize)->ImageRotation {
+    match (mirrored, rotation_steps % 4) {
+      (false, 0) => ImageRotation::None,
+      (false, 1) => ImageRotation::NinetyCW,
+      (false, 2) => ImageRotation::OneEighty,
+      (false, _) => ImageRotation::NinetyCCW,
+      (true, 0) => ImageRotation::Mirrored,
+      (true, 1) => ImageRotation::MirroredNinetyCW,
+      (true, 2) => ImageRotation::MirroredOneEighty,
+      (true, _) => ImageRotation::MirroredNinetyCCW
+    }
+  }
+
+    // the orientation after additionally rotating the displayed image by 90 degrees clockwise
+  pub fn rotated_cw(&self)->ImageRotation {
+    ImageRotation::from_parts(self.is_mirrored(), self.rotation_steps() + 1)
+  }
+
+  pub fn rotated_ccw(&self)->ImageRotation {
+    ImageRotation::from_parts(self.is_mirrored(), self.rotation_steps() + 3)
+  }
+
+    // the orientation after additionally flipping the displayed image horizontally.
+    // mirroring after a rotation is the same as mirroring first, then rotating the other way
+  pub fn flipped(&self)->ImageRotation {
+    ImageRotation::from_parts(!self.is_mirrored(), 4 - self.rotation_steps())
+  }
+
+    // the exif orientation tag values, 1 to 8
+  pub fn from_exif_orientation(orientation: u32)->Option<ImageRotation> {
+    match orientation {
+      1 => Some(ImageRotation::None),
+      2 => Some(ImageRotation::Mirrored),
+      3 => Some(ImageRotation::OneEighty),
+      4 => Some(ImageRotation::MirroredOneEighty),
+      5 => Some(ImageRotation::MirroredNinetyCCW),
+      6 => Some(ImageRotation::NinetyCW),
+      7 => Some(ImageRotation::MirroredNinetyCW),
+      8 => Some(ImageRotation::NinetyCCW),
+      _ => None
+    }
+  }
+
+  pub fn to_exif_orientation(self)->u32 {
+    match self {
+      ImageRotation::None => 1,
+      ImageRotation::Mirrored => 2,
+      ImageRotation::OneEighty => 3,
+      ImageRotation::MirroredOneEighty => 4,
+      ImageRotation::MirroredNinetyCCW => 5,
+      ImageRotation::NinetyCW => 6,
+      ImageRotation::MirroredNinetyCW => 7,
+      ImageRotation::NinetyCCW => 8
+    }
+  }
+}
+
+  // How far an image's data is scaled down from its full resolution
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageQuality {
+  Preview, // quickly decoded, low resolution stand-in
+  Scaled, // just large enough for the size it was decoded for
+  Full
+}
+
+  // previews are decoded at 1/8th of the full resolution, the smallest scale libjpeg-turbo supports
+const PREVIEW_SCALE_DIVISOR: usize = 8;
+  // exif thumbnails with an aspect ratio further off than this from the image's are padded, and not used
+const MAX_THUMBNAIL_ASPECT_DIFFERENCE: f64 = 0.02;
+
 pub struct ImageData {
   image: Image<u8>,
-  rotation: ImageRotation
+  rotation: ImageRotation,
+  full_size: [usize; 2], // size of the image at full resolution. Equal to the size of `image` for full quality data
+  pub quality: ImageQuality,
+  mip_levels: Vec<Image<u8>>, // downscaled versions of `image` for texture mipmaps, each half the size of the one before. Empty until generated
+  compressed_levels: Vec<Vec<u8>>, // `image` and its mip levels compressed to BC1 blocks, see `compress_mipmaps`. Empty unless compressed
+  icc_profile: Option<Vec<u8>>, // the color profile embedded in the image, as long as the pixel data hasn't been converted to srgb
+  frame_delay: Option<Duration>, // how long this frame is shown, if it is part of an animation
+  animation_frames: Vec<ImageData>, // the frames following this one, for animated images. each is a full frame of the same size
+  sharpness: Option<f64>, // see `measure_sharpness`. None until measured
+  pub location: Option<GpsLocation>, // where the image was taken, if it was read from the image's metadata
+  pub exposure: ExposureSettings // read along with the location
 }
 
 impl ImageData {
   pub fn load(path: &Path)->Result<ImageData, ImageLoadError> {
-    let img_res = stb_image::image::load(&path);
+      // the video module opens clips itself, see `load_video_still`
+    if video::is_video(path) {
+      return load_video_still(path);
+    }
+
+    ImageData::load_from_memory(path, &fs::read(path)?)
+  }
+
+    // like `load`, for an image file that was already read into memory. the path is only used for the file type, and for reading videos
+  pub fn load_from_memory(path: &Path, file: &[u8])->Result<ImageData, ImageLoadError> {
+    if is_animation_format(path) {
+      return load_animation(path, file);
+    }
+
+    if video::is_video(path) {
+      return load_video_still(path);
+    }
+
+      // libjpeg-turbo is a lot faster than stb_image for large jpegs. stb_image handles everything else,
+      // as well as jpegs libjpeg-turbo can't convert to rgb
+    if is_jpeg(path) {
+      let rotation = load_rotation(file)?;
+      match decode_jpeg_scaled(file, rotation, |full_size| full_size) {
+        Ok(data) => return Ok(data),
+        Err(error) => warn!("Couldn't decode {} with libjpeg-turbo, falling back to stb_image. {}", path.display(), error)
+      }
+    }
+
+    let img_res = stb_image::image::load_from_memory(file);
     let image = match img_res {
       LoadResult::ImageU8(img) => img,
       LoadResult::Error(msg) => return Err(ImageLoadError::StbImageError(msg)),
       LoadResult::ImageF32(_) => return Err(ImageLoadError::FloatImage),
     };
 
-    let img_file = std::fs::File::open(&path)?;
-    let exif_reader = exif::Reader::new(&mut std::io::BufReader::new(&img_file))?;
-    let orientation_field = exif_reader.get_field(exif::Tag::Orientation, false);
-
-    let rotation = orientation_field.map_or(ImageRotation::None, |orientation_field| {
-      match orientation_field.value.get_uint(0) { // orientation is a vec of u16 values. Only one is expected, values 1 to 8, for different rotations and flips
-        Some(1) => ImageRotation::None,
-        Some(3) => ImageRotation::OneEighty,
-        Some(6) => ImageRotation::NinetyCW,
-        Some(8) => ImageRotation::NinetyCCW,
-        Some(id) => {
-          println!("Orientation {} is not supported.", id); // 2, 4, 5, 7
-          ImageRotation::None
-        },
-        None => {
-          println!("Unknown orientation value {:?}", orientation_field);
-          ImageRotation::None
+    let rotation = load_rotation(file)?;
+    let full_size = [image.width, image.height];
+
+    Ok(ImageData {
+      image,
+      rotation,
+      full_size,
+      quality: ImageQuality::Full,
+      mip_levels: Vec::new(),
+      compressed_levels: Vec::new(),
+      icc_profile: None, // :todo: read embedded profiles for non-jpeg files
+      frame_delay: None,
+      animation_frames: Vec::new(),
+      sharpness: None,
+      location: None,
+      exposure: ExposureSettings::default()
+    })
+  }
+
+    // decodes a low resolution version of the image, using libjpeg-turbo's DCT scaling.
+    // this skips most of the decoding work, so it is much faster than a full load
+  pub fn load_preview(path: &Path)->Result<ImageData, ImageLoadError> {
+    ImageData::load_preview_from_memory(&fs::read(path)?)
+  }
+
+  pub fn load_preview_from_memory(file: &[u8])->Result<ImageData, ImageLoadError> {
+    let rotation = load_rotation(file)?;
+    let mut data = decode_jpeg_scaled(file, rotation, |full_size| {
+      [(full_size[0] / PREVIEW_SCALE_DIVISOR).max(1), (full_size[1] / PREVIEW_SCALE_DIVISOR).max(1)]
+    })?;
+    data.quality = ImageQuality::Preview;
+
+    Ok(data)
+  }
+
+    // the thumbnail most cameras embed in the exif data, about 160 pixels wide. it only takes reading the exif data and the image's header,
+    // so it can be shown even before a preview. None for images without one, and for thumbnails that some cameras pad to a different aspect ratio than the image
+  pub fn load_exif_thumbnail(file: &[u8])->Result<Option<ImageData>, ImageLoadError> {
+    let exif_data = match exif::Reader::new().read_from_container(&mut io::Cursor::new(file)) {
+      Ok(exif_data) => exif_data,
+      Err(exif::Error::NotFound(_)) => return Ok(None),
+      Err(error) => return Err(error.into())
+    };
+
+      // the thumbnail's offset is from the start of the exif data
+    let thumbnail_field = |tag| exif_data.get_field(tag, exif::In::THUMBNAIL).and_then(|field| field.value.get_uint(0)).map(|value| value as usize);
+    let (Some(offset), Some(length)) = (thumbnail_field(exif::Tag::JPEGInterchangeFormat), thumbnail_field(exif::Tag::JPEGInterchangeFormatLength)) else {
+      return Ok(None);
+    };
+    let Some(thumbnail) = exif_data.buf().get(offset..(offset + length)) else {
+      return Ok(None);
+    };
+
+      // the thumbnail is encoded the way the image is, so it uses the image's color profile
+    let (full_size, icc_profile) = catch_jpeg_errors(|| {
+      let decompress = mozjpeg::Decompress::with_markers(&[ICC_PROFILE_MARKER]).from_mem(file)?;
+      Ok(([decompress.width(), decompress.height()], read_icc_profile(&decompress)))
+    })?;
+    let mut data = decode_jpeg_scaled(thumbnail, rotation_of(&exif_data), |size| size)?;
+
+    let [width, height] = data.resolution();
+    let aspect_ratio = width as f64 / height as f64;
+    let full_aspect_ratio = full_size[0] as f64 / full_size[1] as f64;
+    if (aspect_ratio / full_aspect_ratio - 1.0).abs() > MAX_THUMBNAIL_ASPECT_DIFFERENCE {
+      return Ok(None);
+    }
+
+    data.full_size = full_size;
+    data.quality = ImageQuality::Preview;
+    data.icc_profile = icc_profile;
+    Ok(Some(data))
+  }
+
+    // decodes the image at the smallest DCT scale that still covers the image's area when it is fit into `fit_size`.
+    // falls back to decoding at full resolution if no scale is small enough
+  pub fn load_to_fit(path: &Path, fit_size: [u32; 2])->Result<ImageData, ImageLoadError> {
+    if video::is_video(path) {
+      return load_video_still(path);
+    }
+
+    ImageData::load_to_fit_from_memory(path, &fs::read(path)?, fit_size)
+  }
+
+  pub fn load_to_fit_from_memory(path: &Path, file: &[u8], fit_size: [u32; 2])->Result<ImageData, ImageLoadError> {
+      // only jpegs can be decoded scaled
+    if !is_jpeg(path) {
+      return ImageData::load_from_memory(path, file);
+    }
+
+    let rotation = load_rotation(file)?;
+    let needed_size = move |full_size: [usize; 2]| {
+      let scale = fit_scale(full_size, &rotation, fit_size);
+      [(full_size[0] as f64 * scale).ceil() as usize, (full_size[1] as f64 * scale).ceil() as usize]
+    };
+    decode_jpeg_scaled(file, rotation, needed_size)
+  }
+
+    // width and height of the pixel data
+  pub fn resolution(&self)->[usize; 2] {
+    [self.image.width, self.image.height]
+  }
+
+  pub fn rotation(&self)->ImageRotation {
+    self.rotation
+  }
+
+  pub fn full_size(&self)->[usize; 2] {
+    self.full_size
+  }
+
+    // the pixel data as rgba, mirrored and rotated by `rotation`. returns the rotated size, and the pixels
+  pub fn to_oriented_rgba(&self, rotation: ImageRotation)->([usize; 2], Vec<u8>) {
+    let Image { width, height, depth, ref data } = self.image;
+    let steps = rotation.rotation_steps();
+    let [out_width, out_height] = match steps {
+      1 | 3 => [height, width],
+      _ => [width, height]
+    };
+
+    let mut out = vec![255; out_width * out_height * 4];
+    for (y, row) in data.chunks_exact(width * depth).enumerate() {
+      for (x, pixel) in row.chunks_exact(depth).enumerate() {
+        let x = if rotation.is_mirrored() { width - 1 - x } else { x };
+          // clockwise 90 degree steps
+        let (out_x, out_y) = match steps {
+          0 => (x, y),
+          1 => (height - 1 - y, x),
+          2 => (width - 1 - x, height - 1 - y),
+          _ => (y, width - 1 - x)
+        };
+
+        let out_start = (out_y * out_width + out_x) * 4;
+        match pixel {
+          [r, g, b, ..] => out[out_start..(out_start + 3)].copy_from_slice(&[*r, *g, *b]),
+          _ => out[out_start..(out_start + 3)].fill(pixel[0])
         }
       }
-    });
+    }
+
+    ([out_width, out_height], out)
+  }
+
+    // builds the mip chain down to 1x1, each level averaging 2x2 blocks of the one above.
+    // compressed textures can't have their mipmaps generated on the gpu, so this happens on the loading threads instead
+  pub fn generate_mipmaps(&mut self) {
+    self.mip_levels.clear();
+    self.compressed_levels.clear();
+    loop {
+      let source = self.mip_levels.last().unwrap_or(&self.image);
+      if source.width == 1 && source.height == 1 {
+        break;
+      }
+      let level = downsample(source);
+      self.mip_levels.push(level);
+    }
+
+    for frame in &mut self.animation_frames {
+      frame.generate_mipmaps();
+    }
+  }
+
+    // compresses the pixel data and its generated mip levels for textures created with `TextureCompression::Cpu`,
+    // so that the upload doesn't leave the compression to the driver on the main thread
+  pub fn compress_mipmaps(&mut self) {
+    self.compressed_levels = (0..self.mip_level_count()).map(|level| {
+      let image = self.mip_level(level);
+      bc1::compress(&image.data, image.width, image.height, image.depth)
+    }).collect();
+
+    for frame in &mut self.animation_frames {
+      frame.compress_mipmaps();
+    }
+  }
+
+    // halves the pixel data until neither side is larger than `max_size`. generated mipmaps are discarded
+  pub fn shrink_to(&mut self, max_size: usize) {
+    while self.image.width.max(self.image.height) > max_size {
+      self.image = downsample(&self.image);
+    }
+    self.mip_levels.clear();
+    self.compressed_levels.clear();
+  }
+
+  fn mip_level_count(&self)->usize {
+    1 + self.mip_levels.len()
+  }
+
+    // memory held by the pixel data, including mip levels and animation frames
+  pub fn byte_size(&self)->usize {
+    self.image.data.len()
+      + self.mip_levels.iter().map(|level| level.data.len()).sum::<usize>()
+      + self.compressed_levels.iter().map(Vec::len).sum::<usize>()
+      + self.animation_frames.iter().map(|frame| frame.byte_size()).sum::<usize>()
+  }
+
+  fn mip_level(&self, level: usize)->&Image<u8> {
+    if level == 0 {
+      &self.image
+    } else {
+      &self.mip_levels[level - 1]
+    }
+  }
+
+    // rows `start..end` of the pixel data of mip level `level`, borrowed for uploading into a texture
+  fn raw_rows(&self, level: usize, start: usize, end: usize)->RawImage2d<'_, u8> {
+    let image = self.mip_level(level);
+    let row_len = image.width * image.depth;
+    RawImage2d {
+      data: Cow::Borrowed(&image.data[start * row_len..end * row_len]),
+      width: image.width as u32,
+      height: (end - start) as u32,
+      format: ClientFormat::U8U8U8
+    }
+  }
+
+    // compresses the pixel data into a jpeg. rotation and full size are not stored
+  pub fn encode_jpeg(&self, quality: f32)->Result<Vec<u8>, ImageLoadError> {
+    encode_rgb_jpeg([self.image.width, self.image.height], &self.image.data, quality)
+  }
+
+    // compresses the pixel data into a jpeg, mirrored and rotated by the image's rotation so it looks the way it's shown
+  pub fn encode_oriented_jpeg(&self, quality: f32)->Result<Vec<u8>, ImageLoadError> {
+    let (size, rgba) = self.to_oriented_rgba(self.rotation);
+    encode_rgba_jpeg(size, &rgba, quality)
+  }
+
+    // inverse of `encode_jpeg`, with the rotation, full size and color profile that weren't stored in the jpeg
+  pub fn decode_jpeg(bytes: &[u8], rotation: ImageRotation, full_size: [usize; 2], quality: ImageQuality, icc_profile: Option<Vec<u8>>)->Result<ImageData, ImageLoadError> {
+    let (_, size, data) = catch_jpeg_errors(|| {
+      let decompress = mozjpeg::Decompress::new_mem(bytes)?;
+      decompress_rgb(decompress, |size| size)
+    })?;
 
     Ok(ImageData {
-      image,
-      rotation
+      image: Image::new(size[0], size[1], 3, data),
+      rotation,
+      full_size,
+      quality,
+      mip_levels: Vec::new(),
+      compressed_levels: Vec::new(),
+      icc_profile,
+      frame_delay: None,
+      animation_frames: Vec::new(),
+      sharpness: None,
+      location: None,
+      exposure: ExposureSettings::default()
+    })
+  }
+
+  pub fn icc_profile(&self)->Option<&[u8]> {
+    self.icc_profile.as_deref()
+  }
+
+    // the variance of the laplacian of the image's brightness. blurry images have few strong edges, which gives them a low variance.
+    // only comparable between images decoded at the same resolution, like the frames of a burst
+  pub fn measure_sharpness(&mut self) {
+    let Image { width, height, depth, ref data } = self.image;
+    if width < 3 || height < 3 {
+      return;
+    }
+
+    let luma: Vec<f64> = data.chunks_exact(depth)
+      .map(|pixel| match pixel {
+        [r, g, b, ..] => 0.299 * *r as f64 + 0.587 * *g as f64 + 0.114 * *b as f64,
+        _ => pixel[0] as f64
+      })
+      .collect();
+
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for y in 1..(height - 1) {
+      for x in 1..(width - 1) {
+        let i = y * width + x;
+        let laplacian = luma[i - width] + luma[i + width] + luma[i - 1] + luma[i + 1] - 4.0 * luma[i];
+        sum += laplacian;
+        sum_squares += laplacian * laplacian;
+      }
+    }
+
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    self.sharpness = Some(sum_squares / count - mean * mean);
+  }
+
+    // a perceptual hash of the image, which differs in only a few bits for visually similar images.
+    // each bit is whether a cell of a 9x8 grid over the image is brighter than the cell to its right
+  pub fn difference_hash(&self)->u64 {
+    const GRID_WIDTH: usize = 9;
+    const GRID_HEIGHT: usize = 8;
+
+    let Image { width, height, depth, ref data } = self.image;
+      // an empty image has no cells to compare, and can't be split into rows
+    if width == 0 || height == 0 || depth == 0 {
+      return 0;
+    }
+    let mut brightness = [[0.0f64; GRID_WIDTH]; GRID_HEIGHT];
+    let mut pixel_counts = [[0u32; GRID_WIDTH]; GRID_HEIGHT];
+
+    for (y, row) in data.chunks_exact(width * depth).enumerate() {
+      let cell_y = y * GRID_HEIGHT / height;
+      for (x, pixel) in row.chunks_exact(depth).enumerate() {
+        let cell_x = x * GRID_WIDTH / width;
+        let luma = match pixel {
+          [r, g, b, ..] => 0.299 * *r as f64 + 0.587 * *g as f64 + 0.114 * *b as f64,
+          _ => pixel[0] as f64
+        };
+        brightness[cell_y][cell_x] += luma;
+        pixel_counts[cell_y][cell_x] += 1;
+      }
+    }
+
+    let mut hash = 0u64;
+    for (row, counts) in brightness.iter().zip(pixel_counts.iter()) {
+      let averages: Vec<_> = row.iter().zip(counts.iter()).map(|(&sum, &count)| sum / count.max(1) as f64).collect();
+      for pair in averages.windows(2) {
+        hash = (hash << 1) | (pair[0] > pair[1]) as u64;
+      }
+    }
+
+    hash
+  }
+
+    // converts the pixel data from the embedded color profile to srgb, which is what textures are displayed as.
+    // images without a profile are assumed to be srgb already. has to happen before mipmaps are generated
+  pub fn convert_to_srgb(&mut self)->Result<(), ImageLoadError> {
+    let icc_profile = match self.icc_profile.take() {
+      Some(icc_profile) => icc_profile,
+      None => return Ok(())
+    };
+
+    let source_profile = lcms2::Profile::new_icc(&icc_profile)?;
+    let srgb_profile = lcms2::Profile::new_srgb();
+    let transform = lcms2::Transform::<u8, u8>::new(&source_profile, lcms2::PixelFormat::RGB_8, &srgb_profile, lcms2::PixelFormat::RGB_8, lcms2::Intent::Perceptual)?;
+    transform.transform_in_place(&mut self.image.data);
+      // animations store their profile once, for all frames
+    for frame in &mut self.animation_frames {
+      transform.transform_in_place(&mut frame.image.data);
+    }
+
+    Ok(())
+  }
+}
+
+  // compresses rgb pixel data of `size` into a jpeg
+pub fn encode_rgb_jpeg(size: [usize; 2], rgb: &[u8], quality: f32)->Result<Vec<u8>, ImageLoadError> {
+  catch_jpeg_errors(|| {
+    let mut compress = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_RGB);
+    compress.set_size(size[0], size[1]);
+    compress.set_quality(quality);
+
+    let mut compress = compress.start_compress(Vec::new())?;
+    compress.write_scanlines(rgb)?;
+    compress.finish()
+  })
+}
+
+  // compresses rgba pixel data of `size` into a jpeg, dropping the alpha channel
+pub fn encode_rgba_jpeg(size: [usize; 2], rgba: &[u8], quality: f32)->Result<Vec<u8>, ImageLoadError> {
+  catch_jpeg_errors(|| {
+    let mut compress = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_EXT_RGBA);
+    compress.set_size(size[0], size[1]);
+    compress.set_quality(quality);
+
+    let mut compress = compress.start_compress(Vec::new())?;
+    compress.write_scanlines(rgba)?;
+    compress.finish()
+  })
+}
+
+  // the scale at which an image of `full_size` fits into `fit_size`, once rotated. Never more than 1, images aren't scaled up
+pub fn fit_scale(full_size: [usize; 2], rotation: &ImageRotation, fit_size: [u32; 2])->f64 {
+  let rotated_size = rotated(full_size, rotation);
+  let x_scale = fit_size[0] as f64 / rotated_size[0] as f64;
+  let y_scale = fit_size[1] as f64 / rotated_size[1] as f64;
+  x_scale.min(y_scale).min(1.0)
+}
+
+  // halves the image in both dimensions, by averaging 2x2 blocks of pixels. odd rows and columns at the edge are averaged with themselves.
+  // the average is taken in srgb space, which slightly darkens fine high contrast detail. at the sizes mipmaps are shown at this isn't noticeable
+fn downsample(source: &Image<u8>)->Image<u8> {
+  let width = (source.width / 2).max(1);
+  let height = (source.height / 2).max(1);
+  let depth = source.depth;
+
+  let mut data = Vec::with_capacity(width * height * depth);
+  for y in 0..height {
+    let rows = [(2 * y).min(source.height - 1), (2 * y + 1).min(source.height - 1)];
+    for x in 0..width {
+      let cols = [(2 * x).min(source.width - 1), (2 * x + 1).min(source.width - 1)];
+      for channel in 0..depth {
+        let sum: u32 = rows.iter()
+          .flat_map(|&row| cols.iter().map(move |&col| source.data[(row * source.width + col) * depth + channel] as u32))
+          .sum();
+        data.push(((sum + 2) / 4) as u8); // rounded
+      }
+    }
+  }
+
+  Image::new(width, height, depth, data)
+}
+
+fn rotated(size: [usize; 2], rotation: &ImageRotation)->[usize; 2] {
+  match rotation.rotation_steps() {
+    1 | 3 => [size[1], size[0]],
+    _ => [size[0], size[1]]
+  }
+}
+
+  // decodes the jpeg at path with libjpeg-turbo, at the smallest supported scale (n/8) that is at least as large as the size returned by `requested_size` for the image's full size
+fn decode_jpeg_scaled<F>(file: &[u8], rotation: ImageRotation, requested_size: F)->Result<ImageData, ImageLoadError>
+  where F: FnOnce([usize; 2])->[usize; 2] {
+
+  let (full_size, size, data, icc_profile) = catch_jpeg_errors(|| {
+    let decompress = mozjpeg::Decompress::with_markers(&[ICC_PROFILE_MARKER]).from_mem(file)?;
+    let icc_profile = read_icc_profile(&decompress);
+    let (full_size, size, data) = decompress_rgb(decompress, requested_size)?;
+    Ok((full_size, size, data, icc_profile))
+  })?;
+
+  let image = Image::new(size[0], size[1], 3, data);
+  let quality = if size == full_size {
+    ImageQuality::Full
+  } else {
+    ImageQuality::Scaled
+  };
+
+  Ok(ImageData {
+    image,
+    rotation,
+    full_size,
+    quality,
+    mip_levels: Vec::new(),
+    compressed_levels: Vec::new(),
+    icc_profile,
+    frame_delay: None,
+    animation_frames: Vec::new(),
+    sharpness: None,
+    location: None,
+    exposure: ExposureSettings::default()
+  })
+}
+
+const ICC_PROFILE_MARKER: mozjpeg::Marker = mozjpeg::Marker::APP(2);
+const ICC_PROFILE_SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+
+  // icc profiles are split over several APP2 markers, each starting with the signature, its sequence number (from 1) and the total number of markers
+fn read_icc_profile<R>(decompress: &mozjpeg::Decompress<R>)->Option<Vec<u8>> {
+  let header_len = ICC_PROFILE_SIGNATURE.len() + 2;
+  let mut chunks: Vec<_> = decompress.markers()
+    .filter(|marker| marker.marker == ICC_PROFILE_MARKER && marker.data.len() > header_len && marker.data.starts_with(ICC_PROFILE_SIGNATURE))
+    .map(|marker| (marker.data[ICC_PROFILE_SIGNATURE.len()], &marker.data[header_len..]))
+    .collect();
+
+  if chunks.is_empty() {
+    return None;
+  }
+  chunks.sort_unstable_by_key(|(sequence_number, _)| *sequence_number);
+
+  Some(chunks.into_iter().flat_map(|(_, data)| data.iter().copied()).collect())
+}
+
+fn decompress_rgb<R, F>(mut decompress: mozjpeg::Decompress<R>, requested_size: F)->io::Result<([usize; 2], [usize; 2], Vec<u8>)>
+  where R: io::BufRead,
+        F: FnOnce([usize; 2])->[usize; 2] {
+
+  let full_size = [decompress.width(), decompress.height()];
+  decompress.scale(scale_numerator(full_size, requested_size(full_size)));
+
+  let mut decompress = decompress.rgb()?;
+  let size = [decompress.width(), decompress.height()];
+  let pixels = decompress.read_scanlines::<u8>()?;
+  decompress.finish()?;
+
+  Ok((full_size, size, pixels))
+}
+
+fn is_animation_format(path: &Path)->bool {
+  path.extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| {
+      let ext_lowercase = ext.to_lowercase();
+      ext_lowercase == "gif" || ext_lowercase == "webp"
     })
+}
+
+  // frame delays below this are treated like browsers do, as meant to be shown at the default speed
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+  // decodes all frames of a gif or webp file. files with a single frame are loaded as still images
+fn load_animation(path: &Path, file: &[u8])->Result<ImageData, ImageLoadError> {
+  let reader = io::Cursor::new(file);
+  let is_gif = path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+
+  let (frames, icc_profile) = if is_gif {
+    let mut decoder = ::image::codecs::gif::GifDecoder::new(reader)?;
+    let icc_profile = decoder.icc_profile()?;
+    (decoder.into_frames().collect_frames()?, icc_profile)
+  } else {
+    let mut decoder = ::image::codecs::webp::WebPDecoder::new(reader)?;
+    let icc_profile = decoder.icc_profile()?;
+    if decoder.has_animation() {
+      (decoder.into_frames().collect_frames()?, icc_profile)
+    } else {
+      let image = ::image::DynamicImage::from_decoder(decoder)?.into_rgba8();
+      (vec![::image::Frame::new(image)], icc_profile)
+    }
+  };
+
+  let mut frames = frames.into_iter().map(|frame| {
+    let delay = Duration::from(frame.delay());
+    let delay = if delay < MIN_FRAME_DELAY { DEFAULT_FRAME_DELAY } else { delay };
+
+    let buffer = frame.into_buffer();
+    let (width, height) = (buffer.width() as usize, buffer.height() as usize);
+      // textures don't have an alpha channel, transparent areas are shown black. :todo: blend with the background color instead
+    let data = buffer.pixels()
+      .flat_map(|pixel| {
+        let [r, g, b, a] = pixel.0;
+        [r, g, b].map(|channel| ((channel as u32 * a as u32 + 127) / 255) as u8)
+      })
+      .collect();
+
+    ImageData {
+      image: Image::new(width, height, 3, data),
+      rotation: ImageRotation::None,
+      full_size: [width, height],
+      quality: ImageQuality::Full,
+      mip_levels: Vec::new(),
+      compressed_levels: Vec::new(),
+      icc_profile: None,
+      frame_delay: Some(delay),
+      animation_frames: Vec::new(),
+      sharpness: None,
+      location: None,
+      exposure: ExposureSettings::default()
+    }
+  });
+
+  let mut data = frames.next().ok_or(ImageLoadError::NoFrames)?;
+  data.animation_frames = frames.collect();
+  if data.animation_frames.is_empty() {
+    data.frame_delay = None;
+  }
+  data.icc_profile = icc_profile;
+
+  Ok(data)
+}
+
+  // the first frame of a video clip, shown until the clip is played
+fn load_video_still(path: &Path)->Result<ImageData, ImageLoadError> {
+  let frame = video::VideoDecoder::open(path)?.next_frame()?.ok_or(ImageLoadError::NoFrames)?;
+  let [width, height] = frame.size;
+
+  Ok(ImageData {
+    image: Image::new(width, height, 3, frame.data),
+    rotation: ImageRotation::None, // :todo: apply the track's rotation matrix, phones store portrait clips rotated
+    full_size: frame.size,
+    quality: ImageQuality::Full,
+    mip_levels: Vec::new(),
+    compressed_levels: Vec::new(),
+    icc_profile: None,
+    frame_delay: None,
+    animation_frames: Vec::new(),
+    sharpness: None,
+    location: None,
+    exposure: ExposureSettings::default()
+  })
+}
+
+  // libjpeg reports fatal errors by unwinding, which mozjpeg expects to be caught like this.
+  // callers only touch data local to the closure, so there is no broken state to observe after an unwind
+fn catch_jpeg_errors<T, F>(f: F)->Result<T, ImageLoadError>
+  where F: FnOnce()->io::Result<T> {
+
+  match crash::catch_unwind_quietly(f) {
+    Ok(result) => Ok(result?),
+    Err(panic_payload) => {
+      let message = panic_payload.downcast::<String>().map_or_else(|_| "Unknown error".to_string(), |message| *message);
+      Err(ImageLoadError::JpegError(message))
+    }
   }
 }
 
+  // the smallest numerator n for a scale of n/8 that scales full_size to at least requested_size on both axes
+fn scale_numerator(full_size: [usize; 2], requested_size: [usize; 2])->u8 {
+  let axis_numerator = |full: usize, requested: usize| (requested * 8).div_ceil(full.max(1));
+  let numerator = axis_numerator(full_size[0], requested_size[0]).max(axis_numerator(full_size[1], requested_size[1]));
+  numerator.clamp(1, 8) as u8
+}
+
+pub fn is_jpeg(path: &Path)->bool {
+  path.extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| {
+      let ext_lowercase = ext.to_lowercase();
+      ext_lowercase == "jpg" || ext_lowercase == "jpeg"
+    })
+}
+
+fn load_rotation(file: &[u8])->Result<ImageRotation, ImageLoadError> {
+  let exif_res = exif::Reader::new().read_from_container(&mut io::Cursor::new(file));
+  let exif_data = match exif_res {
+    Ok(exif_data) => exif_data,
+    Err(exif::Error::NotFound(_)) => return Ok(ImageRotation::None), // images without exif data are shown as they are
+    Err(error) => return Err(error.into())
+  };
+  Ok(rotation_of(&exif_data))
+}
+
+fn rotation_of(exif_data: &exif::Exif)->ImageRotation {
+  let orientation_field = exif_data.get_field(exif::Tag::Orientation, exif::In::PRIMARY);
+
+  orientation_field.map_or(ImageRotation::None, |orientation_field| {
+    match orientation_field.value.get_uint(0) { // orientation is a vec of u16 values. Only one is expected, values 1 to 8, for different rotations and flips
+      Some(id) => ImageRotation::from_exif_orientation(id).unwrap_or_else(|| {
+        warn!("Unknown orientation {}", id);
+        ImageRotation::None
+      }),
+      None => {
+        warn!("Unknown orientation value {:?}", orientation_field);
+        ImageRotation::None
+      }
+    }
+  })
+}
+
 pub struct ImageTexture {
-  pub texture: CompressedSrgbTexture2d,
-  pub size: [usize; 2],
-  pub rotation: ImageRotation
+  pub texture: GpuTexture, // the first frame, for animations
+  pub size: [usize; 2], // size of the full resolution image, regardless of the texture's resolution. Used for placement
+  pub rotation: ImageRotation,
+  pub quality: ImageQuality,
+  pub frame_delay: Option<Duration>, // how long the first frame is shown, for animations
+  pub animation_frames: Vec<AnimationFrame>, // the frames after the first, for animations
+  pub sharpness: Option<f64>,
+  pub location: Option<GpsLocation>,
+  pub exposure: ExposureSettings,
+  pub tiles: Option<ImageTiles> // for images larger than a texture can be. `texture` holds the largest mip level that fits then
+}
+
+pub struct AnimationFrame {
+  pub texture: GpuTexture,
+  pub delay: Duration
 }
 
 impl ImageTexture {
-  pub fn from_data<F: Facade>(data: ImageData, gl_ctx: &F)->Result<ImageTexture, TextureCreationError> {
-    let ImageData {
-      image, 
-      rotation
-    } = data;
-
-    let Image {
-      width,
-      height,
-      data,
-      ..
-    } = image;
+  pub fn is_animated(&self)->bool {
+    !self.animation_frames.is_empty()
+  }
+
+    // the frame to show `elapsed` after the animation started, looping, and how long until the frame after it is due.
+    // still images always return their texture, with no next frame
+  pub fn frame_at(&self, elapsed: Duration)->(&GpuTexture, Option<Duration>) {
+    let first_delay = match self.frame_delay {
+      Some(first_delay) if self.is_animated() => first_delay,
+      _ => return (&self.texture, None)
+    };
+
+    let frames = std::iter::once((&self.texture, first_delay))
+      .chain(self.animation_frames.iter().map(|frame| (&frame.texture, frame.delay)));
+    let total: Duration = frames.clone().map(|(_, delay)| delay).sum();
+
+    let mut remaining = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos().max(1)) as u64);
+    for (texture, delay) in frames {
+      if remaining < delay {
+        return (texture, Some(delay - remaining));
+      }
+      remaining -= delay;
+    }
+    (&self.texture, Some(first_delay)) // only reached through rounding
+  }
+
+  pub fn rotated_size(&self)->[usize; 2] {
+    rotated(self.size, &self.rotation)
+  }
+
+    // width and height of the image data, which may be lower than `size`. for tiled images that is of the tiles, not of the texture
+  pub fn resolution(&self)->[usize; 2] {
+    match self.tiles {
+      Some(ref tiles) => tiles.data.resolution(),
+      None => [self.texture.width() as usize, self.texture.height() as usize]
+    }
+  }
+
+    // estimate of the video memory used by the texture, its animation frames and tiles, see `texture_byte_size`
+  pub fn byte_size(&self)->usize {
+    texture_byte_size(&self.texture)
+      + self.animation_frames.iter().map(|frame| texture_byte_size(&frame.texture)).sum::<usize>()
+      + self.tiles.as_ref().map_or(0, ImageTiles::byte_size)
+  }
+}
+
+  // estimated video memory used by the texture, including its mipmaps, which add about a third. BC1 takes half a byte per pixel,
+  // uncompressed textures 4. compressed textures whose format the driver chose, without BC1 support, are counted like BC1
+fn texture_byte_size(texture: &GpuTexture)->usize {
+  let (width, height) = (texture.width() as usize, texture.height() as usize);
+  let level_size = match texture {
+    GpuTexture::Compressed(_) => bc1::compressed_len(width, height),
+    GpuTexture::Uncompressed(_) => width * height * 4
+  };
+  level_size * 4 / 3
+}
+
+  // How image textures are stored on the gpu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureCompression {
+  #[default]
+  Driver, // BC1 compressed by the driver as the pixel data is uploaded, which some drivers do slowly, stalling the frame
+  Uncompressed, // plain srgb textures. 8 times the video memory, and the quickest to upload
+  Cpu // BC1 compressed on the loading threads, see `ImageData::compress_mipmaps`, and uploaded as it is
+}
+
+  // The texture of an image, in the format `TextureCompression` asks for
+pub enum GpuTexture {
+  Compressed(CompressedSrgbTexture2d),
+  Uncompressed(SrgbTexture2d)
+}
+
+impl GpuTexture {
+  pub fn width(&self)->u32 {
+    self.dimensions().0
+  }
+
+  pub fn height(&self)->u32 {
+    self.dimensions().1
+  }
+
+  pub fn dimensions(&self)->(u32, u32) {
+    match self {
+      GpuTexture::Compressed(texture) => texture.dimensions(),
+      GpuTexture::Uncompressed(texture) => texture.dimensions()
+    }
+  }
+
+  pub fn get_mipmap_levels(&self)->u32 {
+    match self {
+      GpuTexture::Compressed(texture) => texture.get_mipmap_levels(),
+      GpuTexture::Uncompressed(texture) => texture.get_mipmap_levels()
+    }
+  }
+
+    // for use as a uniform, with glium's default sampling, like `Texture2d::sampled`
+  pub fn sampled(&self)->GpuTextureSampler<'_> {
+    GpuTextureSampler(self, SamplerBehavior::default())
+  }
+
+  fn write(&self, level: u32, rect: Rect, rows: RawImage2d<'_, u8>) {
+    match self {
+      GpuTexture::Compressed(texture) => texture.mipmap(level).unwrap().write(rect, rows), // levels are checked by the upload
+      GpuTexture::Uncompressed(texture) => texture.mipmap(level).unwrap().write(rect, rows)
+    }
+  }
+
+    // writes BC1 blocks into a compressed texture. fails for uncompressed textures, and compressed ones the driver chose the format of
+  fn write_bc1(&self, level: u32, rect: Rect, blocks: &[u8])->Result<(), ()> {
+    match self {
+      GpuTexture::Compressed(texture) => texture.mipmap(level).unwrap()
+        .write_compressed_data(rect, blocks, rect.width, rect.height, CompressedSrgbFormat::S3tcDxt1NoAlpha),
+      GpuTexture::Uncompressed(_) => Err(())
+    }
+  }
+}
+
+  // A `GpuTexture` with the sampling to draw it with, built like glium's `Sampler`
+pub struct GpuTextureSampler<'a>(&'a GpuTexture, SamplerBehavior);
+
+impl GpuTextureSampler<'_> {
+  pub fn minify_filter(mut self, filter: MinifySamplerFilter)->Self {
+    self.1.minify_filter = filter;
+    self
+  }
+
+  pub fn magnify_filter(mut self, filter: MagnifySamplerFilter)->Self {
+    self.1.magnify_filter = filter;
+    self
+  }
+
+  pub fn wrap_function(mut self, function: SamplerWrapFunction)->Self {
+    self.1.wrap_function = (function, function, function);
+    self
+  }
+}
+
+impl AsUniformValue for GpuTextureSampler<'_> {
+  fn as_uniform_value(&self)->UniformValue<'_> {
+    match self.0 {
+      GpuTexture::Compressed(texture) => UniformValue::CompressedSrgbTexture2d(texture, Some(self.1)),
+      GpuTexture::Uncompressed(texture) => UniformValue::SrgbTexture2d(texture, Some(self.1))
+    }
+  }
+}
+
+  // rows are uploaded in multiples of the 4x4 block size of S3TC, so that every chunk covers whole compressed blocks
+const UPLOAD_ROW_ALIGNMENT: usize = 4;
+
+  // An image whose data is uploaded into its texture a chunk of rows at a time, spread over several frames,
+  // so that uploading a large image doesn't stall the frame it arrives in.
+  // Images larger than the gpu's maximum texture size only have the mip levels that fit uploaded, the larger ones are tiled, see `ImageTiles`
+pub struct TextureUpload {
+  texture: GpuTexture,
+  data: ImageData,
+  bc1_blocks: bool, // whether the data's compressed levels are written into the texture, instead of its pixels
+  base_level: usize, // the mip level of the data that goes into the texture's first level. above 0 for tiled images
+  level_count: usize, // mip levels of the data to upload up to, the smaller of what the texture and the data have
+  level: usize, // the mip level of the data being uploaded
+  rows_uploaded: usize, // rows of `level` that are uploaded
+    // animations upload each of their other frames after the first, one after another
+  queued_frames: VecDeque<ImageData>,
+  frame_upload: Option<Box<TextureUpload>>,
+  uploaded_frames: Vec<AnimationFrame>
+}
+
+impl TextureUpload {
+  pub fn new<F: Facade>(mut data: ImageData, gl_ctx: &F, texture_pool: &mut TexturePool)->Result<TextureUpload, TextureCreationError> {
+      // the loading threads normally generated these already
+    if data.mip_levels.is_empty() {
+      data.generate_mipmaps();
+    }
+
+    let max_texture_size = gl_ctx.get_context().get_capabilities().max_texture_size.max(1) as usize;
+    let mut base_level = 0;
+    while base_level + 1 < data.mip_level_count() && data.mip_level(base_level).width.max(data.mip_level(base_level).height) > max_texture_size {
+      base_level += 1;
+    }
+    let base_image = data.mip_level(base_level);
+    let (width, height) = (base_image.width as u32, base_image.height as u32);
+
+    let bc1_supported = CompressedSrgbFormat::S3tcDxt1NoAlpha.is_supported(gl_ctx.get_context().as_ref());
+    let (texture, level) = match texture_pool.take(width, height) {
+      Some(texture) => (texture, base_level),
+      None if texture_pool.compression == TextureCompression::Uncompressed => {
+        let texture = SrgbTexture2d::empty_with_format(gl_ctx, SrgbFormat::U8U8U8U8, MipmapsOption::EmptyMipmaps, width, height)?;
+        (GpuTexture::Uncompressed(texture), base_level)
+      },
+      None if bc1_supported => {
+        let texture = CompressedSrgbTexture2d::empty_with_format(gl_ctx, CompressedSrgbFormat::S3tcDxt1NoAlpha, CompressedMipmapsOption::EmptyMipmaps, width, height)?;
+        (GpuTexture::Compressed(texture), base_level)
+      },
+      None => {
+          // an empty texture needs a specific compressed format. without one, the texture has to be created together with its full resolution data
+        let texture = CompressedSrgbTexture2d::with_mipmaps(gl_ctx, data.raw_rows(base_level, 0, height as usize), CompressedMipmapsOption::EmptyMipmaps)?;
+        (GpuTexture::Compressed(texture), base_level + 1)
+      }
+    };
+      // the pool only holds textures made the same way, so with bc1 supported the compressed ones are all BC1
+    let bc1_blocks = texture_pool.compression == TextureCompression::Cpu && bc1_supported && !data.compressed_levels.is_empty();
 
-    let raw_img = RawImage2d::from_raw_rgb(data, (width as u32, height as u32));
-    let texture = CompressedSrgbTexture2d::new(gl_ctx, raw_img)?;
-    let size = [width, height];
+    let level_count = data.mip_level_count().min(base_level + texture.get_mipmap_levels() as usize);
+    let queued_frames = std::mem::take(&mut data.animation_frames).into();
 
-    Ok(ImageTexture {
+    Ok(TextureUpload {
       texture,
-      rotation,
-      size
+      data,
+      bc1_blocks,
+      base_level,
+      level_count,
+      level,
+      rows_uploaded: 0,
+      queued_frames,
+      frame_upload: None,
+      uploaded_frames: Vec::new()
     })
   }
 
-  pub fn rotated_size(&self)->[usize; 2] {
-    match self.rotation {
-      ImageRotation::None | ImageRotation::OneEighty => [self.size[0], self.size[1]],
-      ImageRotation::NinetyCW | ImageRotation::NinetyCCW => [self.size[1], self.size[0]]
+    // memory held by the image data that is still to be uploaded. the pixels are only freed once the upload finishes
+  pub fn data_byte_size(&self)->usize {
+    self.data.byte_size()
+      + self.queued_frames.iter().map(|frame| frame.byte_size()).sum::<usize>()
+      + self.frame_upload.as_ref().map_or(0, |frame_upload| frame_upload.data_byte_size())
+  }
+
+    // uploads the next chunk of rows, of roughly `max_pixels` pixels. returns how many pixels were uploaded
+  pub fn upload_rows<F: Facade>(&mut self, gl_ctx: &F, texture_pool: &mut TexturePool, max_pixels: usize)->Result<usize, TextureCreationError> {
+    if self.level < self.level_count {
+      return Ok(self.upload_level_rows(max_pixels));
+    }
+
+    if self.frame_upload.is_none() {
+      match self.queued_frames.pop_front() {
+        Some(frame) => self.frame_upload = Some(Box::new(TextureUpload::new(frame, gl_ctx, texture_pool)?)),
+        None => return Ok(0)
+      }
+    }
+
+    let frame_upload = self.frame_upload.as_mut().unwrap(); // set above
+    let uploaded = frame_upload.upload_rows(gl_ctx, texture_pool, max_pixels)?;
+    if frame_upload.is_complete() {
+      let frame = self.frame_upload.take().unwrap().finish();
+      self.uploaded_frames.push(AnimationFrame {
+        texture: frame.texture,
+        delay: frame.frame_delay.unwrap_or(DEFAULT_FRAME_DELAY)
+      });
+    }
+
+    Ok(uploaded)
+  }
+
+  fn upload_level_rows(&mut self, max_pixels: usize)->usize {
+    let level_image = self.data.mip_level(self.level);
+    let (width, height) = (level_image.width, level_image.height);
+    let row_count = (max_pixels / width).max(1);
+    let row_count = row_count.div_ceil(UPLOAD_ROW_ALIGNMENT) * UPLOAD_ROW_ALIGNMENT;
+
+    let start = self.rows_uploaded;
+    let end = (start + row_count).min(height);
+
+    let rect = Rect { left: 0, bottom: start as u32, width: width as u32, height: (end - start) as u32 };
+    let texture_level = (self.level - self.base_level) as u32; // level is below level_count, which the texture has
+    let mut written = false;
+    if self.bc1_blocks {
+        // the rows start at a multiple of the block size, so they cover whole rows of blocks
+      let blocks = &self.data.compressed_levels[self.level][bc1::compressed_len(width, start)..bc1::compressed_len(width, end)];
+      written = self.texture.write_bc1(texture_level, rect, blocks).is_ok();
+      if !written {
+        warn!("Couldn't upload compressed texture data, uploading the pixels instead");
+        self.bc1_blocks = false;
+      }
+    }
+    if !written {
+      self.texture.write(texture_level, rect, self.data.raw_rows(self.level, start, end));
+    }
+
+    if end == height {
+      self.level += 1;
+      self.rows_uploaded = 0;
+        // the tiles of tiled images are made from the pixels, so the blocks aren't needed past the upload
+      if self.level == self.level_count {
+        self.data.compressed_levels = Vec::new();
+      }
+    } else {
+      self.rows_uploaded = end;
+    }
+
+    (end - start) * width
+  }
+
+  pub fn is_complete(&self)->bool {
+    self.level >= self.level_count && self.frame_upload.is_none() && self.queued_frames.is_empty()
+  }
+
+  pub fn finish(self)->ImageTexture {
+    let data = self.data;
+    ImageTexture {
+      texture: self.texture,
+      size: data.full_size(),
+      rotation: data.rotation(),
+      quality: data.quality,
+      frame_delay: data.frame_delay,
+      animation_frames: self.uploaded_frames,
+      sharpness: data.sharpness,
+      location: data.location,
+      exposure: data.exposure,
+        // the tiles need the image data that didn't fit into the texture
+      tiles: if self.base_level > 0 { Some(ImageTiles::new(data, self.base_level)) } else { None }
+    }
+  }
+
+    // gives the textures of an upload that is no longer needed back to the pool
+  pub fn abort(self, texture_pool: &mut TexturePool) {
+    texture_pool.recycle_texture(self.texture);
+    if let Some(frame_upload) = self.frame_upload {
+      frame_upload.abort(texture_pool);
+    }
+    for frame in self.uploaded_frames {
+      texture_pool.recycle_texture(frame.texture);
+    }
+  }
+}
+
+  // Keeps the textures of images that were dropped, so that new images of the same size can be uploaded into them
+  // instead of allocating a new texture each time. Most images in a folder come from the same camera, so sizes repeat a lot
+pub struct TexturePool {
+  free_textures: Vec<GpuTexture>, // oldest first
+  max_free_count: usize,
+  compression: TextureCompression // of the textures it creates
+}
+
+impl TexturePool {
+  pub fn new(max_free_count: usize, compression: TextureCompression)->TexturePool {
+    TexturePool {
+      free_textures: Vec::with_capacity(max_free_count),
+      max_free_count,
+      compression
+    }
+  }
+
+    // estimated video memory held by the unused textures
+  pub fn byte_size(&self)->usize {
+    self.free_textures.iter().map(texture_byte_size).sum()
+  }
+
+  fn take(&mut self, width: u32, height: u32)->Option<GpuTexture> {
+    let position = self.free_textures.iter().position(|texture| texture.dimensions() == (width, height))?;
+    Some(self.free_textures.remove(position))
+  }
+
+  pub fn recycle(&mut self, image: ImageTexture) {
+    self.recycle_texture(image.texture);
+    for frame in image.animation_frames {
+      self.recycle_texture(frame.texture);
+    }
+  }
+
+  fn recycle_texture(&mut self, texture: GpuTexture) {
+    self.free_textures.push(texture);
+    if self.free_textures.len() > self.max_free_count {
+      self.free_textures.remove(0);
+    }
+  }
+}
+
+  // tiles are this many pixels on each side, not counting their border
+const TILE_SIZE: usize = 1024;
+  // tiles repeat this many pixels of their neighbours around their edges, so that filtering doesn't leave seams where tiles meet
+const TILE_BORDER: usize = 1;
+  // how many uploaded tiles an image keeps. tiles out of view are freed beyond this, least recently in view first
+const MAX_TILE_TEXTURES: usize = 48;
+
+  // The mip levels of an image that are larger than the gpu's maximum texture size, cut into tiles.
+  // Only the tiles in view at the mip level the image is shown at are uploaded, a few per frame, see `PlacedImage::stream_tiles`.
+  // Until they are, the image's texture shows through where they are missing
+pub struct ImageTiles {
+  data: ImageData, // with its mip levels. the ones below `overview_level` are tiled
+  overview_level: usize, // the mip level the image's texture starts at
+  textures: HashMap<TileKey, SrgbTexture2d>, // the uploaded tiles
+  last_in_view: HashMap<TileKey, u64>, // the update each uploaded tile was last in view at
+  in_view: Vec<TileKey>, // the tiles in view as of the last update, closest to the center of the view first
+  update_count: u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct TileKey {
+  level: usize,
+  col: usize,
+  row: usize
+}
+
+impl ImageTiles {
+  fn new(data: ImageData, overview_level: usize)->ImageTiles {
+    ImageTiles {
+      data,
+      overview_level,
+      textures: HashMap::new(),
+      last_in_view: HashMap::new(),
+      in_view: Vec::new(),
+      update_count: 0
+    }
+  }
+
+    // estimated video memory used by the uploaded tiles, which are uncompressed, see `texture_byte_size`
+  pub fn byte_size(&self)->usize {
+    self.textures.values().map(|texture| texture.width() as usize * texture.height() as usize * 4 * 4 / 3).sum()
+  }
+
+    // the pixels of the tile within its level, as x and y ranges
+  fn tile_rect(&self, key: TileKey)->([usize; 2], [usize; 2]) {
+    let level_image = self.data.mip_level(key.level);
+    let x = [key.col * TILE_SIZE, ((key.col + 1) * TILE_SIZE).min(level_image.width)];
+    let y = [key.row * TILE_SIZE, ((key.row + 1) * TILE_SIZE).min(level_image.height)];
+    (x, y)
+  }
+
+    // finds the tiles in view for an image shown at `texels_per_pixel` full resolution pixels per physical screen pixel,
+    // and uploads those that are missing, about `max_pixels` pixels of them. returns whether tiles in view are still missing
+  fn update<F: Facade>(&mut self, transform: &UvTransform, view_size: &LogicalSize<f64>, texels_per_pixel: f64, gl_ctx: &F, max_pixels: usize)->Result<bool, TextureCreationError> {
+    self.update_count += 1;
+    self.in_view.clear();
+
+      // the largest mip level that is still at least as detailed as the screen, like `ImageDisplay::draw_image` picks it.
+      // the texture covers the levels from `overview_level` on by itself
+    let level = texels_per_pixel.max(1.0).log2().floor() as usize;
+    if level >= self.overview_level {
+      return Ok(false);
+    }
+
+    let level_image = self.data.mip_level(level);
+    let (width, height) = (level_image.width, level_image.height);
+    let view_center = [view_size.width / 2.0, view_size.height / 2.0];
+    let mut in_view = Vec::new();
+    for row in 0..height.div_ceil(TILE_SIZE) {
+      for col in 0..width.div_ceil(TILE_SIZE) {
+        let key = TileKey { level, col, row };
+        let (x, y) = self.tile_rect(key);
+        let corners = [[x[0], y[0]], [x[1], y[0]], [x[1], y[1]], [x[0], y[1]]]
+          .map(|[px, py]| transform.apply([px as f64 / width as f64, py as f64 / height as f64]));
+
+        let min_x = corners.iter().map(|pos| pos.x).fold(f64::INFINITY, f64::min);
+        let max_x = corners.iter().map(|pos| pos.x).fold(f64::NEG_INFINITY, f64::max);
+        let min_y = corners.iter().map(|pos| pos.y).fold(f64::INFINITY, f64::min);
+        let max_y = corners.iter().map(|pos| pos.y).fold(f64::NEG_INFINITY, f64::max);
+        if max_x < 0.0 || min_x > view_size.width || max_y < 0.0 || min_y > view_size.height {
+          continue;
+        }
+
+        let center_distance = ((min_x + max_x) / 2.0 - view_center[0]).hypot((min_y + max_y) / 2.0 - view_center[1]);
+        in_view.push((center_distance, key));
+      }
+    }
+    in_view.sort_unstable_by(|(a, _), (b, _)| a.total_cmp(b));
+    self.in_view = in_view.into_iter().map(|(_, key)| key).collect();
+
+    let mut remaining_pixels = max_pixels;
+    let mut missing = false;
+    for &key in &self.in_view {
+      self.last_in_view.insert(key, self.update_count);
+      if self.textures.contains_key(&key) {
+        continue;
+      }
+      if remaining_pixels == 0 {
+        missing = true;
+        continue;
+      }
+
+      let texture = self.upload_tile(key, gl_ctx)?;
+      remaining_pixels = remaining_pixels.saturating_sub(texture.width() as usize * texture.height() as usize);
+      self.textures.insert(key, texture);
+    }
+
+    if self.textures.len() > MAX_TILE_TEXTURES {
+      let mut out_of_view: Vec<_> = self.textures.keys()
+        .filter(|key| !self.in_view.contains(key))
+        .map(|&key| (self.last_in_view.get(&key).copied().unwrap_or(0), key))
+        .collect();
+      out_of_view.sort_unstable_by_key(|&(last_in_view, _)| last_in_view);
+      for (_, key) in out_of_view.into_iter().take(self.textures.len() - MAX_TILE_TEXTURES) {
+        self.textures.remove(&key);
+        self.last_in_view.remove(&key);
+      }
+    }
+
+    Ok(missing)
+  }
+
+    // the tile's pixels with its border, as far as the level reaches. tiles are uncompressed, so that they upload without the driver compressing them
+  fn upload_tile<F: Facade>(&self, key: TileKey, gl_ctx: &F)->Result<SrgbTexture2d, TextureCreationError> {
+    let level_image = self.data.mip_level(key.level);
+    let (x, y) = self.tile_rect(key);
+    let x = [x[0].saturating_sub(TILE_BORDER), (x[1] + TILE_BORDER).min(level_image.width)];
+    let y = [y[0].saturating_sub(TILE_BORDER), (y[1] + TILE_BORDER).min(level_image.height)];
+
+    let depth = level_image.depth;
+    let row_len = level_image.width * depth;
+    let mut data = Vec::with_capacity((x[1] - x[0]) * (y[1] - y[0]) * depth);
+    for row in y[0]..y[1] {
+      data.extend_from_slice(&level_image.data[row * row_len + x[0] * depth..row * row_len + x[1] * depth]);
     }
+
+    let raw = RawImage2d {
+      data: Cow::Owned(data),
+      width: (x[1] - x[0]) as u32,
+      height: (y[1] - y[0]) as u32,
+      format: ClientFormat::U8U8U8
+    };
+    SrgbTexture2d::with_mipmaps(gl_ctx, raw, MipmapsOption::AutoGeneratedMipmaps)
+  }
+
+    // the uploaded tiles in view, to be drawn over the image's texture. each with the part of the image it covers and the part of its texture that
+    // shows it, both in texture coordinates
+  pub fn uploaded_in_view(&self)->impl Iterator<Item=(&SrgbTexture2d, [[f64; 2]; 2], [[f32; 2]; 2])> + '_ {
+    self.in_view.iter().filter_map(move |key| {
+      let texture = self.textures.get(key)?;
+      let level_image = self.data.mip_level(key.level);
+      let (x, y) = self.tile_rect(*key);
+      let image_rect = [
+        [x[0] as f64 / level_image.width as f64, y[0] as f64 / level_image.height as f64],
+        [x[1] as f64 / level_image.width as f64, y[1] as f64 / level_image.height as f64]
+      ];
+
+      let border = [x[0].min(TILE_BORDER), y[0].min(TILE_BORDER)];
+      let (texture_width, texture_height) = (texture.width() as f32, texture.height() as f32);
+      let texture_rect = [
+        [border[0] as f32 / texture_width, border[1] as f32 / texture_height],
+        [(border[0] + x[1] - x[0]) as f32 / texture_width, (border[1] + y[1] - y[0]) as f32 / texture_height]
+      ];
+      Some((texture, image_rect, texture_rect))
+    })
+  }
+}
+
+  // Maps texture coordinates of an image, from 0 to 1 across the unrotated image, to where they are on screen
+#[derive(Debug, Clone, Copy)]
+pub struct UvTransform {
+  origin: LogicalPosition<f64>, // where 0, 0 is
+  u_axis: [f64; 2], // how far 1, 0 is from there
+  v_axis: [f64; 2] // and 0, 1
+}
+
+impl UvTransform {
+  pub fn apply(&self, uv: [f64; 2])->LogicalPosition<f64> {
+    LogicalPosition::new(
+      self.origin.x + uv[0] * self.u_axis[0] + uv[1] * self.v_axis[0],
+      self.origin.y + uv[0] * self.u_axis[1] + uv[1] * self.v_axis[1]
+    )
   }
 }
 
 pub struct PlacedImage {
   pub image: ImageTexture,
   pub pos: LogicalPosition<f64>,
-  pub scale: f64
+  pub scale: f64,
+  zoom: Tween, // magnification relative to the size in the display mode, at least 1
+  pan: [Tween; 2], // offset of the image center from the view center, while zoomed in
+  fling: Fling, // of the pan, after it was let go of
+  last_tick: Option<Instant> // of the zoom and pan animation, while it runs
+}
+
+  // how far images can be zoomed in, relative to their size in the display mode
+const MAX_ZOOM: f64 = 20.0;
+  // animations that weren't ticked for this long, e.g. of an image that was shown again, skip to where they were going
+const MAX_TICK_GAP: Duration = Duration::from_millis(100);
+  // zooms and pans this close to their target, relative and in logical pixels, arrive at it
+const ZOOM_TOLERANCE: f64 = 0.001;
+const PAN_TOLERANCE: f64 = 0.1;
+
+  // How images are sized to the view, before they are zoomed in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayMode {
+  Fit, // the whole image is visible
+  Fill, // the image covers the view, cropping what sticks out. shows how a crop to the view's aspect ratio would look, e.g. a vertical crop of a horizontal frame
+  ActualSize // one image pixel per display pixel
+}
+
+impl DisplayMode {
+  pub fn next(self)->DisplayMode {
+    match self {
+      DisplayMode::Fit => DisplayMode::Fill,
+      DisplayMode::Fill => DisplayMode::ActualSize,
+      DisplayMode::ActualSize => DisplayMode::Fit
+    }
+  }
+
+  pub fn label(self)->&'static str {
+    match self {
+      DisplayMode::Fit => "Fit",
+      DisplayMode::Fill => "Fill",
+      DisplayMode::ActualSize => "Actual size (100%)"
+    }
+  }
 }
 
 impl PlacedImage {
@@ -110,10 +1419,89 @@ impl PlacedImage {
     PlacedImage {
       image: image,
       pos: LogicalPosition::new(0.0, 0.0),
-      scale: 1.0
+      scale: 1.0,
+      zoom: Tween::new(1.0),
+      pan: [Tween::new(0.0), Tween::new(0.0)],
+      fling: Fling::default(),
+      last_tick: None
     }
   }
 
+    // whether the image is zoomed in, or on its way there
+  pub fn is_zoomed(&self)->bool {
+    self.zoom.target() > 1.0
+  }
+
+    // the zoom and pan the image is animating towards
+  pub fn zoom(&self)->(f64, LogicalPosition<f64>) {
+    (self.zoom.target(), LogicalPosition::new(self.pan[0].target(), self.pan[1].target()))
+  }
+
+    // e.g. to restore an earlier zoom, without animating to it. takes effect with the next placement
+  pub fn set_zoom(&mut self, zoom: f64, pan: LogicalPosition<f64>) {
+    self.zoom.jump(zoom.clamp(1.0, MAX_ZOOM));
+    self.pan[0].jump(pan.x);
+    self.pan[1].jump(pan.y);
+    self.fling.stop();
+  }
+
+    // animates to the zoom and pan, see `animate`
+  pub fn zoom_to(&mut self, zoom: f64, pan: LogicalPosition<f64>) {
+    self.zoom.set_target(zoom.clamp(1.0, MAX_ZOOM));
+    self.pan[0].set_target(pan.x);
+    self.pan[1].set_target(pan.y);
+    self.fling.stop();
+  }
+
+    // animates to the zoom multiplied by `factor`, keeping the point under `anchor` in place
+  pub fn zoom_at(&mut self, factor: f64, anchor: LogicalPosition<f64>, view_size: &LogicalSize<f64>) {
+    let (zoom, pan) = self.zoom();
+    let new_zoom = (zoom * factor).clamp(1.0, MAX_ZOOM);
+    let applied_factor = new_zoom / zoom;
+
+    let center_x = view_size.width / 2.0;
+    let center_y = view_size.height / 2.0;
+    let pos_x = anchor.x - (anchor.x - (center_x + pan.x)) * applied_factor;
+    let pos_y = anchor.y - (anchor.y - (center_y + pan.y)) * applied_factor;
+    self.zoom_to(new_zoom, LogicalPosition::new(pos_x - center_x, pos_y - center_y));
+  }
+
+    // moves the zoomed in image right away, and keeps it moving once the pan is let go of if it was moving fast.
+    // takes effect with the next placement, which keeps it covering the view
+  pub fn pan_by(&mut self, dx: f64, dy: f64, now: Instant) {
+    self.pan[0].shift(dx);
+    self.pan[1].shift(dy);
+    self.fling.push([dx, dy], now);
+    self.last_tick.get_or_insert(now);
+  }
+
+    // moves the zoom and pan on towards their targets, and flung pans along. returns whether they are still moving,
+    // the next placement takes them into account
+  pub fn animate(&mut self, now: Instant)->bool {
+    let dt = match self.last_tick {
+      Some(last_tick) if now - last_tick > MAX_TICK_GAP => {
+        let (zoom, pan) = self.zoom();
+        self.set_zoom(zoom, pan);
+        self.last_tick = None;
+        return false;
+      },
+      Some(last_tick) => now - last_tick,
+      None => Duration::ZERO
+    };
+
+    if let Some(delta) = self.fling.tick(dt, now) {
+      self.pan[0].shift(delta[0]);
+      self.pan[1].shift(delta[1]);
+    }
+    let zoom_tolerance = self.zoom.target() * ZOOM_TOLERANCE;
+    let zooming = self.zoom.tick(dt, zoom_tolerance);
+    let panning = [self.pan[0].tick(dt, PAN_TOLERANCE), self.pan[1].tick(dt, PAN_TOLERANCE)].contains(&true);
+
+    let moving = zooming || panning || self.fling.is_active();
+    self.last_tick = if moving { Some(now) } else { None };
+    moving
+  }
+
   pub fn scaled_size(&self)->LogicalSize<f64> {
     let rotated_size = self.image.rotated_size();
 
@@ -128,29 +1516,88 @@ impl PlacedImage {
                LogicalPosition::new(self.pos.x + scaled_size.width / 2.0, self.pos.y + scaled_size.height / 2.0),
                LogicalPosition::new(self.pos.x - scaled_size.width / 2.0, self.pos.y + scaled_size.height / 2.0)];
 
-    let rotation_steps = match self.image.rotation {
-      ImageRotation::None => 0,
-      ImageRotation::NinetyCW => 1,
-      ImageRotation::OneEighty => 2,
-      ImageRotation::NinetyCCW => 3
+    let mut uv = if self.image.rotation.is_mirrored() {
+      [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]] // tl and tr, as well as br and bl, swap places
+    } else {
+      [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
     };
-
-    let mut uv = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
-    uv.rotate_right(rotation_steps);
+    uv.rotate_right(self.image.rotation.rotation_steps());
 
     [(pos[0], uv[0]), (pos[1], uv[1]), (pos[2], uv[2]), (pos[3], uv[3])]
   }
 
-    // sets scale to fit into a rectangle of `size`, and centers itself within that rectangle
-  pub fn place_to_fit(&mut self, size: &LogicalSize<f64>, padding: f64) {
+    // see `UvTransform`. the corners of the image have texture coordinates of only 0 and 1, whatever its rotation
+  pub fn uv_transform(&self)->UvTransform {
+    let corners = self.corner_data();
+    let corner_at = |uv: [f32; 2]| corners.iter().find(|(_, corner_uv)| *corner_uv == uv).map(|&(pos, _)| pos).unwrap(); // every corner uv is there once
+    let origin = corner_at([0.0, 0.0]);
+    let u_end = corner_at([1.0, 0.0]);
+    let v_end = corner_at([0.0, 1.0]);
+    UvTransform {
+      origin,
+      u_axis: [u_end.x - origin.x, u_end.y - origin.y],
+      v_axis: [v_end.x - origin.x, v_end.y - origin.y]
+    }
+  }
+
+    // logical width the unrotated image is shown at, along its texture's u axis. the same as the scaled width unless rotated by 90 or 270 degrees
+  pub fn shown_texture_width(&self)->f64 {
+    let u_axis = self.uv_transform().u_axis;
+    u_axis[0].hypot(u_axis[1])
+  }
+
+    // uploads the tiles of a tiled image that are in `view_size` as the image is placed, about `max_pixels` pixels of them.
+    // returns whether tiles in view are still missing, to be called again on the next frame then
+  pub fn stream_tiles<F: Facade>(&mut self, view_size: &LogicalSize<f64>, scale_factor: f64, gl_ctx: &F, max_pixels: usize)->Result<bool, TextureCreationError> {
+    let transform = self.uv_transform();
+    let shown_width = self.shown_texture_width() * scale_factor; // in physical pixels
+    let Some(ref mut tiles) = self.image.tiles else {
+      return Ok(false);
+    };
+
+    let texels_per_pixel = tiles.data.resolution()[0] as f64 / shown_width;
+    tiles.update(&transform, view_size, texels_per_pixel, gl_ctx, max_pixels)
+  }
+
+    // whether `point` lies on the image as it is placed
+  pub fn contains(&self, point: LogicalPosition<f64>)->bool {
+    let scaled_size = self.scaled_size();
+    (point.x - self.pos.x).abs() <= scaled_size.width / 2.0 && (point.y - self.pos.y).abs() <= scaled_size.height / 2.0
+  }
+
+    // swaps in a better quality texture of the same image, keeping the current placement. returns the replaced texture
+  pub fn upgrade(&mut self, image: ImageTexture)->ImageTexture {
+    std::mem::replace(&mut self.image, image)
+  }
+
+    // sets scale to size the image to a rectangle of `size` as `mode` says, magnified by the zoom, and centers itself within that rectangle offset by the pan.
+    // fitted images keep `padding` free around them in total, split between the sides. actual size is one image pixel per physical pixel of a display with `scale_factor`.
+    // the pan is limited so that an image larger than the rectangle doesn't leave an edge of it uncovered
+  pub fn place(&mut self, size: &LogicalSize<f64>, padding: f64, mode: DisplayMode, scale_factor: f64) {
     let rotated_size = self.image.rotated_size();
 
-    let x_scale = (size.width - padding) / (rotated_size[0] as f64);
-    let y_scale = (size.height - padding) / (rotated_size[1] as f64);
-    self.scale = x_scale.min(y_scale);
+    let mode_scale = match mode {
+      DisplayMode::Fit => {
+        let x_scale = ((size.width - padding) / (rotated_size[0] as f64)).max(0.0);
+        let y_scale = ((size.height - padding) / (rotated_size[1] as f64)).max(0.0);
+        x_scale.min(y_scale)
+      },
+      DisplayMode::Fill => (size.width / (rotated_size[0] as f64)).max(size.height / (rotated_size[1] as f64)),
+      DisplayMode::ActualSize => 1.0 / scale_factor
+    };
+    self.scale = mode_scale * self.zoom.value();
+
+      // the pan is limited for the size the image is shown at, and the one it is animating to
+    let target_scale = mode_scale * self.zoom.target();
+    for (axis, pan) in self.pan.iter_mut().enumerate() {
+      let view_length = if axis == 0 { size.width } else { size.height };
+      let max_pan = ((rotated_size[axis] as f64 * self.scale - view_length) / 2.0).max(0.0);
+      let max_target_pan = ((rotated_size[axis] as f64 * target_scale - view_length) / 2.0).max(0.0);
+      pan.clamp(-max_pan, max_pan, -max_target_pan, max_target_pan);
+    }
 
-    self.pos.x = size.width / 2.0;
-    self.pos.y = size.height / 2.0;
+    self.pos.x = size.width / 2.0 + self.pan[0].value();
+    self.pos.y = size.height / 2.0 + self.pan[1].value();
   }
 }
 
@@ -158,8 +1605,13 @@ impl PlacedImage {
 pub enum ImageLoadError {
   FloatImage,
   StbImageError(String),
+  JpegError(String),
   IoError(io::Error),
-  ExifError(exif::Error)
+  ExifError(exif::Error),
+  ColorProfileError(lcms2::Error),
+  DecodeError(::image::ImageError),
+  VideoError(video::VideoError),
+  NoFrames
 }
 
 use std::fmt;
@@ -169,8 +1621,13 @@ impl fmt::Display for ImageLoadError {
     match self {
       FloatImage => write!(f, "stb_image returned an F32 image, which is not handled currently."),
       StbImageError(error) => write!(f, "stb_image load error: {}", error),
+      JpegError(error) => write!(f, "libjpeg-turbo error: {}", error),
       IoError(error) => write!(f, "File read error: {}", error),
       ExifError(error) => write!(f, "Could not read exif data: {}", error),
+      ColorProfileError(error) => write!(f, "Could not convert from the embedded color profile: {}", error),
+      DecodeError(error) => write!(f, "Could not decode the image: {}", error),
+      VideoError(error) => write!(f, "Could not read the video: {}", error),
+      NoFrames => write!(f, "The file doesn't contain any frames"),
     }
   }
 }
@@ -181,6 +1638,9 @@ impl Error for ImageLoadError {
     match self {
       IoError(error) => Some(error),
       ExifError(error) => Some(error),
+      ColorProfileError(error) => Some(error),
+      DecodeError(error) => Some(error),
+      VideoError(error) => Some(error),
       _ => None
     }
   }
@@ -196,4 +1656,22 @@ impl From<exif::Error> for ImageLoadError {
   fn from(error: exif::Error)->Self {
     ImageLoadError::ExifError(error)
   }
-}
\ No newline at end of file
+}
+
+impl From<lcms2::Error> for ImageLoadError {
+  fn from(error: lcms2::Error)->Self {
+    ImageLoadError::ColorProfileError(error)
+  }
+}
+
+impl From<::image::ImageError> for ImageLoadError {
+  fn from(error: ::image::ImageError)->Self {
+    ImageLoadError::DecodeError(error)
+  }
+}
+
+impl From<video::VideoError> for ImageLoadError {
+  fn from(error: video::VideoError)->Self {
+    ImageLoadError::VideoError(error)
+  }
+}
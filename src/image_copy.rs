@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+use crate::background_job::BackgroundJob;
+use crate::image::{ImageData, ImageLoadError, ImageRotation};
+
+  // An image decoded at full resolution for the clipboard, as it is displayed
+pub struct ClipboardImage {
+  pub size: [usize; 2],
+  pub rgba: Vec<u8>
+}
+
+  // Decodes an image for copying on a separate thread, since that takes too long for a frame.
+  // Textures only hold a compressed, often downscaled version of the image, so the file is decoded again
+pub struct ImageCopy {
+  pub path: PathBuf,
+  job: BackgroundJob<ClipboardImage, ImageLoadError>
+}
+
+impl ImageCopy {
+    // `rotation` replaces the file's own orientation, to copy the image the way it is shown
+  pub fn start(path: PathBuf, rotation: Option<ImageRotation>, color_management: bool)->ImageCopy {
+    let thread_path = path.clone();
+    let job = BackgroundJob::spawn("decoding", move |_| decode(&thread_path, rotation, color_management));
+
+    ImageCopy {
+      path,
+      job
+    }
+  }
+
+    // the decoded image, or the error message if decoding failed, once it is done
+  pub fn poll(&self)->Option<Result<ClipboardImage, String>> {
+    self.job.poll()
+  }
+}
+
+fn decode(path: &Path, rotation: Option<ImageRotation>, color_management: bool)->Result<ClipboardImage, ImageLoadError> {
+  let mut data = ImageData::load(path)?;
+  if color_management {
+    data.convert_to_srgb()?;
+  }
+
+  let (size, rgba) = data.to_oriented_rgba(rotation.unwrap_or(data.rotation()));
+  Ok(ClipboardImage { size, rgba })
+}
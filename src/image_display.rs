@@ -1,13 +1,15 @@
 use std::error::Error;
+use std::time::Duration;
 use glium::{
-  Display, Frame, Surface,
+  Display, Surface,
   VertexBuffer,
-  Program,
+  Program, DrawParameters, Blend, Rect,
   index::{NoIndices, PrimitiveType},
-  implement_vertex, uniform, uniforms::{MinifySamplerFilter, MagnifySamplerFilter}
+  texture::SrgbTexture2d,
+  implement_vertex, uniform, uniforms::{MinifySamplerFilter, MagnifySamplerFilter, SamplerWrapFunction}
 };
-use glium::glutin::dpi::LogicalSize;
-use crate::image::PlacedImage;
+use glium::glutin::dpi::{LogicalSize, LogicalPosition};
+use crate::image::{PlacedImage, UvTransform};
 
 #[derive(Copy, Clone, Debug)]
 struct Vertex {
@@ -16,15 +18,25 @@ struct Vertex {
 }
 implement_vertex!(Vertex, pos, tex_coord);
 
+  // images shown at less than this fraction of their texture's resolution are drawn with the downscaling program
+const DOWNSCALE_THRESHOLD: f64 = 0.5;
+  // the lanczos filter's footprint grows with this, so it is capped to keep the loop in the shader bounded.
+  // the mip level is chosen so that this is only exceeded once the smallest mip levels are reached
+const MAX_FILTER_SCALE: f64 = 2.0;
+
 pub struct ImageDisplay {
   program: glium::Program,
+  downscale_program: glium::Program, // lanczos resampling, for fine detail that aliases with plain trilinear filtering
   vert_buf: VertexBuffer<Vertex>,
   idx_buf: NoIndices,
   view_matrix: [[f32; 4]; 4], 
+  display_size: LogicalSize<f64>,
+  scale_factor: f64,
+  pub high_quality_downscaling: bool
 }
 
 impl ImageDisplay {
-  pub fn new(display: &Display, display_size: &LogicalSize<f64>)->Result<ImageDisplay, ImageDisplayCreationError> { //:todo: custom error
+  pub fn new(display: &Display, display_size: &LogicalSize<f64>, scale_factor: f64)->Result<ImageDisplay, ImageDisplayCreationError> { //:todo: custom error
     let vertex_buffer = VertexBuffer::empty_dynamic(display, 4)?;
     let index_buffer  = NoIndices(PrimitiveType::TriangleStrip);
 
@@ -32,6 +44,7 @@ impl ImageDisplay {
       #version 330
 
       uniform mat4 transform;
+      uniform vec2 offset; // from where the image is placed, during transitions
 
       in vec2 pos;
       in vec2 tex_coord;
@@ -39,7 +52,7 @@ impl ImageDisplay {
 
       void main() {
         f_tex_coord = tex_coord;
-        gl_Position = transform * vec4(pos, 0.0, 1.0);
+        gl_Position = transform * vec4(pos + offset, 0.0, 1.0);
       }
     "#;
 
@@ -47,45 +60,250 @@ impl ImageDisplay {
       #version 330
 
       uniform sampler2D img;
+      uniform float opacity;
 
       in vec2 f_tex_coord;
       out vec4 color;
 
       void main() {
-        color = texture(img, f_tex_coord);
+        color = vec4(texture(img, f_tex_coord).rgb, opacity);
+      }
+    "#;
+
+      // lanczos-2, evaluated in the given mip level at texel centers, where linear filtering returns the texel unchanged.
+      // srgb textures are converted to linear on sampling, so the filter works in linear light
+    let downscale_fragment_shader_src = r#"
+      #version 330
+
+      uniform sampler2D img;
+      uniform float lod; // mip level to resample from
+      uniform float filter_scale; // texels of that level per screen pixel, between 1 and 2
+      uniform float opacity;
+
+      in vec2 f_tex_coord;
+      out vec4 color;
+
+      const float PI = 3.14159265;
+      const int MAX_TAPS = 9; // 2 * lanczos radius * max filter scale, and one more for the window not being texel aligned
+
+      float lanczos2(float x) {
+        if (abs(x) < 0.0001) {
+          return 1.0;
+        }
+        if (abs(x) >= 2.0) {
+          return 0.0;
+        }
+        float px = PI * x;
+        return 2.0 * sin(px) * sin(px / 2.0) / (px * px);
+      }
+
+      void main() {
+        vec2 level_size = vec2(textureSize(img, int(lod)));
+        vec2 center = f_tex_coord * level_size - 0.5; // in texel units, with texel centers on integers
+        vec2 first_tap = ceil(center - 2.0 * filter_scale);
+
+        vec4 sum = vec4(0.0);
+        float weight_sum = 0.0;
+        for (int y = 0; y < MAX_TAPS; y++) {
+          for (int x = 0; x < MAX_TAPS; x++) {
+            vec2 tap = first_tap + vec2(x, y);
+            vec2 offset = (tap - center) / filter_scale;
+            float weight = lanczos2(offset.x) * lanczos2(offset.y);
+            if (weight != 0.0) {
+              sum += weight * textureLod(img, (tap + 0.5) / level_size, lod);
+              weight_sum += weight;
+            }
+          }
+        }
+
+          // the negative lobes can overshoot at hard edges
+        color = vec4(clamp(sum / weight_sum, 0.0, 1.0).rgb, opacity);
       }
     "#;
 
     let program = Program::from_source(display, vertex_shader_src, fragment_shader_src, None)?;
+    let downscale_program = Program::from_source(display, vertex_shader_src, downscale_fragment_shader_src, None)?;
 
     let mut image_display = ImageDisplay {
       program,
+      downscale_program,
       vert_buf: vertex_buffer,
       idx_buf: index_buffer,
-      view_matrix: [[0.0; 4]; 4]
+      view_matrix: [[0.0; 4]; 4],
+      display_size: *display_size,
+      scale_factor,
+      high_quality_downscaling: true
     };
-    image_display.set_display_size(display_size);
+    image_display.set_display_size(display_size, scale_factor);
 
     Ok(image_display)
   }
 
-  pub fn set_display_size(&mut self, size: &LogicalSize<f64>) {
+  pub fn set_display_size(&mut self, size: &LogicalSize<f64>, scale_factor: f64) {
     self.view_matrix = display_to_gl(size);
+    self.display_size = *size;
+    self.scale_factor = scale_factor;
+  }
+
+    // draws the image, or for animations the frame that is due `elapsed` after the animation started
+  pub fn draw_image<S: Surface>(&mut self, placed_image: &PlacedImage, elapsed: Duration, target: &mut S) {
+    self.draw_image_blended(placed_image, elapsed, &ImageBlend::OPAQUE, target);
+  }
+
+    // like `draw_image`, for an image placed within `viewport` rather than the whole view. what sticks out of the viewport is cut off
+  pub fn draw_image_in<S: Surface>(&mut self, placed_image: &PlacedImage, elapsed: Duration, viewport: &Viewport, target: &mut S) {
+    let blend = ImageBlend {
+      offset: [viewport.origin.x as f32, viewport.origin.y as f32],
+      clip: Some(*viewport),
+      ..ImageBlend::OPAQUE
+    };
+    self.draw_image_blended(placed_image, elapsed, &blend, target);
+  }
+
+    // like `draw_image`, faded and moved as `blend` says
+  pub fn draw_image_blended<S: Surface>(&mut self, placed_image: &PlacedImage, elapsed: Duration, blend: &ImageBlend, target: &mut S) {
+    self.write_vertices(placed_image);
+    let draw_parameters = self.draw_parameters(blend);
+
+    let (texture, _) = placed_image.image.frame_at(elapsed);
+    let sampler = texture.sampled()
+      .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+      .magnify_filter(MagnifySamplerFilter::Linear);
+
+      // texture pixels per physical screen pixel
+    let texels_per_pixel = texture.width() as f64 / (placed_image.shown_texture_width() * self.scale_factor);
+
+    if self.high_quality_downscaling && texels_per_pixel > 1.0 / DOWNSCALE_THRESHOLD {
+        // the largest mip level that is still at least as detailed as the screen, resampled from there
+      let max_lod = texture.get_mipmap_levels() - 1;
+      let lod = (texels_per_pixel.log2().floor() as u32).min(max_lod);
+      let filter_scale = (texels_per_pixel / 2f64.powi(lod as i32)).min(MAX_FILTER_SCALE);
+
+      let uniforms = uniform! {
+        transform: self.view_matrix,
+        offset: blend.offset,
+        img: sampler.wrap_function(SamplerWrapFunction::Clamp),
+        lod: lod as f32,
+        filter_scale: filter_scale as f32,
+        opacity: blend.opacity
+      };
+
+      target.draw(&self.vert_buf, self.idx_buf, &self.downscale_program, &uniforms, &draw_parameters).expect("Drawing image geometry failed.");
+    } else {
+      let uniforms = uniform! {
+        transform: self.view_matrix,
+        offset: blend.offset,
+        img: sampler,
+        opacity: blend.opacity
+      };
+
+      target.draw(&self.vert_buf, self.idx_buf, &self.program, &uniforms, &draw_parameters).expect("Drawing image geometry failed.");
+    }
+
+    if let Some(ref tiles) = placed_image.image.tiles {
+      self.draw_tiles(tiles.uploaded_in_view(), &placed_image.uv_transform(), blend, target);
+    }
+  }
+
+    // draws the uploaded tiles of a tiled image over its texture. they are at about the resolution they are shown at, so plain filtering does
+  fn draw_tiles<'a, S: Surface>(&mut self, tiles: impl Iterator<Item=(&'a SrgbTexture2d, [[f64; 2]; 2], [[f32; 2]; 2])>, transform: &UvTransform, blend: &ImageBlend, target: &mut S) {
+    let draw_parameters = self.draw_parameters(blend);
+    for (texture, image_rect, texture_rect) in tiles {
+      let [[u0, v0], [u1, v1]] = image_rect;
+      let [[s0, t0], [s1, t1]] = texture_rect;
+        // in triangle strip order
+      let verts: Vec<_> = [([u0, v0], [s0, t0]), ([u1, v0], [s1, t0]), ([u0, v1], [s0, t1]), ([u1, v1], [s1, t1])].iter()
+        .map(|&(uv, tex_coord)| {
+          let pos = transform.apply(uv);
+          Vertex { pos: [pos.x as f32, pos.y as f32], tex_coord }
+        })
+        .collect();
+      self.vert_buf.write(&verts);
+
+      let uniforms = uniform! {
+        transform: self.view_matrix,
+        offset: blend.offset,
+        img: texture.sampled()
+          .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+          .magnify_filter(MagnifySamplerFilter::Linear)
+          .wrap_function(SamplerWrapFunction::Clamp),
+        opacity: blend.opacity
+      };
+
+      target.draw(&self.vert_buf, self.idx_buf, &self.program, &uniforms, &draw_parameters).expect("Drawing image tile geometry failed.");
+    }
+  }
+
+    // draws a frame of a playing video in place of the placed image, which shows the clip's first frame
+  pub fn draw_video_frame<S: Surface>(&mut self, placed_image: &PlacedImage, frame: &SrgbTexture2d, target: &mut S) {
+    self.write_vertices(placed_image);
+
+    let uniforms = uniform! {
+      transform: self.view_matrix,
+      offset: ImageBlend::OPAQUE.offset,
+      img: frame.sampled()
+        .minify_filter(MinifySamplerFilter::Linear)
+        .magnify_filter(MagnifySamplerFilter::Linear),
+      opacity: ImageBlend::OPAQUE.opacity
+    };
+
+    target.draw(&self.vert_buf, self.idx_buf, &self.program, &uniforms, &Default::default()).expect("Drawing video geometry failed.");
   }
 
-  pub fn draw_image(&mut self, placed_image: &PlacedImage, target: &mut Frame) {
+  fn draw_parameters(&self, blend: &ImageBlend)->DrawParameters<'static> {
+      // scissor rects are in physical pixels, from the bottom left
+    let scissor = blend.clip.map(|clip| {
+      let left = (clip.origin.x * self.scale_factor).round();
+      let right = ((clip.origin.x + clip.size.width) * self.scale_factor).round();
+      let top = (clip.origin.y * self.scale_factor).round();
+      let bottom = ((clip.origin.y + clip.size.height) * self.scale_factor).round();
+      let display_height = (self.display_size.height * self.scale_factor).round();
+      Rect {
+        left: left.max(0.0) as u32,
+        bottom: (display_height - bottom).max(0.0) as u32,
+        width: (right - left).max(0.0) as u32,
+        height: (bottom - top).max(0.0) as u32
+      }
+    });
+
+    DrawParameters {
+      blend: if blend.opacity < 1.0 { Blend::alpha_blending() } else { Blend::default() },
+      scissor,
+      ..Default::default()
+    }
+  }
+
+  fn write_vertices(&mut self, placed_image: &PlacedImage) {
     let mut corner_data = placed_image.corner_data(); // ordered tl, tr, br, bl
     corner_data.swap(2, 3); // make the order tl, tr, br, bl, as needed for the triangle strip
     let verts: Vec<_> = corner_data.iter().map(|&(pos, tex_coord)| Vertex{pos: [pos.x as f32, pos.y as f32], tex_coord}).collect();
 
     self.vert_buf.write(&verts);
+  }
+}
 
-    let uniforms = uniform! {
-      transform: self.view_matrix,
-      img: placed_image.image.texture.sampled().minify_filter(MinifySamplerFilter::NearestMipmapLinear).magnify_filter(MagnifySamplerFilter::Linear)
-    };
+  // How an image is drawn beyond its placement, e.g. during a transition (see `Transition`) or in a cell of the compare view
+#[derive(Debug, Clone, Copy)]
+pub struct ImageBlend {
+  pub opacity: f32, // blended over what is drawn already below 1
+  pub offset: [f32; 2], // in logical pixels, from where the image is placed
+  pub clip: Option<Viewport> // nothing outside of it is drawn
+}
+
+impl ImageBlend {
+  pub const OPAQUE: ImageBlend = ImageBlend { opacity: 1.0, offset: [0.0, 0.0], clip: None };
+}
+
+  // A part of the view, in logical pixels
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+  pub origin: LogicalPosition<f64>, // top left
+  pub size: LogicalSize<f64>
+}
 
-    target.draw(&self.vert_buf, &self.idx_buf, &self.program, &uniforms, &Default::default()).expect("Drawing image geometry failed.");
+impl Viewport {
+  pub fn contains(&self, pos: LogicalPosition<f64>)->bool {
+    pos.x >= self.origin.x && pos.x < self.origin.x + self.size.width && pos.y >= self.origin.y && pos.y < self.origin.y + self.size.height
   }
 }
 
@@ -0,0 +1,137 @@
+use std::fs;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::image::{self, ImageData, ImageLoadError};
+use crate::image_handling::ImageSummary;
+
+  // How exported images are written, e.g. downscaled for web-size proofs
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+  pub max_edge: Option<u32>, // pixels on the long edge jpegs are scaled down to. exported as they are if None
+  pub quality: u8, // of the jpegs scaled down, from 1 to 100
+  pub color_management: bool
+}
+
+  // What an export writes, worked out before it starts, so it can be looked over before anything is written
+pub struct ExportPlan {
+  pub kind: ExportKind,
+  pub destination: PathBuf, // the new folder, or the archive. neither exists yet
+  pub files: Vec<PlannedFile>,
+  pub options: ExportOptions
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExportKind {
+  Folder,
+  Archive // a zip file, with the files in a folder of its name
+}
+
+pub struct PlannedFile {
+  pub summary: ImageSummary,
+  pub target: PathBuf // the path inside the destination, with the file name as it is on disk
+}
+
+impl PlannedFile {
+    // the target as the name of a zip entry, with / between folders. parts of file names that aren't valid unicode are replaced
+  pub fn entry_name(&self)->String {
+    self.target.iter().map(|part| part.to_string_lossy()).collect::<Vec<_>>().join("/")
+  }
+}
+
+impl ExportPlan {
+    // exports `images` into a new folder or archive in `folder`, called `name` or the first name after it that is new.
+    // with `numbered`, the files are prefixed with their position, so they sort in the order given
+  pub fn new(kind: ExportKind, folder: &Path, name: &str, images: Vec<ImageSummary>, numbered: bool, options: ExportOptions)->ExportPlan {
+    let destination = unused_path(folder, name, if kind == ExportKind::Archive { "zip" } else { "" });
+      // unzipping creates a folder of the archive's name for its files
+    let prefix = match kind {
+      ExportKind::Folder => PathBuf::new(),
+      ExportKind::Archive => destination.file_stem().map_or_else(|| PathBuf::from(name), PathBuf::from)
+    };
+
+      // padded to the same width, so the files sort by number in file managers too
+    let width = images.len().to_string().len().max(3);
+    let files = images.into_iter().enumerate().map(|(idx, summary)| {
+        // from the name on disk, `ImageSummary::file_name` escapes names that aren't valid unicode
+      let mut file_name = if numbered { OsString::from(format!("{:0width$}_", idx + 1, width = width)) } else { OsString::new() };
+      file_name.push(summary.path.file_name().unwrap_or_default());
+      PlannedFile { target: prefix.join(file_name), summary }
+    }).collect();
+
+    ExportPlan { kind, destination, files, options }
+  }
+}
+
+  // The contents an image is exported with: its file as it is, or for jpegs longer than the options' long edge, scaled down to it.
+  // The file is decoded like for showing it, at the smallest dct scale that covers the size, then scaled and compressed again.
+  // Scaled images are saved the way they are shown, with the orientation the user chose applied to the pixels.
+  // Only jpegs are scaled, other images and video clips are exported as they are
+pub fn export_contents(summary: &ImageSummary, options: &ExportOptions)->Result<Vec<u8>, ImageLoadError> {
+  let file = fs::read(&summary.path)?;
+  let max_edge = match options.max_edge {
+    Some(max_edge) if image::is_jpeg(&summary.path) => max_edge,
+    _ => return Ok(file)
+  };
+
+  let mut data = ImageData::load_to_fit_from_memory(&summary.path, &file, [max_edge, max_edge])?;
+  if data.full_size()[0].max(data.full_size()[1]) <= max_edge as usize {
+    return Ok(file);
+  }
+  if options.color_management {
+    data.convert_to_srgb()?;
+  }
+
+  let (size, rgba) = data.to_oriented_rgba(summary.orientation.unwrap_or(data.rotation()));
+  let (size, rgba) = scale_to_fit(size, &rgba, max_edge as usize);
+  image::encode_rgba_jpeg(size, &rgba, options.quality as f32)
+}
+
+  // `name` inside `folder`, with `extension` unless it is empty. if that exists already, the name is followed by the first number that makes it new
+pub fn unused_path(folder: &Path, name: &str, extension: &str)->PathBuf {
+  let file_name = |name: &str| if extension.is_empty() { name.to_string() } else { format!("{}.{}", name, extension) };
+  let mut path = folder.join(file_name(name));
+  let mut number = 2;
+  while path.exists() {
+    path = folder.join(file_name(&format!("{} {}", name, number)));
+    number += 1;
+  }
+  path
+}
+
+  // scales rgba pixels down so neither side is longer than `max_edge`. each pixel averages the pixels it covers.
+  // jpegs are decoded at the smallest dct scale above the size, so this only scales down by less than half
+fn scale_to_fit(size: [usize; 2], rgba: &[u8], max_edge: usize)->([usize; 2], Vec<u8>) {
+  let scale = max_edge as f64 / size[0].max(size[1]) as f64;
+  if scale >= 1.0 {
+    return (size, rgba.to_vec());
+  }
+  let out_size = [((size[0] as f64 * scale).round() as usize).max(1), ((size[1] as f64 * scale).round() as usize).max(1)];
+
+    // the range of source pixels covering pixel `out` of `out_len`, at least one pixel wide
+  let source_range = |out: usize, out_len: usize, len: usize| {
+    let start = out * len / out_len;
+    let end = ((out + 1) * len / out_len).max(start + 1);
+    start..end
+  };
+
+  let mut out = Vec::with_capacity(out_size[0] * out_size[1] * 4);
+  for out_y in 0..out_size[1] {
+    let rows = source_range(out_y, out_size[1], size[1]);
+    for out_x in 0..out_size[0] {
+      let columns = source_range(out_x, out_size[0], size[0]);
+      let mut sum = [0u32; 4];
+      for y in rows.clone() {
+        for x in columns.clone() {
+          let start = (y * size[0] + x) * 4;
+          for (channel_sum, &value) in sum.iter_mut().zip(&rgba[start..start + 4]) {
+            *channel_sum += value as u32;
+          }
+        }
+      }
+      let count = (rows.len() * columns.len()) as u32;
+      out.extend(sum.iter().map(|&channel_sum| ((channel_sum + count / 2) / count) as u8));
+    }
+  }
+
+  (out_size, out)
+}
@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::collections::HashMap;
+use crate::image::{self, ImageData, ImageLoadError};
+use crate::worker_pool::{WorkerPool, Worker, CancelToken};
+use glium::glutin::event_loop::{EventLoop, EventLoopClosed};
+use super::loader_pool::{self, LoadNotification};
+use super::preview_cache::PreviewCache;
+use log::{error, warn};
+
+  // how many of the 64 hash bits may differ for two images to still count as duplicates
+const MAX_HASH_DISTANCE: u32 = 4;
+
+pub struct HashRequest {
+  pub path: PathBuf,
+  pub idx: usize, // collection index of the image
+  pub token: CancelToken // the token of the duplicate search the request belongs to
+}
+
+pub struct HashWorker {
+  preview_cache: Option<Arc<PreviewCache>>
+}
+
+impl Worker for HashWorker {
+  type Input = HashRequest;
+  type Output = Option<u64>; // None if the search was cancelled
+  type Error = ImageLoadError;
+
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    let HashRequest { ref path, ref token, .. } = *input;
+
+      // a different folder was loaded in the meantime
+    if token.is_cancelled() {
+      return Ok(None);
+    }
+
+      // the hash only needs a few pixels, so jpegs are hashed from their previews, which are quick to decode or already cached
+    let data = if image::is_jpeg(path) {
+      loader_pool::load_preview(path, self.preview_cache.as_deref())?
+    } else {
+      ImageData::load(path)?
+    };
+    Ok(Some(data.difference_hash()))
+  }
+}
+
+pub type HashPool = WorkerPool<HashWorker>;
+pub fn new(size: usize, event_loop: &EventLoop<LoadNotification>, preview_cache: Option<Arc<PreviewCache>>)->HashPool {
+  let event_loop_proxy = event_loop.create_proxy();
+  WorkerPool::new(size, move |_| {
+    HashWorker {
+      preview_cache: preview_cache.clone()
+    }
+  }, move |request: HashRequest, result| {
+      // images that couldn't be hashed still count towards the search, as ones without duplicates
+    let hash = match result {
+      Ok(Some(hash)) => Some(hash),
+      Ok(None) => return,
+      Err(error) => {
+        warn!("Hash pool: Couldn't load {}, {}", request.path.display(), error);
+        None
+      }
+    };
+
+    if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::ImageHashed { hash, idx: request.idx, token: request.token }) {
+      error!("Hash pool: Event loop closed");
+    }
+  })
+}
+
+  // Hashes every image of a folder, and groups the ones that look the same.
+  // Hashing runs in the background, the groups are available once every image was hashed
+pub struct DuplicateSearch {
+  token: CancelToken,
+  hashes: HashMap<usize, u64>, // keys index into the collection
+  remaining: usize, // how many hash results are still outstanding
+  groups: Option<Vec<Vec<usize>>> // collection indices of images that look the same, each group in collection order
+}
+
+impl DuplicateSearch {
+  pub fn start<I>(images: I, hash_pool: &HashPool)->DuplicateSearch
+    where I: Iterator<Item=(usize, PathBuf)> {
+
+    let token = CancelToken::new();
+    let mut remaining = 0;
+    for (idx, path) in images {
+      hash_pool.submit(HashRequest { path, idx, token: token.clone() }, idx);
+      remaining += 1;
+    }
+
+    DuplicateSearch {
+      token,
+      hashes: HashMap::new(),
+      remaining,
+      groups: if remaining == 0 { Some(Vec::new()) } else { None }
+    }
+  }
+
+    // takes in a hash result. returns true if it was the last one.
+    // results of searches in previously loaded folders are ignored
+  pub fn receive(&mut self, hash: Option<u64>, idx: usize, token: &CancelToken)->bool {
+    if !token.same_as(&self.token) || self.groups.is_some() {
+      return false;
+    }
+
+    if let Some(hash) = hash {
+      self.hashes.insert(idx, hash);
+    }
+    self.remaining -= 1;
+
+    if self.remaining == 0 {
+      self.groups = Some(group_similar(&self.hashes));
+      true
+    } else {
+      false
+    }
+  }
+
+  pub fn groups(&self)->Option<&[Vec<usize>]> {
+    self.groups.as_deref()
+  }
+}
+
+impl Drop for DuplicateSearch {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
+
+  // groups images whose hashes are within MAX_HASH_DISTANCE of each other, also through other images of the group.
+  // compares every pair, which is fine for the size of a folder from a shoot
+fn group_similar(hashes: &HashMap<usize, u64>)->Vec<Vec<usize>> {
+  let mut hashes: Vec<_> = hashes.iter().map(|(&idx, &hash)| (idx, hash)).collect();
+  hashes.sort_unstable();
+
+    // union-find over positions in `hashes`
+  let mut parents: Vec<_> = (0..hashes.len()).collect();
+  fn root(parents: &mut [usize], mut node: usize)->usize {
+    while parents[node] != node {
+      parents[node] = parents[parents[node]];
+      node = parents[node];
+    }
+    node
+  }
+
+  for a in 0..hashes.len() {
+    for b in (a + 1)..hashes.len() {
+      if (hashes[a].1 ^ hashes[b].1).count_ones() <= MAX_HASH_DISTANCE {
+        let (root_a, root_b) = (root(&mut parents, a), root(&mut parents, b));
+        parents[root_a.max(root_b)] = root_a.min(root_b);
+      }
+    }
+  }
+
+  let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+  for (position, &(idx, _)) in hashes.iter().enumerate() {
+    let group_root = root(&mut parents, position);
+    groups.entry(group_root).or_default().push(idx);
+  }
+
+  let mut groups: Vec<_> = groups.into_values().filter(|group| group.len() > 1).collect();
+  groups.sort_unstable();
+  groups
+}
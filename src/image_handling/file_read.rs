@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use crate::worker_pool::CancelToken;
+use crate::platform;
+use memmap2::{Mmap, MmapOptions};
+use log::warn;
+
+  // how long reading an image file may take before the loader gives up on it
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+  // files that aren't downloaded from cloud storage yet need the time to download
+const PLACEHOLDER_READ_TIMEOUT: Duration = Duration::from_secs(60);
+  // how long to wait before each retry of a failed read
+const RETRY_DELAYS: [Duration; 3] = [Duration::from_millis(250), Duration::from_secs(1), Duration::from_secs(4)];
+
+  // Reads an image file into memory for the loader, so that it can be decoded without touching the disk again.
+  // Network drives can hang on a read for minutes, e.g. when the connection drops out, which would keep a loader thread busy for as long.
+  // The read happens on a thread of its own instead, which the loader stops waiting for after a timeout. A read that hangs for good only keeps that thread around.
+  // Reads that fail in ways that tend to pass, like a dropped connection, are retried a few times with growing delays in between.
+  // Cloud storage like iCloud Drive, Dropbox and OneDrive can keep placeholders of files that aren't downloaded, which the platform downloads once they are read.
+  // If `memory_mapped`, files are mapped into memory instead, which saves copying them and lets the system read ahead, see `map_file`
+pub fn read_file(path: &Path, token: &CancelToken, memory_mapped: bool)->Result<FileContents, FileReadError> {
+  let is_placeholder = fs::metadata(path).is_ok_and(|metadata| platform::is_cloud_placeholder(&metadata));
+  let timeout = if is_placeholder { PLACEHOLDER_READ_TIMEOUT } else { READ_TIMEOUT };
+    // placeholders download as they are read, which is better left to a plain read
+  let memory_mapped = memory_mapped && !is_placeholder;
+
+  let mut retry_delays = RETRY_DELAYS.iter();
+  loop {
+    if token.is_cancelled() {
+      return Err(FileReadError::Cancelled);
+    }
+
+    match read_with_timeout(path.to_path_buf(), timeout, memory_mapped) {
+      Ok(Some(file)) => return Ok(file),
+      Ok(None) if is_placeholder => return Err(FileReadError::NotDownloaded(timeout)),
+      Ok(None) => return Err(FileReadError::TimedOut(timeout)),
+      Err(error) => match retry_delays.next() {
+        Some(&delay) if is_transient(&error) => {
+          warn!("Couldn't read {}, retrying in {}ms. {}", path.display(), delay.as_millis(), error);
+          thread::sleep(delay);
+        },
+        _ => return Err(FileReadError::IoError(error))
+      }
+    }
+  }
+}
+
+  // None if the read didn't complete in time. the read thread is left to finish on its own then
+fn read_with_timeout(path: PathBuf, timeout: Duration, memory_mapped: bool)->io::Result<Option<FileContents>> {
+  let (sender, receiver) = mpsc::channel();
+  thread::spawn(move || {
+    let read_res = if memory_mapped { map_file(&path) } else { fs::read(&path).map(FileContents::Read) };
+      // the loader stopped waiting if the send fails
+    sender.send(read_res).ok();
+  });
+
+  match receiver.recv_timeout(timeout) {
+    Ok(read_res) => read_res.map(Some),
+    Err(RecvTimeoutError::Timeout) => Ok(None),
+    Err(RecvTimeoutError::Disconnected) => Err(io::Error::other("The read thread stopped"))
+  }
+}
+
+  // on linux the pages are read in right away, on the read thread, so drives that hang still run into the timeout. elsewhere they are read
+  // as the image is decoded. mapped reads are opt-in: a file that is cut short while it is mapped, e.g. by another program writing it, crashes the process
+fn map_file(path: &Path)->io::Result<FileContents> {
+  let file = File::open(path)?;
+    // empty files can't be mapped
+  if file.metadata()?.len() == 0 {
+    return Ok(FileContents::Read(Vec::new()));
+  }
+    // the mapping is only read from, and dropped along with the decoded file
+  let mmap = unsafe { MmapOptions::new().populate().map(&file)? };
+  Ok(FileContents::Mapped(mmap))
+}
+
+  // A file handed to the decoder, read into memory or mapped into it
+pub enum FileContents {
+  Read(Vec<u8>),
+  Mapped(Mmap)
+}
+
+impl Deref for FileContents {
+  type Target = [u8];
+
+  fn deref(&self)->&[u8] {
+    match self {
+      FileContents::Read(bytes) => bytes,
+      FileContents::Mapped(mmap) => mmap
+    }
+  }
+}
+
+  // errors of network drives and busy files, which may not happen again
+fn is_transient(error: &io::Error)->bool {
+  use io::ErrorKind::*;
+  matches!(error.kind(), Interrupted | WouldBlock | TimedOut | ConnectionReset | ConnectionAborted | NotConnected | NetworkDown | StaleNetworkFileHandle | ResourceBusy)
+}
+
+#[derive(Debug)]
+pub enum FileReadError {
+  IoError(io::Error),
+  TimedOut(Duration),
+  NotDownloaded(Duration), // a cloud storage placeholder that didn't download in time
+  Cancelled // the load was cancelled while waiting to retry
+}
+
+impl fmt::Display for FileReadError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::FileReadError::*;
+    match self {
+      IoError(error) => write!(f, "File read error: {}", error),
+      TimedOut(timeout) => write!(f, "Reading the file took longer than {}s, the drive may not be responding", timeout.as_secs()),
+      NotDownloaded(timeout) => write!(f, "The file isn't downloaded from cloud storage yet, and didn't download within {}s", timeout.as_secs()),
+      Cancelled => write!(f, "The read was cancelled"),
+    }
+  }
+}
+
+impl Error for FileReadError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::FileReadError::*;
+    match self {
+      IoError(error) => Some(error),
+      _ => None
+    }
+  }
+}
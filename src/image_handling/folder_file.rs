@@ -0,0 +1,97 @@
+use std::error::Error;
+use std::io::{self, Write};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use super::ratings_file::RatingsFile;
+
+  // A yaml file Fotoleine keeps about the loaded folder, like the rejects or the image orientations. It is saved where the ratings are, see `RatingsFile::folder_file_path`.
+  // Saving writes a temporary file next to it first, which then replaces the file, so an interrupted save leaves the old contents in place
+pub struct FolderFile {
+  file_path: PathBuf,
+  fallback_path: Option<PathBuf>, // the file in the folder itself, read until the file is saved in another location
+  file_name: &'static str // for error messages
+}
+
+impl FolderFile {
+  pub fn new(folder_path: &Path, file_name: &'static str, ratings_file: &RatingsFile)->FolderFile {
+    let file_path = ratings_file.folder_file_path(folder_path, file_name);
+    let fallback_path = Some(folder_path.join(file_name)).filter(|fallback_path| *fallback_path != file_path);
+    FolderFile {
+      file_path,
+      fallback_path,
+      file_name
+    }
+  }
+
+    // None if there is no file yet
+  pub fn load<T: DeserializeOwned>(&self)->Result<Option<T>, FolderFileError> {
+    let path = match self.fallback_path {
+      Some(ref fallback_path) if !self.file_path.exists() && fallback_path.is_file() => fallback_path,
+      _ => &self.file_path
+    };
+    if !path.exists() {
+      return Ok(None);
+    }
+    if path.is_dir() {
+      return Err(FolderFileError::PathIsDir(self.file_name));
+    }
+
+    let file = File::open(path).map_err(|error| FolderFileError::Open(self.file_name, error))?;
+    serde_yaml::from_reader(file).map(Some).map_err(|error| FolderFileError::Deserialize(self.file_name, error))
+  }
+
+  pub fn save<T: Serialize + ?Sized>(&self, contents: &T)->Result<(), FolderFileError> {
+    let s = serde_yaml::to_string(contents).map_err(|error| FolderFileError::Serialize(self.file_name, error))?;
+
+      // the hidden and central folders are only created once there is something to keep in them. the path always has a folder, it is joined onto one
+    let file_folder = self.file_path.parent().unwrap();
+    fs::create_dir_all(file_folder).map_err(|error| FolderFileError::Write(self.file_name, error))?;
+    let mut tmp_file = tempfile::NamedTempFile::new_in(file_folder).map_err(|error| FolderFileError::Write(self.file_name, error))?;
+    tmp_file.as_file_mut().write_all(s.as_bytes()).map_err(|error| FolderFileError::Write(self.file_name, error))?;
+    tmp_file.persist(&self.file_path).map_err(|error| FolderFileError::Persist(self.file_name, error))?;
+
+    Ok(())
+  }
+}
+
+  // each with the name of the file
+#[derive(Debug)]
+pub enum FolderFileError {
+  PathIsDir(&'static str),
+  Open(&'static str, io::Error),
+  Deserialize(&'static str, serde_yaml::Error),
+  Serialize(&'static str, serde_yaml::Error),
+  Write(&'static str, io::Error),
+  Persist(&'static str, tempfile::PersistError)
+}
+
+impl fmt::Display for FolderFileError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::FolderFileError::*;
+    match self {
+      PathIsDir(file_name) => write!(f, "The path to {} is a directory.", file_name),
+      Open(file_name, error) => write!(f, "Could not open {}: {}", file_name, error),
+      Deserialize(file_name, error) => write!(f, "Could not deserialize the contents of {}: {}", file_name, error),
+      Serialize(file_name, error) => write!(f, "Could not serialize the contents of {}: {}", file_name, error),
+      Write(file_name, error) => write!(f, "Could not write {}: {}", file_name, error),
+      Persist(file_name, error) => write!(f, "Could not persist the temporary file for {}: {}", file_name, error),
+    }
+  }
+}
+
+impl Error for FolderFileError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::FolderFileError::*;
+    match self {
+      PathIsDir(_) => None,
+      Open(_, error) => Some(error),
+      Deserialize(_, error) => Some(error),
+      Serialize(_, error) => Some(error),
+      Write(_, error) => Some(error),
+      Persist(_, error) => Some(error)
+    }
+  }
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+use crate::worker_pool::{WorkerPool, Worker, CancelToken};
+use glium::glutin::event_loop::{EventLoop, EventLoopProxy, EventLoopClosed};
+use super::loader_pool::LoadNotification;
+use super::loaded_dir::{self, FolderContents, ListedBatch, DirLoadError};
+use super::ratings_file::RatingsFile;
+use log::error;
+
+  // how often the scan wakes up the main thread to show its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+  // Lists a folder that is being loaded and reads its ratings, orientations and session in the background.
+  // Listing a folder with thousands of files on a network drive can take seconds, and the window should keep responding meanwhile.
+  // The images are handed on in batches as they are found: the first one replaces the loaded folder, and the ones after it are added to it,
+  // so the first images show before the listing is done. Runs on the scan pool, where the scan of a folder that is replaced stops
+pub struct FolderScan {
+  path: PathBuf,
+  token: CancelToken,
+  progress: Arc<ScanProgress>,
+  messages: Receiver<ScanMessage>
+}
+
+#[derive(Default)]
+struct ScanProgress {
+  listed: AtomicUsize, // files in the folder found so far
+  checked: AtomicUsize // of those, how many were checked for being images
+}
+
+  // What the scan found since it was last received from
+pub(super) enum ScanMessage {
+  Contents(Box<FolderContents>), // the first images, with the files Fotoleine keeps in the folder
+  Listed(ListedBatch), // images found after those
+  Done(Result<(), DirLoadError>)
+}
+
+pub struct ScanRequest {
+  path: PathBuf,
+  follow_symlinks: bool,
+  ratings_file: RatingsFile,
+  token: CancelToken,
+  progress: Arc<ScanProgress>,
+  messages: Sender<ScanMessage>
+}
+
+pub struct ScanWorker {
+  event_loop_proxy: EventLoopProxy<LoadNotification>
+}
+
+impl Worker for ScanWorker {
+  type Input = ScanRequest;
+  type Output = ();
+  type Error = DirLoadError;
+
+  fn execute(&mut self, request: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    let mut last_notified = Instant::now();
+    let on_progress = |listed, checked| {
+      request.progress.listed.store(listed, Ordering::Relaxed);
+      request.progress.checked.store(checked, Ordering::Relaxed);
+      if last_notified.elapsed() >= PROGRESS_INTERVAL {
+        last_notified = Instant::now();
+          // the event loop closing is reported once the scan completes
+        self.event_loop_proxy.send_event(LoadNotification::FolderScanProgress).ok();
+      }
+    };
+      // the receiver is gone if another folder was loaded in the meantime, the token stops the scan then
+    let send = |message| {
+      if request.messages.send(message).is_ok() {
+        self.event_loop_proxy.send_event(LoadNotification::FolderListed).ok();
+      }
+    };
+    loaded_dir::scan_folder(&request.path, request.follow_symlinks, &request.ratings_file, &request.token, on_progress,
+      |contents| send(ScanMessage::Contents(Box::new(contents))), |listed| send(ScanMessage::Listed(listed)))
+  }
+}
+
+pub type ScanPool = WorkerPool<ScanWorker>;
+pub fn new(size: usize, event_loop: &EventLoop<LoadNotification>)->ScanPool {
+  let worker_proxy = event_loop.create_proxy();
+  let event_loop_proxy = event_loop.create_proxy();
+  WorkerPool::new(size, move |_| {
+    ScanWorker {
+      event_loop_proxy: worker_proxy.clone()
+    }
+  }, move |request: ScanRequest, result| {
+    if request.messages.send(ScanMessage::Done(result)).is_err() {
+      return;
+    }
+    if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::FolderScanned) {
+      error!("Folder scan: Event loop closed");
+    }
+  })
+}
+
+impl FolderScan {
+  pub fn start(path: PathBuf, follow_symlinks: bool, ratings_file: RatingsFile, scan_pool: &ScanPool)->FolderScan {
+    let token = CancelToken::new();
+    let progress = Arc::new(ScanProgress::default());
+    let (sender, receiver) = mpsc::channel();
+    scan_pool.submit(ScanRequest {
+      path: path.clone(),
+      follow_symlinks,
+      ratings_file,
+      token: token.clone(),
+      progress: Arc::clone(&progress),
+      messages: sender
+    }, 0);
+
+    FolderScan {
+      path,
+      token,
+      progress,
+      messages: receiver
+    }
+  }
+
+  pub fn path(&self)->&Path {
+    &self.path
+  }
+
+    // how many files were listed so far, and how many of those were checked for being images
+  pub fn progress(&self)->(usize, usize) {
+    (self.progress.listed.load(Ordering::Relaxed), self.progress.checked.load(Ordering::Relaxed))
+  }
+
+    // what the scan found since the last call, in the order it was found. ends with `ScanMessage::Done` once the scan is over
+  pub(super) fn receive(&mut self)->Vec<ScanMessage> {
+    let mut messages = Vec::new();
+    loop {
+      match self.messages.try_recv() {
+        Ok(message) => {
+          let done = matches!(message, ScanMessage::Done(_));
+          messages.push(message);
+          if done {
+            break;
+          }
+        },
+        Err(TryRecvError::Empty) => break,
+        Err(TryRecvError::Disconnected) => {
+          messages.push(ScanMessage::Done(Err(DirLoadError::ScanStopped)));
+          break;
+        }
+      }
+    }
+    messages
+  }
+}
+
+impl Drop for FolderScan {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+use std::fs::{self, DirEntry};
+use std::ffi::OsString;
+use std::collections::{HashMap, HashSet};
+use crate::image;
+use crate::metadata::ImageMetadata;
+use super::integrity_scan::{self, Damage};
+use crate::worker_pool::CancelToken;
+use glium::glutin::event_loop::{EventLoopProxy, EventLoopClosed};
+use super::loader_pool::LoadNotification;
+use super::loaded_dir::file_is_relevant;
+use log::{error, warn};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+  // Looks for images added to a folder, e.g. by a tethering tool or while a memory card is copied into it.
+  // The folder is listed again every half second rather than watched through the platform's file events, which aren't reported for
+  // many network drives and card readers. Files that are still being written grow between two listings, they only arrive once their size settled.
+  // The metadata of arrivals is read and their files are checked on the watch thread as well, since the scans of the folder only cover the images it started with
+pub struct FolderWatch {
+  token: CancelToken,
+  arrivals: Receiver<Vec<(DirEntry, ImageMetadata, Option<Damage>)>>
+}
+
+impl FolderWatch {
+  pub fn start(path: PathBuf, known_names: HashSet<OsString>, follow_symlinks: bool, event_loop_proxy: EventLoopProxy<LoadNotification>)->FolderWatch {
+    let token = CancelToken::new();
+    let (sender, receiver) = mpsc::channel();
+
+    let thread_token = token.clone();
+    thread::spawn(move || {
+      let mut known_names = known_names;
+      let mut last_sizes: HashMap<OsString, u64> = HashMap::new(); // of the new files seen in the last listing
+      let mut listing_failed = false;
+
+      loop {
+        thread::sleep(POLL_INTERVAL);
+        if thread_token.is_cancelled() {
+          return;
+        }
+
+        let dir_iter = match fs::read_dir(&path) {
+          Ok(dir_iter) => {
+            listing_failed = false;
+            dir_iter
+          },
+          Err(error) => {
+              // e.g. while a network drive is disconnected. only logged once, the folder may come back
+            if !listing_failed {
+              warn!("Folder watch: Couldn't list {}: {}", path.display(), error);
+              listing_failed = true;
+            }
+            continue;
+          }
+        };
+
+        let mut arrived = Vec::new();
+        let mut sizes = HashMap::new();
+        for entry in dir_iter.filter_map(|entry_res| entry_res.ok()) {
+          let file_name = entry.file_name();
+          if known_names.contains(&file_name) || !file_is_relevant(&entry, follow_symlinks) {
+            continue;
+          }
+
+          let size = entry.metadata().map_or(0, |metadata| metadata.len());
+          if size > 0 && last_sizes.get(&file_name) == Some(&size) {
+            arrived.push(entry);
+          } else {
+            sizes.insert(file_name, size);
+          }
+        }
+        last_sizes = sizes;
+
+        if arrived.is_empty() {
+          continue;
+        }
+
+        arrived.sort_unstable_by_key(|entry| entry.file_name());
+        let arrivals: Vec<_> = arrived.into_iter().map(|entry| {
+          known_names.insert(entry.file_name());
+            // gifs, webps and videos don't carry exif data from a camera
+          let metadata = if image::is_jpeg(&entry.path()) {
+            ImageMetadata::read(&entry.path())
+          } else {
+            ImageMetadata::default()
+          };
+          let damage = integrity_scan::check_file(&entry.path());
+          (entry, metadata, damage)
+        }).collect();
+
+        if sender.send(arrivals).is_err() {
+          return;
+        }
+        if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::ImagesArrived) {
+          error!("Folder watch: Event loop closed");
+          return;
+        }
+      }
+    });
+
+    FolderWatch {
+      token,
+      arrivals: receiver
+    }
+  }
+
+    // the images that arrived since the last call, in the order of their names within each listing
+  pub fn receive(&mut self)->Vec<(DirEntry, ImageMetadata, Option<Damage>)> {
+    self.arrivals.try_iter().flatten().collect()
+  }
+}
+
+impl Drop for FolderWatch {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
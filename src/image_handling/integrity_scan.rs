@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
+use std::thread;
+use crate::platform;
+use crate::worker_pool::CancelToken;
+use glium::glutin::event_loop::{EventLoopProxy, EventLoopClosed};
+use super::loader_pool::LoadNotification;
+use log::error;
+
+  // Checks every file of a folder for damage in the background, so that files cut off by an interrupted transfer are found
+  // before the folder is delivered, not when the image is shown. Only the structure of the files is read, not their image data:
+  // the segments of jpegs up to their image data and the marker they end with, the header and trailer of gifs and webps,
+  // and the boxes of video clips. Like the metadata scan, it runs on a thread of its own, reading one file after the other
+pub struct IntegrityScan {
+  token: CancelToken,
+  paths: Sender<(usize, PathBuf)>, // of the files still to be checked, see `extend`
+  results: Receiver<(usize, Option<Damage>)>,
+  damaged: HashMap<usize, Damage>, // keys index into the collection
+  remaining: usize // how many files haven't been checked yet
+}
+
+  // what is wrong with a damaged file
+#[derive(Debug, Clone)]
+pub enum Damage {
+  Empty,
+  UnknownHeader(&'static str), // the file doesn't start like the format its extension names
+  Truncated, // the file ends before its contents do
+  Malformed, // the structure of the file is broken
+  Unreadable(String) // with the error
+}
+
+impl fmt::Display for Damage {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::Damage::*;
+    match self {
+      Empty => write!(f, "the file is empty"),
+      UnknownHeader(format) => write!(f, "the file doesn't start like a {} file", format),
+      Truncated => write!(f, "the file ends early, like after an interrupted copy"),
+      Malformed => write!(f, "the structure of the file is broken"),
+      Unreadable(error) => write!(f, "the file couldn't be read, {}", error),
+    }
+  }
+}
+
+impl IntegrityScan {
+  pub fn start(images: Vec<(usize, PathBuf)>, event_loop_proxy: EventLoopProxy<LoadNotification>)->IntegrityScan {
+    let token = CancelToken::new();
+    let (sender, receiver) = mpsc::channel();
+    let (paths, thread_paths) = mpsc::channel();
+    let remaining = images.len();
+    for image in images {
+      let _ = paths.send(image);
+    }
+
+    let thread_token = token.clone();
+    thread::spawn(move || {
+        // ends once the scan is dropped along with the sending end
+      for (idx, path) in thread_paths {
+          // a different folder was loaded in the meantime
+        if thread_token.is_cancelled() {
+          return;
+        }
+
+        if sender.send((idx, check_file(&path))).is_err() {
+          return;
+        }
+        if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::IntegrityChecked) {
+          error!("Integrity scan: Event loop closed");
+          return;
+        }
+      }
+    });
+
+    IntegrityScan {
+      token,
+      paths,
+      results: receiver,
+      damaged: HashMap::new(),
+      remaining
+    }
+  }
+
+    // takes in the results checked so far. returns true if the scan completed with them
+  pub fn receive(&mut self)->bool {
+    if self.is_complete() {
+      return false;
+    }
+
+    loop {
+      match self.results.try_recv() {
+        Ok((idx, damage)) => {
+          self.insert(idx, damage);
+          self.remaining -= 1;
+        },
+        Err(TryRecvError::Empty) => break,
+        Err(TryRecvError::Disconnected) => {
+          error!("Integrity scan: The scan thread stopped with {} files left", self.remaining);
+          self.remaining = 0;
+          break;
+        }
+      }
+    }
+
+    self.is_complete()
+  }
+
+    // for images the folder scan found after the scan started, which are checked after the ones before
+  pub fn extend(&mut self, images: Vec<(usize, PathBuf)>) {
+    self.remaining += images.len();
+    for image in images {
+      let _ = self.paths.send(image);
+    }
+  }
+
+  pub fn is_complete(&self)->bool {
+    self.remaining == 0
+  }
+
+    // for images added to the folder after the scan started, which were checked elsewhere
+  pub fn insert(&mut self, idx: usize, damage: Option<Damage>) {
+    if let Some(damage) = damage {
+      self.damaged.insert(idx, damage);
+    }
+  }
+
+    // None for files that are fine, or weren't checked yet
+  pub fn get(&self, idx: usize)->Option<&Damage> {
+    self.damaged.get(&idx)
+  }
+
+    // collection indexes of the damaged files found so far, sorted
+  pub fn damaged_idxs(&self)->Vec<usize> {
+    let mut idxs: Vec<_> = self.damaged.keys().copied().collect();
+    idxs.sort_unstable();
+    idxs
+  }
+}
+
+impl Drop for IntegrityScan {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
+
+  // None if nothing is wrong with the file, as far as its structure tells. files in cloud storage that aren't downloaded yet
+  // aren't checked, reading them would download them
+pub fn check_file(path: &Path)->Option<Damage> {
+  let len = match fs::metadata(path) {
+    Ok(metadata) if platform::is_cloud_placeholder(&metadata) => return None,
+    Ok(metadata) => metadata.len(),
+    Err(error) => return Some(Damage::Unreadable(error.to_string()))
+  };
+  if len == 0 {
+    return Some(Damage::Empty);
+  }
+
+  let ext_lowercase = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_lowercase()).unwrap_or_default();
+  let check = match ext_lowercase.as_str() {
+    "jpg" | "jpeg" => check_jpeg,
+    "gif" => check_gif,
+    "webp" => check_webp,
+    "mp4" | "mov" => check_video,
+    _ => return None
+  };
+  let res = File::open(path).and_then(|file| check(BufReader::new(file), len));
+  match res {
+    Ok(damage) => damage,
+    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Some(Damage::Truncated),
+    Err(error) => Some(Damage::Unreadable(error.to_string()))
+  }
+}
+
+const JPEG_SOI: u8 = 0xD8; // start of image
+const JPEG_EOI: u8 = 0xD9; // end of image
+const JPEG_SOS: u8 = 0xDA; // start of scan, the image data follows it
+
+  // walks the segments up to the first scan, then looks for the end of image marker. it is usually the last two bytes, but some cameras
+  // add data after it, e.g. the video of motion photos, so the rest of the file is searched if it isn't. the image data can't contain the marker,
+  // while the thumbnail in the exif data has one of its own, which is why the search starts after it
+fn check_jpeg(mut reader: BufReader<File>, len: u64)->io::Result<Option<Damage>> {
+  let mut soi = [0u8; 2];
+  reader.read_exact(&mut soi)?;
+  if soi != [0xFF, JPEG_SOI] {
+    return Ok(Some(Damage::UnknownHeader("JPEG")));
+  }
+
+  loop {
+    let mut marker = [0u8; 2];
+    reader.read_exact(&mut marker)?;
+    if marker[0] != 0xFF {
+      return Ok(Some(Damage::Malformed));
+    }
+    let mut marker = marker[1];
+    while marker == 0xFF { // fill bytes
+      marker = read_u8(&mut reader)?;
+    }
+    match marker {
+      JPEG_EOI => return Ok(Some(Damage::Malformed)), // no image data
+      0x01 | 0xD0..=0xD7 => continue, // markers without a segment
+      _ => {}
+    }
+    let segment_len = u16::from_be_bytes([read_u8(&mut reader)?, read_u8(&mut reader)?]);
+    if segment_len < 2 {
+      return Ok(Some(Damage::Malformed));
+    }
+    reader.seek_relative(i64::from(segment_len) - 2)?;
+    if reader.stream_position()? > len {
+      return Ok(Some(Damage::Truncated));
+    }
+    if marker == JPEG_SOS {
+      break;
+    }
+  }
+
+  let scan_start = reader.stream_position()?;
+  if len >= scan_start + 2 {
+    reader.seek(SeekFrom::Start(len - 2))?;
+    let mut end = [0u8; 2];
+    reader.read_exact(&mut end)?;
+    if end == [0xFF, JPEG_EOI] {
+      return Ok(None);
+    }
+  }
+
+  reader.seek(SeekFrom::Start(scan_start))?;
+  let mut buffer = vec![0u8; 64 * 1024];
+  let mut after_ff = false;
+  loop {
+    let read = reader.read(&mut buffer)?;
+    if read == 0 {
+      return Ok(Some(Damage::Truncated));
+    }
+    for &byte in &buffer[..read] {
+      if after_ff && byte == JPEG_EOI {
+        return Ok(None);
+      }
+      after_ff = byte == 0xFF;
+    }
+  }
+}
+
+  // gifs end with a trailer byte. some writers pad the file with zeroes after it
+fn check_gif(mut reader: BufReader<File>, len: u64)->io::Result<Option<Damage>> {
+  let mut header = [0u8; 6];
+  reader.read_exact(&mut header)?;
+  if &header != b"GIF87a" && &header != b"GIF89a" {
+    return Ok(Some(Damage::UnknownHeader("GIF")));
+  }
+
+  let tail_len = len.min(64);
+  reader.seek(SeekFrom::Start(len - tail_len))?;
+  let mut tail = vec![0u8; tail_len as usize];
+  reader.read_exact(&mut tail)?;
+  match tail.iter().rev().find(|&&byte| byte != 0) {
+    Some(0x3B) => Ok(None),
+    _ => Ok(Some(Damage::Truncated))
+  }
+}
+
+  // the riff header holds the length of the whole file
+fn check_webp(mut reader: BufReader<File>, len: u64)->io::Result<Option<Damage>> {
+  let mut header = [0u8; 12];
+  reader.read_exact(&mut header)?;
+  if &header[0..4] != b"RIFF" || &header[8..12] != b"WEBP" {
+    return Ok(Some(Damage::UnknownHeader("WebP")));
+  }
+  let riff_len = u32::from_le_bytes(header[4..8].try_into().unwrap());
+  if u64::from(riff_len) + 8 > len { Ok(Some(Damage::Truncated)) } else { Ok(None) }
+}
+
+  // walks the boxes at the top of mp4 and mov files, which have to fill the file. a clip without the box describing its tracks can't be played,
+  // it is usually missing because it is written last
+fn check_video(mut reader: BufReader<File>, len: u64)->io::Result<Option<Damage>> {
+  let mut position = 0;
+  let mut has_movie = false;
+  while position < len {
+    if len - position < 8 {
+      return Ok(Some(Damage::Truncated));
+    }
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let box_type = &header[4..8];
+    if position == 0 && !box_type.iter().all(|byte| byte.is_ascii_graphic() || *byte == b' ') {
+      return Ok(Some(Damage::UnknownHeader("video")));
+    }
+    has_movie |= box_type == b"moov";
+
+    let box_len = match u32::from_be_bytes(header[0..4].try_into().unwrap()) {
+      0 => len - position, // the box extends to the end of the file
+      1 => {
+        let mut large_len = [0u8; 8];
+        reader.read_exact(&mut large_len)?;
+        let large_len = u64::from_be_bytes(large_len);
+        if large_len < 16 {
+          return Ok(Some(Damage::Malformed));
+        }
+        reader.seek_relative((large_len - 16) as i64)?;
+        large_len
+      },
+      box_len if box_len < 8 => return Ok(Some(Damage::Malformed)),
+      box_len => {
+        reader.seek_relative(i64::from(box_len) - 8)?;
+        u64::from(box_len)
+      }
+    };
+    position += box_len;
+  }
+
+  if position > len || !has_movie { Ok(Some(Damage::Truncated)) } else { Ok(None) }
+}
+
+fn read_u8(reader: &mut impl Read)->io::Result<u8> {
+  let mut byte = [0u8; 1];
+  reader.read_exact(&mut byte)?;
+  Ok(byte[0])
+}
@@ -0,0 +1,33 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+use log::warn;
+
+  // A record of the changes made in a folder, like ratings, rejections and exports, one line each with the time it was made,
+  // to look up what was done when, and to reconstruct mistakes. Only ever appended to, so it also keeps what was changed back
+pub struct Journal {
+  file_path: PathBuf
+}
+
+impl Journal {
+  pub fn new(file_path: PathBuf)->Journal {
+    Journal { file_path }
+  }
+
+    // the change was made already, failing to record it only loses the record
+  pub fn record(&self, entry: &str) {
+    if let Err(error) = self.append(entry) {
+      warn!("Couldn't add to the journal {}: {}", self.file_path.display(), error);
+    }
+  }
+
+  fn append(&self, entry: &str)->Result<(), io::Error> {
+      // the hidden and central folders are only created once there is something to keep in them
+    if let Some(folder) = self.file_path.parent() {
+      fs::create_dir_all(folder)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&self.file_path)?;
+    writeln!(file, "{} {}", humantime::format_rfc3339_seconds(SystemTime::now()), entry)
+  }
+}
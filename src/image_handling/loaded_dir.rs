@@ -2,27 +2,106 @@ use std::error::Error;
 use std::io::{self, Write};
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::ffi::{OsStr, OsString};
 use std::fs::{self, File, DirEntry};
-use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet, BTreeSet};
+use std::convert::Infallible;
+use std::sync::mpsc;
 use glium::backend::Facade;
+use glium::glutin::dpi::LogicalPosition;
 use glium::texture::TextureCreationError;
-use crate::image::{ImageTexture, PlacedImage};
-use super::ImageHandlingServices;
+use crate::image::{ImageData, ImageTexture, ImageRotation, PlacedImage, ImageQuality, TexturePool, TextureUpload, fit_scale};
+use crate::worker_pool::{WorkerPool, Worker, CancelToken, Priority};
+use super::{ImageHandlingServices, MemoryUsage, Direction, NavigationPace, StepTimes};
+use super::loader_pool::{LoadRequest, LoadResult, LoadFailure, LoadError};
+use super::folder_file::{FolderFile, FolderFileError};
+use super::orientations::ImageOrientations;
+use super::rejects::ImageRejects;
+use super::selects::ImageSelects;
+use super::duplicates::DuplicateSearch;
+use super::thumbnails::{Thumbnails, Thumbnail};
+use super::preview_prefetch::PreviewPrefetch;
+use super::preview_cache::CompressedPreview;
+use super::metadata_scan::MetadataScan;
+use super::integrity_scan::{IntegrityScan, Damage};
+use super::folder_watch::FolderWatch;
+use super::session::{Session, SessionZoom};
+use super::ratings_file::RatingsFile;
+use super::ratings_library::RatingsLibrary;
+use super::journal::Journal;
+use super::statistics::{self, StatisticsSplit, RatingCounts};
+use crate::metadata::MetadataFilter;
+use crate::external_tools;
+use crate::{image, video};
+use log::{debug, warn};
+
+  // how many unused textures to keep around for reuse
+const TEXTURE_POOL_SIZE: usize = 4;
+  // how many steps in a row it takes to turn the direction the load set leans towards, so stepping back once to compare doesn't
+const DIRECTION_CHANGE_STEPS: usize = 2;
+  // how many images the compare view shows at once
+pub const MAX_PINNED: usize = 4;
+  // how many threads check the listed files for being images. that reads each file's metadata, which on network drives
+  // is mostly waiting for the server, so more threads than cores still help
+const ENTRY_CHECK_THREAD_COUNT: usize = 8;
+  // how often the listing of a folder hands on the images it found, for them to be shown while it goes on
+const BATCH_INTERVAL: Duration = Duration::from_millis(250);
 
   // A loaded directory of images we want to display
 pub struct LoadedDir {
+  path: PathBuf,
   collection: Vec<DirEntry>,
+  broken_link_count: usize, // links to images that don't exist, which were skipped
   name_to_idx: HashMap<String, usize>,
 
   active_idxs: Vec<usize>, // List of image indices currently in the list that the user traverses. Indexes into collection
   load_pivot: usize, // indexes into active_idxs
   current_idx: usize, // current show image, indexes into active_idxs
+  direction: Direction, // which way the load set leans, see `ImageLoadingPolicy::get_load_set`
+  opposite_steps: usize, // steps in a row against `direction`
+  step_times: StepTimes, // of the last steps through the images, for the loading policy to judge the pace from
+  pace: NavigationPace, // the pace the load set was last made for
 
   loaded_images: HashMap<usize, PlacedImage>, // all loaded images. keys index into collection
-  pending_loads: HashSet<usize>, // keys index into collection
+  load_set: Vec<usize>, // images the loading policy currently wants loaded, in priority order. Indexes into collection
+  last_shown: HashMap<usize, u64>, // show_count at the time each image was last shown. keys index into collection
+  show_count: u64, // incremented on every change of the shown image, to order images by how recently they were shown
+  texture_pool: TexturePool,
+  pending_loads: HashMap<usize, PendingLoad>, // the most recent load request per image. keys index into collection
+  failed_loads: HashMap<usize, LoadError>, // load error for images whose last load failed. keys index into collection
+  queued_uploads: Vec<(usize, ImageData)>, // received images waiting for their texture upload. indexes into collection
+  active_upload: Option<(usize, TextureUpload)>, // the texture upload in progress. indexes into collection
+  load_times: HashMap<usize, Duration>, // how long the worker took to load each image at its final quality, for profiling. keys index into collection
 
   ratings: ImageRatings,
-  rating_filter: Option<Rating>
+  filters: Filters,
+  raw_paths: HashMap<usize, PathBuf>, // the raw files of the same name as images in the folder, shot alongside them. keys index into collection
+  raws_without_image: Vec<PathBuf>, // raw files in the folder without an image of the same name, sorted
+  metadata_scan: MetadataScan,
+  integrity_scan: IntegrityScan,
+  selection: BTreeSet<usize>, // images that batch operations apply to. indexes into collection
+  selection_anchor: Option<usize>, // the image range selections start from, the most recently toggled. indexes into collection
+  bookmarks: BTreeSet<usize>, // images marked to come back to later. indexes into collection
+  pinned: Vec<usize>, // images shown next to each other in the compare view, in the order they were pinned. indexes into collection
+  orientations: ImageOrientations,
+  rejects: ImageRejects,
+  selects: ImageSelects,
+  journal: Journal, // of the changes made in the folder
+  session_file: FolderFile, // where `save_session` saves to
+  pending_session: Option<Session>, // restored once the folder is listed completely, see `finish_listing`
+  duplicate_search: Option<DuplicateSearch>,
+  thumbnails: Thumbnails,
+  preview_prefetch: PreviewPrefetch,
+  folder_watch: Option<FolderWatch>, // while the folder is watched for new images
+  follow_arrivals: bool, // whether new images are shown as they arrive
+  pending_metadata_filter: Option<MetadataFilter>, // restored from the session, set once the metadata scan completes
+  kept_zooms: HashMap<usize, SessionZoom> // of zoomed in images that aren't loaded, e.g. evicted or restored from the session. applied once they are loaded. indexes into collection
+}
+
+struct PendingLoad {
+  token: CancelToken,
+  preview_only: bool // see `LoadRequest::preview_only`. the preview ends the load then
 }
 
 fn offset_idx(idx: usize, max: usize, offset: i32)->usize {
@@ -34,64 +113,185 @@ fn offset_idx(idx: usize, max: usize, offset: i32)->usize {
   signed_idx.max(0).min(max - 1) as usize // clamp to [0, max-1]
 }
 
-impl LoadedDir {
-  pub fn new(path: &Path, services: &ImageHandlingServices)->Result<LoadedDir, DirLoadError> {
-    if !path.is_dir() {
-      return Err(DirLoadError::NotADirectory);
-    }
-
-    let dir_iter = fs::read_dir(path)?;
-
-    let mut collection: Vec<_> = dir_iter
-      .filter_map(|entry_res| entry_res.ok())
-      .filter(|entry| file_is_relevant(entry)) // filters for JPG files, and guarantees unicode filenames
-      .collect();
+  // What the scan of a folder found first, to create a `LoadedDir` from. See `scan_folder`
+pub(super) struct FolderContents {
+  path: PathBuf,
+  listed: ListedBatch,
+  name_to_idx: HashMap<String, usize>,
+  ratings: ImageRatings,
+  orientations: ImageOrientations,
+  rejects: ImageRejects,
+  selects: ImageSelects,
+  journal: Journal,
+  session: Session,
+  session_file: FolderFile
+}
 
-    if collection.len() == 0 {
-      return Err(DirLoadError::NoRelevantImages);
+  // lists the folder and reads the files Fotoleine keeps in it. everything that touches the disk happens here rather than in `LoadedDir::new`, so it can run off the main thread.
+  // the first images found go to `on_contents` along with those files, the ones found after them to `on_batch`, for `LoadedDir::add_listed`.
+  // `on_progress` is called with how many files were listed so far, and how many of those were checked for being images
+pub(super) fn scan_folder(path: &Path, follow_symlinks: bool, ratings_file: &RatingsFile, token: &CancelToken, on_progress: impl FnMut(usize, usize),
+  mut on_contents: impl FnMut(FolderContents), mut on_batch: impl FnMut(ListedBatch))->Result<(), DirLoadError> {
+
+  let mut found_images = false;
+  list_folder(path, follow_symlinks, token, on_progress, |listed| {
+    if found_images {
+      on_batch(listed);
+    } else if !listed.images.is_empty() {
+      on_contents(read_folder_files(path, ratings_file, listed)?);
+      found_images = true;
     }
+    Ok(())
+  })?;
 
-    collection.sort_unstable_by_key(|entry| entry.file_name());
+  if found_images { Ok(()) } else { Err(DirLoadError::NoRelevantImages) }
+}
 
-    let mut name_to_idx = HashMap::new();
-    for (idx, entry) in collection.iter().enumerate() {
-      let file_name = entry.file_name().into_string().unwrap();
-      name_to_idx.insert(file_name, idx);
-    }
+fn read_folder_files(path: &Path, ratings_file: &RatingsFile, listed: ListedBatch)->Result<FolderContents, DirLoadError> {
+  let name_to_idx = names_to_idxs(&listed.images);
+  let mut ratings = ImageRatings::new(path, ratings_file, &name_to_idx)?;
+  if ratings_file.library {
+    ratings.collect_in_library(path);
+  }
+  let orientations = ImageOrientations::new(path, ratings_file)?;
+  let rejects = ImageRejects::new(path, ratings_file)?;
+  let selects = ImageSelects::new(path, ratings_file)?;
+  let journal = Journal::new(ratings_file.journal_path(path));
+    // folders that were never opened before have no session, and start over
+  let session_file = FolderFile::new(path, "session.yaml", ratings_file);
+  let session = session_file.load().unwrap_or_else(|error| {
+    warn!("Couldn't restore the session of {}: {}", path.display(), error);
+    None
+  }).unwrap_or_default();
+
+  Ok(FolderContents {
+    path: path.to_path_buf(),
+    listed,
+    name_to_idx,
+    ratings,
+    orientations,
+    rejects,
+    selects,
+    journal,
+    session,
+    session_file
+  })
+}
+
+impl LoadedDir {
+  pub(super) fn new(contents: FolderContents, services: &ImageHandlingServices)->LoadedDir {
+    let FolderContents { path, listed, name_to_idx, ratings, orientations, rejects, selects, journal, session, session_file } = contents;
+    let ListedBatch { images: collection, raw_files, broken_link_count } = listed;
 
     let active_idxs = (0..collection.len()).collect();
     let current_idx = 0;
     let load_pivot = 0;
 
     let loaded_images = HashMap::with_capacity(services.loading_policy.max_loaded_image_count());
-    let pending_loads = HashSet::new();
+    let pending_loads = HashMap::new();
+    let failed_loads = HashMap::new();
 
-    let ratings = ImageRatings::new(&path, &name_to_idx)?;
+    let metadata_scan = MetadataScan::start(collection.iter().map(|entry| entry.path()).enumerate().collect(), services.event_loop_proxy.clone());
+    let integrity_scan = IntegrityScan::start(collection.iter().map(|entry| entry.path()).enumerate().collect(), services.event_loop_proxy.clone());
 
     let mut loaded_dir = LoadedDir {
+      path,
       collection,
+      broken_link_count,
       name_to_idx,
       
       active_idxs,
       load_pivot,
       current_idx,
+      direction: Direction::Forward,
+      opposite_steps: 0,
+      step_times: StepTimes::default(),
+      pace: NavigationPace::Stepping,
 
       loaded_images,
+      load_set: Vec::new(),
+      last_shown: HashMap::new(),
+      show_count: 0,
+      texture_pool: TexturePool::new(TEXTURE_POOL_SIZE, services.texture_compression),
       pending_loads,
+      failed_loads,
+      queued_uploads: Vec::new(),
+      active_upload: None,
+      load_times: HashMap::new(),
       ratings,
-      rating_filter: None
+      filters: Filters::default(),
+      raw_paths: HashMap::new(),
+      raws_without_image: Vec::new(),
+      metadata_scan,
+      integrity_scan,
+      selection: BTreeSet::new(),
+      selection_anchor: None,
+      bookmarks: BTreeSet::new(),
+      pinned: Vec::new(),
+      orientations,
+      rejects,
+      selects,
+      journal,
+      session_file,
+      pending_session: Some(session),
+      duplicate_search: None,
+      thumbnails: Thumbnails::new(),
+      preview_prefetch: PreviewPrefetch::new(),
+      folder_watch: None,
+      follow_arrivals: true,
+      pending_metadata_filter: None,
+      kept_zooms: HashMap::new()
     };
 
-    loaded_dir.update_loaded(services);
+    loaded_dir.pair_raw_files(raw_files);
+    let images = loaded_dir.collection.iter().map(|entry| entry.path()).enumerate();
+    loaded_dir.thumbnails.request(images.clone(), loaded_dir.current_collection_idx(), &services.thumbnail_pool);
+    loaded_dir.preview_prefetch.request(images, loaded_dir.current_collection_idx(), &services.prefetch_pool);
 
-    Ok(loaded_dir)
+    loaded_dir
   }
 
-  pub fn offset_current(&mut self, offset: i32, services: &ImageHandlingServices) {
-    self.current_idx = offset_idx(self.current_idx, self.active_idxs.len(), offset);
+    // stops at the first and last image, unless `wrap` is set to go around to the other end
+  pub fn offset_current(&mut self, offset: i32, wrap: bool, services: &ImageHandlingServices) {
+    let step_direction = if offset < 0 { Direction::Backward } else { Direction::Forward };
+    let jump = services.loading_policy.is_jump(offset);
+    if offset == 0 || step_direction == self.direction {
+      self.opposite_steps = 0;
+    } else {
+      self.opposite_steps += 1;
+      if self.opposite_steps >= DIRECTION_CHANGE_STEPS || jump {
+        debug!("Loading ahead {:?} now", step_direction);
+        self.direction = step_direction;
+        self.opposite_steps = 0;
+      }
+    }
+
+    self.current_idx = if wrap {
+      (self.current_idx as i32 + offset).rem_euclid(self.active_idxs.len() as i32) as usize
+    } else {
+      offset_idx(self.current_idx, self.active_idxs.len(), offset)
+    };
+    if jump {
+      self.load_pivot = self.current_idx;
+    }
+    let now = Instant::now();
+    self.step_times.record(now);
+    self.pace = services.loading_policy.pace(&self.step_times, now);
     self.update_loaded(services);
   }
 
+    // makes a new load set once the pace changed since the last step, e.g. when the user stopped flicking through the images.
+    // returns when this should be called again at the latest, if the pace may change without another step
+  pub fn update_pace(&mut self, services: &ImageHandlingServices, now: Instant)->Option<Instant> {
+    let pace = services.loading_policy.pace(&self.step_times, now);
+    if pace != self.pace {
+      debug!("Navigation pace changed to {:?}", pace);
+      self.pace = pace;
+      self.update_loaded(services);
+    }
+    services.loading_policy.next_pace_change(&self.step_times, now)
+  }
+
   pub fn current_collection_idx(&self)->usize {
     self.collection_idx(self.current_idx)
   }
@@ -100,42 +300,720 @@ impl LoadedDir {
     self.active_idxs[idx]
   }
 
+    // the images passing the filters in the order they are traversed, as collection indexes
+  pub fn active_collection_idxs(&self)->&[usize] {
+    &self.active_idxs
+  }
+
+    // the position of the shown image in `active_collection_idxs`
+  pub fn current_active_idx(&self)->usize {
+    self.current_idx
+  }
+
+    // the position of the image in `active_collection_idxs`, None if the filters hide it
+  pub fn active_position(&self, coll_idx: usize)->Option<usize> {
+    active_position(&self.active_idxs, self.filters.selects_only, coll_idx).ok()
+  }
+
+  pub fn show_active_idx(&mut self, idx: usize, services: &ImageHandlingServices) {
+    self.current_idx = idx.min(self.active_idxs.len() - 1);
+    self.update_loaded(services);
+  }
+
+    // the orientation the user gave the image, if they changed it
+  pub fn orientation(&self, coll_idx: usize)->Option<ImageRotation> {
+    self.orientations.get(&self.file_name_string(coll_idx))
+  }
+
   pub fn collection_image_count(&self)->usize {
     self.collection.len()
   }
 
+    // the raw file shot alongside the image, if there is one in the folder
+  pub fn raw_path(&self, coll_idx: usize)->Option<&Path> {
+    self.raw_paths.get(&coll_idx).map(PathBuf::as_path)
+  }
+
+    // how many jpegs have no raw file, and the raw files without an image, e.g. after an incomplete copy from a memory card.
+    // None if the folder has no raw files at all, since many cameras are set up to only save jpegs
+  pub fn incomplete_pairs(&self)->Option<(usize, &[PathBuf])> {
+    if self.raw_paths.is_empty() && self.raws_without_image.is_empty() {
+      return None;
+    }
+    let jpegs_without_raw = (0..self.collection.len()).filter(|&coll_idx| self.has_file_type(coll_idx, FileType::MissingRaw)).count();
+    Some((jpegs_without_raw, &self.raws_without_image))
+  }
+
+  pub fn broken_link_count(&self)->usize {
+    self.broken_link_count
+  }
+
+    // how many images pass the filters
+  pub fn active_image_count(&self)->usize {
+    self.active_idxs.len()
+  }
+
+    // the images passing the filters, in the order they are traversed
+  pub fn active_image_summaries(&self)->Vec<ImageSummary> {
+    self.active_idxs.iter().map(|&coll_idx| self.image_summary(coll_idx)).collect()
+  }
+
+    // the selects in their order, whether the filters hide them or not
+  pub fn select_summaries(&self)->Vec<ImageSummary> {
+    self.select_idxs().into_iter().map(|coll_idx| self.image_summary(coll_idx)).collect()
+  }
+
+  fn image_summary(&self, coll_idx: usize)->ImageSummary {
+    let file_name = self.file_name_string(coll_idx);
+    ImageSummary {
+      path: self.collection_path(coll_idx),
+      rating: self.ratings.get_rating(&file_name),
+      orientation: self.orientations.get(&file_name),
+      file_name
+    }
+  }
+
+  pub fn path(&self)->&Path {
+    &self.path
+  }
+
   pub fn current_image(&self)->Option<&PlacedImage> {
     self.loaded_images.get(&self.current_collection_idx())
   }
 
-  pub fn current_image_mut(&mut self)->Option<&mut PlacedImage> {
-    self.loaded_images.get_mut(&self.current_collection_idx())
+    // any loaded image, e.g. the one shown before the current one
+  pub fn loaded_image(&self, coll_idx: usize)->Option<&PlacedImage> {
+    self.loaded_images.get(&coll_idx)
+  }
+
+  pub fn loaded_image_mut(&mut self, coll_idx: usize)->Option<&mut PlacedImage> {
+    self.loaded_images.get_mut(&coll_idx)
   }
 
   pub fn current_path(&self)->PathBuf {
-    self.collection[self.current_collection_idx()].path()
+    self.collection_path(self.current_collection_idx())
+  }
+
+  pub fn collection_path(&self, coll_idx: usize)->PathBuf {
+    self.collection[coll_idx].path()
+  }
+
+    // the error of the last load of the current image, if that load failed
+  pub fn current_load_failure(&self)->Option<&LoadError> {
+    self.failed_loads.get(&self.current_collection_idx())
+  }
+
+    // re-submits the load of the current image if its last load failed. returns whether a retry was submitted
+  pub fn retry_current(&mut self, services: &ImageHandlingServices)->bool {
+    let coll_idx = self.current_collection_idx();
+    if self.failed_loads.remove(&coll_idx).is_some() {
+      self.submit_load_request(coll_idx, 0, services.decode_fit_size, false, services); // the shown image always has top priority
+      true
+    } else {
+      false
+    }
   }
 
   fn file_name_string(&self, coll_idx: usize)->String {
-    self.collection[coll_idx].file_name().into_string().unwrap() // the image filter removes any entries which don't have a rust-string-representable filename
+    name_key(&self.collection[coll_idx].file_name())
   }
 
-  pub fn set_current_rating(&mut self, rating: Rating) {
+  pub fn set_current_rating(&mut self, rating: Rating)->Result<(), RatingsSaveError> {
     let file_name = self.file_name_string(self.current_collection_idx());
-    let save_res = self.ratings.set_rating(file_name, rating);
-    if let Err(error) = save_res {
-      println!("Failed to save ratings: {}", error);
+    let previous = self.ratings.get_rating(&file_name);
+    self.ratings.set_rating(file_name.clone(), rating)?;
+    self.record_rating(&file_name, previous, rating);
+    Ok(())
+  }
+
+    // rates several images at once, saving the ratings file only once
+  pub fn set_ratings(&mut self, coll_idxs: &[usize], rating: Rating)->Result<(), RatingsSaveError> {
+    let file_names: Vec<_> = coll_idxs.iter().map(|&coll_idx| self.file_name_string(coll_idx)).collect();
+    let previous: Vec<_> = file_names.iter().map(|file_name| self.ratings.get_rating(file_name)).collect();
+    self.ratings.set_ratings(file_names.clone(), rating)?;
+    for (file_name, previous) in file_names.iter().zip(previous) {
+      self.record_rating(file_name, previous, rating);
+    }
+    Ok(())
+  }
+
+    // ratings that stay the same aren't changes worth recording
+  fn record_rating(&self, file_name: &str, previous: Rating, rating: Rating) {
+    if previous != rating {
+      self.journal.record(&format!("Rated {} {}, was {}", file_name, rating.name().to_lowercase(), previous.name().to_lowercase()));
     }
   }
 
+    // for changes made outside the loaded folder's files, like exports into it
+  pub fn record_in_journal(&self, entry: &str) {
+    self.journal.record(entry);
+  }
+
+  pub fn set_selection_rating(&mut self, rating: Rating)->Result<(), RatingsSaveError> {
+    let selection: Vec<_> = self.selection.iter().copied().collect();
+    self.set_ratings(&selection, rating)
+  }
+
+  pub fn toggle_current_selected(&mut self) {
+    let coll_idx = self.current_collection_idx();
+    if !self.selection.remove(&coll_idx) {
+      self.selection.insert(coll_idx);
+    }
+    self.selection_anchor = Some(coll_idx);
+  }
+
+    // adds the images from the selection anchor to the shown image to the selection.
+    // only images that pass the rating filter are added. without an anchor, just the shown image is
+  pub fn select_range_to_current(&mut self) {
+    let end = self.current_idx;
+    let start = self.selection_anchor
+      .and_then(|anchor| self.active_position(anchor))
+      .unwrap_or(end);
+
+    let range = start.min(end)..=start.max(end);
+    self.selection.extend(self.active_idxs[range].iter().copied());
+    self.selection_anchor = Some(self.current_collection_idx());
+  }
+
+  pub fn clear_selection(&mut self) {
+    self.selection.clear();
+    self.selection_anchor = None;
+  }
+
+  pub fn selection_count(&self)->usize {
+    self.selection.len()
+  }
+
+  pub fn is_current_selected(&self)->bool {
+    self.selection.contains(&self.current_collection_idx())
+  }
+
+    // returns whether the shown image is bookmarked now
+  pub fn toggle_current_bookmark(&mut self)->bool {
+    let coll_idx = self.current_collection_idx();
+    if self.bookmarks.remove(&coll_idx) {
+      false
+    } else {
+      self.bookmarks.insert(coll_idx)
+    }
+  }
+
+  pub fn bookmark_count(&self)->usize {
+    self.bookmarks.len()
+  }
+
+  pub fn is_current_bookmarked(&self)->bool {
+    self.bookmarks.contains(&self.current_collection_idx())
+  }
+
+    // returns whether the shown image is pinned now. None if it couldn't be pinned, since `MAX_PINNED` images are pinned already
+  pub fn toggle_current_pinned(&mut self, services: &ImageHandlingServices)->Option<bool> {
+    let coll_idx = self.current_collection_idx();
+    let pinned = match self.pinned.iter().position(|&pinned_idx| pinned_idx == coll_idx) {
+      Some(position) => {
+        self.pinned.remove(position);
+        false
+      },
+      None if self.pinned.len() < MAX_PINNED => {
+        self.pinned.push(coll_idx);
+        true
+      },
+      None => return None
+    };
+    self.update_loaded(services);
+    Some(pinned)
+  }
+
+  pub fn unpin_all(&mut self, services: &ImageHandlingServices) {
+    self.pinned.clear();
+    self.update_loaded(services);
+  }
+
+    // the images of the compare view: the pinned ones, and the shown one after them if it isn't pinned and there is room.
+    // empty while nothing is pinned
+  pub fn compare_images(&self)->Vec<usize> {
+    let mut images = self.pinned.clone();
+    let current_coll_idx = self.current_collection_idx();
+    if !images.is_empty() && images.len() < MAX_PINNED && !images.contains(&current_coll_idx) {
+      images.push(current_coll_idx);
+    }
+    images
+  }
+
+    // shows the next bookmarked image after the shown one, or before it if not `forward`, going around at the ends.
+    // bookmarks the filters hide are skipped. returns the number of the bookmark shown, and how many are shown in total
+  pub fn show_next_bookmark(&mut self, forward: bool, services: &ImageHandlingServices)->Option<(usize, usize)> {
+    let shown: Vec<usize> = self.active_idxs.iter().enumerate()
+      .filter(|(_, coll_idx)| self.bookmarks.contains(coll_idx))
+      .map(|(idx, _)| idx)
+      .collect();
+
+    let next = if forward {
+      shown.iter().position(|&idx| idx > self.current_idx).or((!shown.is_empty()).then_some(0))
+    } else {
+      shown.iter().rposition(|&idx| idx < self.current_idx).or(shown.len().checked_sub(1))
+    };
+
+    let number = next?;
+    self.current_idx = shown[number];
+    self.update_loaded(services);
+
+    Some((number + 1, shown.len()))
+  }
+
   pub fn get_current_rating(&self)->Rating {
     let file_name = self.file_name_string(self.current_collection_idx());
     self.ratings.get_rating(&file_name)
   }
 
-  pub fn set_rating_filter(&mut self, rating: Option<Rating>, services: &ImageHandlingServices) {
-    let new_active_idxs = 
-      if let Some(rating) = rating {
+    // changes the orientation of the shown image, and remembers it for the next time the folder is loaded.
+    // does nothing while the image isn't loaded, since the orientation it is changed from isn't known before then
+  pub fn reorient_current<F>(&mut self, reorient: F, services: &ImageHandlingServices)->Result<(), FolderFileError>
+    where F: FnOnce(ImageRotation)->ImageRotation {
+
+    let coll_idx = self.current_collection_idx();
+    let file_name = self.file_name_string(coll_idx);
+    let placed_image = match self.loaded_images.get_mut(&coll_idx) {
+      Some(placed_image) => placed_image,
+      None => return Ok(())
+    };
+
+    let rotation = reorient(placed_image.image.rotation);
+    placed_image.image.rotation = rotation;
+      // turning the image sideways changes how large it needs to be decoded
+    self.reload_low_resolution(services);
+
+    self.orientations.set(file_name.clone(), rotation)?;
+    self.journal.record(&format!("Changed the orientation of {}", file_name));
+    Ok(())
+  }
+
+  pub fn is_current_rejected(&self)->bool {
+    self.rejects.contains(&self.file_name_string(self.current_collection_idx()))
+  }
+
+    // rejects the shown image, or takes its rejection back. rejected images are hidden right away if `hide`, or while the reject filter hides them,
+    // which shows the next image in their place. they stay shown if every image is rejected. returns whether the image is rejected now
+  pub fn toggle_current_rejected(&mut self, hide: bool, services: &ImageHandlingServices)->Result<bool, FolderFileError> {
+    let file_name = self.file_name_string(self.current_collection_idx());
+    let rejected = !self.rejects.contains(&file_name);
+    self.rejects.set(file_name.clone(), rejected)?;
+    self.journal.record(&format!("{} {}", if rejected { "Rejected" } else { "Took back the rejection of" }, file_name));
+
+    let rejected_filter = if hide && rejected { Some(false) } else { self.filters.rejected };
+    if rejected_filter.is_some() {
+      self.apply_filters(Filters { rejected: rejected_filter, ..self.filters.clone() }, services);
+    }
+    Ok(rejected)
+  }
+
+    // the selects that are in the folder, in their order, as collection indexes
+  fn select_idxs(&self)->Vec<usize> {
+    self.selects.names().iter().filter_map(|file_name| self.name_to_idx.get(file_name)).copied().collect()
+  }
+
+  pub fn select_count(&self)->usize {
+    self.select_idxs().len()
+  }
+
+    // where the shown image is in the selects, None if it isn't one
+  pub fn current_select_position(&self)->Option<usize> {
+    let coll_idx = self.current_collection_idx();
+    self.select_idxs().iter().position(|&select| select == coll_idx)
+  }
+
+    // adds the shown image to the end of the selects, or removes it. while only the selects are shown, the next one takes a removed one's place,
+    // and all images are shown again once none are left. returns whether the image is a select now
+  pub fn toggle_current_select(&mut self, services: &ImageHandlingServices)->Result<bool, FolderFileError> {
+    let file_name = self.file_name_string(self.current_collection_idx());
+    let selected = self.selects.toggle(file_name.clone())?;
+    self.journal.record(&format!("{} {} {} the selects", if selected { "Added" } else { "Removed" }, file_name, if selected { "to" } else { "from" }));
+
+    if self.filters.selects_only && !selected {
+      let current_idx = self.current_idx;
+      if self.apply_filters(self.filters.clone(), services) {
+        self.current_idx = current_idx.min(self.active_idxs.len() - 1);
+        self.update_loaded(services);
+      } else {
+        self.apply_filters(Filters { selects_only: false, ..self.filters.clone() }, services);
+      }
+    }
+    Ok(selected)
+  }
+
+    // swaps the shown image with the select before or after it. returns false if it isn't a select, or already the first or last
+  pub fn move_current_select(&mut self, earlier: bool, services: &ImageHandlingServices)->Result<bool, FolderFileError> {
+    let file_name = self.file_name_string(self.current_collection_idx());
+    let moved_to = self.selects.shift(&file_name, earlier)?;
+    if let Some(position) = moved_to {
+      self.journal.record(&format!("Moved {} to select {}", file_name, position + 1));
+    }
+    let moved = moved_to.is_some();
+      // the shown image moves along with its place in the selects
+    if moved && self.filters.selects_only {
+      self.apply_filters(self.filters.clone(), services);
+    }
+    Ok(moved)
+  }
+
+    // shows only the selects while set, in their order. returns false, and leaves the filters as they were, if no select passes the other filters
+  pub fn set_selects_filter(&mut self, selects_only: bool, services: &ImageHandlingServices)->bool {
+    self.apply_filters(Filters { selects_only, ..self.filters.clone() }, services)
+  }
+
+  pub fn get_selects_filter(&self)->bool {
+    self.filters.selects_only
+  }
+
+    // starts hashing every image in the folder to find duplicates. returns false if a search was already started
+  pub fn find_duplicates(&mut self, services: &ImageHandlingServices)->bool {
+    if self.duplicate_search.is_some() {
+      return false;
+    }
+
+    let images = self.collection.iter().map(|entry| entry.path()).enumerate();
+    self.duplicate_search = Some(DuplicateSearch::start(images, &services.hash_pool));
+    true
+  }
+
+    // takes in a result of the duplicate search. returns the number of duplicate groups once the search finished
+  pub fn receive_hash(&mut self, hash: Option<u64>, idx: usize, token: &CancelToken)->Option<usize> {
+    let duplicate_search = self.duplicate_search.as_mut()?;
+    if duplicate_search.receive(hash, idx, token) {
+      duplicate_search.groups().map(|groups| groups.len())
+    } else {
+      None
+    }
+  }
+
+    // takes in a thumbnail. returns the collection index of the image it belongs to, if it is of this folder and could be made
+  pub fn receive_thumbnail(&mut self, thumbnail: Option<Thumbnail>, idx: usize, token: &CancelToken)->Option<usize> {
+    self.thumbnails.receive(thumbnail, idx, token)
+  }
+
+  pub fn thumbnail(&self, coll_idx: usize)->Option<&Thumbnail> {
+    self.thumbnails.get(coll_idx)
+  }
+
+    // how many thumbnails are still being made
+  pub fn remaining_thumbnail_count(&self)->usize {
+    self.thumbnails.remaining()
+  }
+
+    // takes in a prefetched preview, if it is of this folder
+  pub fn receive_prefetched_preview(&mut self, preview: Option<CompressedPreview>, idx: usize, token: &CancelToken) {
+    self.preview_prefetch.receive(preview, idx, token);
+  }
+
+    // how many previews are still being prefetched
+  pub fn remaining_prefetch_count(&self)->usize {
+    self.preview_prefetch.remaining()
+  }
+
+    // groups of images that look the same, once the duplicate search finished
+  pub fn duplicate_groups(&self)->Option<&[Vec<usize>]> {
+    self.duplicate_search.as_ref().and_then(|duplicate_search| duplicate_search.groups())
+  }
+
+    // shows the first image of the next duplicate group after the shown image, wrapping around at the end.
+    // images the rating filter hides are skipped. returns the number of the group, starting at 1, and its size
+  pub fn show_next_duplicate_group(&mut self, services: &ImageHandlingServices)->Option<(usize, usize)> {
+    let groups = self.duplicate_groups()?;
+
+    let first_shown = |group: &Vec<usize>| group.iter().find_map(|&coll_idx| self.active_position(coll_idx));
+    let next = groups.iter().enumerate()
+      .filter_map(|(group_idx, group)| first_shown(group).map(|idx| (idx, group_idx, group.len())))
+      .min_by_key(|&(idx, _, _)| (idx <= self.current_idx, idx));
+
+    let (idx, group_idx, group_size) = next?;
+    self.current_idx = idx;
+    self.update_loaded(services);
+
+    Some((group_idx + 1, group_size))
+  }
+
+    // returns false, and leaves the filters as they were, if no image passes the new filter
+  pub fn set_rating_filter(&mut self, rating: Option<Rating>, services: &ImageHandlingServices)->bool {
+    self.apply_filters(Filters { rating, ..self.filters.clone() }, services)
+  }
+
+  pub fn get_rating_filter(&self)->Option<Rating> {
+    self.filters.rating
+  }
+
+    // returns false, and leaves the filters as they were, if no image passes the new filter.
+    // images whose metadata wasn't read yet are only shown while no metadata filter is set
+  pub fn set_metadata_filter(&mut self, metadata: MetadataFilter, services: &ImageHandlingServices)->bool {
+    self.pending_metadata_filter = None;
+    self.apply_filters(Filters { metadata, ..self.filters.clone() }, services)
+  }
+
+  pub fn get_metadata_filter(&self)->&MetadataFilter {
+    &self.filters.metadata
+  }
+
+    // returns false, and leaves the filters as they were, if no image passes the new filter
+  pub fn set_file_type_filter(&mut self, file_type: Option<FileType>, services: &ImageHandlingServices)->bool {
+    self.apply_filters(Filters { file_type, ..self.filters.clone() }, services)
+  }
+
+  pub fn get_file_type_filter(&self)->Option<FileType> {
+    self.filters.file_type
+  }
+
+  pub fn set_reject_filter(&mut self, rejected: Option<bool>, services: &ImageHandlingServices)->bool {
+    self.apply_filters(Filters { rejected, ..self.filters.clone() }, services)
+  }
+
+  pub fn get_reject_filter(&self)->Option<bool> {
+    self.filters.rejected
+  }
+
+    // only shows the selected images while set. returns false, and leaves the filters as they were, if no selected image passes the other filters
+  pub fn set_selection_filter(&mut self, selection_only: bool, services: &ImageHandlingServices)->bool {
+    self.apply_filters(Filters { selection_only, ..self.filters.clone() }, services)
+  }
+
+  pub fn clear_filters(&mut self, services: &ImageHandlingServices) {
+    self.pending_metadata_filter = None;
+    self.apply_filters(Filters::default(), services);
+  }
+
+    // takes in the metadata read so far. returns true if the scan of the folder completed with it
+  pub fn receive_metadata(&mut self, services: &ImageHandlingServices)->bool {
+    let complete = self.metadata_scan.receive();
+    if complete {
+      if let Some(metadata_filter) = self.pending_metadata_filter.take() {
+        self.set_metadata_filter(metadata_filter, services);
+      }
+    }
+    complete
+  }
+
+  pub fn metadata_scan(&self)->&MetadataScan {
+    &self.metadata_scan
+  }
+
+    // takes in the files checked so far. returns true if the check of the folder completed with them
+  pub fn receive_integrity(&mut self)->bool {
+    self.integrity_scan.receive()
+  }
+
+    // what is wrong with the image's file, if the integrity scan found it damaged
+  pub fn damage(&self, coll_idx: usize)->Option<&Damage> {
+    self.integrity_scan.get(coll_idx)
+  }
+
+    // the file names of the damaged files found so far, in name order
+  pub fn damaged_file_names(&self)->Vec<String> {
+    self.integrity_scan.damaged_idxs().into_iter().map(|coll_idx| self.file_name_string(coll_idx)).collect()
+  }
+
+    // how many images of the folder have each rating, regardless of the filters.
+    // images whose metadata wasn't read yet count as unknown when split by it
+  pub fn rating_statistics(&self, split: StatisticsSplit)->Vec<RatingCounts> {
+    let images = (0..self.collection.len()).map(|coll_idx| {
+      (self.ratings.get_rating(&self.file_name_string(coll_idx)), self.metadata_scan.get(coll_idx))
+    });
+    statistics::count_ratings(images, split, &self.metadata_scan.cameras())
+  }
+
+    // picks up where the last session in the folder left off. the metadata filter waits for the metadata scan,
+    // since images whose metadata wasn't read yet don't pass it
+  fn restore_session(&mut self, session: Session, services: &ImageHandlingServices) {
+    let shown = session.shown.as_ref().and_then(|file_name| self.name_to_idx.get(file_name)).copied();
+    if let Some(coll_idx) = shown {
+      self.current_idx = coll_idx; // nothing is filtered yet, so collection indexes are indexes into the active images
+      self.load_pivot = coll_idx;
+    }
+
+    let filters = Filters {
+      rating: session.rating_filter.map(Rating::from_u8),
+      file_type: session.file_type_filter,
+      rejected: session.reject_filter,
+      selects_only: session.selects_only,
+      metadata: MetadataFilter::default(),
+      selection_only: false
+    };
+      // no image passes the filters anymore, so they are left unset
+    if !self.apply_filters(filters, services) {
+      self.update_loaded(services);
+    }
+    if session.metadata_filter.is_active() {
+      self.pending_metadata_filter = Some(session.metadata_filter);
+    }
+
+    let coll_idx_of = |file_name: &String| self.name_to_idx.get(file_name).copied();
+    self.selection = session.selection.iter().filter_map(coll_idx_of).collect();
+    self.selection_anchor = session.selection_anchor.as_ref().and_then(coll_idx_of);
+    self.bookmarks = session.bookmarks.iter().filter_map(coll_idx_of).collect();
+
+      // the zoom was for the shown image, which the filters may hide now
+    if let (Some(coll_idx), Some(zoom)) = (shown, session.zoom) {
+      if coll_idx == self.current_collection_idx() {
+        self.kept_zooms.insert(coll_idx, zoom);
+      }
+    }
+
+    self.follow_arrivals = !session.staying_put;
+    if session.watching {
+      self.set_watching(true, services);
+    }
+  }
+
+    // saves where the user left off, for `restore_session` the next time the folder is loaded. the session file is kept as it is
+    // while the folder is still being listed, the session in it wasn't restored yet
+  pub fn save_session(&self)->Result<(), FolderFileError> {
+    if self.pending_session.is_some() {
+      return Ok(());
+    }
+
+    let current_coll_idx = self.current_collection_idx();
+    let zoom = match self.loaded_images.get(&current_coll_idx) {
+      Some(placed_image) if placed_image.is_zoomed() => {
+        let (zoom, pan) = placed_image.zoom();
+        Some(SessionZoom { zoom, pan: [pan.x, pan.y] })
+      },
+      Some(_) => None,
+      None => self.kept_zooms.get(&current_coll_idx).copied()
+    };
+
+    let session = Session {
+      shown: Some(self.file_name_string(current_coll_idx)),
+      rating_filter: self.filters.rating.map(|rating| rating.to_u8()),
+      file_type_filter: self.filters.file_type,
+      reject_filter: self.filters.rejected,
+      selects_only: self.filters.selects_only,
+      metadata_filter: self.pending_metadata_filter.clone().unwrap_or_else(|| self.filters.metadata.clone()),
+      selection: self.selection.iter().map(|&coll_idx| self.file_name_string(coll_idx)).collect(),
+      selection_anchor: self.selection_anchor.map(|coll_idx| self.file_name_string(coll_idx)),
+      bookmarks: self.bookmarks.iter().map(|&coll_idx| self.file_name_string(coll_idx)).collect(),
+      zoom,
+      watching: self.is_watching(),
+      staying_put: !self.follow_arrivals
+    };
+    self.session_file.save(&session)
+  }
+
+    // watches the folder for images added to it, e.g. by a tethering tool, until the watch is turned off or another folder is loaded
+  pub fn set_watching(&mut self, watching: bool, services: &ImageHandlingServices) {
+    self.folder_watch = if watching {
+      let known_names = self.collection.iter().map(|entry| entry.file_name()).collect();
+      Some(FolderWatch::start(self.path.clone(), known_names, services.follow_symlinks, services.event_loop_proxy.clone()))
+    } else {
+      None
+    };
+  }
+
+  pub fn is_watching(&self)->bool {
+    self.folder_watch.is_some()
+  }
+
+  pub fn set_follow_arrivals(&mut self, follow: bool) {
+    self.follow_arrivals = follow;
+  }
+
+  pub fn follows_arrivals(&self)->bool {
+    self.follow_arrivals
+  }
+
+    // adds the images the folder watch found to the end of the collection, see `append_images`.
+    // the newest one is shown if arrivals are followed and it passes the filters. returns how many images were added
+  pub fn receive_arrivals(&mut self, services: &ImageHandlingServices)->usize {
+    let arrivals = match self.folder_watch {
+      Some(ref mut folder_watch) => folder_watch.receive(),
+      None => return 0
+    };
+    if arrivals.is_empty() {
+      return 0;
+    }
+
+    let (entries, scanned): (Vec<_>, Vec<_>) = arrivals.into_iter().map(|(entry, metadata, damage)| (entry, (metadata, damage))).unzip();
+    let first_new = self.append_images(entries, self.collection.len(), services);
+    for (coll_idx, (metadata, damage)) in (first_new..).zip(scanned) {
+      if let Some(raw_path) = external_tools::find_raw_file(&self.collection_path(coll_idx)) {
+        self.raw_paths.insert(coll_idx, raw_path);
+      }
+      self.metadata_scan.insert(coll_idx, metadata);
+      self.integrity_scan.insert(coll_idx, damage);
+    }
+
+      // the images passing the filters before still do, so this can't fail
+    self.apply_filters(self.filters.clone(), services);
+    let newest = self.active_idxs.len() - 1;
+    if self.follow_arrivals && self.collection_idx(newest) >= first_new {
+      self.current_idx = newest;
+      self.update_loaded(services);
+    }
+
+    self.collection.len() - first_new
+  }
+
+    // adds the images the folder scan found after the first ones, while the folder is already shown. the shown image stays
+  pub(super) fn add_listed(&mut self, listed: ListedBatch, services: &ImageHandlingServices) {
+    let ListedBatch { images, raw_files, broken_link_count } = listed;
+    self.broken_link_count += broken_link_count;
+    let first_new = self.append_images(images, self.current_collection_idx(), services);
+    let new_images: Vec<_> = (first_new..self.collection.len()).map(|coll_idx| (coll_idx, self.collection_path(coll_idx))).collect();
+    self.metadata_scan.extend(new_images.clone());
+    self.integrity_scan.extend(new_images);
+    self.pair_raw_files(raw_files);
+
+      // the images passing the filters before still do, so this can't fail
+    self.apply_filters(self.filters.clone(), services);
+  }
+
+    // restores the session once the folder scan is done, since the image it left off at may not have been found before
+  pub(super) fn finish_listing(&mut self, services: &ImageHandlingServices) {
+    if let Some(session) = self.pending_session.take() {
+      self.restore_session(session, services);
+    }
+  }
+
+    // puts the images at the end of the collection, out of name order, so that the indexes of the others stay valid. their thumbnails and previews
+    // are made closest to `center_idx` first. returns the collection index of the first one
+  fn append_images(&mut self, entries: Vec<DirEntry>, center_idx: usize, services: &ImageHandlingServices)->usize {
+    let first_new = self.collection.len();
+    let new_images = entries.iter().map(|entry| entry.path()).enumerate().map(|(offset, path)| (first_new + offset, path));
+    self.thumbnails.request(new_images.clone(), center_idx, &services.thumbnail_pool);
+    self.preview_prefetch.request(new_images, center_idx, &services.prefetch_pool);
+    for entry in entries {
+      let file_name = name_key(&entry.file_name());
+      self.ratings.add_image(file_name.clone());
+      self.name_to_idx.insert(file_name, self.collection.len());
+      self.collection.push(entry);
+    }
+    first_new
+  }
+
+    // pairs the raw files with the images of the same name, keeping those without one apart. raw files listed before their image get paired
+    // when it is added
+  fn pair_raw_files(&mut self, raw_files: Vec<PathBuf>) {
+    let mut image_idxs: HashMap<OsString, Vec<usize>> = HashMap::new();
+    for (coll_idx, entry) in self.collection.iter().enumerate() {
+      if let Some(stem) = entry.path().file_stem() {
+        image_idxs.entry(stem.to_os_string()).or_default().push(coll_idx);
+      }
+    }
+
+    let unpaired = std::mem::take(&mut self.raws_without_image);
+    for raw_path in unpaired.into_iter().chain(raw_files) {
+      match raw_path.file_stem().and_then(|stem| image_idxs.get(stem)) {
+        Some(coll_idxs) => {
+          for &coll_idx in coll_idxs {
+            self.raw_paths.insert(coll_idx, raw_path.clone());
+          }
+        },
+        None => self.raws_without_image.push(raw_path)
+      }
+    }
+    self.raws_without_image.sort_unstable();
+  }
+
+    // restricts the images that are traversed to those passing all filters
+  fn apply_filters(&mut self, filters: Filters, services: &ImageHandlingServices)->bool {
+    let mut new_active_idxs: Vec<_> =
+      if let Some(rating) = filters.rating {
         let file_names = self.ratings.filter_ratings(rating);
         let mut idxs: Vec<_> = file_names.iter().filter_map(|&file_name| self.name_to_idx.get(file_name)).map(|idx| *idx).collect();
         idxs.sort_unstable();
@@ -145,107 +1023,618 @@ impl LoadedDir {
         (0..self.collection.len()).collect()
       };
 
+    if filters.metadata.is_active() {
+      new_active_idxs.retain(|&coll_idx| self.metadata_scan.get(coll_idx).is_some_and(|metadata| filters.metadata.matches(metadata)));
+    }
+
+    if let Some(file_type) = filters.file_type {
+      new_active_idxs.retain(|&coll_idx| self.has_file_type(coll_idx, file_type));
+    }
+
+    if let Some(rejected) = filters.rejected {
+      new_active_idxs.retain(|&coll_idx| self.rejects.contains(&self.file_name_string(coll_idx)) == rejected);
+    }
+
+    if filters.selection_only {
+      new_active_idxs.retain(|coll_idx| self.selection.contains(coll_idx));
+    }
+
+    if filters.selects_only {
+      let passing: HashSet<_> = new_active_idxs.into_iter().collect();
+      new_active_idxs = self.select_idxs().into_iter().filter(|coll_idx| passing.contains(coll_idx)).collect();
+    }
+
+    if new_active_idxs.is_empty() {
+      return false;
+    }
+
     let coll_idx = self.current_collection_idx();
-    let new_current = match new_active_idxs.binary_search(&coll_idx) {
+    let new_current = match active_position(&new_active_idxs, filters.selects_only, coll_idx) {
       Ok(idx) => idx,
       Err(idx) => idx
     };
     let new_current = new_current.max(0).min(new_active_idxs.len() - 1);
 
-    self.rating_filter = rating;
+    self.filters = filters;
     self.active_idxs = new_active_idxs;
     self.load_pivot = new_current;
     self.current_idx = new_current;
     self.update_loaded(services);
+    true
   }
 
-  pub fn get_rating_filter(&self)->Option<Rating> {
-    self.rating_filter
+  fn has_file_type(&self, coll_idx: usize, file_type: FileType)->bool {
+    let path = self.collection_path(coll_idx);
+    match file_type {
+      FileType::Jpeg => image::is_jpeg(&path),
+      FileType::Raw => self.raw_paths.contains_key(&coll_idx),
+      FileType::MissingRaw => image::is_jpeg(&path) && !self.raw_paths.contains_key(&coll_idx),
+      FileType::Video => video::is_video(&path)
+    }
   }
 
-  fn update_loaded(&mut self, services: &ImageHandlingServices) {
-    let (new_pivot, load_set) = services.loading_policy.get_load_set(self.load_pivot, self.current_idx, self.active_idxs.len());
+  pub(super) fn update_loaded(&mut self, services: &ImageHandlingServices) {
+    let (new_pivot, load_set) = services.loading_policy.get_load_set(self.load_pivot, self.current_idx, self.active_idxs.len(), self.direction, self.pace);
     self.load_pivot = new_pivot;
 
-    let load_coll_idxs: Vec<_> = load_set.iter().map(|&idx| self.collection_idx(idx)).collect();
-    
-    self.loaded_images.retain(|&key, _| {
-      for &idx in &load_coll_idxs {
-        if idx == key {
-          return true;
-        }
+      // pinned images stay loaded, right after the shown one
+    let mut load_coll_idxs = self.pinned.clone();
+    load_coll_idxs.extend(load_set.iter().map(|&idx| self.collection_idx(idx)).filter(|coll_idx| !self.pinned.contains(coll_idx)));
+
+    self.show_count += 1;
+    self.last_shown.insert(self.current_collection_idx(), self.show_count);
+
+    self.load_set = load_coll_idxs.clone();
+    self.evict_over_budget(services);
+      // forget failures outside the load set, so that the load is tried again once the image comes back into range
+    self.failed_loads.retain(|key, _| load_coll_idxs.contains(key));
+      // cancel loads that are no longer needed, so workers can skip them, and their results get dropped on arrival.
+      // loads of images that are still wanted are kept, also when the active set changed, and get their new priority below
+    self.pending_loads.retain(|key, pending_load| {
+      let keep = load_coll_idxs.contains(key);
+      if !keep {
+        pending_load.token.cancel();
       }
-      return false;
+      keep
     });
+      // received images that left the load set aren't uploaded anymore
+    self.queued_uploads.retain(|(coll_idx, _)| load_coll_idxs.contains(coll_idx));
+    if self.active_upload.as_ref().is_some_and(|(coll_idx, _)| !load_coll_idxs.contains(coll_idx)) {
+      let (_, upload) = self.active_upload.take().unwrap();
+      upload.abort(&mut self.texture_pool);
+    }
+
+      // the load set is in priority order relative to the pivot, but the shown image goes before everything else.
+      // queued loads are moved to their new position as well, so the shown image jumps the queue even if it was requested as a prefetch earlier
+    let current_coll_idx = self.current_collection_idx();
+    let load_priority = |coll_idx: usize| -> Option<Priority> {
+      if coll_idx == current_coll_idx {
+        Some(0)
+      } else {
+        load_coll_idxs.iter().position(|&idx| idx == coll_idx).map(|position| position + 1)
+      }
+    };
+
+    services.loader_pool.reprioritize(|request| {
+      if request.token.is_cancelled() {
+        None
+      } else {
+        load_priority(request.idx)
+      }
+    });
+
+    let previews_suffice = services.loading_policy.previews_suffice(self.pace);
+    for &coll_idx in &load_coll_idxs {
+      if self.needs_load(coll_idx, previews_suffice) {
+        let priority = load_priority(coll_idx).unwrap(); // coll_idx is from the load set, so it always has a priority
+        self.submit_load_request(coll_idx, priority, services.decode_fit_size, previews_suffice, services);
+      }
+    }
+  }
+
+    // images outside the load set stay loaded, in case they are shown again, until their textures exceed the memory budget.
+    // those are then evicted, least recently shown first. the budget only covers them, the load set itself is always kept
+  fn evict_over_budget(&mut self, services: &ImageHandlingServices) {
+    let budget = services.loading_policy.texture_memory_budget;
+    let mut used: usize = self.loaded_images.iter()
+      .filter(|(coll_idx, _)| !self.load_set.contains(coll_idx))
+      .map(|(_, placed_image)| placed_image.image.byte_size())
+      .sum();
+    if used <= budget {
+      return;
+    }
+
+      // images that were prefetched, but never shown, go first
+    let mut eviction_order: Vec<_> = self.loaded_images.keys()
+      .filter(|coll_idx| !self.load_set.contains(coll_idx))
+      .map(|&coll_idx| (self.last_shown.get(&coll_idx).copied().unwrap_or(0), coll_idx))
+      .collect();
+    eviction_order.sort_unstable();
+
+    for (_, coll_idx) in eviction_order {
+      if used <= budget {
+        break;
+      }
 
-    for coll_idx in load_coll_idxs {
-      if self.needs_load(coll_idx) {
-        self.submit_load_request(coll_idx, services);
+      if let Some(placed_image) = self.loaded_images.remove(&coll_idx) {
+        used -= placed_image.image.byte_size();
+        self.keep_zoom(coll_idx, &placed_image);
+        self.texture_pool.recycle(placed_image.image);
       }
+      self.last_shown.remove(&coll_idx);
+    }
+  }
+
+    // so that the image is zoomed in the same way once it is loaded again, e.g. to compare the same detail of two images of a burst
+  fn keep_zoom(&mut self, coll_idx: usize, placed_image: &PlacedImage) {
+    if placed_image.is_zoomed() {
+      let (zoom, pan) = placed_image.zoom();
+      self.kept_zooms.insert(coll_idx, SessionZoom { zoom, pan: [pan.x, pan.y] });
     }
   }
 
-  fn needs_load(&self, coll_idx: usize)->bool {
-    !self.loaded_images.contains_key(&coll_idx) && !self.pending_loads.contains(&coll_idx)
+    // zooms the shown image back out. images keep their zoom while navigating otherwise
+  pub fn reset_current_zoom(&mut self) {
+    let coll_idx = self.current_collection_idx();
+    self.kept_zooms.remove(&coll_idx);
+    if let Some(placed_image) = self.loaded_images.get_mut(&coll_idx) {
+      placed_image.zoom_to(1.0, LogicalPosition::new(0.0, 0.0));
+    }
+  }
+
+    // zooms all images back out
+  pub fn reset_zooms(&mut self) {
+    self.kept_zooms.clear();
+    for placed_image in self.loaded_images.values_mut() {
+      placed_image.set_zoom(1.0, LogicalPosition::new(0.0, 0.0));
+    }
+  }
+
+    // images that only got as far as their preview need a load too, e.g. if the full image was dropped from a full loader queue,
+    // unless `previews_suffice`
+  fn needs_load(&self, coll_idx: usize, previews_suffice: bool)->bool {
+    let loaded = self.loaded_images.get(&coll_idx).is_some_and(|placed_image| previews_suffice || placed_image.image.quality != ImageQuality::Preview);
+    !loaded && !self.pending_loads.contains_key(&coll_idx) && !self.failed_loads.contains_key(&coll_idx)
   }
 
-  fn submit_load_request(&mut self, coll_idx: usize, services: &ImageHandlingServices) {
+    // loads the shown image again at full resolution once it is shown larger than it was decoded, e.g. at actual size or zoomed in.
+    // images are otherwise only decoded just large enough to fit the view
+  pub fn load_full_resolution_if_needed(&mut self, scale_factor: f64, services: &ImageHandlingServices) {
+      // the preview-only loads while flicking through the images are upgraded once the pace slows down, see `update_pace`
+    if services.loading_policy.previews_suffice(self.pace) {
+      return;
+    }
+    let coll_idx = self.current_collection_idx();
+    let needs_full_resolution = self.loaded_images.get(&coll_idx).is_some_and(|placed_image| {
+      let texture = &placed_image.image;
+      let shown_width = texture.size[0] as f64 * placed_image.scale * scale_factor; // in physical pixels
+      texture.quality != ImageQuality::Full && shown_width > texture.resolution()[0] as f64 + 1.0
+    });
+
+    if needs_full_resolution && !self.pending_loads.contains_key(&coll_idx) && !self.failed_loads.contains_key(&coll_idx) {
+      self.submit_load_request(coll_idx, 0, None, false, services);
+    }
+  }
+
+    // `fit_size` is the size to decode the image for, as in `LoadRequest`
+  fn submit_load_request(&mut self, coll_idx: usize, priority: Priority, fit_size: Option<[u32; 2]>, preview_only: bool, services: &ImageHandlingServices) {
     let path = self.collection[coll_idx].path();
-    self.pending_loads.insert(coll_idx);
-    services.loader_pool.submit((path, coll_idx));
+    let token = CancelToken::new();
+    let send_preview = !self.loaded_images.contains_key(&coll_idx); // a reload already has something better than a preview to show
+    let preview_only = preview_only && send_preview;
+    self.pending_loads.insert(coll_idx, PendingLoad { token: token.clone(), preview_only });
+
+    let request = LoadRequest {
+      path,
+      idx: coll_idx,
+      token,
+      fit_size,
+      send_preview,
+      preview_only,
+      prefetched_preview: if send_preview { self.preview_prefetch.get(coll_idx) } else { None }
+    };
+    if let Some(dropped) = services.loader_pool.submit(request, priority) {
+      self.receive_load_drop(dropped.idx, &dropped.token);
+    }
   }
 
-  pub fn receive_image<F: Facade>(&mut self, services: &ImageHandlingServices, gl_ctx: &F)->Result<(), TextureCreationError> {
-    let load_output_res = services.loader_pool.output.recv(); // :todo: pass error to outside
-    if let Ok(load_output) = load_output_res {
-      let (image_data, idx) = load_output;
+    // re-requests loaded images whose resolution is too low for the current decode size, e.g. after the window grew.
+    // the loaded textures stay in place until the new ones arrive
+  pub fn reload_low_resolution(&mut self, services: &ImageHandlingServices) {
+    let fit_size = match services.decode_fit_size {
+      Some(fit_size) => fit_size,
+      None => return
+    };
 
-      if !self.loaded_images.contains_key(&idx) {
+    let current_coll_idx = self.current_collection_idx();
+    let low_resolution_idxs: Vec<_> = self.loaded_images.iter()
+      .filter(|(idx, _)| !self.pending_loads.contains_key(idx))
+      .filter(|(_, placed_image)| {
+        let texture = &placed_image.image;
+        let scale = fit_scale(texture.size, &texture.rotation, fit_size);
+        let needed_width = (texture.size[0] as f64 * scale).ceil() as usize;
+        texture.quality != ImageQuality::Full && texture.resolution()[0] < needed_width
+      })
+      .map(|(&idx, _)| idx)
+      .collect();
 
-        let texture = ImageTexture::from_data(image_data, gl_ctx)?;
-        let placed_image = PlacedImage::new(texture);
+    for coll_idx in low_resolution_idxs {
+        // the shown image first, the rest after any loads already queued
+      let priority = if coll_idx == current_coll_idx { 0 } else { Priority::MAX };
+      self.submit_load_request(coll_idx, priority, services.decode_fit_size, false, services);
+    }
+  }
 
-        self.loaded_images.insert(idx, placed_image);
-        if !self.pending_loads.remove(&idx) {
-          println!("Loaded {}, but no corresponding pending load existed.", idx);
+    // whether a load result belongs to the load request that is currently pending for that image.
+    // results of cancelled requests, or of requests from a previously loaded directory, don't.
+  fn is_pending_request(&self, idx: usize, token: &CancelToken)->bool {
+    self.pending_loads.get(&idx).is_some_and(|pending_load| pending_load.token.same_as(token))
+  }
+
+    // queues a received image for its texture upload. the upload itself happens in `process_uploads`, spread over several frames
+  pub fn receive_image(&mut self, result: LoadResult) {
+    let LoadResult { data: image_data, idx, token, load_time } = result;
+
+      // drop stale results before doing the texture upload, which is the expensive part
+    if !self.is_pending_request(idx, &token) {
+      return;
+    }
+
+      // the load stays pending after a preview arrives, since the full resolution image still follows, unless only the preview was asked for
+    if image_data.quality != ImageQuality::Preview {
+      self.pending_loads.remove(&idx);
+      self.load_times.insert(idx, load_time);
+    } else if self.pending_loads.get(&idx).is_some_and(|pending_load| pending_load.preview_only) {
+      self.pending_loads.remove(&idx);
+    }
+
+      // a queued preview that wasn't uploaded yet is replaced by the better image, instead of being uploaded for nothing
+    match self.queued_uploads.iter_mut().find(|(queued_idx, _)| *queued_idx == idx) {
+      Some((_, queued_data)) => {
+        if queued_data.resolution()[0] < image_data.resolution()[0] {
+          *queued_data = image_data;
+        }
+      },
+      None => self.queued_uploads.push((idx, image_data))
+    }
+  }
+
+    // how long the loader took for the image, if it was loaded at full quality
+  pub fn load_time(&self, coll_idx: usize)->Option<Duration> {
+    self.load_times.get(&coll_idx).copied()
+  }
+
+  pub fn loaded_image_count(&self)->usize {
+    self.loaded_images.len()
+  }
+
+    // estimated video memory used by the textures of loaded images, see `ImageTexture::byte_size`
+  fn texture_memory(&self)->usize {
+    self.loaded_images.values().map(|placed_image| placed_image.image.byte_size()).sum()
+  }
+
+    // received images still to be uploaded, including the one being uploaded
+  pub fn pending_upload_count(&self)->usize {
+    self.queued_uploads.len() + self.active_upload.iter().count()
+  }
+
+    // decoded bytes are those of images waiting for or in their upload. the ones still in the loader's channel aren't known here
+  pub fn memory_usage(&self)->MemoryUsage {
+    let queued_bytes: usize = self.queued_uploads.iter().map(|(_, image_data)| image_data.byte_size()).sum();
+    let uploading_bytes = self.active_upload.as_ref().map_or(0, |(_, upload)| upload.data_byte_size());
+    MemoryUsage {
+      decoded_bytes: queued_bytes + uploading_bytes,
+      texture_bytes: self.texture_memory(),
+      pooled_texture_bytes: self.texture_pool.byte_size(),
+      prefetched_preview_bytes: self.preview_prefetch.byte_size()
+    }
+  }
+
+  pub fn has_pending_uploads(&self)->bool {
+    self.active_upload.is_some() || !self.queued_uploads.is_empty()
+  }
+
+    // uploads about `max_pixels` pixels of queued images into their textures. the shown image goes first, then the load set in priority order
+  pub fn process_uploads<F: Facade>(&mut self, services: &ImageHandlingServices, gl_ctx: &F, max_pixels: usize)->Result<(), TextureCreationError> {
+    let mut remaining_pixels = max_pixels;
+
+    while remaining_pixels > 0 {
+      let (idx, mut upload) = match self.active_upload.take() {
+        Some(active_upload) => active_upload,
+        None => {
+          let position = match self.next_upload_position() {
+            Some(position) => position,
+            None => break
+          };
+          let (idx, image_data) = self.queued_uploads.remove(position);
+          (idx, TextureUpload::new(image_data, gl_ctx, &mut self.texture_pool)?)
         }
-      } else {
-        println!("Image {} was already loaded!", idx);
       };
 
-      Ok(())
-    } else {
-      println!("loader pool output channel closed!");
-      Ok(())
+      let uploaded_pixels = upload.upload_rows(gl_ctx, &mut self.texture_pool, remaining_pixels)?;
+      remaining_pixels = remaining_pixels.saturating_sub(uploaded_pixels);
+
+      if upload.is_complete() {
+        self.finish_upload(idx, upload.finish(), services);
+      } else {
+        self.active_upload = Some((idx, upload));
+      }
+    }
+
+    Ok(())
+  }
+
+  fn next_upload_position(&self)->Option<usize> {
+    let current_coll_idx = self.current_collection_idx();
+    self.queued_uploads.iter()
+      .enumerate()
+      .min_by_key(|(_, (coll_idx, _))| {
+        if *coll_idx == current_coll_idx {
+          0
+        } else {
+          self.load_set.iter().position(|idx| idx == coll_idx).map_or(usize::MAX, |position| position + 1)
+        }
+      })
+      .map(|(position, _)| position)
+  }
+
+  fn finish_upload(&mut self, idx: usize, mut texture: ImageTexture, services: &ImageHandlingServices) {
+    if let Some(rotation) = self.orientations.get(&self.file_name_string(idx)) {
+      texture.rotation = rotation;
+    }
+
+    match self.loaded_images.get_mut(&idx) {
+      None => {
+        let mut placed_image = PlacedImage::new(texture);
+        if let Some(zoom) = self.kept_zooms.remove(&idx) {
+          placed_image.set_zoom(zoom.zoom, LogicalPosition::new(zoom.pan[0], zoom.pan[1]));
+        }
+        self.loaded_images.insert(idx, placed_image);
+      },
+      Some(placed_image) if placed_image.image.resolution()[0] < texture.resolution()[0] => {
+        let replaced_texture = placed_image.upgrade(texture);
+        self.texture_pool.recycle(replaced_texture);
+      },
+      Some(_) => {
+        warn!("Image {} was already loaded!", idx);
+        self.texture_pool.recycle(texture);
+      }
+    };
+
+    self.evict_over_budget(services);
+  }
+
+    // returns whether the failure was for a pending request. failures of stale requests are ignored
+    // forgets a load the loader pool dropped from its full queue. it is requested again once the load set is updated, e.g. on the next image change,
+    // rather than right away, which would only push another load out of the queue
+  pub fn receive_load_drop(&mut self, idx: usize, token: &CancelToken) {
+    if self.is_pending_request(idx, token) {
+      self.pending_loads.remove(&idx);
+    }
+  }
+
+  pub fn receive_load_failure(&mut self, failure: LoadFailure)->bool {
+    let LoadFailure { request: LoadRequest { idx, token, .. }, error } = failure;
+    if !self.is_pending_request(idx, &token) {
+      return false;
+    }
+    self.pending_loads.remove(&idx);
+      // a preview might have arrived, but the failure placeholder should be shown instead
+    if let Some(placed_image) = self.loaded_images.remove(&idx) {
+      self.keep_zoom(idx, &placed_image);
+      self.texture_pool.recycle(placed_image.image);
+    }
+    self.queued_uploads.retain(|(queued_idx, _)| *queued_idx != idx);
+    if self.active_upload.as_ref().is_some_and(|(upload_idx, _)| *upload_idx == idx) {
+      let (_, upload) = self.active_upload.take().unwrap();
+      upload.abort(&mut self.texture_pool);
+    }
+    self.failed_loads.insert(idx, error);
+    true
+  }
+}
+
+impl Drop for LoadedDir {
+    // a new directory replaces this one, none of the pending loads are of interest anymore
+  fn drop(&mut self) {
+    for pending_load in self.pending_loads.values() {
+      pending_load.token.cancel();
+    }
+  }
+}
+
+  // the images of a folder and their ratings, without loading anything, for working with the ratings outside the viewer. sorted by file name
+pub fn read_folder_ratings(path: &Path, follow_symlinks: bool, ratings_file: &RatingsFile)->Result<Vec<(PathBuf, Rating)>, DirLoadError> {
+  let mut collection = Vec::new();
+  list_folder(path, follow_symlinks, &CancelToken::new(), |_, _| {}, |batch| {
+    collection.extend(batch.images);
+    Ok(())
+  })?;
+  if collection.is_empty() {
+    return Err(DirLoadError::NoRelevantImages);
+  }
+  collection.sort_unstable_by_key(|entry| entry.file_name());
+
+  let name_to_idx = names_to_idxs(&collection);
+  let ratings = ImageRatings::new(path, ratings_file, &name_to_idx)?;
+
+  Ok(collection.iter()
+    .map(|entry| (entry.path(), ratings.get_rating(&name_key(&entry.file_name()))))
+    .collect())
+}
+
+  // What a part of a folder's listing found, see `list_folder`
+#[derive(Default)]
+pub(super) struct ListedBatch {
+  images: Vec<DirEntry>, // sorted by file name
+  raw_files: Vec<PathBuf>,
+  broken_link_count: usize
+}
+
+impl ListedBatch {
+  fn is_empty(&self)->bool {
+    self.images.is_empty() && self.raw_files.is_empty() && self.broken_link_count == 0
+  }
+
+  fn add_checked(&mut self, entry: DirEntry, kind: EntryKind) {
+    match kind {
+      EntryKind::Image => self.images.push(entry),
+      EntryKind::BrokenLink => {
+        warn!("Skipped {}, it links to a file that doesn't exist", entry.path().display());
+        self.broken_link_count += 1;
+      },
+      EntryKind::Other => {}
+    }
+  }
+
+  fn take_sorted(&mut self)->ListedBatch {
+    let mut batch = std::mem::take(self);
+    batch.images.sort_unstable_by_key(|entry| entry.file_name());
+    batch
+  }
+}
+
+struct EntryChecker {
+  follow_symlinks: bool
+}
+
+impl Worker for EntryChecker {
+  type Input = DirEntry;
+  type Output = EntryKind;
+  type Error = Infallible;
+
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    Ok(classify_entry(input, self.follow_symlinks))
+  }
+}
+
+  // the files are checked for being images on a pool of workers while the folder is still being listed. what was found is handed to `on_batch`
+  // about every `BATCH_INTERVAL` once there are images among it, and the rest at the end, so the first images can be shown before a large folder
+  // on a slow network drive is listed. `on_progress` is called with how many files were listed so far, and how many of those were checked.
+  // stops once `token` is cancelled
+fn list_folder(path: &Path, follow_symlinks: bool, token: &CancelToken, mut on_progress: impl FnMut(usize, usize), mut on_batch: impl FnMut(ListedBatch)->Result<(), DirLoadError>)->Result<(), DirLoadError> {
+  if !path.is_dir() {
+    return Err(DirLoadError::NotADirectory);
+  }
+
+  let dir_iter = fs::read_dir(path)?;
+
+  let (sender, checked) = mpsc::channel();
+  let pool = WorkerPool::new(ENTRY_CHECK_THREAD_COUNT, move |_| EntryChecker { follow_symlinks }, move |entry, kind: Result<EntryKind, Infallible>| {
+    let Ok(kind) = kind;
+    let _ = sender.send((entry, kind));
+  });
+
+  let mut batch = ListedBatch::default();
+  let mut last_batch = Instant::now();
+  let mut listed_count = 0;
+  let mut checked_count = 0;
+  for entry in dir_iter.filter_map(|entry_res| entry_res.ok()) {
+    if token.is_cancelled() {
+      return Err(DirLoadError::ScanStopped);
+    }
+
+      // raw files aren't shown, but images can be filtered by whether they have one
+    let entry_path = entry.path();
+    if external_tools::is_raw_file(&entry_path) {
+      batch.raw_files.push(entry_path);
+    }
+    pool.submit(entry, 0);
+    listed_count += 1;
+
+    for (entry, kind) in checked.try_iter() {
+      batch.add_checked(entry, kind);
+      checked_count += 1;
+    }
+    on_progress(listed_count, checked_count);
+    if !batch.images.is_empty() && last_batch.elapsed() >= BATCH_INTERVAL {
+      on_batch(batch.take_sorted())?;
+      last_batch = Instant::now();
+    }
+  }
+
+  while checked_count < listed_count {
+    let (entry, kind) = checked.recv().map_err(|_| DirLoadError::ScanStopped)?;
+    batch.add_checked(entry, kind);
+    checked_count += 1;
+    on_progress(listed_count, checked_count);
+  }
+
+  if !batch.is_empty() {
+    on_batch(batch.take_sorted())?;
+  }
+  Ok(())
+}
+
+fn names_to_idxs(collection: &[DirEntry])->HashMap<String, usize> {
+  let mut name_to_idx = HashMap::new();
+  for (idx, entry) in collection.iter().enumerate() {
+    name_to_idx.insert(name_key(&entry.file_name()), idx);
+  }
+  name_to_idx
+}
+
+  // The name an image is stored under in the ratings, orientations and session files. That's its file name, unless the name isn't valid unicode,
+  // like names written by some older cameras and file systems. The bytes that aren't valid are written as \xNN escapes then, so the files stay readable text,
+  // and backslashes as \\, so that no two names get the same key. Names with a backslash are escaped too, even when they are valid unicode
+fn name_key(file_name: &OsStr)->String {
+  if let Some(name) = file_name.to_str().filter(|name| !name.contains('\\')) {
+    return name.to_string();
+  }
+
+  let mut key = String::new();
+  for chunk in file_name.as_encoded_bytes().utf8_chunks() {
+    key.push_str(&chunk.valid().replace('\\', "\\\\"));
+    for byte in chunk.invalid() {
+      key.push_str(&format!("\\x{:02X}", byte));
     }
   }
+  key
+}
+
+enum EntryKind {
+  Image,
+  BrokenLink, // named like an image, but the file it links to doesn't exist
+  Other
 }
 
-fn file_is_relevant(entry:&DirEntry)->bool {
+  // symbolic links are resolved to the file they point to if `follow_symlinks` is set, which is how folders of linked selects are staged.
+  // the name is checked first, so that only images are looked up on the disk, which is slow on network drives
+fn classify_entry(entry: &DirEntry, follow_symlinks: bool)->EntryKind {
   let path = entry.path();
-  if !path.is_file() {
-    return false;
+  if !has_image_name(&path) {
+    return EntryKind::Other;
   }
 
-    // The filename is not representable as a rust string
-    // This is required for saving image ratings
-  if entry.file_name().into_string().is_err() {
-    return false;
+  let is_link = entry.file_type().is_ok_and(|file_type| file_type.is_symlink());
+  if is_link && !follow_symlinks {
+    return EntryKind::Other;
+  }
+
+  match fs::metadata(&path) { // follows links
+    Ok(metadata) if metadata.is_file() => EntryKind::Image,
+    Ok(_) => EntryKind::Other,
+    Err(_) if is_link => EntryKind::BrokenLink,
+    Err(_) => EntryKind::Other
   }
+}
 
+pub(super) fn file_is_relevant(entry: &DirEntry, follow_symlinks: bool)->bool {
+  matches!(classify_entry(entry, follow_symlinks), EntryKind::Image)
+}
+
+  // JPG, GIF, WebP, MP4 and MOV files, except the ._ files macOS leaves on other file systems
+fn has_image_name(path: &Path)->bool {
   let ext_str = path.extension().and_then(|ext| ext.to_str());
 
   if ext_str.is_none() { // no extension, or no unicode extension
     return false;
   }
   let ext_lowercase = ext_str.unwrap().to_lowercase();
-  let ext_matches = ext_lowercase == "jpg" || ext_lowercase == "jpeg";
+  let ext_matches = ["jpg", "jpeg", "gif", "webp", "mp4", "mov"].contains(&ext_lowercase.as_str());
 
-  let stem_str = path.file_stem().and_then(|stem| stem.to_str());
-  if stem_str.is_none() { // no stem, or no unicode stem
-    return false;
-  }
-  let stem_okay = !stem_str.unwrap().starts_with("._");
+    // the stem may not be valid unicode, see `name_key`
+  let stem_okay = path.file_stem().is_some_and(|stem| !stem.as_encoded_bytes().starts_with(b"._"));
 
   ext_matches && stem_okay
 }
@@ -258,6 +1647,8 @@ pub enum DirLoadError {
   NoRelevantImages,
   IoError(io::Error),
   RatingsLoadError(RatingsLoadError),
+  FolderFileError(FolderFileError), // of the files the folder's orientations, rejects and the like are kept in
+  ScanStopped, // the scan thread ended without a result
 }
 
 impl fmt::Display for DirLoadError {
@@ -268,6 +1659,8 @@ impl fmt::Display for DirLoadError {
       NoRelevantImages => write!(f, "Given directory does not contain any images to display"),
       IoError(error) => write!(f, "Could not read directory entries: {}", error),
       RatingsLoadError(error) => write!(f, "Could not load the ratings file: {}", error),
+      FolderFileError(error) => write!(f, "Could not load the folder's settings: {}", error),
+      ScanStopped => write!(f, "The folder scan stopped unexpectedly"),
     }
   }
 }
@@ -280,6 +1673,8 @@ impl Error for DirLoadError {
       NoRelevantImages => None,
       IoError(error) => Some(error),
       RatingsLoadError(error) => Some(error),
+      FolderFileError(error) => Some(error),
+      ScanStopped => None,
     }
   }
 }
@@ -296,45 +1691,93 @@ impl From<RatingsLoadError> for DirLoadError {
   }
 }
 
+impl From<FolderFileError> for DirLoadError {
+  fn from(error: FolderFileError)->Self {
+    DirLoadError::FolderFileError(error)
+  }
+}
+
 struct ImageRatings {
   ratings_data: RatingsData,
-  folder_path: PathBuf,
   ratings_file_path: PathBuf,
+  library: Option<RatingsLibrary> // the copy of the ratings collected across folders, if it is enabled
 }
 
 impl ImageRatings {
     // the HashMap would ideally be a HashSet, but there doesn't seem to be an easy way to pretend it is one
-  fn new<V>(folder_path: &Path, known_images: &HashMap<String, V>)->Result<ImageRatings, RatingsLoadError> {
-    let folder_path = folder_path.to_path_buf();
-
-    let mut ratings_file_path = folder_path.clone();
-    ratings_file_path.push("ratings.yaml");
+  fn new<V>(folder_path: &Path, ratings_file: &RatingsFile, known_images: &HashMap<String, V>)->Result<ImageRatings, RatingsLoadError> {
+    let ratings_file_path = ratings_file.path(folder_path);
 
-    let ratings_data = RatingsData::load(&ratings_file_path, known_images)?;
+      // ratings saved before another location was chosen move there with the next rating
+    let load_path = match ratings_file.fallback_path(folder_path) {
+      Some(fallback_path) if !ratings_file_path.exists() => fallback_path,
+      _ => ratings_file_path.clone()
+    };
+    let ratings_data = RatingsData::load(&load_path, known_images)?;
 
     Ok(ImageRatings {
       ratings_data,
-      folder_path,
       ratings_file_path,
+      library: None
     })
   }
 
+    // adds the folder to the library, and keeps it up to date with the ratings saved from now on.
+    // the library is a copy, failing to keep it doesn't keep the ratings from being saved
+  fn collect_in_library(&mut self, folder_path: &Path) {
+    self.library = match RatingsLibrary::open(folder_path) {
+      Ok(library) => library,
+      Err(error) => {
+        warn!("Couldn't open the ratings library for {}, not collecting its ratings: {}", folder_path.display(), error);
+        None
+      }
+    };
+    self.save_to_library();
+  }
+
+  fn save_to_library(&mut self) {
+    if let Some(ref mut library) = self.library {
+      if let Err(error) = library.save(self.ratings_data.ratings.iter()) {
+        warn!("Couldn't save the ratings to the ratings library: {}", error);
+      }
+    }
+  }
+
   fn set_rating(&mut self, img_name: String, rating: Rating)->Result<(), RatingsSaveError> {
     self.ratings_data.ratings.insert(img_name, rating);
     self.save_ratings()
   }
 
+  fn set_ratings<I>(&mut self, img_names: I, rating: Rating)->Result<(), RatingsSaveError>
+    where I: IntoIterator<Item=String> {
+
+    for img_name in img_names {
+      self.ratings_data.ratings.insert(img_name, rating);
+    }
+    self.save_ratings()
+  }
+
+    // for images added to the folder after it was loaded. they may have a rating from an earlier file of the same name
+  fn add_image(&mut self, img_name: String) {
+    let rating = self.ratings_data.orphaned_ratings.remove(&img_name).unwrap_or(Rating::Low);
+    self.ratings_data.ratings.insert(img_name, rating);
+  }
+
   fn get_rating(&self, img_name: &String)->Rating {
     *self.ratings_data.ratings.get(img_name).unwrap()
   }
 
-  fn save_ratings(&self)->Result<(), RatingsSaveError> {
+  fn save_ratings(&mut self)->Result<(), RatingsSaveError> {
     let s = serde_yaml::to_string(&self.ratings_data)?;
 
-    let mut tmp_file = tempfile::NamedTempFile::new_in(&self.folder_path)?;
+      // the hidden and central folders are only created once there is something to keep in them
+    let file_folder = self.ratings_file_path.parent().ok_or(RatingsSaveError::NoFolder)?;
+    fs::create_dir_all(file_folder)?;
+    let mut tmp_file = tempfile::NamedTempFile::new_in(file_folder)?;
     tmp_file.as_file_mut().write(s.as_bytes())?;
     tmp_file.persist(&self.ratings_file_path)?;
 
+    self.save_to_library();
     Ok(())
   }
 
@@ -343,6 +1786,58 @@ impl ImageRatings {
   }
 }
 
+  // What there is to know about an image without loading it
+pub struct ImageSummary {
+  pub path: PathBuf,
+  pub file_name: String,
+  pub rating: Rating,
+  pub orientation: Option<ImageRotation> // the orientation the user chose, replacing the one in the file
+}
+
+  // where `coll_idx` is in `active_idxs`, or where it would go if it isn't there. they are in collection order, unless they follow the order of the selects,
+  // where the place of images that aren't selects is the start
+fn active_position(active_idxs: &[usize], selects_order: bool, coll_idx: usize)->Result<usize, usize> {
+  if selects_order {
+    active_idxs.iter().position(|&idx| idx == coll_idx).ok_or(0)
+  } else {
+    active_idxs.binary_search(&coll_idx)
+  }
+}
+
+  // Which images are traversed. Each filter that is set narrows them down further
+#[derive(Debug, Clone, Default)]
+struct Filters {
+  rating: Option<Rating>,
+  metadata: MetadataFilter,
+  file_type: Option<FileType>,
+  rejected: Option<bool>, // only the rejected images if true, and only the others if false
+  selects_only: bool, // only the selects, in their order rather than the collection's
+  selection_only: bool
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum FileType {
+  Jpeg,
+  Raw, // images shot with a raw file alongside them
+  MissingRaw, // jpegs without a raw file
+  Video
+}
+
+impl FileType {
+  pub fn all()->[FileType; 4] {
+    [FileType::Jpeg, FileType::Raw, FileType::MissingRaw, FileType::Video]
+  }
+
+  pub fn label(self)->&'static str {
+    match self {
+      FileType::Jpeg => "JPEGs",
+      FileType::Raw => "Images with raw files",
+      FileType::MissingRaw => "JPEGs without raw files",
+      FileType::Video => "Videos"
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Rating {
   Low,
@@ -371,6 +1866,14 @@ impl Rating {
   }
 
   pub fn max()->u8 { return 2; }
+
+  pub fn name(&self)->&'static str {
+    match self {
+      Rating::Low => "Low",
+      Rating::Medium => "Medium",
+      Rating::High => "High"
+    }
+  }
 }
 
 struct RatingsData {
@@ -436,6 +1939,7 @@ impl Serialize for RatingsData {
 
 #[derive(Debug)]
 pub enum RatingsSaveError {
+  NoFolder, // the ratings file path has no folder to save it in
   SerializeError(serde_yaml::Error),
   WriteError(io::Error),
   PersistError(tempfile::PersistError)
@@ -445,6 +1949,7 @@ impl fmt::Display for RatingsSaveError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
     use self::RatingsSaveError::*;
     match self {
+      NoFolder => write!(f, "The ratings file has no folder to be saved in"),
       SerializeError(error) => write!(f, "Could not serialize the ratings map: {}", error),
       WriteError(error) => write!(f, "Could not write ratings to file: {}", error),
       PersistError(error) => write!(f, "Could not persist the temporary ratings file: {}", error),
@@ -456,6 +1961,7 @@ impl Error for RatingsSaveError {
   fn source(&self)->Option<&(dyn Error + 'static)> {
     use self::RatingsSaveError::*;
     match self {
+      NoFolder => None,
       SerializeError(error) => Some(error),
       WriteError(error) => Some(error),
       PersistError(error) => Some(error)
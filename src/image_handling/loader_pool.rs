@@ -1,59 +1,475 @@
-use std::path::PathBuf;
-use std::sync::mpsc::Sender;
-use crate::image::ImageData;
-use crate::worker_pool::{WorkerPool, Worker};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::image::{self, ImageData, ImageLoadError, ImageQuality, TextureCompression};
+use crate::video;
+use crate::worker_pool::{WorkerPool, Worker, CancelToken, Priority, PoolStats, PoolActivity, TaskSubmitter, ShutdownError};
+use crate::metadata;
+use crate::remote_control::RemoteRequest;
 use glium::glutin::event_loop::{EventLoop, EventLoopProxy, EventLoopClosed};
+use super::preview_cache::{PreviewCache, CompressedPreview};
+use super::thumbnails::Thumbnail;
+use super::file_read::{self, FileReadError, FileContents};
+use log::{error, warn};
 
-  // using separate channels to notify about load, and actually send the load,
-  // because the payload for winit user events is constrained to be Clone, which is not what I want.
-#[derive(Debug)]
+  // What the background threads tell the event loop. Results of the worker pools travel along with their notification,
+  // the indices and tokens in them tell which request they belong to
 pub enum LoadNotification {
-  ImageLoaded,
-  LoadFailed
+  ImageLoaded(Box<LoadResult>), // boxed, the image data would make every notification as large as it
+  ImageHashed { hash: Option<u64>, idx: usize, token: CancelToken }, // a result of the duplicate search. None if the image couldn't be loaded
+  ThumbnailMade { thumbnail: Option<Thumbnail>, idx: usize, token: CancelToken }, // None if the image couldn't be loaded
+  PreviewPrefetched { preview: Option<CompressedPreview>, idx: usize, token: CancelToken }, // None if the image couldn't be loaded
+  MetadataRead, // the metadata scan read another image
+  IntegrityChecked, // the integrity scan checked another file
+  ImagesArrived, // the folder watch found new images
+  FolderScanProgress, // the scan of a folder being loaded found more files
+  FolderListed, // the scan of a folder being loaded found more images to show
+  FolderScanned, // the scan of a folder being loaded completed
+  LoadFailed(Box<LoadFailure>),
+  LoadDropped { idx: usize, token: CancelToken }, // the load was dropped from a full queue of the loader pool, it can be requested again
+  ActionTriggered, // an input device other than the keyboard triggered an action
+  RemoteRequest(RemoteRequest)
+}
+
+pub struct LoadResult {
+  pub data: ImageData,
+  pub idx: usize, // collection index the load was requested for
+  pub token: CancelToken,
+  pub load_time: Duration // how long loading and preparing the image took, for profiling
+}
+
+  // A load that failed, with the request it failed for
+pub struct LoadFailure {
+  pub request: LoadRequest,
+  pub error: LoadError
+}
+
+#[derive(Clone)]
+pub struct LoadRequest {
+  pub path: PathBuf,
+  pub idx: usize, // collection index of the image
+  pub token: CancelToken,
+  pub fit_size: Option<[u32; 2]>, // decode at a resolution just large enough to fit into this size. full resolution if None
+  pub send_preview: bool, // whether to send a low resolution preview before the actual load result
+  pub preview_only: bool, // whether the preview is the load result, without the image being decoded in full. only for jpegs, others are loaded as usual
+  pub prefetched_preview: Option<Arc<CompressedPreview>> // sent as the preview instead of looking it up in the preview cache, see `PreviewPrefetch`
+}
+
+  // Images are loaded in two stages, with their own threads: reading the file, and decoding it.
+  // Reads are mostly spent waiting on the disk, and spinning disks and network drives slow down under many of them at once,
+  // while decodes keep a cpu core busy. So each stage can have as many threads as suits it, set with `LoaderThreadCounts`
+  // The first decode thread only decodes the shown image, so it never waits for prefetched images to decode.
+  // Both stages have a limited queue, so quickly moving through a folder doesn't pile up files waiting to be decoded. Loads dropped from a full
+  // queue are reported, see `LoadDropped`. Decodes also wait while too much decoded data hasn't been received yet, see `MAX_UNRECEIVED_BYTES`
+pub struct LoaderPool {
+  read_pool: WorkerPool<ReadWorker>, // dropped first, so no reads are handed to the decode pool after it stopped
+  decode_pool: WorkerPool<DecodeWorker>,
+  stopping: Arc<AtomicBool> // set when the pool is dropped, so decode workers don't wait for data to be received anymore
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoaderThreadCounts {
+  pub read: usize,
+  pub decode: usize
+}
+
+  // how many loads may wait for a read worker, and how many read files per decode worker may wait to be decoded
+const READ_QUEUE_LIMIT: usize = 16;
+const DECODE_QUEUE_LIMIT_PER_THREAD: usize = 2;
+  // decode workers don't start decoding another image while the decoded data sent but not received yet is more than this
+const MAX_UNRECEIVED_BYTES: usize = 256 * 1024 * 1024;
+const UNRECEIVED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+  // if the cpu core count isn't known
+const DEFAULT_DECODE_THREAD_COUNT: usize = 4;
+
+impl LoaderPool {
+    // returns the load that was dropped to make room in the queue, if it was full
+  pub fn submit(&self, request: LoadRequest, priority: Priority)->Option<LoadRequest> {
+    self.read_pool.submit(ReadRequest { request, priority }, priority).map(|read_request| read_request.request)
+  }
+
+    // assigns new priorities to the loads that are waiting for either stage. loads `prioritize` returns None for are dropped
+  pub fn reprioritize<F>(&self, mut prioritize: F)
+    where F: FnMut(&LoadRequest)->Option<Priority> {
+
+    self.read_pool.reprioritize(|read_request| {
+      let priority = prioritize(&read_request.request)?;
+      read_request.priority = priority;
+      Some(priority)
+    });
+    self.decode_pool.reprioritize(|decode_request| prioritize(&decode_request.request));
+  }
+
+  pub fn read_stats(&self)->PoolStats {
+    self.read_pool.stats()
+  }
+
+  pub fn decode_stats(&self)->PoolStats {
+    self.decode_pool.stats()
+  }
+
+  pub fn activity(&self)->PoolActivity {
+    self.read_pool.activity().and(self.decode_pool.activity())
+  }
+
+    // starts or stops threads of either stage, see `WorkerPool::resize`
+  pub fn resize(&mut self, thread_counts: LoaderThreadCounts) {
+    self.read_pool.resize(thread_counts.read);
+    self.decode_pool.resize(thread_counts.decode);
+    self.decode_pool.set_queue_limit(Some(decode_queue_limit(self.decode_pool.stats().worker_count)));
+  }
+
+    // see `WorkerPool::shutdown`. reads are stopped first, so that they don't hand files to the stopped decode pool
+  pub fn shutdown(&mut self, deadline: Instant)->Result<(), ShutdownError> {
+    self.stopping.store(true, Ordering::Relaxed);
+    let read_res = self.read_pool.shutdown(deadline);
+    ShutdownError::combine(read_res, self.decode_pool.shutdown(deadline))
+  }
+}
+
+impl Drop for LoaderPool {
+  fn drop(&mut self) {
+    self.stopping.store(true, Ordering::Relaxed);
+  }
+}
+
+pub struct ReadRequest {
+  request: LoadRequest,
+  priority: Priority // the load's priority in the read pool, to be decoded with the same priority
+}
+
+pub struct ReadWorker {
+  event_loop_proxy: EventLoopProxy<LoadNotification>,
+  preview_cache: Option<Arc<PreviewCache>>,
+  memory_mapped: bool, // whether files are mapped into memory instead of read, see `file_read::read_file`
+  decode_pool: TaskSubmitter<DecodeRequest>
+}
+
+impl Worker for ReadWorker {
+  type Input = ReadRequest;
+  type Output = (); // the file goes on to the decode pool
+  type Error = FileReadError;
+
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    let ReadRequest { ref request, priority } = *input;
+    let path = &request.path;
+
+      // the image went out of the load set while this request was queued, skip it entirely.
+      // nothing is sent, so the event loop never expects a result for it
+    if request.token.is_cancelled() {
+      return Ok(());
+    }
+
+      // cached and prefetched previews are sent before the file is read, which can take a while on network drives.
+      // they are prepared for upload in the decode pool, which gets to them before the file since they were submitted first
+    let start = Instant::now();
+    let wants_preview = request.send_preview && image::is_jpeg(path);
+    let cached_preview = if wants_preview { self.cached_preview(request) } else { None };
+    let preview_sent = cached_preview.is_some();
+    if let Some(preview_data) = cached_preview {
+      self.submit_decode(DecodeRequest { request: request.clone(), input: DecodeInput::CachedPreview(Some(Box::new(preview_data))), start }, priority);
+        // the file isn't needed at all then
+      if request.preview_only {
+        return Ok(());
+      }
+    }
+
+      // the video module opens clips itself through `load_video_still`, so nothing is read for them here
+    let load_start = Instant::now();
+    let file = if video::is_video(path) {
+      FileContents::Read(Vec::new())
+    } else {
+      match file_read::read_file(path, &request.token, self.memory_mapped) {
+        Ok(file) => file,
+        Err(FileReadError::Cancelled) => return Ok(()),
+        Err(error) => return Err(error)
+      }
+    };
+
+    if request.token.is_cancelled() {
+      return Ok(());
+    }
+    self.submit_decode(DecodeRequest { request: request.clone(), input: DecodeInput::File { file, preview_sent }, start: load_start }, priority);
+    Ok(())
+  }
+}
+
+impl ReadWorker {
+    // the prefetched preview of the image if it has one, which saves going to the disk, or else the one in the preview cache
+  fn cached_preview(&self, request: &LoadRequest)->Option<ImageData> {
+    if let Some(ref preview) = request.prefetched_preview {
+      match preview.decode() {
+        Ok(preview_data) => return Some(preview_data),
+        Err(error) => warn!("Read worker: Couldn't decode the prefetched preview of {}, {}", request.path.display(), error)
+      }
+    }
+    self.preview_cache.as_ref()?.get(&request.path)
+  }
+
+    // the dropped decode can be of any load. a dropped cached preview isn't reported, the load goes on with its file, unless only the preview was wanted
+  fn submit_decode(&self, decode_request: DecodeRequest, priority: Priority) {
+    if let Some(DecodeRequest { request, input, .. }) = self.decode_pool.submit(decode_request, priority) {
+      if matches!(input, DecodeInput::File { .. }) || request.preview_only {
+        notify(&self.event_loop_proxy, LoadNotification::LoadDropped { idx: request.idx, token: request.token });
+      }
+    }
+  }
+}
+
+pub struct DecodeRequest {
+  request: LoadRequest,
+  input: DecodeInput,
+  start: Instant // when the load started, for profiling. before the file was read, so the load time includes reading it
+}
+
+enum DecodeInput {
+  CachedPreview(Option<Box<ImageData>>), // only needs to be prepared for upload. taken out while it is. boxed, since image data is much larger than the other variant
+  File { file: FileContents, preview_sent: bool } // the image file, and whether a cached preview was sent already
 }
 
-pub struct LoadWorker {
+pub struct DecodeWorker {
   id: usize,
   event_loop_proxy: EventLoopProxy<LoadNotification>,
+  preview_cache: Option<Arc<PreviewCache>>,
+  color_management: bool,
+  compress_textures: bool, // whether images are compressed for their textures here, see `TextureCompression::Cpu`
+  sent_bytes: Arc<AtomicUsize>, // decoded data sent, but not received yet
+  stopping: Arc<AtomicBool>
 }
 
-impl Worker for LoadWorker {
-  type Input = (PathBuf, usize);
-  type Output = (ImageData, usize);
-
-  fn execute(&mut self, input: Self::Input, output: &Sender<Self::Output>) {
-    let (path, idx) = input;
-    let img_data_res = ImageData::load(&path);
-    let event_message = 
-      if let Ok(img_data) = img_data_res {
-        let output_data = (img_data, idx);
-        let send_res = output.send(output_data);
-        match send_res {
-          Ok(_) => {
-            LoadNotification::ImageLoaded
-          },
-          Err(error) => {
-            println!("Worker {}: channel send failed, {}", self.id, error);
-            LoadNotification::LoadFailed
-          }
-        }
-      } else {
-        LoadNotification::LoadFailed
-      };
-
-    match self.event_loop_proxy.send_event(event_message) {
-      Ok(()) => {},
-      Err(EventLoopClosed(_)) => println!("Worker {}: Event loop closed", self.id)
+impl Worker for DecodeWorker {
+  type Input = DecodeRequest;
+  type Output = Option<LoadResult>; // None if the load was cancelled
+  type Error = ImageLoadError;
+
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    let DecodeRequest { ref request, ref mut input, start } = *input;
+    let LoadRequest { ref path, idx, ref token, fit_size, send_preview, preview_only, .. } = *request;
+
+    if token.is_cancelled() {
+      return Ok(None);
+    }
+
+    let (file, preview_sent) = match input {
+      DecodeInput::CachedPreview(preview_data) => {
+        return Ok(preview_data.take().map(|preview_data| {
+          let mut preview_data = *preview_data;
+          self.prepare_for_upload(&mut preview_data, path, &[]);
+          self.load_result(preview_data, idx, token, start)
+        }));
+      },
+      DecodeInput::File { file, preview_sent } => (file, *preview_sent)
     };
+
+      // the event loop takes in the results as it gets to their notifications. until it caught up, decoding more would only pile up memory
+    while self.sent_bytes.load(Ordering::Relaxed) > MAX_UNRECEIVED_BYTES {
+      if token.is_cancelled() || self.stopping.load(Ordering::Relaxed) {
+        return Ok(None);
+      }
+      thread::sleep(UNRECEIVED_POLL_INTERVAL);
+    }
+
+    if preview_only && image::is_jpeg(path) {
+      match decode_preview(path, file, self.preview_cache.as_deref()) {
+        Ok(mut preview_data) => {
+          self.prepare_for_upload(&mut preview_data, path, file);
+          return Ok(Some(self.load_result(preview_data, idx, token, start)));
+        },
+        Err(error) => warn!("Decode worker {}: Couldn't load preview for {}, loading it in full instead. {}", self.id, path.display(), error)
+      }
+    }
+
+      // send a quick low resolution preview first, so that something can be shown while the full resolution image decodes.
+      // failing to create a preview isn't an error, the full load still follows. only jpegs can be decoded quickly enough for previews
+    if send_preview && image::is_jpeg(path) && !preview_sent {
+      let preview_start = Instant::now();
+        // the exif thumbnail shows right away, while the preview decodes. the preview is still worth it, it's sharper and gets cached
+      match ImageData::load_exif_thumbnail(file) {
+        Ok(Some(mut thumbnail_data)) => {
+          self.prepare_for_upload(&mut thumbnail_data, path, file);
+          notify(&self.event_loop_proxy, LoadNotification::ImageLoaded(Box::new(self.load_result(thumbnail_data, idx, token, preview_start))));
+        },
+        Ok(None) => {},
+        Err(error) => warn!("Decode worker {}: Couldn't load the exif thumbnail of {}, {}", self.id, path.display(), error)
+      }
+
+      match decode_preview(path, file, self.preview_cache.as_deref()) {
+        Ok(mut preview_data) => {
+          self.prepare_for_upload(&mut preview_data, path, file);
+          notify(&self.event_loop_proxy, LoadNotification::ImageLoaded(Box::new(self.load_result(preview_data, idx, token, preview_start))));
+        },
+        Err(error) => warn!("Decode worker {}: Couldn't load preview for {}, {}", self.id, path.display(), error)
+      }
+    }
+    if token.is_cancelled() {
+      return Ok(None);
+    }
+
+    let mut img_data = match fit_size {
+        // the scaled decode doesn't handle every jpeg, the full load is the fallback for those
+      Some(fit_size) => ImageData::load_to_fit_from_memory(path, file, fit_size).or_else(|error| {
+        warn!("Decode worker {}: Couldn't load {} scaled, loading at full resolution instead. {}", self.id, path.display(), error);
+        ImageData::load_from_memory(path, file)
+      }),
+      None => ImageData::load_from_memory(path, file)
+    }?;
+    self.prepare_for_upload(&mut img_data, path, file);
+    Ok(Some(self.load_result(img_data, idx, token, start)))
+  }
+}
+
+impl DecodeWorker {
+    // counts the data as sent, it is sent right after
+  fn load_result(&self, data: ImageData, idx: usize, token: &CancelToken, start: Instant)->LoadResult {
+    self.sent_bytes.fetch_add(data.byte_size(), Ordering::Relaxed);
+    LoadResult { data, idx, token: token.clone(), load_time: start.elapsed() }
+  }
+
+    // the expensive processing that needs to happen before a texture upload, done here to keep it off the main thread.
+    // `file` is the image file read into memory, and empty for cached previews, which are shown without a location and exposure settings until the image loads
+  fn prepare_for_upload(&self, data: &mut ImageData, path: &Path, file: &[u8]) {
+    if self.color_management {
+        // the colors are off without the conversion, but the image is still worth showing
+      if let Err(error) = data.convert_to_srgb() {
+        warn!("Decode worker {}: Couldn't color manage {}, {}", self.id, path.display(), error);
+      }
+    }
+      // previews are too small for the sharpness to tell blurry and sharp images apart
+    if data.quality != ImageQuality::Preview {
+      data.measure_sharpness();
+    }
+      // only jpegs from cameras and phones carry a location and exposure settings
+    if image::is_jpeg(path) && !file.is_empty() {
+      (data.location, data.exposure) = metadata::read_embedded(path, file);
+    }
+    data.generate_mipmaps();
+    if self.compress_textures {
+      data.compress_mipmaps();
+    }
+  }
+}
+
+fn notify(event_loop_proxy: &EventLoopProxy<LoadNotification>, notification: LoadNotification) {
+  if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(notification) {
+    error!("Loader: Event loop closed");
+  }
+}
+
+  // the low resolution preview of a jpeg, from the preview cache if it has one. newly decoded previews are added to the cache
+pub(super) fn load_preview(path: &Path, preview_cache: Option<&PreviewCache>)->Result<ImageData, ImageLoadError> {
+  if let Some(preview_cache) = preview_cache {
+    if let Some(cached_preview) = preview_cache.get(path) {
+      return Ok(cached_preview);
+    }
   }
+
+  decode_preview(path, &std::fs::read(path)?, preview_cache)
+}
+
+  // decodes the preview of a jpeg that was read into memory, and adds it to the preview cache
+fn decode_preview(path: &Path, file: &[u8], preview_cache: Option<&PreviewCache>)->Result<ImageData, ImageLoadError> {
+  let preview = ImageData::load_preview_from_memory(file)?;
+
+  if let Some(preview_cache) = preview_cache {
+    if let Err(error) = preview_cache.insert(path, &preview) {
+      warn!("Couldn't cache preview for {}, {}", path.display(), error);
+    }
+  }
+
+  Ok(preview)
 }
 
-pub type LoaderPool = WorkerPool<LoadWorker>;
-pub fn new(size: usize, event_loop: &EventLoop<LoadNotification>)->LoaderPool {
-  WorkerPool::new(size, |id| {
-    LoadWorker {
-      id: id,
-      event_loop_proxy: event_loop.create_proxy()
+  // `sent_bytes` counts the bytes of decoded data in notifications the event loop didn't handle yet. the event loop subtracts what it takes in
+pub fn new(thread_counts: LoaderThreadCounts, event_loop: &EventLoop<LoadNotification>, preview_cache: Option<Arc<PreviewCache>>, color_management: bool, memory_mapped: bool, texture_compression: TextureCompression, sent_bytes: Arc<AtomicUsize>)->LoaderPool {
+  let stopping = Arc::new(AtomicBool::new(false));
+  let decode_thread_count = thread_counts.decode.max(1);
+  let mut decode_pool = WorkerPool::with_queue_limit(decode_thread_count, Some(decode_queue_limit(decode_thread_count)), {
+    let event_loop_proxy = event_loop.create_proxy();
+    let preview_cache = preview_cache.clone();
+    let stopping = Arc::clone(&stopping);
+    move |id| {
+      DecodeWorker {
+        id,
+        event_loop_proxy: event_loop_proxy.clone(),
+        preview_cache: preview_cache.clone(),
+        color_management,
+        compress_textures: texture_compression == TextureCompression::Cpu,
+        sent_bytes: Arc::clone(&sent_bytes),
+        stopping: Arc::clone(&stopping)
+      }
+    }
+  }, {
+    let event_loop_proxy = event_loop.create_proxy();
+    move |decode_request: DecodeRequest, result| match result {
+      Ok(Some(load_result)) => notify(&event_loop_proxy, LoadNotification::ImageLoaded(Box::new(load_result))),
+      Ok(None) => {},
+      Err(error) => notify(&event_loop_proxy, LoadNotification::LoadFailed(Box::new(LoadFailure { request: decode_request.request, error: LoadError::Decode(error) })))
     }
-  })
-}
\ No newline at end of file
+  });
+    // the shown image always has top priority, see `LoadedDir::update_loaded`
+  decode_pool.reserve_worker(0);
+
+  let read_pool = WorkerPool::with_queue_limit(thread_counts.read.max(1), Some(READ_QUEUE_LIMIT), {
+    let event_loop_proxy = event_loop.create_proxy();
+    let decode_submitter = decode_pool.submitter();
+    move |_| {
+      ReadWorker {
+        event_loop_proxy: event_loop_proxy.clone(),
+        preview_cache: preview_cache.clone(),
+        memory_mapped,
+        decode_pool: decode_submitter.clone()
+      }
+    }
+  }, {
+    let event_loop_proxy = event_loop.create_proxy();
+    move |read_request: ReadRequest, result| {
+      if let Err(error) = result {
+        notify(&event_loop_proxy, LoadNotification::LoadFailed(Box::new(LoadFailure { request: read_request.request, error: LoadError::Read(error) })));
+      }
+    }
+  });
+
+  LoaderPool {
+    read_pool,
+    decode_pool,
+    stopping
+  }
+}
+
+fn decode_queue_limit(decode_thread_count: usize)->usize {
+  decode_thread_count * DECODE_QUEUE_LIMIT_PER_THREAD
+}
+
+  // one decode thread per cpu core. the decode pool keeps one of them for the shown image, see `LoaderPool`
+pub fn default_decode_thread_count()->usize {
+  thread::available_parallelism().map_or(DEFAULT_DECODE_THREAD_COUNT, NonZeroUsize::get)
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+  Read(FileReadError),
+  Decode(ImageLoadError)
+}
+
+impl fmt::Display for LoadError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    match self {
+      LoadError::Read(error) => write!(f, "{}", error),
+      LoadError::Decode(error) => write!(f, "{}", error)
+    }
+  }
+}
+
+impl Error for LoadError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    match self {
+      LoadError::Read(error) => Some(error),
+      LoadError::Decode(error) => Some(error)
+    }
+  }
+}
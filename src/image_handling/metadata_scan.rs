@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
+use std::thread;
+use std::collections::{HashMap, BTreeSet};
+use crate::image;
+use crate::metadata::{ImageMetadata, CameraBody};
+use crate::worker_pool::CancelToken;
+use glium::glutin::event_loop::{EventLoopProxy, EventLoopClosed};
+use super::loader_pool::LoadNotification;
+use log::error;
+
+  // Reads the metadata of every image of a folder in the background, for filtering by it.
+  // Runs on a thread of its own, reading one file after the other. The reads are small,
+  // so they would mostly add overhead on the loader threads, and shouldn't hold up image loads there
+pub struct MetadataScan {
+  token: CancelToken,
+  paths: Sender<(usize, PathBuf)>, // of the images still to be scanned, see `extend`
+  results: Receiver<(usize, ImageMetadata)>,
+  metadata: HashMap<usize, ImageMetadata>, // keys index into the collection
+  remaining: usize // how many images haven't been read yet
+}
+
+impl MetadataScan {
+  pub fn start(images: Vec<(usize, PathBuf)>, event_loop_proxy: EventLoopProxy<LoadNotification>)->MetadataScan {
+    let token = CancelToken::new();
+    let (sender, receiver) = mpsc::channel();
+    let (paths, thread_paths) = mpsc::channel();
+    let remaining = images.len();
+    for image in images {
+      let _ = paths.send(image);
+    }
+
+    let thread_token = token.clone();
+    thread::spawn(move || {
+        // ends once the scan is dropped along with the sending end
+      for (idx, path) in thread_paths {
+          // a different folder was loaded in the meantime
+        if thread_token.is_cancelled() {
+          return;
+        }
+
+          // gifs, webps and videos don't carry exif data from a camera
+        let metadata = if image::is_jpeg(&path) {
+          ImageMetadata::read(&path)
+        } else {
+          ImageMetadata::default()
+        };
+
+        if sender.send((idx, metadata)).is_err() {
+          return;
+        }
+        if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::MetadataRead) {
+          error!("Metadata scan: Event loop closed");
+          return;
+        }
+      }
+    });
+
+    MetadataScan {
+      token,
+      paths,
+      results: receiver,
+      metadata: HashMap::new(),
+      remaining
+    }
+  }
+
+    // takes in the results read so far. returns true if the scan completed with them
+  pub fn receive(&mut self)->bool {
+    if self.is_complete() {
+      return false;
+    }
+
+    loop {
+      match self.results.try_recv() {
+        Ok((idx, metadata)) => {
+          self.metadata.insert(idx, metadata);
+          self.remaining -= 1;
+        },
+        Err(TryRecvError::Empty) => break,
+        Err(TryRecvError::Disconnected) => {
+          error!("Metadata scan: The scan thread stopped with {} images left", self.remaining);
+          self.remaining = 0;
+          break;
+        }
+      }
+    }
+
+    self.is_complete()
+  }
+
+    // for images the folder scan found after the scan started, which are read after the ones before
+  pub fn extend(&mut self, images: Vec<(usize, PathBuf)>) {
+    self.remaining += images.len();
+    for image in images {
+      let _ = self.paths.send(image);
+    }
+  }
+
+  pub fn is_complete(&self)->bool {
+    self.remaining == 0
+  }
+
+  pub fn remaining(&self)->usize {
+    self.remaining
+  }
+
+    // for images added to the folder after the scan started, whose metadata was read elsewhere
+  pub fn insert(&mut self, idx: usize, metadata: ImageMetadata) {
+    self.metadata.insert(idx, metadata);
+  }
+
+  pub fn get(&self, idx: usize)->Option<&ImageMetadata> {
+    self.metadata.get(&idx)
+  }
+
+    // the distinct values found so far, each in sorted order
+  pub fn cameras(&self)->Vec<CameraBody> {
+    let cameras: BTreeSet<_> = self.metadata.values().filter_map(|metadata| metadata.camera.clone()).collect();
+    cameras.into_iter().collect()
+  }
+
+  pub fn lenses(&self)->Vec<String> {
+    let lenses: BTreeSet<_> = self.metadata.values().filter_map(|metadata| metadata.lens.clone()).collect();
+    lenses.into_iter().collect()
+  }
+
+  pub fn isos(&self)->Vec<u32> {
+    let isos: BTreeSet<_> = self.metadata.values().filter_map(|metadata| metadata.iso).collect();
+    isos.into_iter().collect()
+  }
+}
+
+impl Drop for MetadataScan {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
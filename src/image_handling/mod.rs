@@ -1,46 +1,327 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::ops::RangeInclusive;
-use loader_pool::{LoaderPool, LoadNotification};
-use loaded_dir::{LoadedDir, DirLoadError};
-use glium::glutin::event_loop::EventLoop;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use loader_pool::{LoaderPool, LoaderThreadCounts, LoadNotification, LoadResult};
+use duplicates::HashPool;
+use thumbnails::ThumbnailPool;
+use preview_prefetch::PrefetchPool;
+use loaded_dir::LoadedDir;
+use folder_scan::{FolderScan, ScanPool, ScanMessage};
+use preview_cache::PreviewCache;
+use glium::glutin::event_loop::{EventLoop, EventLoopProxy};
+use log::warn;
+use crate::worker_pool::PoolStats;
+use crate::image::TextureCompression;
 
 mod loaded_dir;
 pub mod loader_pool;
-pub use loaded_dir::Rating;
+mod file_read;
+mod preview_cache;
+mod folder_file;
+mod orientations;
+mod rejects;
+mod selects;
+mod duplicates;
+mod thumbnails;
+mod preview_prefetch;
+mod metadata_scan;
+mod integrity_scan;
+mod folder_watch;
+mod folder_scan;
+mod session;
+mod statistics;
+mod ratings_file;
+mod ratings_library;
+mod journal;
+pub use loaded_dir::{Rating, DirLoadError, FileType, ImageSummary, read_folder_ratings, MAX_PINNED};
+pub use statistics::StatisticsSplit;
+pub use thumbnails::Thumbnail;
+pub use ratings_file::{RatingsFile, RatingsLocation, DEFAULT_RATINGS_FILE_NAME};
 
 pub struct ImageHandling {
   pub services: ImageHandlingServices,
-  pub loaded_dir: Option<LoadedDir>
+  pub loaded_dir: Option<LoadedDir>,
+  pub folder_scan: Option<FolderScan> // of the folder that replaces the loaded one once its first images are found, until it is listed completely
 }
 
 impl ImageHandling {
-  pub fn new(buffer_zone_count: usize, load_behind_count: usize, load_ahead_count: usize, thread_pool_size: usize, event_loop: &EventLoop<LoadNotification>)->ImageHandling {
-    let services = ImageHandlingServices::new(buffer_zone_count, load_behind_count, load_ahead_count, thread_pool_size, event_loop);
+  pub fn new(load_set_counts: LoadSetCounts, texture_memory_budget: usize, loader_threads: LoaderThreadCounts, color_management: bool, memory_mapped_reads: bool, texture_compression: TextureCompression, event_loop: &EventLoop<LoadNotification>)->ImageHandling {
+    let services = ImageHandlingServices::new(load_set_counts, texture_memory_budget, loader_threads, color_management, memory_mapped_reads, texture_compression, event_loop);
     ImageHandling {
       services,
-      loaded_dir: None
+      loaded_dir: None,
+      folder_scan: None
     }
   }
 
-  pub fn load_path(&mut self, path: &Path)->Result<(), DirLoadError> {
-    let loaded_dir = LoadedDir::new(path, &self.services)?;
-    self.loaded_dir = Some(loaded_dir);
-    Ok(())
+    // starts scanning the folder, see `receive_folder_scan`. replaces the scan of a folder loaded before, if it is still running
+  pub fn load_path(&mut self, path: &Path) {
+      // folders opened read-only aren't collected in the ratings library either
+    let ratings_file = RatingsFile { library: self.services.ratings_file.library && !self.services.read_only, ..self.services.ratings_file.clone() };
+    self.folder_scan = Some(FolderScan::start(path.to_path_buf(), self.services.follow_symlinks, ratings_file, &self.services.scan_pool));
+  }
+
+    // replaces the loaded folder with the scanned one once its first images are found, and adds the images found after them to it
+  pub fn receive_folder_scan(&mut self)->FolderScanUpdate {
+    let mut update = FolderScanUpdate::default();
+    let messages = match self.folder_scan {
+      Some(ref mut folder_scan) => folder_scan.receive(),
+      None => return update
+    };
+
+    for message in messages {
+      match message {
+        ScanMessage::Contents(contents) => {
+          self.save_session();
+          self.loaded_dir = Some(LoadedDir::new(*contents, &self.services));
+          update.replaced = true;
+        },
+        ScanMessage::Listed(listed) => {
+          if let Some(ref mut loaded_dir) = self.loaded_dir {
+            loaded_dir.add_listed(listed, &self.services);
+          }
+        },
+        ScanMessage::Done(result) => {
+          let folder_scan = self.folder_scan.take().unwrap();
+            // does nothing for the folder loaded before, if none of the scanned one's images were found
+          if let Some(ref mut loaded_dir) = self.loaded_dir {
+            loaded_dir.finish_listing(&self.services);
+          }
+          update.completed = Some((folder_scan.path().to_path_buf(), result));
+        }
+      }
+    }
+    update
+  }
+
+    // saves where the user left off in the loaded folder, to pick up from there the next time it is loaded
+  pub fn save_session(&self) {
+    if self.services.read_only {
+      return;
+    }
+    if let Some(ref loaded_dir) = self.loaded_dir {
+      if let Err(error) = loaded_dir.save_session() {
+        warn!("Couldn't save the session of {}: {}", loaded_dir.path().display(), error);
+      }
+    }
+  }
+
+    // how much memory image data takes up, between being decoded and being shown
+  pub fn memory_usage(&self)->MemoryUsage {
+    let loaded_dir_usage = self.loaded_dir.as_ref().map_or(MemoryUsage::default(), |loaded_dir| loaded_dir.memory_usage());
+    MemoryUsage {
+      decoded_bytes: self.services.loaded_bytes.load(Ordering::Relaxed) + loaded_dir_usage.decoded_bytes,
+      ..loaded_dir_usage
+    }
+  }
+
+    // hands an image the loader pool sent to the loaded folder. images loaded for a folder that was replaced since are dropped there
+  pub fn receive_image(&mut self, result: LoadResult) {
+    self.services.received_bytes(result.data.byte_size());
+    if let Some(ref mut loaded_dir) = self.loaded_dir {
+      loaded_dir.receive_image(result);
+    }
+  }
+
+    // stops the background work of the worker pools, waiting up to `timeout` for the workers to finish what they are doing.
+    // the loader pool goes first, since the thumbnail and prefetch pools wait for it to be idle
+  pub fn shutdown(&mut self, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    if let Err(error) = self.services.loader_pool.shutdown(deadline) {
+      warn!("Loader pool didn't shut down cleanly: {}", error);
+    }
+    if let Err(error) = self.services.hash_pool.shutdown(deadline) {
+      warn!("Hash pool didn't shut down cleanly: {}", error);
+    }
+    if let Err(error) = self.services.thumbnail_pool.shutdown(deadline) {
+      warn!("Thumbnail pool didn't shut down cleanly: {}", error);
+    }
+    if let Err(error) = self.services.prefetch_pool.shutdown(deadline) {
+      warn!("Prefetch pool didn't shut down cleanly: {}", error);
+    }
+    if let Err(error) = self.services.scan_pool.shutdown(deadline) {
+      warn!("Scan pool didn't shut down cleanly: {}", error);
+    }
+  }
+
+    // changes how many images are loaded around the shown one. the loaded folder's load set is made again right away,
+    // so loads that are no longer wanted are cancelled and newly wanted ones requested
+  pub fn set_load_set_counts(&mut self, counts: LoadSetCounts) {
+    self.services.loading_policy.set_counts(counts);
+    if let Some(ref mut loaded_dir) = self.loaded_dir {
+      loaded_dir.update_loaded(&self.services);
+    }
+  }
+
+    // whether folders loaded from now on show the images that symbolic links in them point to
+  pub fn set_follow_symlinks(&mut self, follow_symlinks: bool) {
+    self.services.follow_symlinks = follow_symlinks;
+  }
+
+    // while read-only, nothing is written about the folders that are loaded, like their sessions
+  pub fn set_read_only(&mut self, read_only: bool) {
+    self.services.read_only = read_only;
+  }
+
+    // for folders loaded after this
+  pub fn set_ratings_file(&mut self, ratings_file: RatingsFile) {
+    self.services.ratings_file = ratings_file;
+  }
+
+    // size in physical pixels that images are displayed in. images are decoded just large enough to fit into it
+  pub fn set_display_size(&mut self, size: [u32; 2]) {
+    self.services.decode_fit_size = Some(size);
+    if let Some(ref mut loaded_dir) = self.loaded_dir {
+      loaded_dir.reload_low_resolution(&self.services);
+    }
   }
 }
 
+  // What came of the folder scan since it was last received from, see `ImageHandling::receive_folder_scan`
+#[derive(Default)]
+pub struct FolderScanUpdate {
+  pub replaced: bool, // the scanned folder replaced the loaded one
+  pub completed: Option<(PathBuf, Result<(), DirLoadError>)> // the path of the folder and whether it could be loaded, once the scan is over
+}
+
 pub struct ImageHandlingServices {
   loader_pool: LoaderPool,
-  loading_policy: ImageLoadingPolicy 
+  hash_pool: HashPool, // for the duplicate search, kept separate so it doesn't hold up image loads
+  thumbnail_pool: ThumbnailPool, // for the filmstrip, only working while the loader pool has nothing to do
+  prefetch_pool: PrefetchPool, // for the previews of the whole folder, only working once the loader and thumbnail pools had nothing to do for a moment
+  scan_pool: ScanPool, // for the scans of folders being loaded
+  loading_policy: ImageLoadingPolicy,
+  event_loop_proxy: EventLoopProxy<LoadNotification>, // for the metadata and integrity scans, and folder watches
+  loaded_bytes: Arc<AtomicUsize>, // decoded image data the loader pool sent, that wasn't received yet
+  decode_fit_size: Option<[u32; 2]>, // None decodes images at full resolution
+  texture_compression: TextureCompression, // of the textures of loaded folders
+  follow_symlinks: bool, // whether folders show the images that symbolic links point to
+  ratings_file: RatingsFile, // where the ratings of folders are saved
+  read_only: bool
 }
 
 impl ImageHandlingServices {
-  fn new(buffer_zone_count: usize, load_behind_count: usize, load_ahead_count: usize, thread_pool_size: usize, event_loop: &EventLoop<LoadNotification>)->ImageHandlingServices {
-    let loader_pool = loader_pool::new(thread_pool_size, event_loop);
-    let loading_policy = ImageLoadingPolicy::new(buffer_zone_count, load_behind_count, load_ahead_count);
+  fn new(load_set_counts: LoadSetCounts, texture_memory_budget: usize, loader_threads: LoaderThreadCounts, color_management: bool, memory_mapped_reads: bool, texture_compression: TextureCompression, event_loop: &EventLoop<LoadNotification>)->ImageHandlingServices {
+    let preview_cache = open_preview_cache().map(Arc::new);
+    let loaded_bytes = Arc::new(AtomicUsize::new(0));
+    let loader_pool = loader_pool::new(loader_threads, event_loop, preview_cache.clone(), color_management, memory_mapped_reads, texture_compression, Arc::clone(&loaded_bytes));
+    let hash_pool = duplicates::new(HASH_POOL_SIZE, event_loop, preview_cache.clone());
+    let thumbnail_pool = thumbnails::new(THUMBNAIL_POOL_SIZE, event_loop, preview_cache.clone(), color_management, loader_pool.activity());
+    let prefetch_pool = preview_prefetch::new(PREFETCH_POOL_SIZE, event_loop, preview_cache, loader_pool.activity().and(thumbnail_pool.activity()));
+    let scan_pool = folder_scan::new(SCAN_POOL_SIZE, event_loop);
+    let loading_policy = ImageLoadingPolicy::new(load_set_counts, texture_memory_budget);
     ImageHandlingServices {
       loader_pool,
-      loading_policy
+      hash_pool,
+      thumbnail_pool,
+      prefetch_pool,
+      scan_pool,
+      loading_policy,
+      event_loop_proxy: event_loop.create_proxy(),
+      loaded_bytes,
+      decode_fit_size: None,
+      texture_compression,
+      follow_symlinks: true,
+      ratings_file: RatingsFile::default(),
+      read_only: false
+    }
+  }
+
+    // of the stages of the loader pool, reading files and decoding them
+  pub fn loader_stats(&self)->(PoolStats, PoolStats) {
+    (self.loader_pool.read_stats(), self.loader_pool.decode_stats())
+  }
+
+    // takes effect for the loads that start from now on
+  pub fn set_loader_threads(&mut self, thread_counts: LoaderThreadCounts) {
+    self.loader_pool.resize(thread_counts);
+  }
+
+  pub fn texture_memory_budget(&self)->usize {
+    self.loading_policy.texture_memory_budget
+  }
+
+    // to be called for every image the loader pool sent, whether it is used or not
+  fn received_bytes(&self, byte_size: usize) {
+    self.loaded_bytes.fetch_sub(byte_size, Ordering::Relaxed);
+  }
+}
+
+  // Bytes of image data held in memory. Texture sizes are estimates, see `ImageTexture::byte_size`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+  pub decoded_bytes: usize, // decoded images on their way to a texture: sent by the loader, queued for upload or being uploaded
+  pub texture_bytes: usize, // textures of the loaded images
+  pub pooled_texture_bytes: usize, // unused textures kept around for reuse
+  pub prefetched_preview_bytes: usize // compressed previews of the loaded folder, see `PreviewPrefetch`
+}
+
+const HASH_POOL_SIZE: usize = 2;
+const THUMBNAIL_POOL_SIZE: usize = 1;
+const PREFETCH_POOL_SIZE: usize = 1;
+  // a second worker starts the scan of a folder dropped right after another one, while the first scan is still waiting on the disk to notice it is stopped
+const SCAN_POOL_SIZE: usize = 2;
+const PREVIEW_CACHE_MAX_SIZE: u64 = 512 * 1024 * 1024;
+
+  // the app works fine without a preview cache, it can just only show previews once they are decoded
+fn open_preview_cache()->Option<PreviewCache> {
+  let dir = PreviewCache::default_dir()?;
+  match PreviewCache::open(dir.clone(), PREVIEW_CACHE_MAX_SIZE) {
+    Ok(preview_cache) => Some(preview_cache),
+    Err(error) => {
+      warn!("Couldn't open the preview cache at {}: {}", dir.display(), error);
+      None
+    }
+  }
+}
+
+  // How many images are loaded around the shown one, see `ImageLoadingPolicy`
+#[derive(Debug, Clone, Copy)]
+pub struct LoadSetCounts {
+  pub buffer_zone: usize, // how far the shown image can move from the pivot before the load set moves along
+  pub behind: usize, // beyond the buffer zone
+  pub ahead: usize
+}
+
+  // Which way the user is moving through the images, which the load set leans towards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+  Forward,
+  Backward
+}
+
+  // How quickly the user moves through the images, which the load set adapts to. See `ImageLoadingPolicy::pace`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NavigationPace {
+  Flicking, // several steps a second: the load set reaches further ahead, and only loads previews
+  Stepping,
+  Lingering // no step for a while: the load set reaches further both ways, at full quality
+}
+
+  // the pace is judged from the times of this many of the last steps
+const PACE_SAMPLE_STEPS: usize = 4;
+  // steps closer together than this on average are flicking through the images, as long as the next one follows as quickly
+const FLICK_STEP_INTERVAL: Duration = Duration::from_millis(300);
+  // the user lingers on an image once they didn't step for this long
+const LINGER_DURATION: Duration = Duration::from_secs(3);
+  // how much further ahead the load set reaches while flicking, and how much further both ways while lingering
+const FLICK_AHEAD_FACTOR: usize = 2;
+const LINGER_DEPTH_FACTOR: usize = 2;
+  // steps at least this far are jumps to another part of the folder, rather than moves through it
+const JUMP_DISTANCE: usize = 10;
+
+  // When the user last stepped from one image to the next, for the loading policy to judge their pace from
+#[derive(Debug, Default)]
+struct StepTimes(VecDeque<Instant>); // oldest first
+
+impl StepTimes {
+  fn record(&mut self, now: Instant) {
+    self.0.push_back(now);
+    if self.0.len() > PACE_SAMPLE_STEPS {
+      self.0.pop_front();
     }
   }
 }
@@ -48,28 +329,74 @@ impl ImageHandlingServices {
 struct ImageLoadingPolicy {
   buffer_zone_count: usize, // how many images ahead and behind you can move around before triggering new loads // :todo: naming.
   load_behind_count: usize,
-  load_ahead_count: usize
+  load_ahead_count: usize,
+  texture_memory_budget: usize // bytes of texture memory that loaded images outside the load set may take up
 }
 
 impl ImageLoadingPolicy {
-  fn new(buffer_zone_count: usize, load_behind_count: usize, load_ahead_count: usize)->ImageLoadingPolicy {
+  fn new(counts: LoadSetCounts, texture_memory_budget: usize)->ImageLoadingPolicy {
     ImageLoadingPolicy {
-      buffer_zone_count,
-      load_behind_count,
-      load_ahead_count
+      buffer_zone_count: counts.buffer_zone,
+      load_behind_count: counts.behind,
+      load_ahead_count: counts.ahead,
+      texture_memory_budget
     }
   }
 
+  fn set_counts(&mut self, counts: LoadSetCounts) {
+    self.buffer_zone_count = counts.buffer_zone;
+    self.load_behind_count = counts.behind;
+    self.load_ahead_count = counts.ahead;
+  }
+
   pub fn max_loaded_image_count(&self)->usize {
-    return 1 + self.buffer_zone_count * 2 + self.load_behind_count + self.load_ahead_count;
+    return 1 + self.buffer_zone_count * 2 + (self.load_behind_count + self.load_ahead_count) * LINGER_DEPTH_FACTOR;
+  }
+
+    // flicking once the last steps came quickly one after the other, lingering once there was no step for a while
+  fn pace(&self, step_times: &StepTimes, now: Instant)->NavigationPace {
+    let (Some(&first), Some(&last)) = (step_times.0.front(), step_times.0.back()) else {
+      return NavigationPace::Stepping;
+    };
+
+    let since_last = now.saturating_duration_since(last);
+    let full_sample = step_times.0.len() == PACE_SAMPLE_STEPS;
+    if full_sample && since_last < FLICK_STEP_INTERVAL && (last - first) / (PACE_SAMPLE_STEPS as u32 - 1) < FLICK_STEP_INTERVAL {
+      NavigationPace::Flicking
+    } else if since_last >= LINGER_DURATION {
+      NavigationPace::Lingering
+    } else {
+      NavigationPace::Stepping
+    }
+  }
+
+    // when the pace changes next if the user doesn't step again, from flicking to stepping to lingering
+  fn next_pace_change(&self, step_times: &StepTimes, now: Instant)->Option<Instant> {
+    let &last = step_times.0.back()?;
+    match self.pace(step_times, now) {
+      NavigationPace::Flicking => Some(last + FLICK_STEP_INTERVAL),
+      NavigationPace::Stepping => Some(last + LINGER_DURATION),
+      NavigationPace::Lingering => None
+    }
+  }
+
+    // jumps move the pivot to where they land, so the load set starts over around it even if the buffer zone reaches that far,
+    // and load ahead in their direction right away
+  fn is_jump(&self, offset: i32)->bool {
+    offset.unsigned_abs() as usize >= JUMP_DISTANCE
+  }
+
+    // while flicking through the images, they are only shown long enough to tell them apart, so their full decodes would be wasted
+  fn previews_suffice(&self, pace: NavigationPace)->bool {
+    pace == NavigationPace::Flicking
   }
 
-    // which images to load based on the policy, in order of priority
-  pub fn get_load_set(&self, pivot: usize, shown_idx: usize, max: usize)->(usize, Vec<usize>) { // new pivot, load range
+    // which images to load based on the policy, in order of priority. while moving backwards, the images before the pivot count as ahead
+  pub fn get_load_set(&self, pivot: usize, shown_idx: usize, max: usize, direction: Direction, pace: NavigationPace)->(usize, Vec<usize>) { // new pivot, load range
     if self.buffer_zone_range(pivot).contains(&(shown_idx as i32)) {
-      (pivot, self.load_set_around_pivot(pivot, max))
+      (pivot, self.load_set_around_pivot(pivot, max, direction, pace))
     } else {
-      (shown_idx, self.load_set_around_pivot(shown_idx, max))
+      (shown_idx, self.load_set_around_pivot(shown_idx, max, direction, pace))
     }
   }
 
@@ -80,9 +407,18 @@ impl ImageLoadingPolicy {
     start..=end
   }
 
-  fn load_set_around_pivot(&self, pivot: usize, max: usize)->Vec<usize> {
-    let start = (pivot as i32) - (self.buffer_zone_count as i32) - (self.load_behind_count as i32);
-    let end = (pivot as i32) + (self.buffer_zone_count as i32) + (self.load_ahead_count as i32);
+  fn load_set_around_pivot(&self, pivot: usize, max: usize, direction: Direction, pace: NavigationPace)->Vec<usize> {
+    let (behind_count, ahead_count) = match pace {
+      NavigationPace::Flicking => (self.load_behind_count, self.load_ahead_count * FLICK_AHEAD_FACTOR),
+      NavigationPace::Stepping => (self.load_behind_count, self.load_ahead_count),
+      NavigationPace::Lingering => (self.load_behind_count * LINGER_DEPTH_FACTOR, self.load_ahead_count * LINGER_DEPTH_FACTOR)
+    };
+    let (before_count, after_count) = match direction {
+      Direction::Forward => (behind_count, ahead_count),
+      Direction::Backward => (ahead_count, behind_count)
+    };
+    let start = (pivot as i32) - (self.buffer_zone_count as i32) - (before_count as i32);
+    let end = (pivot as i32) + (self.buffer_zone_count as i32) + (after_count as i32);
 
     let start = clamp(start, 0, (max - 1) as i32) as usize;
     let end = clamp(end, 0, (max - 1) as i32) as usize;
@@ -90,8 +426,12 @@ impl ImageLoadingPolicy {
     let mut idxs: Vec<_> = (start..=end).collect();
     idxs.sort_unstable_by(|&a, &b| {
 
-      let a_ahead = a >= pivot;
-      let b_ahead = b >= pivot;
+      let is_ahead = |idx: usize| match direction {
+        Direction::Forward => idx >= pivot,
+        Direction::Backward => idx <= pivot
+      };
+      let a_ahead = is_ahead(a);
+      let b_ahead = is_ahead(b);
 
       let a_dist = (pivot as i32 - a as i32).abs();
       let b_dist = (pivot as i32 - b as i32).abs();
@@ -99,7 +439,7 @@ impl ImageLoadingPolicy {
       // the sort function sorts in increasing size
       // so if we want a to be before b, the ordering should be Less, i.e. a < b
 
-        // prioritize loading any images ahead of (i.e. after) the pivot over those before
+        // prioritize loading any images ahead of (i.e. after, or before while moving backwards) the pivot over those behind
         // false compares as Less to true, so reverse to get Less if a is ahead, and b is not
       a_ahead.cmp(&b_ahead).reverse()
         .then(a_dist.cmp(&b_dist)) // if on the same side, prioritize images closer to the pivot
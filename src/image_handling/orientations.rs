@@ -0,0 +1,53 @@
+use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use crate::image::ImageRotation;
+use super::folder_file::{FolderFile, FolderFileError};
+use super::ratings_file::RatingsFile;
+use log::warn;
+
+  // Orientations the user chose for images whose exif orientation is wrong. These replace the exif orientation when the image is shown.
+  // Saved as a file name: exif orientation value map in an `orientations.yaml` file, kept where the ratings are
+pub struct ImageOrientations {
+  overrides: HashMap<String, ImageRotation>,
+  file: FolderFile
+}
+
+impl ImageOrientations {
+  pub fn new(folder_path: &Path, ratings_file: &RatingsFile)->Result<ImageOrientations, FolderFileError> {
+    let file = FolderFile::new(folder_path, "orientations.yaml", ratings_file);
+    let overrides = file.load::<HashMap<String, u32>>()?.map(parse_overrides).unwrap_or_default();
+
+    Ok(ImageOrientations {
+      overrides,
+      file
+    })
+  }
+
+  pub fn get(&self, img_name: &str)->Option<ImageRotation> {
+    self.overrides.get(img_name).copied()
+  }
+
+  pub fn set(&mut self, img_name: String, rotation: ImageRotation)->Result<(), FolderFileError> {
+    self.overrides.insert(img_name, rotation);
+    self.save()
+  }
+
+  fn save(&self)->Result<(), FolderFileError> {
+      // sorted, to keep the file diffable
+    let sorted: BTreeMap<_, _> = self.overrides.iter().map(|(img_name, rotation)| (img_name, rotation.to_exif_orientation())).collect();
+    self.file.save(&sorted)
+  }
+}
+
+  // entries for images that are no longer in the folder are kept, they don't get in the way
+fn parse_overrides(deser_map: HashMap<String, u32>)->HashMap<String, ImageRotation> {
+  deser_map.into_iter()
+    .filter_map(|(img_name, orientation)| {
+      let rotation = ImageRotation::from_exif_orientation(orientation);
+      if rotation.is_none() {
+        warn!("Ignoring invalid orientation {} for {}", orientation, img_name);
+      }
+      rotation.map(|rotation| (img_name, rotation))
+    })
+    .collect()
+}
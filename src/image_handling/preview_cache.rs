@@ -0,0 +1,313 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use crate::image::{ImageData, ImageRotation, ImageQuality, ImageLoadError};
+use log::warn;
+
+const ENTRY_MAGIC: &[u8; 4] = b"FLPV";
+const ENTRY_VERSION: u8 = 3;
+const ENTRY_HEADER_LEN: usize = 4 + 1 + 4 + 4 + 1 + 4; // magic, version, full width, full height, rotation, color profile length. the profile follows the header
+const ENTRY_EXTENSION: &str = "fpv";
+const PREVIEW_JPEG_QUALITY: f32 = 85.0;
+
+  // eviction removes entries until the cache is this fraction of its maximum size,
+  // so that not every insert into a full cache triggers another eviction pass
+const EVICTION_TARGET_RATIO: f64 = 0.9;
+
+  // On-disk store of downscaled previews, so that previews of previously opened folders don't need to be decoded again.
+  // Entries are keyed by the image's path, size and modification time, so changed files miss the cache.
+  // Least recently used entries are evicted once the cache grows over its maximum size, with use tracked through the entries' modification time.
+pub struct PreviewCache {
+  dir: PathBuf,
+  max_size: u64, // in bytes
+  total_size: Mutex<u64>, // bytes used by all entries, as far as this process knows
+}
+
+impl PreviewCache {
+  pub fn open(dir: PathBuf, max_size: u64)->Result<PreviewCache, PreviewCacheError> {
+    fs::create_dir_all(&dir)?;
+
+    let total_size = fs::read_dir(&dir)?
+      .filter_map(|entry_res| entry_res.ok())
+      .filter_map(|entry| entry.metadata().ok())
+      .map(|metadata| metadata.len())
+      .sum();
+
+    Ok(PreviewCache {
+      dir,
+      max_size,
+      total_size: Mutex::new(total_size)
+    })
+  }
+
+    // the platform's cache directory for fotoleine previews, if there is one
+  pub fn default_dir()->Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("fotoleine").join("previews"))
+  }
+
+    // returns None on any miss, including unreadable or outdated entries
+  pub fn get(&self, image_path: &Path)->Option<ImageData> {
+    let entry_path = self.entry_path(image_path).ok()?;
+    let bytes = fs::read(&entry_path).ok()?;
+
+    match parse_entry(&bytes) {
+      Ok(data) => {
+          // mark the entry as recently used
+        if let Ok(file) = File::options().write(true).open(&entry_path) {
+          let _ = file.set_modified(SystemTime::now());
+        }
+        Some(data)
+      },
+      Err(error) => {
+        warn!("Removing invalid preview cache entry {}: {}", entry_path.display(), error);
+        self.remove_entry(&entry_path);
+        None
+      }
+    }
+  }
+
+    // the entry as it is stored, without decoding it. only its header is checked, see `CompressedPreview::decode`
+  pub fn get_compressed(&self, image_path: &Path)->Option<CompressedPreview> {
+    let entry_path = self.entry_path(image_path).ok()?;
+    let bytes = fs::read(&entry_path).ok()?;
+    if !has_entry_header(&bytes) {
+      warn!("Removing invalid preview cache entry {}", entry_path.display());
+      self.remove_entry(&entry_path);
+      return None;
+    }
+
+    if let Ok(file) = File::options().write(true).open(&entry_path) {
+      let _ = file.set_modified(SystemTime::now());
+    }
+    Some(CompressedPreview { bytes })
+  }
+
+  pub fn insert(&self, image_path: &Path, data: &ImageData)->Result<(), PreviewCacheError> {
+    let entry_path = self.entry_path(image_path)?;
+    let bytes = serialize_entry(data)?;
+
+    let mut tmp_file = tempfile::NamedTempFile::new_in(&self.dir)?;
+    tmp_file.as_file_mut().write_all(&bytes)?;
+      // an entry written for the same image before is replaced, its size no longer counts
+    let replaced_len = fs::metadata(&entry_path).map_or(0, |metadata| metadata.len());
+    tmp_file.persist(&entry_path)?;
+
+    let over_budget = {
+      let mut total_size = self.total_size.lock().expect("Couldn't lock the preview cache size");
+      *total_size = total_size.saturating_sub(replaced_len) + bytes.len() as u64;
+      *total_size > self.max_size
+    };
+
+    if over_budget {
+      self.evict()?;
+    }
+
+    Ok(())
+  }
+
+  fn entry_path(&self, image_path: &Path)->Result<PathBuf, PreviewCacheError> {
+    let metadata = fs::metadata(image_path)?;
+    let modified = metadata.modified()?
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .map_or(0, |duration| duration.as_nanos());
+    let canonical_path = image_path.canonicalize()?;
+
+      // DefaultHasher isn't guaranteed to stay the same across rust versions. A change only means that the cache misses once, though
+    let mut hasher = DefaultHasher::new();
+    canonical_path.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    modified.hash(&mut hasher);
+
+    Ok(self.dir.join(format!("{:016x}.{}", hasher.finish(), ENTRY_EXTENSION)))
+  }
+
+  fn remove_entry(&self, entry_path: &Path) {
+    if let Ok(metadata) = fs::metadata(entry_path) {
+      if fs::remove_file(entry_path).is_ok() {
+        let mut total_size = self.total_size.lock().expect("Couldn't lock the preview cache size");
+        *total_size = total_size.saturating_sub(metadata.len());
+      }
+    }
+  }
+
+    // removes the least recently used entries until the cache is below its eviction target
+  fn evict(&self)->Result<(), PreviewCacheError> {
+      // holding the lock for the whole pass keeps other workers from evicting at the same time
+    let mut total_size = self.total_size.lock().expect("Couldn't lock the preview cache size");
+
+    let mut entries: Vec<_> = fs::read_dir(&self.dir)?
+      .filter_map(|entry_res| entry_res.ok())
+      .filter(|entry| entry.path().extension().is_some_and(|ext| ext == ENTRY_EXTENSION))
+      .filter_map(|entry| {
+        let metadata = entry.metadata().ok()?;
+        let last_used = metadata.modified().ok()?;
+        Some((last_used, metadata.len(), entry.path()))
+      })
+      .collect();
+    entries.sort_unstable_by_key(|(last_used, _, _)| *last_used);
+
+      // recount, since other processes might have changed the cache in the meantime
+    *total_size = entries.iter().map(|(_, size, _)| size).sum();
+    let target_size = (self.max_size as f64 * EVICTION_TARGET_RATIO) as u64;
+
+    for (_, size, path) in entries {
+      if *total_size <= target_size {
+        break;
+      }
+      if fs::remove_file(&path).is_ok() {
+        *total_size -= size;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+  // A preview in the format of a cache entry, for keeping previews in memory at a fraction of their decoded size
+pub struct CompressedPreview {
+  bytes: Vec<u8>
+}
+
+impl CompressedPreview {
+  pub fn new(data: &ImageData)->Result<CompressedPreview, PreviewCacheError> {
+    Ok(CompressedPreview { bytes: serialize_entry(data)? })
+  }
+
+  pub fn decode(&self)->Result<ImageData, PreviewCacheError> {
+    parse_entry(&self.bytes)
+  }
+
+  pub fn byte_size(&self)->usize {
+    self.bytes.len()
+  }
+}
+
+fn serialize_entry(data: &ImageData)->Result<Vec<u8>, PreviewCacheError> {
+  let jpeg = data.encode_jpeg(PREVIEW_JPEG_QUALITY)?;
+  let full_size = data.full_size();
+
+  let mut bytes = Vec::with_capacity(ENTRY_HEADER_LEN + jpeg.len());
+  bytes.extend_from_slice(ENTRY_MAGIC);
+  bytes.push(ENTRY_VERSION);
+  bytes.extend_from_slice(&(full_size[0] as u32).to_le_bytes());
+  bytes.extend_from_slice(&(full_size[1] as u32).to_le_bytes());
+  bytes.push(rotation_to_u8(data.rotation()));
+    // previews are cached as decoded, so that turning color management on or off applies to cached previews too
+  let icc_profile = data.icc_profile().unwrap_or(&[]);
+  bytes.extend_from_slice(&(icc_profile.len() as u32).to_le_bytes());
+  bytes.extend_from_slice(icc_profile);
+  bytes.extend_from_slice(&jpeg);
+
+  Ok(bytes)
+}
+
+fn parse_entry(bytes: &[u8])->Result<ImageData, PreviewCacheError> {
+  if !has_entry_header(bytes) {
+    return Err(PreviewCacheError::InvalidEntry);
+  }
+
+  let read_u32 = |offset: usize| u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]) as usize;
+  let full_size = [read_u32(5), read_u32(9)];
+  let rotation = rotation_from_u8(bytes[13]).ok_or(PreviewCacheError::InvalidEntry)?;
+  let icc_profile_len = read_u32(14);
+
+  let jpeg_start = ENTRY_HEADER_LEN + icc_profile_len;
+  if bytes.len() < jpeg_start {
+    return Err(PreviewCacheError::InvalidEntry);
+  }
+  let icc_profile = if icc_profile_len > 0 {
+    Some(bytes[ENTRY_HEADER_LEN..jpeg_start].to_vec())
+  } else {
+    None
+  };
+
+  let data = ImageData::decode_jpeg(&bytes[jpeg_start..], rotation, full_size, ImageQuality::Preview, icc_profile)?;
+  Ok(data)
+}
+
+fn has_entry_header(bytes: &[u8])->bool {
+  bytes.len() >= ENTRY_HEADER_LEN && &bytes[0..4] == ENTRY_MAGIC && bytes[4] == ENTRY_VERSION
+}
+
+fn rotation_to_u8(rotation: ImageRotation)->u8 {
+  match rotation {
+    ImageRotation::None => 0,
+    ImageRotation::NinetyCW => 1,
+    ImageRotation::OneEighty => 2,
+    ImageRotation::NinetyCCW => 3,
+    ImageRotation::Mirrored => 4,
+    ImageRotation::MirroredNinetyCW => 5,
+    ImageRotation::MirroredOneEighty => 6,
+    ImageRotation::MirroredNinetyCCW => 7
+  }
+}
+
+fn rotation_from_u8(val: u8)->Option<ImageRotation> {
+  match val {
+    0 => Some(ImageRotation::None),
+    1 => Some(ImageRotation::NinetyCW),
+    2 => Some(ImageRotation::OneEighty),
+    3 => Some(ImageRotation::NinetyCCW),
+    4 => Some(ImageRotation::Mirrored),
+    5 => Some(ImageRotation::MirroredNinetyCW),
+    6 => Some(ImageRotation::MirroredOneEighty),
+    7 => Some(ImageRotation::MirroredNinetyCCW),
+    _ => None
+  }
+}
+
+#[derive(Debug)]
+pub enum PreviewCacheError {
+  IoError(io::Error),
+  PersistError(tempfile::PersistError),
+  ImageError(ImageLoadError),
+  InvalidEntry
+}
+
+impl fmt::Display for PreviewCacheError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::PreviewCacheError::*;
+    match self {
+      IoError(error) => write!(f, "Preview cache file error: {}", error),
+      PersistError(error) => write!(f, "Could not persist the temporary preview file: {}", error),
+      ImageError(error) => write!(f, "Could not encode or decode the preview: {}", error),
+      InvalidEntry => write!(f, "The preview cache entry is invalid or from an older version"),
+    }
+  }
+}
+
+impl Error for PreviewCacheError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::PreviewCacheError::*;
+    match self {
+      IoError(error) => Some(error),
+      PersistError(error) => Some(error),
+      ImageError(error) => Some(error),
+      InvalidEntry => None
+    }
+  }
+}
+
+impl From<io::Error> for PreviewCacheError {
+  fn from(error: io::Error)->Self {
+    PreviewCacheError::IoError(error)
+  }
+}
+
+impl From<tempfile::PersistError> for PreviewCacheError {
+  fn from(error: tempfile::PersistError)->Self {
+    PreviewCacheError::PersistError(error)
+  }
+}
+
+impl From<ImageLoadError> for PreviewCacheError {
+  fn from(error: ImageLoadError)->Self {
+    PreviewCacheError::ImageError(error)
+  }
+}
@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::image;
+use crate::worker_pool::{WorkerPool, Worker, CancelToken, PoolActivity};
+use glium::glutin::event_loop::{EventLoop, EventLoopClosed};
+use super::loader_pool::{self, LoadNotification};
+use super::preview_cache::{PreviewCache, CompressedPreview, PreviewCacheError};
+use log::{error, warn};
+
+  // how long the pools prefetching waits for have to be idle before it starts on another image, so moving through the folder isn't slowed down by it
+const PAUSE_DURATION: Duration = Duration::from_millis(500);
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+  // prefetched previews are still added to the preview cache once the folder's are over this, they just aren't kept in memory
+const MAX_PREFETCHED_BYTES: usize = 256 * 1024 * 1024;
+
+pub struct PrefetchRequest {
+  pub path: PathBuf,
+  pub idx: usize, // collection index of the image
+  pub token: CancelToken // the token of the folder's prefetch
+}
+
+pub struct PrefetchWorker {
+  preview_cache: Option<Arc<PreviewCache>>,
+  busy_activity: PoolActivity // of the pools that go before prefetching
+}
+
+impl Worker for PrefetchWorker {
+  type Input = PrefetchRequest;
+  type Output = Option<CompressedPreview>; // None if the prefetch was cancelled
+  type Error = PreviewCacheError;
+
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    let PrefetchRequest { ref path, ref token, .. } = *input;
+
+    let mut idle_since = None;
+    while !token.is_cancelled() {
+      if !self.busy_activity.is_idle() {
+        idle_since = None;
+      } else if idle_since.get_or_insert_with(Instant::now).elapsed() >= PAUSE_DURATION {
+        break;
+      }
+      thread::sleep(IDLE_POLL_INTERVAL);
+    }
+      // a different folder was loaded in the meantime
+    if token.is_cancelled() {
+      return Ok(None);
+    }
+
+      // cached previews are kept as they are stored, without decoding and compressing them again
+    if let Some(preview) = self.preview_cache.as_ref().and_then(|preview_cache| preview_cache.get_compressed(path)) {
+      return Ok(Some(preview));
+    }
+    let data = loader_pool::load_preview(path, self.preview_cache.as_deref())?;
+    Ok(Some(CompressedPreview::new(&data)?))
+  }
+}
+
+pub type PrefetchPool = WorkerPool<PrefetchWorker>;
+  // prefetching waits for `busy_activity` to be idle for a moment, e.g. for the loader and thumbnail pools
+pub fn new(size: usize, event_loop: &EventLoop<LoadNotification>, preview_cache: Option<Arc<PreviewCache>>, busy_activity: PoolActivity)->PrefetchPool {
+  let event_loop_proxy = event_loop.create_proxy();
+  WorkerPool::new(size, move |_| {
+    PrefetchWorker {
+      preview_cache: preview_cache.clone(),
+      busy_activity: busy_activity.clone()
+    }
+  }, move |request: PrefetchRequest, result| {
+      // previews that couldn't be made are still reported, so the count of the remaining ones goes down
+    let preview = match result {
+      Ok(Some(preview)) => Some(preview),
+      Ok(None) => return,
+      Err(error) => {
+        warn!("Prefetch pool: Couldn't load the preview of {}, {}", request.path.display(), error);
+        None
+      }
+    };
+
+    if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::PreviewPrefetched { preview, idx: request.idx, token: request.token }) {
+      error!("Prefetch pool: Event loop closed");
+    }
+  })
+}
+
+  // Low resolution previews of a folder's jpegs, decoded in the background while nothing else is loading, so that images
+  // coming into the load set can show their preview right away instead of waiting for the disk. They also end up in the preview cache.
+  // Images close to the one shown when the folder was loaded get theirs first
+pub struct PreviewPrefetch {
+  token: CancelToken,
+  previews: HashMap<usize, Arc<CompressedPreview>>, // keys index into the collection
+  byte_size: usize, // of all previews
+  remaining: usize // how many previews are still being made
+}
+
+impl PreviewPrefetch {
+  pub fn new()->PreviewPrefetch {
+    PreviewPrefetch {
+      token: CancelToken::new(),
+      previews: HashMap::new(),
+      byte_size: 0,
+      remaining: 0
+    }
+  }
+
+    // prefetches the previews of the images, the ones closest to `center_idx` first. only jpegs have previews, the others are skipped
+  pub fn request<I>(&mut self, images: I, center_idx: usize, prefetch_pool: &PrefetchPool)
+    where I: Iterator<Item=(usize, PathBuf)> {
+
+    for (idx, path) in images.filter(|(_, path)| image::is_jpeg(path)) {
+      prefetch_pool.submit(PrefetchRequest { path, idx, token: self.token.clone() }, idx.abs_diff(center_idx));
+      self.remaining += 1;
+    }
+  }
+
+    // takes in a prefetched preview, if it belongs to this folder
+  pub fn receive(&mut self, preview: Option<CompressedPreview>, idx: usize, token: &CancelToken) {
+    if !token.same_as(&self.token) {
+      return;
+    }
+
+    self.remaining -= 1;
+    if let Some(preview) = preview.filter(|preview| self.byte_size + preview.byte_size() <= MAX_PREFETCHED_BYTES) {
+      self.byte_size += preview.byte_size();
+      self.previews.insert(idx, Arc::new(preview));
+    }
+  }
+
+  pub fn get(&self, idx: usize)->Option<Arc<CompressedPreview>> {
+    self.previews.get(&idx).cloned()
+  }
+
+  pub fn byte_size(&self)->usize {
+    self.byte_size
+  }
+
+  pub fn remaining(&self)->usize {
+    self.remaining
+  }
+}
+
+impl Drop for PreviewPrefetch {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
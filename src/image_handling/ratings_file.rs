@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use log::warn;
+
+pub const DEFAULT_RATINGS_FILE_NAME: &str = "ratings.yaml";
+const JOURNAL_FILE_NAME: &str = "journal.log";
+
+  // Where the ratings of a folder are saved. Next to the images by default, but folders delivered to clients can be kept free of it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatingsLocation {
+  #[default]
+  Folder,
+  Hidden, // in a .fotoleine folder inside the loaded folder
+  Central // in the platform's data directory, named after the folder's path
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RatingsFile {
+  pub location: RatingsLocation,
+  pub name: String, // of the file in the folder or its hidden folder. central files are named after the folder
+  pub library: bool // whether the ratings are also collected in the `RatingsLibrary`
+}
+
+impl Default for RatingsFile {
+  fn default()->RatingsFile {
+    RatingsFile {
+      location: RatingsLocation::Folder,
+      name: DEFAULT_RATINGS_FILE_NAME.to_string(),
+      library: false
+    }
+  }
+}
+
+impl RatingsFile {
+    // falls back to the folder if the platform has no data directory
+  pub fn path(&self, folder_path: &Path)->PathBuf {
+    match self.location {
+      RatingsLocation::Folder => folder_path.join(&self.name),
+      RatingsLocation::Hidden => folder_path.join(".fotoleine").join(&self.name),
+      RatingsLocation::Central => match central_path(folder_path) {
+        Some(path) => path,
+        None => {
+          warn!("There is no data directory to keep the ratings of {} in, keeping them in the folder", folder_path.display());
+          folder_path.join(&self.name)
+        }
+      }
+    }
+  }
+
+    // the journal of the changes made in the folder is kept along with its ratings
+  pub fn journal_path(&self, folder_path: &Path)->PathBuf {
+    let central_path = || dirs::data_dir().map(|dir| dir.join("fotoleine").join("journals").join(format!("{}.log", folder_key(folder_path))));
+    match self.location {
+      RatingsLocation::Folder => folder_path.join(JOURNAL_FILE_NAME),
+      RatingsLocation::Hidden => folder_path.join(".fotoleine").join(JOURNAL_FILE_NAME),
+      RatingsLocation::Central => central_path().unwrap_or_else(|| folder_path.join(JOURNAL_FILE_NAME))
+    }
+  }
+
+    // where one of the other files Fotoleine keeps about the folder is saved, like the rejects or the session. they follow the ratings,
+    // so the hidden and central locations keep them out of the folder too. central ones are in a folder of their own per loaded folder
+  pub fn folder_file_path(&self, folder_path: &Path, file_name: &str)->PathBuf {
+    let central_path = || dirs::data_dir().map(|dir| dir.join("fotoleine").join("folders").join(folder_key(folder_path)).join(file_name));
+    match self.location {
+      RatingsLocation::Folder => folder_path.join(file_name),
+      RatingsLocation::Hidden => folder_path.join(".fotoleine").join(file_name),
+      RatingsLocation::Central => central_path().unwrap_or_else(|| folder_path.join(file_name))
+    }
+  }
+
+    // where the ratings were saved before a different location was chosen, to pick them up from there until they are saved again
+  pub fn fallback_path(&self, folder_path: &Path)->Option<PathBuf> {
+    let default_path = folder_path.join(DEFAULT_RATINGS_FILE_NAME);
+    let is_default = self.location == RatingsLocation::Folder && self.name == DEFAULT_RATINGS_FILE_NAME;
+    if !is_default && default_path.is_file() { Some(default_path) } else { None }
+  }
+}
+
+fn central_path(folder_path: &Path)->Option<PathBuf> {
+  dirs::data_dir().map(|dir| dir.join("fotoleine").join("ratings").join(format!("{}.yaml", folder_key(folder_path))))
+}
+
+  // a file name for keeping something about a folder outside of it. the folder's name keeps the files recognizable,
+  // the checksum of its whole path tells apart folders of the same name
+pub(super) fn folder_key(folder_path: &Path)->String {
+  let folder_path = fs::canonicalize(folder_path).unwrap_or_else(|_| folder_path.to_path_buf());
+  let folder_name = folder_path.file_name().map_or_else(|| "root".into(), |name| name.to_string_lossy());
+  let checksum = crc32fast::hash(folder_path.to_string_lossy().as_bytes());
+  format!("{} {:08x}", folder_name, checksum)
+}
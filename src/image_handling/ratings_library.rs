@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use super::loaded_dir::Rating;
+use super::ratings_file;
+
+  // Collects the ratings of every folder opened, in the platform's data directory, for finding rated images across folders,
+  // e.g. the high rated ones of the last month. Kept as one file per folder, named like central ratings files, so saving the ratings
+  // of a folder only rewrites its own file. The ratings files of the folders stay where they are, this is a copy
+pub struct RatingsLibrary {
+  file_path: PathBuf,
+  entry: LibraryEntry
+}
+
+  // what is collected about a folder
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LibraryEntry {
+  folder: PathBuf,
+  images: BTreeMap<String, LibraryRating> // sorted, to keep the file diffable
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LibraryRating {
+  rating: u8, // like in the ratings file
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  rated: Option<String> // when the rating was last changed, in rfc 3339. None for ratings from before the folder was collected
+}
+
+impl RatingsLibrary {
+    // picks up what was collected about the folder before. None if the platform has no data directory to keep the library in
+  pub fn open(folder_path: &Path)->Result<Option<RatingsLibrary>, LibraryError> {
+    let file_path = match dirs::data_dir() {
+      Some(dir) => dir.join("fotoleine").join("library").join(format!("{}.yaml", ratings_file::folder_key(folder_path))),
+      None => return Ok(None)
+    };
+
+    let entry = if file_path.exists() {
+      serde_yaml::from_reader(File::open(&file_path)?)?
+    } else {
+      LibraryEntry::default()
+    };
+    let folder = fs::canonicalize(folder_path).unwrap_or_else(|_| folder_path.to_path_buf());
+    Ok(Some(RatingsLibrary { file_path, entry: LibraryEntry { folder, ..entry } }))
+  }
+
+    // the ratings that changed since they were last saved are marked with the current time.
+    // images no longer in the folder are kept, like orphaned ratings in the ratings file
+  pub fn save<'a, I>(&mut self, ratings: I)->Result<(), LibraryError>
+    where I: IntoIterator<Item=(&'a String, &'a Rating)> {
+
+    let now = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let mut changed = false;
+    for (img_name, rating) in ratings {
+      let rating = rating.to_u8();
+      match self.entry.images.get_mut(img_name) {
+        Some(library_rating) if library_rating.rating == rating => {},
+        Some(library_rating) => {
+          *library_rating = LibraryRating { rating, rated: Some(now.clone()) };
+          changed = true;
+        },
+        None => {
+          self.entry.images.insert(img_name.clone(), LibraryRating { rating, rated: None });
+          changed = true;
+        }
+      }
+    }
+    if !changed && self.file_path.exists() {
+      return Ok(());
+    }
+
+    let s = serde_yaml::to_string(&self.entry)?;
+    let folder = self.file_path.parent().ok_or(LibraryError::NoFolder)?;
+    fs::create_dir_all(folder)?;
+    let mut tmp_file = tempfile::NamedTempFile::new_in(folder)?;
+    tmp_file.as_file_mut().write_all(s.as_bytes())?;
+    tmp_file.persist(&self.file_path).map_err(|error| error.error)?;
+    Ok(())
+  }
+}
+
+#[derive(Debug)]
+pub enum LibraryError {
+  NoFolder, // the library file path has no folder to save it in
+  Io(io::Error),
+  Yaml(serde_yaml::Error)
+}
+
+impl fmt::Display for LibraryError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::LibraryError::*;
+    match self {
+      NoFolder => write!(f, "The library file has no folder to be saved in"),
+      Io(error) => write!(f, "Could not read or write the library file: {}", error),
+      Yaml(error) => write!(f, "Could not read or write the contents of the library file: {}", error),
+    }
+  }
+}
+
+impl Error for LibraryError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::LibraryError::*;
+    match self {
+      NoFolder => None,
+      Io(error) => Some(error),
+      Yaml(error) => Some(error)
+    }
+  }
+}
+
+impl From<io::Error> for LibraryError {
+  fn from(error: io::Error)->Self {
+    LibraryError::Io(error)
+  }
+}
+
+impl From<serde_yaml::Error> for LibraryError {
+  fn from(error: serde_yaml::Error)->Self {
+    LibraryError::Yaml(error)
+  }
+}
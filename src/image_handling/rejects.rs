@@ -0,0 +1,37 @@
+use std::path::Path;
+use std::collections::BTreeSet;
+use super::folder_file::{FolderFile, FolderFileError};
+use super::ratings_file::RatingsFile;
+
+  // Images the user struck out while culling. Kept apart from the ratings, so a rejected image keeps the rating it had if it is taken back.
+  // Saved as a list of file names in a `rejects.yaml` file, kept where the ratings are
+pub struct ImageRejects {
+  rejected: BTreeSet<String>, // sorted, to keep the file diffable
+  file: FolderFile
+}
+
+impl ImageRejects {
+    // entries for images that are no longer in the folder are kept, they don't get in the way
+  pub fn new(folder_path: &Path, ratings_file: &RatingsFile)->Result<ImageRejects, FolderFileError> {
+    let file = FolderFile::new(folder_path, "rejects.yaml", ratings_file);
+    let rejected = file.load()?.unwrap_or_default();
+
+    Ok(ImageRejects {
+      rejected,
+      file
+    })
+  }
+
+  pub fn contains(&self, img_name: &str)->bool {
+    self.rejected.contains(img_name)
+  }
+
+  pub fn set(&mut self, img_name: String, rejected: bool)->Result<(), FolderFileError> {
+    if rejected {
+      self.rejected.insert(img_name);
+    } else {
+      self.rejected.remove(&img_name);
+    }
+    self.file.save(&self.rejected)
+  }
+}
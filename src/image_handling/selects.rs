@@ -0,0 +1,63 @@
+use std::path::Path;
+use super::folder_file::{FolderFile, FolderFileError};
+use super::ratings_file::RatingsFile;
+
+  // The images picked to keep, e.g. to hand to a client, in the order the user arranged them. Independent of the ratings,
+  // so picking an image doesn't need a rating to be spent on it. Saved as a list of file names in a `selects.yaml` file, kept where the ratings are
+pub struct ImageSelects {
+  selects: Vec<String>, // in the order they are shown and exported
+  file: FolderFile
+}
+
+impl ImageSelects {
+    // entries for images that are no longer in the folder are kept, in case the images come back
+  pub fn new(folder_path: &Path, ratings_file: &RatingsFile)->Result<ImageSelects, FolderFileError> {
+    let file = FolderFile::new(folder_path, "selects.yaml", ratings_file);
+    let selects = file.load()?.unwrap_or_default();
+
+    Ok(ImageSelects {
+      selects,
+      file
+    })
+  }
+
+  pub fn names(&self)->&[String] {
+    &self.selects
+  }
+
+    // where the image is in the selects, None if it isn't one
+  pub fn position(&self, img_name: &str)->Option<usize> {
+    self.selects.iter().position(|name| name == img_name)
+  }
+
+    // adds the image at the end, or removes it. returns whether it is a select now
+  pub fn toggle(&mut self, img_name: String)->Result<bool, FolderFileError> {
+    let selected = match self.position(&img_name) {
+      Some(position) => {
+        self.selects.remove(position);
+        false
+      },
+      None => {
+        self.selects.push(img_name);
+        true
+      }
+    };
+    self.file.save(&self.selects)?;
+    Ok(selected)
+  }
+
+    // swaps the image with the one before or after it. returns its new position, or None if it isn't a select or already at that end
+  pub fn shift(&mut self, img_name: &str, earlier: bool)->Result<Option<usize>, FolderFileError> {
+    let Some(position) = self.position(img_name) else {
+      return Ok(None);
+    };
+    let new_position = if earlier { position.checked_sub(1) } else { Some(position + 1).filter(|&next| next < self.selects.len()) };
+    let Some(new_position) = new_position else {
+      return Ok(None);
+    };
+
+    self.selects.swap(position, new_position);
+    self.file.save(&self.selects)?;
+    Ok(Some(new_position))
+  }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use crate::metadata::MetadataFilter;
+use super::loaded_dir::FileType;
+
+  // Where the user left off in a folder: the shown image, the filters, the selection, the bookmarks, how far the shown image was zoomed in and whether the folder was watched.
+  // Saved to a `session.yaml` file, kept where the ratings are, when another folder is loaded or Fotoleine quits, and restored the next time the folder is loaded.
+  // Images are referred to by file name, so the session still applies after images were added to or removed from the folder
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+  pub shown: Option<String>,
+  pub rating_filter: Option<u8>, // the rating's u8 value, like in the ratings file
+  pub file_type_filter: Option<FileType>,
+  pub reject_filter: Option<bool>, // only rejected images if true, and only the others if false
+  pub selects_only: bool,
+  pub metadata_filter: MetadataFilter,
+  pub selection: Vec<String>,
+  pub selection_anchor: Option<String>,
+  pub bookmarks: Vec<String>,
+  pub zoom: Option<SessionZoom>, // of the shown image, while it is zoomed in
+  pub watching: bool,
+  pub staying_put: bool // whether a watched folder stays on the shown image when new ones arrive
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SessionZoom {
+  pub zoom: f64,
+  pub pan: [f64; 2]
+}
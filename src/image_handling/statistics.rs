@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use crate::metadata::{ImageMetadata, CameraBody};
+use super::loaded_dir::Rating;
+
+  // How the rating counts are broken down into groups
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StatisticsSplit {
+  None,
+  ByDay,
+  ByCamera
+}
+
+impl StatisticsSplit {
+  pub fn all()->[StatisticsSplit; 3] {
+    [StatisticsSplit::None, StatisticsSplit::ByDay, StatisticsSplit::ByCamera]
+  }
+
+  pub fn label(self)->&'static str {
+    match self {
+      StatisticsSplit::None => "All images",
+      StatisticsSplit::ByDay => "By day",
+      StatisticsSplit::ByCamera => "By camera"
+    }
+  }
+}
+
+  // How many images of a group have each rating
+pub struct RatingCounts {
+  pub group: String,
+  pub counts: [usize; 3] // indexed by the rating's u8 value
+}
+
+impl RatingCounts {
+  pub fn total(&self)->usize {
+    self.counts.iter().sum()
+  }
+}
+
+  // counts the ratings of `images`, grouped by `split`. groups are sorted by name, with images missing the metadata in a last group.
+  // `cameras` are all the camera bodies in the folder, to label them
+pub(super) fn count_ratings<'a, I>(images: I, split: StatisticsSplit, cameras: &[CameraBody])->Vec<RatingCounts>
+  where I: Iterator<Item=(Rating, Option<&'a ImageMetadata>)> {
+
+  let mut groups: BTreeMap<(bool, String), [usize; 3]> = BTreeMap::new(); // keyed by whether the group is the unknown one, and its name
+  for (rating, metadata) in images {
+    let group = match split {
+      StatisticsSplit::None => Some("All images".to_string()),
+      StatisticsSplit::ByDay => metadata.and_then(|metadata| metadata.date.clone()),
+      StatisticsSplit::ByCamera => metadata.and_then(|metadata| metadata.camera.as_ref()).map(|camera| camera.label(cameras))
+    };
+    let key = match group {
+      Some(group) => (false, group),
+      None => (true, "Unknown".to_string())
+    };
+
+    groups.entry(key).or_default()[rating.to_u8() as usize] += 1;
+  }
+
+  groups.into_iter()
+    .map(|((_, group), counts)| RatingCounts { group, counts })
+    .collect()
+}
@@ -0,0 +1,223 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use crate::image::{self, ImageData, ImageRotation, ImageQuality, ImageLoadError};
+use crate::worker_pool::{WorkerPool, Worker, CancelToken, PoolActivity};
+use glium::glutin::event_loop::{EventLoop, EventLoopClosed};
+use super::loader_pool::{self, LoadNotification};
+use super::preview_cache::PreviewCache;
+use log::{debug, error, warn};
+
+  // longest side of a thumbnail in pixels, give or take a factor of two
+const THUMBNAIL_SIZE: usize = 256;
+const THUMBNAIL_JPEG_QUALITY: f32 = 80.0;
+  // how often a thumbnail worker checks whether the loader pool is done, while it waits for it
+const LOADER_BUSY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+  // how much of the start of a jpeg is read for its exif thumbnail. the exif data is limited to 64 KiB, which leaves room for the segments before it
+const EXIF_THUMBNAIL_READ_SIZE: u64 = 256 * 1024;
+  // added to the priority of made thumbnails, so that the exif thumbnails of the whole folder are read before them
+const MADE_THUMBNAIL_PRIORITY: usize = usize::MAX / 2;
+
+  // A small version of an image for the filmstrip, kept compressed so that a whole folder's worth only takes a few megabytes
+pub struct Thumbnail {
+  jpeg: Vec<u8>,
+  rotation: ImageRotation, // from the image's exif data
+  full_size: [usize; 2],
+  embedded: bool // whether it is the thumbnail embedded in the exif data, shown until one is made from the image
+}
+
+impl Thumbnail {
+  fn new(mut data: ImageData, embedded: bool, color_management: bool)->Result<Thumbnail, ImageLoadError> {
+    if color_management {
+      data.convert_to_srgb()?;
+    }
+    data.shrink_to(THUMBNAIL_SIZE);
+    Ok(Thumbnail {
+      jpeg: data.encode_jpeg(THUMBNAIL_JPEG_QUALITY)?,
+      rotation: data.rotation(),
+      full_size: data.full_size(),
+      embedded
+    })
+  }
+
+  pub fn rotation(&self)->ImageRotation {
+    self.rotation
+  }
+
+  pub fn is_embedded(&self)->bool {
+    self.embedded
+  }
+
+    // the pixels as rgba, mirrored and rotated by `rotation`. returns the rotated size, and the pixels
+  pub fn to_oriented_rgba(&self, rotation: ImageRotation)->Result<([usize; 2], Vec<u8>), ImageLoadError> {
+    let data = ImageData::decode_jpeg(&self.jpeg, self.rotation, self.full_size, ImageQuality::Preview, None)?;
+    Ok(data.to_oriented_rgba(rotation))
+  }
+
+    // a jpeg mirrored and rotated by `rotation`, like the one shown while dragging the image out of the window
+  pub fn to_oriented_jpeg(&self, rotation: ImageRotation)->Result<Vec<u8>, ImageLoadError> {
+    let (size, rgba) = self.to_oriented_rgba(rotation)?;
+    image::encode_rgba_jpeg(size, &rgba, THUMBNAIL_JPEG_QUALITY)
+  }
+}
+
+pub struct ThumbnailRequest {
+  pub path: PathBuf,
+  pub idx: usize, // collection index of the image
+  pub token: CancelToken, // the token of the folder's thumbnails
+  pub embedded: bool // whether only the exif thumbnail is read, see `Thumbnails::request`
+}
+
+pub struct ThumbnailWorker {
+  preview_cache: Option<Arc<PreviewCache>>,
+  color_management: bool,
+  loader_activity: PoolActivity
+}
+
+impl Worker for ThumbnailWorker {
+  type Input = ThumbnailRequest;
+  type Output = Option<Thumbnail>; // None if the thumbnails were cancelled, or there is no exif thumbnail
+  type Error = ImageLoadError;
+
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error> {
+    let ThumbnailRequest { ref path, ref token, embedded, .. } = *input;
+
+      // exif thumbnails only take a small read, so they don't wait for the loader pool. images without one just wait for their made thumbnail
+    if embedded {
+      if token.is_cancelled() {
+        return Ok(None);
+      }
+        // errors aren't passed on, they would count as the made thumbnail failing
+      let thumbnail = read_exif_thumbnail(path).and_then(|data| data.map(|data| Thumbnail::new(data, true, self.color_management)).transpose());
+      return match thumbnail {
+        Ok(thumbnail) => Ok(thumbnail),
+        Err(error) => {
+          debug!("Thumbnail pool: Couldn't read the exif thumbnail of {}, {}", path.display(), error);
+          Ok(None)
+        }
+      };
+    }
+
+      // thumbnails give way to the loads of the images being looked at, so they only take up time the loader pool doesn't need
+    while !self.loader_activity.is_idle() && !token.is_cancelled() {
+      thread::sleep(LOADER_BUSY_POLL_INTERVAL);
+    }
+      // a different folder was loaded in the meantime
+    if token.is_cancelled() {
+      return Ok(None);
+    }
+
+      // jpegs are made from their previews, which are quick to decode or already cached. decoding them here also caches them for the loader
+    let data = if image::is_jpeg(path) {
+      loader_pool::load_preview(path, self.preview_cache.as_deref())?
+    } else {
+      ImageData::load_to_fit(path, [THUMBNAIL_SIZE as u32; 2])?
+    };
+    Ok(Some(Thumbnail::new(data, false, self.color_management)?))
+  }
+}
+
+fn read_exif_thumbnail(path: &Path)->Result<Option<ImageData>, ImageLoadError> {
+  let mut start = Vec::new();
+  File::open(path)?.take(EXIF_THUMBNAIL_READ_SIZE).read_to_end(&mut start)?;
+  ImageData::load_exif_thumbnail(&start)
+}
+
+pub type ThumbnailPool = WorkerPool<ThumbnailWorker>;
+pub fn new(size: usize, event_loop: &EventLoop<LoadNotification>, preview_cache: Option<Arc<PreviewCache>>, color_management: bool, loader_activity: PoolActivity)->ThumbnailPool {
+  let event_loop_proxy = event_loop.create_proxy();
+  WorkerPool::new(size, move |_| {
+    ThumbnailWorker {
+      preview_cache: preview_cache.clone(),
+      color_management,
+      loader_activity: loader_activity.clone()
+    }
+  }, move |request: ThumbnailRequest, result| {
+      // images without a thumbnail are still reported, so the count of the remaining ones goes down
+    let thumbnail = match result {
+      Ok(Some(thumbnail)) => Some(thumbnail),
+      Ok(None) => return,
+      Err(error) => {
+        warn!("Thumbnail pool: Couldn't load {}, {}", request.path.display(), error);
+        None
+      }
+    };
+
+    if let Err(EventLoopClosed(_)) = event_loop_proxy.send_event(LoadNotification::ThumbnailMade { thumbnail, idx: request.idx, token: request.token }) {
+      error!("Thumbnail pool: Event loop closed");
+    }
+  })
+}
+
+  // The thumbnails of a folder's images, made in the background while the folder is loaded.
+  // Images close to the one shown when the folder was loaded get theirs first. The exif thumbnails of the jpegs are read before
+  // anything else, so the filmstrip fills in right away, then each is replaced by a sharper one made from the image's preview
+pub struct Thumbnails {
+  token: CancelToken,
+  thumbnails: HashMap<usize, Thumbnail>, // keys index into the collection
+  remaining: usize // how many thumbnails are still being made, not counting exif thumbnails
+}
+
+impl Thumbnails {
+  pub fn new()->Thumbnails {
+    Thumbnails {
+      token: CancelToken::new(),
+      thumbnails: HashMap::new(),
+      remaining: 0
+    }
+  }
+
+    // makes the thumbnails of the images, the ones closest to `center_idx` first
+  pub fn request<I>(&mut self, images: I, center_idx: usize, thumbnail_pool: &ThumbnailPool)
+    where I: Iterator<Item=(usize, PathBuf)> {
+
+    for (idx, path) in images {
+      let distance = idx.abs_diff(center_idx);
+      if image::is_jpeg(&path) {
+        thumbnail_pool.submit(ThumbnailRequest { path: path.clone(), idx, token: self.token.clone(), embedded: true }, distance);
+      }
+      thumbnail_pool.submit(ThumbnailRequest { path, idx, token: self.token.clone(), embedded: false }, MADE_THUMBNAIL_PRIORITY + distance);
+      self.remaining += 1;
+    }
+  }
+
+    // takes in a thumbnail. returns the collection index of the image if it belongs to this folder, and was made.
+    // exif thumbnails don't replace made ones, in case they arrive after them
+  pub fn receive(&mut self, thumbnail: Option<Thumbnail>, idx: usize, token: &CancelToken)->Option<usize> {
+    if !token.same_as(&self.token) {
+      return None;
+    }
+
+    match thumbnail {
+      Some(thumbnail) if thumbnail.embedded => {
+        if self.thumbnails.contains_key(&idx) {
+          return None;
+        }
+        self.thumbnails.insert(idx, thumbnail);
+      },
+      thumbnail => {
+        self.remaining -= 1;
+        self.thumbnails.insert(idx, thumbnail?);
+      }
+    }
+    Some(idx)
+  }
+
+  pub fn get(&self, idx: usize)->Option<&Thumbnail> {
+    self.thumbnails.get(&idx)
+  }
+
+  pub fn remaining(&self)->usize {
+    self.remaining
+  }
+}
+
+impl Drop for Thumbnails {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+use crate::actions::Action;
+
+  // how long a navigation key is held before it starts repeating
+const INITIAL_DELAY: Duration = Duration::from_millis(350);
+  // time between repeats right after they start, and once they sped up all the way
+const START_INTERVAL: Duration = Duration::from_millis(120);
+const MIN_INTERVAL: Duration = Duration::from_millis(25);
+  // how long repeats take to speed up all the way, after the initial delay
+const ACCELERATION_DURATION: Duration = Duration::from_millis(1500);
+  // once repeats are at their fastest, each second held after that doubles how many images a repeat moves, up to this many
+const MAX_REPEAT_DISTANCE: usize = 16;
+
+  // Repeats moving to the next or previous image while A/D or the arrow keys are held, faster the longer they are held,
+  // so skimming through a thousand frames takes seconds. Tracks the held key itself instead of relying on the platform's key repeat,
+  // whose rate is the user's typing setting and doesn't speed up
+#[derive(Default)]
+pub struct KeyRepeat {
+  held: Option<HeldKey>
+}
+
+struct HeldKey {
+  forward: bool,
+  distance: usize, // of a single step, e.g. with shift held
+  repeats_from: Instant, // when the initial delay ended
+  next_at: Instant
+}
+
+impl KeyRepeat {
+    // `held` is the direction and step distance of the navigation key held down this frame, None if none is.
+    // the first step is taken when the key is pressed, repeats follow from here.
+    // returns the repeated step if one is due, and when the next one will be
+  pub fn update(&mut self, held: Option<(bool, usize)>, now: Instant)->(Option<Action>, Option<Instant>) {
+    let Some((forward, distance)) = held else {
+      self.held = None;
+      return (None, None);
+    };
+
+    let held_key = match self.held {
+      Some(ref mut held_key) if held_key.forward == forward && held_key.distance == distance => held_key,
+      _ => {
+        let repeats_from = now + INITIAL_DELAY;
+        self.held = Some(HeldKey { forward, distance, repeats_from, next_at: repeats_from });
+        return (None, Some(repeats_from));
+      }
+    };
+
+    if now < held_key.next_at {
+      return (None, Some(held_key.next_at));
+    }
+
+    let repeating_for = now - held_key.repeats_from;
+    let acceleration = (repeating_for.as_secs_f32() / ACCELERATION_DURATION.as_secs_f32()).min(1.0);
+    let interval = START_INTERVAL.mul_f32(1.0 - acceleration) + MIN_INTERVAL.mul_f32(acceleration);
+    let doublings = repeating_for.saturating_sub(ACCELERATION_DURATION).as_secs() as u32;
+    let repeat_distance = 2usize.saturating_pow(doublings).min(MAX_REPEAT_DISTANCE) * held_key.distance;
+
+      // frames that came late don't catch up with a burst of steps
+    held_key.next_at = if held_key.next_at + interval > now { held_key.next_at + interval } else { now + interval };
+    let action = if forward { Action::ShowNext(repeat_distance) } else { Action::ShowPrevious(repeat_distance) };
+    (Some(action), Some(held_key.next_at))
+  }
+}
@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+  // the log file is rotated once it grows beyond this, keeping this many older files around
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+const MAX_OLD_FILES: usize = 3;
+  // how many of the latest lines the console window shows
+const HISTORY_LENGTH: usize = 1000;
+
+pub struct LogLine {
+  pub level: Level,
+  pub text: String
+}
+
+  // The latest log lines, for the console window. Shared with the logger, which appends to it from any thread
+#[derive(Clone)]
+pub struct LogHistory {
+  lines: Arc<Mutex<VecDeque<LogLine>>>
+}
+
+impl LogHistory {
+  fn new()->LogHistory {
+    LogHistory {
+      lines: Arc::new(Mutex::new(VecDeque::with_capacity(HISTORY_LENGTH)))
+    }
+  }
+
+    // oldest first. the logger can't add lines while this is held
+  pub fn lines(&self)->MutexGuard<'_, VecDeque<LogLine>> {
+    self.lines.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn push(&self, line: LogLine) {
+    let mut lines = self.lines();
+    if lines.len() == HISTORY_LENGTH {
+      lines.pop_front();
+    }
+    lines.push_back(line);
+  }
+}
+
+  // Writes log records to stderr, a log file in the platform's data directory, and the history for the console window
+struct Logger {
+  file: Mutex<Option<LogFile>>, // None if the log file couldn't be opened
+  history: LogHistory
+}
+
+impl Log for Logger {
+  fn enabled(&self, metadata: &Metadata)->bool {
+    metadata.level() <= log::max_level()
+  }
+
+  fn log(&self, record: &Record) {
+    if !self.enabled(record.metadata()) {
+      return;
+    }
+
+      // the crate's name is the same for every record, the module is enough to tell where it came from
+    let target = record.target();
+    let target = target.strip_prefix("fotoleine::").unwrap_or(target);
+    let text = format!("{} {:<5} {}: {}", humantime::format_rfc3339_seconds(SystemTime::now()), record.level(), target, record.args());
+
+    eprintln!("{}", text);
+    if let Some(ref mut file) = *self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+        // nowhere left to report this to
+      let _ = file.write_line(&text);
+    }
+    self.history.push(LogLine { level: record.level(), text });
+  }
+
+  fn flush(&self) {
+    if let Some(ref mut file) = *self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) {
+      let _ = file.file.flush();
+    }
+  }
+}
+
+struct LogFile {
+  path: PathBuf,
+  file: File,
+  size: u64 // of the current file, to know when to rotate it
+}
+
+impl LogFile {
+  fn open(path: PathBuf)->io::Result<LogFile> {
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let size = file.metadata()?.len();
+    let mut log_file = LogFile { path, file, size };
+    if log_file.size >= MAX_FILE_SIZE {
+      log_file.rotate()?;
+    }
+    Ok(log_file)
+  }
+
+  fn write_line(&mut self, text: &str)->io::Result<()> {
+    writeln!(self.file, "{}", text)?;
+    self.size += text.len() as u64 + 1;
+    if self.size >= MAX_FILE_SIZE {
+      self.rotate()?;
+    }
+    Ok(())
+  }
+
+    // fotoleine.log becomes fotoleine.1.log, that one fotoleine.2.log and so on, dropping the oldest
+  fn rotate(&mut self)->io::Result<()> {
+    for number in (1..MAX_OLD_FILES).rev() {
+      let older = self.old_path(number);
+      if older.exists() {
+        fs::rename(&older, self.old_path(number + 1))?;
+      }
+    }
+    fs::rename(&self.path, self.old_path(1))?;
+
+    self.file = File::create(&self.path)?;
+    self.size = 0;
+    Ok(())
+  }
+
+  fn old_path(&self, number: usize)->PathBuf {
+    self.path.with_extension(format!("{}.log", number))
+  }
+}
+
+  // the log file path, if the platform has a data directory
+pub fn path()->Option<PathBuf> {
+  dirs::data_local_dir().map(|dir| dir.join("fotoleine").join("logs").join("fotoleine.log"))
+}
+
+  // installs the logger for the log macros. returns the history it appends to, for the console window
+pub fn init()->LogHistory {
+  let history = LogHistory::new();
+
+  let mut file_error = None;
+  let file = match path() {
+    Some(path) => LogFile::open(path.clone()).map_err(|error| file_error = Some(format!("Couldn't open the log file {}: {}", path.display(), error))).ok(),
+    None => {
+      file_error = Some("The platform has no data directory to write the log file to".to_string());
+      None
+    }
+  };
+
+  let logger = Logger { file: Mutex::new(file), history: history.clone() };
+  match log::set_boxed_logger(Box::new(logger)) {
+    Ok(()) => log::set_max_level(LevelFilter::Debug),
+    Err(error) => eprintln!("Couldn't install the logger: {}", error)
+  }
+
+  if let Some(error) = file_error {
+    log::warn!("{}", error);
+  }
+  history
+}
@@ -1,22 +1,128 @@
 use std::error::Error;
-use std::process::Command;
+use std::time::{Duration, Instant};
+use std::path::PathBuf;
 use imgui::*;
 use glium::{
   Surface,
   backend::Facade,
 };
 use glium::glutin::event_loop::EventLoop;
-use glium::glutin::event::{Event, WindowEvent, VirtualKeyCode};
-use glium::glutin::dpi::LogicalSize;
-use support::{init, Program, Framework, LoopSignal, run};
-use image_display::ImageDisplay;
-use image_handling::{ImageHandling, loader_pool::LoadNotification, Rating};
+use glium::glutin::event::{Event, WindowEvent, MouseButton, ElementState, MouseScrollDelta, ModifiersState};
+use glium::glutin::dpi::{LogicalSize, LogicalPosition};
+use support::{init, Program, Framework, FramePacing, LoopSignal, run};
+use image_display::{ImageDisplay, Viewport};
+use image_handling::{ImageHandling, LoadSetCounts, MAX_PINNED, loader_pool::{self, LoadNotification, LoaderThreadCounts}, Rating, DirLoadError, FileType, StatisticsSplit};
+use notifications::{Notifications, Severity};
+use image::{ImageRotation, DisplayMode};
+use preferences::{Preferences, PreferencesError};
+use video::VideoPlayer;
+use external_tools::ExternalTool;
+use image_copy::ImageCopy;
+use contact_sheet::ContactSheetExport;
+use folder_export::FolderExport;
+use image_export::{ExportOptions, ExportPlan, ExportKind};
+use archive_export::ArchiveExport;
+use copy_verification::{CopyVerification, VerificationSource, VerificationReport};
+use view_export::ViewExportError;
+use file_drag::FileDrag;
+use logging::{LogHistory, LogLine};
+use frame_times::FrameTimes;
+use actions::Action;
+use gamepad::Gamepads;
+use midi::{MidiControl, MidiBinding};
+use trackpad::TrackpadSwipe;
+use mouse::ClickZones;
+use command_palette::CommandPalette;
+use remote_control::RemoteCommand;
+use window_geometry::{WindowGeometry, DEFAULT_WINDOW_SIZE};
+use presentation::{Presentation, PresentedImages};
+use filmstrip::FilmstripTextures;
+use key_repeat::KeyRepeat;
+use transition::{Transition, TransitionStyle};
+use overlay_template::{OverlayTemplate, OverlayDetails};
+use log::{Level, warn};
 
 mod support;
 mod image;
 mod image_handling;
 mod image_display;
 mod worker_pool;
+mod notifications;
+mod preferences;
+mod video;
+mod platform;
+mod external_tools;
+mod image_copy;
+mod file_drag;
+mod share;
+mod metadata;
+mod contact_sheet;
+mod folder_export;
+mod image_export;
+mod zip_archive;
+mod archive_export;
+mod view_export;
+mod logging;
+mod crash;
+mod frame_times;
+mod actions;
+mod gamepad;
+mod midi;
+mod trackpad;
+mod mouse;
+mod command_palette;
+mod remote_control;
+mod cli;
+mod window_geometry;
+mod presentation;
+mod filmstrip;
+mod transition;
+mod tween;
+mod key_repeat;
+mod compare;
+mod overlay_template;
+mod background_job;
+mod sha256;
+mod copy_verification;
+mod bc1;
+
+  // how many pixels of received images are uploaded into textures per frame. about a 24 megapixel image over a dozen frames
+const UPLOAD_PIXELS_PER_FRAME: usize = 2 * 1024 * 1024;
+  // frames per second drawn at most in power saver mode, smooth enough for zooms and pans, at half the rate of most displays
+const POWER_SAVER_MAX_FPS: u32 = 30;
+
+const UI_FONT_DATA: &[u8] = include_bytes!("../resources/Inter-Light-BETA.ttf");
+  // in logical pixels, at a ui scale of 1
+const UI_FONT_SIZE: f64 = 18.0;
+const UI_SCALE_RANGE: (f32, f32) = (0.5, 3.0);
+
+  // in logical pixels, on each side of the image
+const MAX_IMAGE_MARGIN: f32 = 200.0;
+
+  // long edges that exported jpegs can be scaled down to, and their quality
+const EXPORT_EDGE_RANGE: (u32, u32) = (256, 8192);
+const EXPORT_EDGE_PRESETS: [u32; 3] = [1080, 2048, 4096];
+const EXPORT_QUALITY_RANGE: (u8, u8) = (50, 100);
+
+  // neutral greys to pick the background from. the middle grey is 18% reflectance, the grey of a grey card
+const BACKGROUND_PRESETS: [(&str, f32); 5] = [("Black", 0.0), ("Dark grey", 0.1), ("Middle grey", 0.46), ("Light grey", 0.75), ("White", 1.0)];
+
+  // how often the progress of background exports is updated on screen
+const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+  // how much ctrl + scrolling zooms, per logical pixel of trackpad scrolling and per line of mouse wheel scrolling
+const PIXEL_ZOOM_SPEED: f64 = 0.01;
+const LINE_ZOOM_FACTOR: f64 = 1.25;
+
+  // limits of the loader threads window. the decode pool keeps one thread for the shown image, and needs one more for the others
+const MAX_READ_THREADS: u32 = 16;
+const MIN_DECODE_THREADS: u32 = 2;
+  // limits of the load set window
+const MAX_BUFFER_ZONE_COUNT: u32 = 10;
+const MAX_LOAD_AROUND_COUNT: u32 = 30;
+
+  // how long quitting waits for the decodes and thumbnails in progress to finish
+const WORKER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
 
 const INVIS_WINDOW_FLAGS: WindowFlags = WindowFlags::from_bits_truncate(WindowFlags::NO_BACKGROUND.bits() | WindowFlags::NO_DECORATION.bits() | WindowFlags::NO_INPUTS.bits() | WindowFlags::NO_SAVED_SETTINGS.bits());
 
@@ -25,53 +131,264 @@ struct Fotoleine {
   font: FontId,
   image_handling: ImageHandling,
   image_display: ImageDisplay,
+  notifications: Notifications,
   scale_factor: f64,
   view_area_size: LogicalSize<f64>,
   bg_col: [f32; 3],
-  show_ui: bool
+  image_margin: f32, // logical pixels on each side of fitted images
+  letterbox_col: Option<[f32; 3]>, // replaces the background color around the image, but not behind the overlay
+  display_mode: DisplayMode,
+  show_ui: bool,
+  animation_idx: Option<usize>, // collection index of the image the current animation playback belongs to
+  animation_start: Instant,
+  transition_style: TransitionStyle,
+  frame_cap: Option<u32>, // frames per second drawn at most, see `FramePacing`
+  power_saver: bool, // caps frames lower still, and lets scrolling wait for its frame
+  transition: Option<Transition>, // from the image shown before, while it is still drawn
+  shared_compare_zoom: bool, // whether zooming and panning an image of the compare view does the same to the others
+  strike_out: bool, // whether X rejects and hides the shown image, instead of selecting it
+  read_only: bool, // whether ratings and everything else that writes files is turned off, see `Action::writes_files`
+  video_player: Option<(usize, VideoPlayer)>, // collection index of the clip being played, and its player
+  external_tools: Vec<ExternalTool>,
+  overlay_template: Option<OverlayTemplate>,
+  color_management: bool,
+  image_copy: Option<ImageCopy>, // the image being decoded for the clipboard
+  contact_sheet_export: Option<ContactSheetExport>,
+  folder_export: Option<FolderExport>,
+  archive_export: Option<ArchiveExport>,
+  pending_export: Option<ExportPlan>, // shown for confirming before it starts
+  export_max_edge: Option<u32>, // long edge jpegs are scaled down to when they are copied or archived
+  export_quality: u8, // of the scaled down jpegs
+  show_export_window: bool,
+  export_edited: bool, // the export size or quality changed since they were last saved
+  copy_verification: Option<CopyVerification>,
+  verification_report: Option<VerificationReport>, // of the last verification, shown until another one starts
+  verify_source: String, // path of the manifest or folder to verify against, as entered
+  show_verify_window: bool,
+  cursor_pos: LogicalPosition<f64>,
+  file_drag: FileDrag,
+  ui_wants_mouse: bool, // whether the cursor was over a UI window in the last frame
+  show_filter_panel: bool,
+  show_statistics_panel: bool,
+  show_filmstrip: bool,
+  filmstrip_textures: FilmstripTextures,
+  statistics_split: StatisticsSplit,
+  log_history: LogHistory,
+  show_log_console: bool,
+  show_perf_hud: bool,
+  frame_times: FrameTimes,
+  upload_time: Duration, // spent on texture uploads in the last frame
+  gamepads: Gamepads,
+  midi_control: Option<MidiControl>, // only listening to midi inputs while there are bindings for them
+  trackpad_swipe: TrackpadSwipe,
+  click_zones: ClickZones,
+  modifiers: ModifiersState,
+  pending_actions: Vec<Action>, // triggered by window events and the command palette, performed in the next frame
+  key_repeat: KeyRepeat,
+  command_palette: CommandPalette,
+  save_view: Option<bool>, // Some(with the ui) when the view is saved after drawing this frame
+  ui_scale: f32, // of the fonts, the style and the overlay, on top of the display's scale factor
+  rescale_ui: bool, // the fonts are rebuilt for the ui scale before the next frame
+  show_ui_scale_window: bool,
+  show_background_window: bool,
+  background_edited: bool, // the background color changed since it was last saved
+  show_loader_threads_window: bool,
+  read_threads: u32,
+  decode_threads: Option<u32>, // None for one per cpu core, like in the preferences
+  show_load_set_window: bool,
+  load_set_counts: LoadSetCounts,
+  presentation: Option<Presentation>,
+  presentation_loop: bool,
+  include_raw_files: bool, // in file drags
+  loaded_preferences: Option<Preferences> // to save settings changed in the app. None if the file couldn't be read, so it isn't overwritten
 }
 
 impl Fotoleine {
-  fn init(mut framework: Framework, display_size: &LogicalSize<f64>, imgui: &mut Context, event_loop: &EventLoop<LoadNotification>)->Result<Fotoleine, FotoleineInitError> {
-    let image_display = ImageDisplay::new(&framework.display, display_size)?;
+  fn init(mut framework: Framework, imgui: &mut Context, event_loop: &EventLoop<LoadNotification>, log_history: LogHistory, preferences_res: Result<Preferences, PreferencesError>, read_only: bool)->Result<Fotoleine, FotoleineInitError> {
+    let scale_factor = framework.display.gl_window().window().scale_factor();
+      // the window may have been restored to its last size, or maximized
+    let display_size: LogicalSize<f64> = framework.display.gl_window().window().inner_size().to_logical(scale_factor);
+    let image_display = ImageDisplay::new(&framework.display, &display_size, scale_factor)?;
       // 2 images on either side of shown that can be flicked between without triggering loads. 
       // keep 2 images before the buffer zone
       // load the next 5 images after the buffer zone
-      //   For a total of 1 + 2 * 2 + 2 + 5 = 12 loaded images at any time
+      //   For a total of 1 + 2 * 2 + 2 + 5 = 12 images that are always loaded
+      // keep previously shown images around while their textures take up less than 512MB in total
       // have 4 worker threads
-    let image_handling = ImageHandling::new(2, 2, 5, 4, &event_loop);
-
-      // consider moving this and the font id storage into framework
-    let inter_font = imgui.fonts().add_font(&[
-      FontSource::TtfData {
-        data: include_bytes!("../resources/Inter-Light-BETA.ttf"),
-        size_pixels: (18.0 * framework.platform.hidpi_factor()) as f32,
-        config: None,
+    let mut notifications = Notifications::new(5);
+    let loaded_preferences = preferences_res
+      .map_err(|error| notifications.warning(format!("Couldn't load preferences, using the defaults: {}", error)))
+      .ok();
+    let preferences = loaded_preferences.clone().unwrap_or_default();
+
+    let external_tools = preferences.external_tools.iter()
+      .filter_map(|config| {
+        ExternalTool::from_config(config)
+          .map_err(|error| notifications.warning(format!("Ignoring the external tool \"{}\": {}", config.name, error)))
+          .ok()
+      })
+      .collect();
+
+    let overlay_template = preferences.overlay_template.as_ref().and_then(|template| {
+      OverlayTemplate::parse(template)
+        .map_err(|error| notifications.warning(format!("Ignoring the overlay template: {}", error)))
+        .ok()
+    });
+
+    let midi_bindings: Vec<_> = preferences.midi_bindings.iter()
+      .filter_map(|config| {
+        MidiBinding::from_config(config)
+          .map_err(|error| notifications.warning(format!("Ignoring the midi binding for \"{}\": {}", config.message, error)))
+          .ok()
+      })
+      .collect();
+    let midi_control = if midi_bindings.is_empty() {
+      None
+    } else {
+      Some(MidiControl::start(midi_bindings, event_loop.create_proxy()))
+    };
+
+    if let Some(port) = preferences.remote_control_port {
+      match preferences.remote_control_token.clone().filter(|token| !token.is_empty()) {
+        Some(token) => if let Err(error) = remote_control::start(port, token, event_loop.create_proxy()) {
+          notifications.warning(format!("Couldn't start the remote control on port {}: {}", port, error));
+        },
+        None => notifications.warning("The remote control needs a remote_control_token in the preferences, it stays off".to_string())
       }
-    ]);
+    }
 
-    framework.renderer.reload_font_texture(imgui)
-      .expect("Couldn't reload font");
+    let loader_threads = LoaderThreadCounts { read: preferences.read_threads, decode: preferences.decode_threads.unwrap_or_else(loader_pool::default_decode_thread_count) };
+    let load_set_counts = LoadSetCounts { buffer_zone: preferences.buffer_zone_count, behind: preferences.load_behind_count, ahead: preferences.load_ahead_count };
+    let mut image_handling = ImageHandling::new(load_set_counts, 512 * 1024 * 1024, loader_threads, preferences.color_management, preferences.memory_mapped_reads, preferences.texture_compression, &event_loop);
+    image_handling.set_follow_symlinks(preferences.follow_symlinks);
+    image_handling.set_ratings_file(preferences.ratings_file());
+    image_handling.set_read_only(read_only);
+    let window_size = framework.display.gl_window().window().inner_size();
+    image_handling.set_display_size([window_size.width, window_size.height]);
 
-    let scale_factor = framework.display.gl_window().window().scale_factor();
+    let ui_scale = preferences.ui_scale.clamp(UI_SCALE_RANGE.0, UI_SCALE_RANGE.1);
+    let inter_font = framework.set_fonts(imgui, UI_FONT_DATA, UI_FONT_SIZE, ui_scale as f64);
 
     Ok(Fotoleine {
       framework,
       font: inter_font,
       image_handling,
       image_display,
+      notifications,
       scale_factor: scale_factor,
-      view_area_size: display_size.clone(),
-      bg_col: [0.1, 0.1, 0.1],
-      show_ui: true
+      view_area_size: display_size,
+      bg_col: preferences.background_color,
+      image_margin: preferences.image_margin.clamp(0.0, MAX_IMAGE_MARGIN),
+      letterbox_col: preferences.letterbox_color,
+      display_mode: DisplayMode::Fit,
+      show_ui: true,
+      animation_idx: None,
+      transition_style: preferences.image_transition,
+      frame_cap: preferences.frame_cap,
+      power_saver: preferences.power_saver,
+      transition: None,
+      shared_compare_zoom: true,
+      strike_out: false,
+      read_only,
+      animation_start: Instant::now(),
+      video_player: None,
+      external_tools,
+      overlay_template,
+      color_management: preferences.color_management,
+      image_copy: None,
+      contact_sheet_export: None,
+      folder_export: None,
+      archive_export: None,
+      pending_export: None,
+      export_max_edge: preferences.export_max_edge.map(|max_edge| max_edge.clamp(EXPORT_EDGE_RANGE.0, EXPORT_EDGE_RANGE.1)),
+      export_quality: preferences.export_quality.clamp(EXPORT_QUALITY_RANGE.0, EXPORT_QUALITY_RANGE.1),
+      show_export_window: false,
+      export_edited: false,
+      copy_verification: None,
+      verification_report: None,
+      verify_source: String::new(),
+      show_verify_window: false,
+      cursor_pos: LogicalPosition::new(0.0, 0.0),
+      file_drag: FileDrag::new(),
+      ui_wants_mouse: false,
+      show_filter_panel: false,
+      show_statistics_panel: false,
+      show_filmstrip: false,
+      filmstrip_textures: FilmstripTextures::default(),
+      statistics_split: StatisticsSplit::None,
+      log_history,
+      show_log_console: false,
+      show_perf_hud: false,
+      frame_times: FrameTimes::new(),
+      upload_time: Duration::ZERO,
+      gamepads: Gamepads::start(event_loop.create_proxy()),
+      midi_control,
+      trackpad_swipe: TrackpadSwipe::new(),
+      click_zones: ClickZones::new(),
+      modifiers: ModifiersState::empty(),
+      pending_actions: Vec::new(),
+      key_repeat: KeyRepeat::default(),
+      command_palette: CommandPalette::new(),
+      save_view: None,
+      ui_scale,
+      rescale_ui: false,
+      show_ui_scale_window: false,
+      show_background_window: false,
+      background_edited: false,
+      show_loader_threads_window: false,
+      read_threads: loader_threads.read as u32,
+      decode_threads: preferences.decode_threads.map(|decode_threads| decode_threads as u32),
+      show_load_set_window: false,
+      load_set_counts,
+      presentation: None,
+      presentation_loop: preferences.presentation_loop,
+      include_raw_files: preferences.include_raw_files,
+      loaded_preferences
     })
   }
 
+    // whether `build_ui` would draw nothing: no overlay, panel, window or notification is shown, so the frame can skip the ui
+  fn ui_is_idle(&self)->bool {
+    if self.presentation.is_some() {
+      return true;
+    }
+    let windows_shown = self.show_filter_panel || self.show_statistics_panel || self.show_filmstrip || self.show_log_console || self.show_perf_hud
+      || self.show_ui_scale_window || self.show_background_window || self.show_export_window || self.pending_export.is_some() || self.show_verify_window
+      || self.show_loader_threads_window || self.show_load_set_window || self.command_palette.is_open();
+    !self.show_ui && !windows_shown && self.notifications.is_empty()
+      && self.center_message().is_none() && self.image_handling.folder_scan.is_none()
+  }
+
+    // the lines shown in the middle of the view in place of the image, shown with the ui hidden too
+  fn center_message(&self)->Option<Vec<String>> {
+    let Some(ref loaded_dir) = self.image_handling.loaded_dir else {
+      return match self.image_handling.folder_scan {
+        Some(_) => None, // the scan's progress is shown instead
+        None => Some(vec!["Drag a folder with images into the window to load it.".to_string()])
+      };
+    };
+
+    if let Some(error) = loaded_dir.current_load_failure() {
+      let path = loaded_dir.current_path();
+      let file_name = path.file_name().map_or(path.as_os_str(), |name| name).to_string_lossy();
+      Some(vec![format!("Couldn't load {}", file_name), error.to_string(), "Press R to retry".to_string()])
+    } else if loaded_dir.current_image().is_none() {
+      Some(vec!["Image loading...".to_string()])
+    } else {
+      None
+    }
+  }
+
   fn build_ui(&mut self, ui:&mut Ui) {
+      // nothing but the image is shown during a presentation
+    if self.presentation.is_some() {
+      return;
+    }
+
     let _font = ui.push_font(self.font);
 
       // disable anything messing with the window drawing area, such that the UI window actually covers the entire drawing area
-    let _window_style = [StyleVar::WindowBorderSize(0.0), StyleVar::WindowRounding(0.0), StyleVar::WindowPadding([0.0, 0.0])].map(|style| ui.push_style_var(style));
+    let window_style = [StyleVar::WindowBorderSize(0.0), StyleVar::WindowRounding(0.0), StyleVar::WindowPadding([0.0, 0.0])].map(|style| ui.push_style_var(style));
 
     ui.window("overlay")
       .flags(INVIS_WINDOW_FLAGS)
@@ -80,15 +397,25 @@ impl Fotoleine {
       .build(|| {
         if let Some(ref loaded_dir) = self.image_handling.loaded_dir {
           if self.show_ui {
-            let border_padding = 10.0; // distance between the window edge and the border of the backing box
-            let backing_padding_x = 10.0; // distance between the backing box edge and actual content, left and right edge
-            let backing_padding_y = 15.0; // same as above, but top/bottom edge
-            let backing_col = [self.bg_col[0], self.bg_col[1], self.bg_col[2], 0.5];
-            let text_top_adjust = 5.0; // for layout, the top of the text bounding box is moved down by this much.
-            let text_height_adjust = 5.0; // the amount of space to remove from the bottom of the text height, to get better spacing and alignment overall. Necessary since I can't get the text baseline position from imgui
+            let scale = self.ui_scale;
+
+              // the shown image's cell in the compare view is outlined, the keys act on it
+            let current_coll_idx = loaded_dir.current_collection_idx();
+            if let Some((_, viewport)) = self.compare_cells().into_iter().find(|&(coll_idx, _)| coll_idx == current_coll_idx) {
+              let thickness = 2.0 * scale;
+              let top_left = [viewport.origin.x as f32 + thickness / 2.0, viewport.origin.y as f32 + thickness / 2.0];
+              let bottom_right = [(viewport.origin.x + viewport.size.width) as f32 - thickness / 2.0, (viewport.origin.y + viewport.size.height) as f32 - thickness / 2.0];
+              ui.get_window_draw_list().add_rect(top_left, bottom_right, [1.0, 1.0, 1.0, 0.6]).thickness(thickness).build();
+            }
+            let border_padding = 10.0 * scale; // distance between the window edge and the border of the backing box
+            let backing_padding_x = 10.0 * scale; // distance between the backing box edge and actual content, left and right edge
+            let backing_padding_y = 15.0 * scale; // same as above, but top/bottom edge
+            let backing_col = self.backing_col(0.5);
+            let text_top_adjust = 5.0 * scale; // for layout, the top of the text bounding box is moved down by this much.
+            let text_height_adjust = 5.0 * scale; // the amount of space to remove from the bottom of the text height, to get better spacing and alignment overall. Necessary since I can't get the text baseline position from imgui
 
-            let rating_line_spacing = 20.0;
-            let filter_border_padding = 5.0;
+            let rating_line_spacing = 20.0 * scale;
+            let filter_border_padding = 5.0 * scale;
 
               // image index in folder
             let collection_count = loaded_dir.collection_image_count();
@@ -132,7 +459,7 @@ impl Fotoleine {
                 };
 
                 let dashed = rating_num != i;
-                let target_dash_width = 5.0;
+                let target_dash_width = 5.0 * scale;
                 let dash_gap_ratio = 0.3; // the gap width is the dash width * this ratio
 
                 let target_stride_width = target_dash_width + target_dash_width * dash_gap_ratio;
@@ -180,24 +507,1411 @@ impl Fotoleine {
                   }
                 }
               }
+
+                // the exposure settings right above the rating box, to judge how much noise and blur to accept while rating
+              if let Some(exposure) = loaded_dir.current_image().map(|placed_image| placed_image.image.exposure).filter(|exposure| !exposure.is_empty()) {
+                let text = ImString::new(exposure.to_string());
+                let mut text_size = ui.calc_text_size(&text);
+                text_size[1] -= text_height_adjust + text_top_adjust;
+
+                let box_bot = backing_tl[1] - border_padding;
+                let text_right = backing_br[0] - backing_padding_x;
+                let text_top = box_bot - backing_padding_y - text_size[1];
+                draw_list.add_rect([text_right - text_size[0] - backing_padding_x, text_top - backing_padding_y], [backing_br[0], box_bot], backing_col).filled(true).build();
+                draw_list.add_text([text_right - text_size[0], text_top - text_top_adjust], [1.0, 1.0, 1.0, 1.0], text);
+              }
+
+                // details about the shown image in the bottom left: its sharpness, to compare the frames of a burst, where it was taken, and the selection
+              let mut info_lines = Vec::new();
+              if let Some(ref template) = self.overlay_template {
+                let coll_idx = loaded_dir.current_collection_idx();
+                let path = loaded_dir.current_path();
+                let file_name = path.file_name().map_or(path.as_os_str(), |name| name).to_string_lossy();
+                let details = OverlayDetails {
+                  index: collection_idx,
+                  count: collection_count,
+                  file_name: &file_name,
+                  rating: loaded_dir.get_current_rating(),
+                  metadata: loaded_dir.metadata_scan().get(coll_idx)
+                };
+                info_lines.extend(template.render(&details));
+              }
+              if let Some(damage) = loaded_dir.damage(loaded_dir.current_collection_idx()) {
+                info_lines.push(format!("Damaged, {}", damage));
+              }
+              if let Some(sharpness) = loaded_dir.current_image().and_then(|placed_image| placed_image.image.sharpness) {
+                info_lines.push(format!("Sharpness {:.0}", sharpness));
+              }
+              if let Some(raw_path) = loaded_dir.raw_path(loaded_dir.current_collection_idx()) {
+                let raw_name = raw_path.file_name().map_or(raw_path.as_os_str(), |name| name).to_string_lossy();
+                info_lines.push(format!("With raw file {} (O to open)", raw_name));
+              }
+              if let Some(location) = loaded_dir.current_image().and_then(|placed_image| placed_image.image.location) {
+                info_lines.push(format!("{} (M to show on a map)", location));
+              }
+              let mut filter_parts = Vec::new();
+              if let Some(file_type) = loaded_dir.get_file_type_filter() {
+                filter_parts.push(file_type.label().to_string());
+              }
+              if loaded_dir.get_metadata_filter().is_active() {
+                filter_parts.push(loaded_dir.get_metadata_filter().to_string());
+              }
+              if loaded_dir.get_selects_filter() {
+                filter_parts.push("selects".to_string());
+              }
+              match loaded_dir.get_reject_filter() {
+                Some(true) => filter_parts.push("rejected".to_string()),
+                Some(false) if !self.strike_out => filter_parts.push("not rejected".to_string()),
+                _ => {}
+              }
+              if !filter_parts.is_empty() {
+                info_lines.push(format!("Showing {} ({} images)", filter_parts.join(", "), loaded_dir.active_image_count()));
+              }
+              if let Some(ref export) = self.contact_sheet_export {
+                info_lines.push(format!("Exporting contact sheets, {}/{} images", export.progress(), export.image_count));
+              }
+              if let Some(ref export) = self.folder_export {
+                let dir_name = export.out_dir.file_name().map_or(export.out_dir.as_os_str(), |name| name).to_string_lossy();
+                info_lines.push(format!("Exporting to {}, {}/{} images", dir_name, export.progress(), export.image_count));
+              }
+              if let Some(ref export) = self.archive_export {
+                info_lines.push(format!("Exporting an archive, {}/{} images", export.progress(), export.image_count));
+              }
+              if let Some(ref verification) = self.copy_verification {
+                info_lines.push(format!("Verifying the copy, {}/{} files", verification.progress(), verification.file_count));
+              }
+              if loaded_dir.selection_count() > 0 {
+                let selected = if loaded_dir.is_current_selected() { "Selected, " } else { "" };
+                info_lines.push(format!("{}{} selected in total", selected, loaded_dir.selection_count()));
+              }
+              if self.read_only {
+                info_lines.push("Read-only, nothing is changed".to_string());
+              }
+              if self.strike_out {
+                info_lines.push("Striking out, X rejects and hides the image".to_string());
+              } else if loaded_dir.is_current_rejected() {
+                info_lines.push("Rejected (Backspace to take back)".to_string());
+              }
+              if loaded_dir.select_count() > 0 {
+                let select = loaded_dir.current_select_position().map_or(String::new(), |position| format!("Select {} of ", position + 1));
+                info_lines.push(format!("{}{} selects", select, loaded_dir.select_count()));
+              }
+              if loaded_dir.bookmark_count() > 0 {
+                let bookmarked = if loaded_dir.is_current_bookmarked() { "Bookmarked, " } else { "" };
+                info_lines.push(format!("{}{} bookmarks in total (N to go to the next)", bookmarked, loaded_dir.bookmark_count()));
+              }
+
+              if !info_lines.is_empty() {
+                let text = ImString::new(info_lines.join("\n"));
+                let mut text_size = ui.calc_text_size(&text);
+                text_size[1] -= text_height_adjust + text_top_adjust;
+
+                let text_left = border_padding + backing_padding_x;
+                let text_top = ui_box_bot - text_size[1];
+                draw_list.add_rect([border_padding, text_top - backing_padding_y], [text_left + text_size[0] + backing_padding_x, ui_box_bot + backing_padding_y], backing_col).filled(true).build();
+                draw_list.add_text([text_left, text_top - text_top_adjust], [1.0, 1.0, 1.0, 1.0], text);
+              }
+
+                // a badge in the top left while the folder is watched, so it is clear at a glance that the shown image may change on its own
+              if loaded_dir.is_watching() {
+                let text = if loaded_dir.follows_arrivals() { "Watching for new images" } else { "Watching for new images, staying put" };
+                let text_size = ui.calc_text_size(text);
+                let badge_padding = 8.0 * scale;
+                let dot_radius = 5.0 * scale;
+
+                let left = border_padding;
+                let top = border_padding;
+                let bot = top + text_size[1] + badge_padding * 2.0;
+                let right = left + badge_padding * 3.0 + dot_radius * 2.0 + text_size[0];
+                draw_list.add_rect([left, top], [right, bot], backing_col).filled(true).build();
+                draw_list.add_circle([left + badge_padding + dot_radius, (top + bot) / 2.0], dot_radius, [0.9, 0.2, 0.2, 1.0]).filled(true).build();
+                draw_list.add_text([left + badge_padding * 2.0 + dot_radius * 2.0, top + badge_padding], [1.0, 1.0, 1.0, 1.0], text);
+              }
             }
           }
+        }
 
-          {
-            if let None = loaded_dir.current_image() {
-              let text = "Image loading...";
-              let text_size = ui.calc_text_size(&text); // :todo: move out text alignment utilities into a function & module
-              ui.set_cursor_pos([(self.view_area_size.width as f32) / 2.0 - text_size[0] / 2.0, (self.view_area_size.height as f32) / 2.0 - text_size[1] / 2.0]);
-              ui.text(text);
-            }
+        if let Some(lines) = self.center_message() {
+          let line_sizes: Vec<_> = lines.iter().map(|line| ui.calc_text_size(line)).collect();
+          let total_height: f32 = line_sizes.iter().map(|size| size[1]).sum();
+          let mut line_top = (self.view_area_size.height as f32) / 2.0 - total_height / 2.0;
+          for (line, size) in lines.iter().zip(line_sizes.iter()) {
+            ui.set_cursor_pos([(self.view_area_size.width as f32) / 2.0 - size[0] / 2.0, line_top]);
+            ui.text(line);
+            line_top += size[1];
           }
-        } else {
-          let text = "Drag a folder with images into the window to load it.";
+        }
+
+          // the scan of a dropped folder in the top center, over the folder loaded before if there is one
+        if let Some(ref folder_scan) = self.image_handling.folder_scan {
+          let folder_name = folder_scan.path().file_name().map_or(folder_scan.path().as_os_str(), |name| name).to_string_lossy();
+          let text = match folder_scan.progress() {
+            (0, _) => format!("Opening {}...", folder_name),
+            (listed, 0) => format!("Listing {}: {} files", folder_name, listed),
+            (listed, checked) => format!("Scanning {}: {}/{} files", folder_name, checked, listed)
+          };
           let text_size = ui.calc_text_size(&text);
-          ui.set_cursor_pos([(self.view_area_size.width as f32) / 2.0 - text_size[0] / 2.0, (self.view_area_size.height as f32) / 2.0 - text_size[1] / 2.0]);
-          ui.text(text);
+          let padding = 8.0 * self.ui_scale;
+          let left = (self.view_area_size.width as f32) / 2.0 - text_size[0] / 2.0;
+          let top = 10.0 * self.ui_scale;
+          let draw_list = ui.get_window_draw_list();
+          draw_list.add_rect([left - padding, top], [left + text_size[0] + padding, top + text_size[1] + padding * 2.0], self.backing_col(0.5)).filled(true).build();
+          draw_list.add_text([left, top + padding], [1.0, 1.0, 1.0, 1.0], text);
         }
+
+        self.draw_notifications(ui);
       });
+
+    drop(window_style);
+    if self.show_filter_panel {
+      self.draw_filter_panel(ui);
+    }
+    if self.show_statistics_panel {
+      self.draw_statistics_panel(ui);
+    }
+    if self.show_filmstrip {
+      self.draw_filmstrip(ui);
+    }
+    if self.show_log_console {
+      self.draw_log_console(ui);
+    }
+    if self.show_perf_hud {
+      self.draw_perf_hud(ui);
+    }
+    if self.show_ui_scale_window {
+      self.draw_ui_scale_window(ui);
+    }
+    if self.show_background_window {
+      self.draw_background_window(ui);
+    }
+    if self.show_export_window {
+      self.draw_export_window(ui);
+    }
+    if self.pending_export.is_some() {
+      self.draw_export_preview(ui);
+    }
+    if self.show_verify_window {
+      self.draw_verify_window(ui);
+    }
+    if self.show_loader_threads_window {
+      self.draw_loader_threads_window(ui);
+    }
+    if self.show_load_set_window {
+      self.draw_load_set_window(ui);
+    }
+    if self.command_palette.is_open() {
+      let commands = actions::commands(&self.external_tools);
+      let display_size = [self.view_area_size.width as f32, self.view_area_size.height as f32];
+      if let Some(action) = self.command_palette.draw(ui, &commands, display_size, self.ui_scale) {
+        self.pending_actions.push(action);
+      }
+    }
+  }
+
+    // the overlay follows the slider while it is dragged, the fonts are rebuilt once it is let go
+  fn draw_ui_scale_window(&mut self, ui: &Ui) {
+    let mut open = true;
+    let mut apply = false;
+    ui.window("UI scale")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 / 2.0, 100.0], Condition::FirstUseEver)
+      .position_pivot([0.5, 0.0])
+      .build(|| {
+        ui.slider_config("Scale", UI_SCALE_RANGE.0, UI_SCALE_RANGE.1)
+          .display_format("%.2f")
+          .build(&mut self.ui_scale);
+        apply = ui.is_item_deactivated_after_edit();
+        ui.same_line();
+        if ui.button("Reset") {
+          self.ui_scale = 1.0;
+          apply = true;
+        }
+      });
+    self.show_ui_scale_window = open;
+
+    if apply {
+      self.rescale_ui = true;
+      let ui_scale = self.ui_scale;
+      self.save_preference("UI scale", |preferences| preferences.ui_scale = ui_scale);
+    }
+  }
+
+    // how copied and archived jpegs are scaled down, e.g. for web-size proofs. saved once the controls are let go, like the background
+  fn draw_export_window(&mut self, ui: &Ui) {
+    let mut open = true;
+    ui.window("Export size")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 / 2.0, 100.0], Condition::FirstUseEver)
+      .position_pivot([0.5, 0.0])
+      .build(|| {
+        let mut scale_down = self.export_max_edge.is_some();
+        if ui.checkbox("Scale JPEGs down", &mut scale_down) {
+          self.export_max_edge = scale_down.then_some(EXPORT_EDGE_PRESETS[1]);
+          self.export_edited = true;
+        }
+        if let Some(ref mut max_edge) = self.export_max_edge {
+          if ui.slider_config("Long edge", EXPORT_EDGE_RANGE.0, EXPORT_EDGE_RANGE.1).display_format("%d px").build(max_edge) {
+            self.export_edited = true;
+          }
+          for (idx, preset) in EXPORT_EDGE_PRESETS.iter().enumerate() {
+            if idx > 0 {
+              ui.same_line();
+            }
+            if ui.button(preset.to_string()) {
+              *max_edge = *preset;
+              self.export_edited = true;
+            }
+          }
+          if ui.slider_config("Quality", EXPORT_QUALITY_RANGE.0, EXPORT_QUALITY_RANGE.1).build(&mut self.export_quality) {
+            self.export_edited = true;
+          }
+        }
+        ui.text_disabled("Applies to copied folders and archives");
+      });
+    self.show_export_window = open;
+
+    if self.export_edited && !ui.is_any_item_active() {
+      self.export_edited = false;
+      let (max_edge, quality) = (self.export_max_edge, self.export_quality);
+      self.save_preference("export size", |preferences| {
+        preferences.export_max_edge = max_edge;
+        preferences.export_quality = quality;
+      });
+    }
+  }
+
+    // every file the pending export writes and where it goes, to confirm it with, or cancel it
+  fn draw_export_preview(&mut self, ui: &Ui) {
+    let plan = match self.pending_export {
+      Some(ref plan) => plan,
+      None => return
+    };
+    let mut open = true;
+    let mut confirmed = false;
+    let mut cancelled = false;
+    let list_size = [ui.current_font_size() * 36.0, ui.current_font_size() * 16.0];
+    ui.window("Export preview")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 / 2.0, 100.0], Condition::Appearing)
+      .position_pivot([0.5, 0.0])
+      .build(|| {
+        let kind = match plan.kind {
+          ExportKind::Folder => "folder",
+          ExportKind::Archive => "archive"
+        };
+        ui.text(format!("{} files go into the new {}", plan.files.len(), kind));
+        ui.text_disabled(plan.destination.to_string_lossy());
+        if let Some(max_edge) = plan.options.max_edge {
+          ui.text_disabled(format!("JPEGs longer than {} px are scaled down", max_edge));
+        }
+        ui.child_window("planned files").size(list_size).border(true).build(|| {
+          let clipper = ListClipper::new(plan.files.len() as i32).begin(ui);
+          for idx in clipper.iter() {
+            let file = &plan.files[idx as usize];
+            ui.text(format!("{}  ->  {}", file.summary.file_name, file.target.display()));
+          }
+        });
+        confirmed = ui.button("Export");
+        ui.same_line();
+        cancelled = ui.button("Cancel");
+      });
+
+    if confirmed {
+      let plan = self.pending_export.take().unwrap();
+      self.start_export(plan);
+    } else if cancelled || !open {
+      self.pending_export = None;
+    }
+  }
+
+  fn start_export(&mut self, plan: ExportPlan) {
+      // read-only mode could have been turned on while the export was shown
+    if self.read_only {
+      self.notifications.warning("Read-only mode is on, nothing can be changed");
+      return;
+    }
+    match plan.kind {
+      ExportKind::Folder if self.folder_export.is_some() => return self.notifications.warning("Images are already being exported"),
+      ExportKind::Archive if self.archive_export.is_some() => return self.notifications.warning("An archive is already being exported"),
+      _ => {}
+    }
+
+    if let Some(ref loaded_dir) = self.image_handling.loaded_dir {
+      let destination_name = plan.destination.file_name().map_or(plan.destination.as_os_str(), |name| name).to_string_lossy();
+      loaded_dir.record_in_journal(&format!("Exported {} files to {}", plan.files.len(), destination_name));
+    }
+    match plan.kind {
+      ExportKind::Folder => self.folder_export = Some(FolderExport::start(plan)),
+      ExportKind::Archive => self.archive_export = Some(ArchiveExport::start(plan))
+    }
+  }
+
+    // a manifest or folder to verify the loaded folder against, and what the last verification found
+  fn draw_verify_window(&mut self, ui: &Ui) {
+    let mut open = true;
+    let mut start = false;
+    let list_size = [ui.current_font_size() * 30.0, ui.current_font_size() * 10.0];
+    ui.window("Verify copy")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 / 2.0, 100.0], Condition::FirstUseEver)
+      .position_pivot([0.5, 0.0])
+      .build(|| {
+        ui.input_text("Manifest or folder", &mut self.verify_source).build();
+        ui.text_disabled("A .sha256 file, or the folder the files were copied from");
+        match self.copy_verification {
+          Some(ref verification) => ui.text(format!("Verifying, {}/{} files", verification.progress(), verification.file_count)),
+          None => start = ui.button("Verify")
+        }
+
+        if let Some(ref report) = self.verification_report {
+          ui.separator();
+          if report.is_complete_copy() {
+            ui.text(format!("All {} files match", report.matched));
+          } else {
+            ui.text(format!("{} files match, {} differ, {} are missing, {} couldn't be read",
+              report.matched, report.mismatched.len(), report.missing.len(), report.unreadable.len()));
+            ui.child_window("verification problems").size(list_size).border(true).build(|| {
+              for name in &report.mismatched {
+                ui.text_colored(Severity::Error.color(), format!("Differs: {}", name));
+              }
+              for name in &report.missing {
+                ui.text_colored(Severity::Error.color(), format!("Missing: {}", name));
+              }
+              for (name, error) in &report.unreadable {
+                ui.text_colored(Severity::Warning.color(), format!("Unreadable: {} ({})", name, error));
+              }
+            });
+          }
+        }
+      });
+    self.show_verify_window = open;
+
+    if start {
+      self.start_verification();
+    }
+  }
+
+    // relative paths are taken to be inside the loaded folder, like the manifest found in it
+  fn start_verification(&mut self) {
+    let folder = match self.image_handling.loaded_dir {
+      Some(ref loaded_dir) => loaded_dir.path().to_path_buf(),
+      None => return self.notifications.warning("Load the folder of the copy to verify it")
+    };
+    let source_path = folder.join(self.verify_source.trim());
+    if self.verify_source.trim().is_empty() || !source_path.exists() {
+      return self.notifications.warning("Enter the path of a checksum manifest or of the folder the files were copied from");
+    }
+    let source = if source_path.is_dir() { VerificationSource::Folder(source_path) } else { VerificationSource::Manifest(source_path) };
+    match CopyVerification::start(&folder, &source) {
+      Ok(verification) => {
+        self.verification_report = None;
+        self.copy_verification = Some(verification);
+      },
+      Err(error) => self.notifications.error(format!("Couldn't verify the copy: {}", error))
+    }
+  }
+
+  fn export_options(&self)->ExportOptions {
+    ExportOptions {
+      max_edge: self.export_max_edge,
+      quality: self.export_quality,
+      color_management: self.color_management
+    }
+  }
+
+    // the background behind the image, to judge prints against neutral greys. the overlay's backing boxes follow it.
+    // below it, the margin around fitted images and a separate color for the area around the image, like letterbox bars.
+    // changes are saved once the controls are let go, rather than for every step of dragging them
+  fn draw_background_window(&mut self, ui: &Ui) {
+    let mut open = true;
+    ui.window("Background")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 / 2.0, 100.0], Condition::FirstUseEver)
+      .position_pivot([0.5, 0.0])
+      .build(|| {
+        if ui.color_edit3("Color", &mut self.bg_col) {
+          self.background_edited = true;
+        }
+        for (idx, (name, grey)) in BACKGROUND_PRESETS.iter().enumerate() {
+          if idx > 0 {
+            ui.same_line();
+          }
+          if ui.color_button_config(name, [*grey, *grey, *grey, 1.0]).flags(ColorEditFlags::NO_TOOLTIP).build() {
+            self.bg_col = [*grey; 3];
+            self.background_edited = true;
+          }
+          if ui.is_item_hovered() {
+            ui.tooltip_text(name);
+          }
+        }
+
+        ui.separator();
+        if ui.slider_config("Margin", 0.0, MAX_IMAGE_MARGIN).display_format("%.0f").build(&mut self.image_margin) {
+          self.background_edited = true;
+        }
+        let mut separate_letterbox = self.letterbox_col.is_some();
+        if ui.checkbox("Separate color around the image", &mut separate_letterbox) {
+          self.letterbox_col = if separate_letterbox { Some([0.0, 0.0, 0.0]) } else { None };
+          self.background_edited = true;
+        }
+        if let Some(ref mut letterbox_col) = self.letterbox_col {
+          if ui.color_edit3("Around the image", letterbox_col) {
+            self.background_edited = true;
+          }
+        }
+      });
+    self.show_background_window = open;
+
+    if self.background_edited && !ui.is_any_item_active() {
+      self.background_edited = false;
+      let (bg_col, image_margin, letterbox_col) = (self.bg_col, self.image_margin, self.letterbox_col);
+      self.save_preference("background", |preferences| {
+        preferences.background_color = bg_col;
+        preferences.image_margin = image_margin;
+        preferences.letterbox_color = letterbox_col;
+      });
+    }
+  }
+
+    // how many threads read and decode images, see `LoaderPool`. applied and saved once a slider is let go
+  fn draw_loader_threads_window(&mut self, ui: &Ui) {
+    let mut open = true;
+    let mut apply = false;
+    let auto_decode_threads = loader_pool::default_decode_thread_count() as u32;
+    ui.window("Loader threads")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 / 2.0, 100.0], Condition::FirstUseEver)
+      .position_pivot([0.5, 0.0])
+      .build(|| {
+        ui.slider("Reads", 1, MAX_READ_THREADS, &mut self.read_threads);
+        apply |= ui.is_item_deactivated_after_edit();
+
+        let mut decode_threads = self.decode_threads.unwrap_or(auto_decode_threads);
+        if ui.slider("Decodes", MIN_DECODE_THREADS, auto_decode_threads.max(MIN_DECODE_THREADS) * 2, &mut decode_threads) {
+          self.decode_threads = Some(decode_threads);
+        }
+        apply |= ui.is_item_deactivated_after_edit();
+        ui.same_line();
+        if ui.button("Auto") {
+          self.decode_threads = None;
+          apply = true;
+        }
+        ui.text_disabled("One of the decode threads only decodes the shown image");
+      });
+    self.show_loader_threads_window = open;
+
+    if apply {
+      let (read_threads, decode_threads) = (self.read_threads as usize, self.decode_threads.map(|decode_threads| decode_threads as usize));
+      self.image_handling.services.set_loader_threads(LoaderThreadCounts { read: read_threads, decode: decode_threads.unwrap_or(auto_decode_threads as usize) });
+      self.save_preference("loader threads", |preferences| {
+        preferences.read_threads = read_threads;
+        preferences.decode_threads = decode_threads;
+      });
+    }
+  }
+
+    // how many images are loaded around the shown one, see `ImageLoadingPolicy`. applied to the loaded folder and saved once a slider is let go
+  fn draw_load_set_window(&mut self, ui: &Ui) {
+    let mut open = true;
+    let mut apply = false;
+    let counts = &mut self.load_set_counts;
+    ui.window("Load set")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 / 2.0, 100.0], Condition::FirstUseEver)
+      .position_pivot([0.5, 0.0])
+      .build(|| {
+        for (label, count, max) in [("Buffer zone", &mut counts.buffer_zone, MAX_BUFFER_ZONE_COUNT), ("Behind", &mut counts.behind, MAX_LOAD_AROUND_COUNT), ("Ahead", &mut counts.ahead, MAX_LOAD_AROUND_COUNT)] {
+          let mut value = *count as u32;
+          if ui.slider(label, 0, max, &mut value) {
+            *count = value as usize;
+          }
+          apply |= ui.is_item_deactivated_after_edit();
+        }
+        ui.text_disabled("The shown image can move through the buffer zone before the images around it load");
+      });
+    self.show_load_set_window = open;
+
+    if apply {
+      let counts = self.load_set_counts;
+      self.image_handling.set_load_set_counts(counts);
+      self.save_preference("load set", |preferences| {
+        preferences.buffer_zone_count = counts.buffer_zone;
+        preferences.load_behind_count = counts.behind;
+        preferences.load_ahead_count = counts.ahead;
+      });
+    }
+  }
+
+    // writes a setting changed in the app to the preferences file. `setting` names it in messages
+  fn save_preference(&mut self, setting: &str, update: impl FnOnce(&mut Preferences)) {
+    match self.loaded_preferences {
+      Some(ref mut preferences) => {
+        update(preferences);
+        if let Err(error) = preferences.save() {
+          self.notifications.error(format!("Couldn't save the {}: {}", setting, error));
+        }
+      },
+      None => self.notifications.warning(format!("The {} isn't saved, since the preferences couldn't be loaded", setting))
+    }
+  }
+
+    // for the backing boxes behind the overlay's white text. light backgrounds are darkened, so the text stays readable on them
+  fn backing_col(&self, alpha: f32)->[f32; 4] {
+    let luminance = 0.2126 * self.bg_col[0] + 0.7152 * self.bg_col[1] + 0.0722 * self.bg_col[2];
+    let darken = if luminance > 0.4 { 0.4 / luminance } else { 1.0 };
+    [self.bg_col[0] * darken, self.bg_col[1] * darken, self.bg_col[2] * darken, alpha]
+  }
+
+    // frame times, how busy the loader is and how the texture memory fills up, to find out where time goes while browsing
+  fn draw_perf_hud(&mut self, ui: &Ui) {
+    let graph_size = [300.0 * self.ui_scale, 60.0 * self.ui_scale];
+    let megabyte = 1024.0 * 1024.0;
+
+    let mut open = true;
+    let frame_times = &mut self.frame_times;
+    let image_handling = &self.image_handling;
+    let upload_time = self.upload_time;
+    ui.window("Performance")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([self.view_area_size.width as f32 - 340.0, 200.0], Condition::FirstUseEver)
+      .build(|| {
+        ui.text(format!("Frame: {:.1}ms, average {:.1}ms, slowest {:.1}ms", frame_times.last(), frame_times.average(), frame_times.max()));
+        ui.text(format!("Texture uploads: {:.1}ms", upload_time.as_secs_f32() * 1000.0));
+        ui.plot_lines("##frame_times", frame_times.times())
+          .graph_size(graph_size)
+          .scale_min(0.0)
+          .build();
+
+        let (reads, decodes) = image_handling.services.loader_stats();
+        ui.separator();
+        ui.text(format!("Reads: {} queued, {}/{} workers busy", reads.queued_count, reads.busy_count, reads.worker_count));
+        ui.text(format!("Decodes: {} queued, {}/{} workers busy", decodes.queued_count, decodes.busy_count, decodes.worker_count));
+        ui.text(format!("Reads: {} done, average {:.0}ms, last {:.0}ms", reads.completed_count, reads.average_task_time.as_secs_f32() * 1000.0, reads.last_task_time.as_secs_f32() * 1000.0));
+        ui.text(format!("Decodes: {} done, average {:.0}ms, last {:.0}ms", decodes.completed_count, decodes.average_task_time.as_secs_f32() * 1000.0, decodes.last_task_time.as_secs_f32() * 1000.0));
+
+        if let Some(ref loaded_dir) = image_handling.loaded_dir {
+          let shown_load = match loaded_dir.load_time(loaded_dir.current_collection_idx()) {
+            Some(load_time) => format!("{:.0}ms", load_time.as_secs_f32() * 1000.0),
+            None => "not loaded in full yet".to_string()
+          };
+          ui.text(format!("Shown image: {}", shown_load));
+          ui.text(format!("Received, waiting for upload: {}", loaded_dir.pending_upload_count()));
+        }
+
+          // the numbers to tune the load ahead count and texture budget against
+        let memory = image_handling.memory_usage();
+        let budget = image_handling.services.texture_memory_budget();
+        let loaded_count = image_handling.loaded_dir.as_ref().map_or(0, |loaded_dir| loaded_dir.loaded_image_count());
+        ui.separator();
+        ui.text(format!("Decoded, not on the GPU yet: {:.0}MB", memory.decoded_bytes as f32 / megabyte));
+        ui.text(format!("Textures: {:.0}MB of {:.0}MB for {} images", memory.texture_bytes as f32 / megabyte, budget as f32 / megabyte, loaded_count));
+        ui.text(format!("Unused textures kept for reuse: {:.0}MB", memory.pooled_texture_bytes as f32 / megabyte));
+        let remaining_prefetches = image_handling.loaded_dir.as_ref().map_or(0, |loaded_dir| loaded_dir.remaining_prefetch_count());
+        ui.text(format!("Prefetched previews: {:.0}MB, {} left", memory.prefetched_preview_bytes as f32 / megabyte, remaining_prefetches));
+      });
+
+    self.show_perf_hud = open;
+  }
+
+    // the latest log lines, with warnings and errors colored like their notifications. follows new lines while scrolled to the bottom
+  fn draw_log_console(&mut self, ui: &Ui) {
+    let mut open = true;
+    ui.window("Log")
+      .opened(&mut open)
+      .collapsible(false)
+      .position([10.0, 400.0], Condition::FirstUseEver)
+      .size([900.0, 300.0], Condition::FirstUseEver)
+      .build(|| {
+        let at_bottom = ui.scroll_y() >= ui.scroll_max_y();
+        for LogLine { level, text } in self.log_history.lines().iter() {
+          let severity = match level {
+            Level::Error => Severity::Error,
+            Level::Warn => Severity::Warning,
+            _ => Severity::Info
+          };
+          ui.text_colored(severity.color(), text);
+        }
+        if at_bottom {
+          ui.set_scroll_here_y_with_ratio(1.0);
+        }
+      });
+
+    self.show_log_console = open;
+  }
+
+    // a bar chart of how many images of the folder have each rating, e.g. to tell a client the number of keepers
+  fn draw_statistics_panel(&mut self, ui: &Ui) {
+    let bar_width = 200.0 * self.ui_scale; // of the longest bar
+    let label_width = 70.0 * self.ui_scale;
+    let bar_col = [0.8, 0.8, 0.8, 1.0];
+
+    let loaded_dir = match self.image_handling.loaded_dir {
+      Some(ref loaded_dir) => loaded_dir,
+      None => return
+    };
+
+    let mut open = true;
+    let mut split = self.statistics_split;
+    ui.window("Ratings")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([10.0, 200.0], Condition::FirstUseEver)
+      .build(|| {
+        let splits = StatisticsSplit::all();
+        let split_labels = splits.map(|split| split.label());
+        let mut split_idx = splits.iter().position(|&other| other == split).unwrap_or(0);
+        if ui.combo_simple_string("Split", &mut split_idx, &split_labels) {
+          split = splits[split_idx];
+        }
+
+        let scan = loaded_dir.metadata_scan();
+        if split != StatisticsSplit::None && !scan.is_complete() {
+          ui.text(format!("Reading metadata, {} images left", scan.remaining()));
+        }
+
+        let groups = loaded_dir.rating_statistics(split);
+        let max_count = groups.iter().flat_map(|group| group.counts).max().unwrap_or(0).max(1);
+        for group in &groups {
+          ui.separator();
+          ui.text(format!("{}: {} images", group.group, group.total()));
+
+          for rating_num in (0..=Rating::max()).rev() {
+            let count = group.counts[rating_num as usize];
+            ui.text(Rating::from_u8(rating_num).name());
+            ui.same_line_with_pos(label_width);
+
+            let bar_tl = ui.cursor_screen_pos();
+            let bar_height = ui.text_line_height();
+            let bar_length = bar_width * count as f32 / max_count as f32;
+            ui.get_window_draw_list()
+              .add_rect(bar_tl, [bar_tl[0] + bar_length, bar_tl[1] + bar_height], bar_col)
+              .filled(true)
+              .build();
+            ui.dummy([bar_width, bar_height]);
+            ui.same_line();
+            ui.text(count.to_string());
+          }
+        }
+      });
+
+    self.show_statistics_panel = open;
+    self.statistics_split = split;
+  }
+
+    // thumbnails of the images around the shown one, in the order they are traversed. clicking one shows it
+  fn draw_filmstrip(&mut self, ui: &Ui) {
+    let scale = self.ui_scale;
+    let cell_size = [120.0 * scale, 80.0 * scale];
+    let cell_spacing = 4.0 * scale;
+    let empty_col = [0.3, 0.3, 0.3, 1.0]; // of images whose thumbnail isn't made yet
+    let current_col = [1.0, 1.0, 1.0, 1.0];
+    let damaged_col = Severity::Error.color(); // of images whose file the integrity scan found damaged
+
+    let loaded_dir = match self.image_handling.loaded_dir {
+      Some(ref loaded_dir) => loaded_dir,
+      None => return
+    };
+    let active_idxs = loaded_dir.active_collection_idxs();
+    let current = loaded_dir.current_active_idx();
+    let first = current as i64 - filmstrip::IMAGES_PER_SIDE as i64;
+    let Framework { ref display, ref mut renderer, .. } = self.framework;
+
+    let mut open = true;
+    let mut clicked = None;
+    ui.window("Filmstrip")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([10.0, self.view_area_size.height as f32 - 200.0 * scale], Condition::FirstUseEver)
+      .build(|| {
+          // the shown image stays in the middle, with empty cells past the first and last image
+        for cell in 0..(filmstrip::IMAGES_PER_SIDE * 2 + 1) {
+          if cell > 0 {
+            ui.same_line_with_spacing(0.0, cell_spacing);
+          }
+          let active_idx = first + cell as i64;
+          let Some(&coll_idx) = usize::try_from(active_idx).ok().and_then(|active_idx| active_idxs.get(active_idx)) else {
+            ui.dummy(cell_size);
+            continue;
+          };
+
+          let tl = ui.cursor_screen_pos();
+          let br = [tl[0] + cell_size[0], tl[1] + cell_size[1]];
+          if ui.invisible_button(format!("##image{}", coll_idx), cell_size) {
+            clicked = Some(active_idx as usize);
+          }
+
+          let texture = loaded_dir.thumbnail(coll_idx).and_then(|thumbnail| {
+            let rotation = loaded_dir.orientation(coll_idx).unwrap_or(thumbnail.rotation());
+            self.filmstrip_textures.get_or_make(coll_idx, thumbnail, rotation, display, renderer.textures())
+          });
+          let draw_list = ui.get_window_draw_list();
+          match texture {
+            Some((texture_id, size)) => {
+              let fit_scale = (cell_size[0] / size[0] as f32).min(cell_size[1] / size[1] as f32);
+              let image_size = [size[0] as f32 * fit_scale, size[1] as f32 * fit_scale];
+              let image_tl = [tl[0] + (cell_size[0] - image_size[0]) / 2.0, tl[1] + (cell_size[1] - image_size[1]) / 2.0];
+              draw_list.add_image(texture_id, image_tl, [image_tl[0] + image_size[0], image_tl[1] + image_size[1]]).build();
+            },
+            None => draw_list.add_rect(tl, br, empty_col).filled(true).build()
+          }
+          if loaded_dir.damage(coll_idx).is_some() {
+            let inset = 3.0 * scale;
+            draw_list.add_rect([tl[0] + inset, tl[1] + inset], [br[0] - inset, br[1] - inset], damaged_col).thickness(2.0 * scale).build();
+          }
+          if active_idx as usize == current {
+            draw_list.add_rect(tl, br, current_col).thickness(2.0 * scale).build();
+          }
+        }
+
+        let remaining = loaded_dir.remaining_thumbnail_count();
+        if remaining > 0 {
+          ui.text(format!("Making thumbnails, {} left", remaining));
+        }
+      });
+
+      // only the images in view keep their textures
+    let in_view = |coll_idx: usize| open && loaded_dir.active_position(coll_idx)
+      .is_some_and(|active_idx| active_idx.abs_diff(current) <= filmstrip::IMAGES_PER_SIDE);
+    self.filmstrip_textures.retain(in_view, renderer.textures());
+
+    self.show_filmstrip = open;
+    if let (Some(active_idx), Some(loaded_dir)) = (clicked, self.image_handling.loaded_dir.as_mut()) {
+      loaded_dir.show_active_idx(active_idx, &self.image_handling.services);
+    }
+  }
+
+    // lets the user pick which file type, camera, lens and ISO range to show images of, out of the values in the loaded folder
+  fn draw_filter_panel(&mut self, ui: &Ui) {
+    let ImageHandling { ref services, ref mut loaded_dir, .. } = self.image_handling;
+    let loaded_dir = match loaded_dir {
+      Some(loaded_dir) => loaded_dir,
+      None => return
+    };
+
+    let mut open = true;
+    let mut changed_file_type = None;
+    let mut changed_reject_filter = None;
+    let mut changed_filter = None;
+    ui.window("Filter")
+      .opened(&mut open)
+      .always_auto_resize(true)
+      .collapsible(false)
+      .position([10.0, 10.0], Condition::FirstUseEver)
+      .build(|| {
+        let file_types = FileType::all();
+        let file_type_labels = file_types.map(|file_type| file_type.label().to_string());
+        let mut file_type = loaded_dir.get_file_type_filter();
+        if filter_combo(ui, "File type", &file_types, &file_type_labels, &mut file_type) {
+          changed_file_type = Some(file_type);
+        }
+        let mut reject_filter = loaded_dir.get_reject_filter();
+        if filter_combo(ui, "Rejects", &[false, true], &["Not rejected".to_string(), "Rejected".to_string()], &mut reject_filter) {
+          changed_reject_filter = Some(reject_filter);
+        }
+
+        let scan = loaded_dir.metadata_scan();
+        if !scan.is_complete() {
+          ui.text(format!("Reading metadata, {} images left", scan.remaining()));
+          return;
+        }
+
+        let mut filter = loaded_dir.get_metadata_filter().clone();
+        let cameras = scan.cameras();
+        let camera_labels: Vec<_> = cameras.iter().map(|camera| camera.label(&cameras)).collect();
+        let lenses = scan.lenses();
+        let isos = scan.isos();
+        let iso_labels: Vec<_> = isos.iter().map(|iso| iso.to_string()).collect();
+
+        let mut changed = filter_combo(ui, "Camera", &cameras, &camera_labels, &mut filter.camera);
+        changed |= filter_combo(ui, "Lens", &lenses, &lenses, &mut filter.lens);
+        changed |= filter_combo(ui, "Lowest ISO", &isos, &iso_labels, &mut filter.min_iso);
+        changed |= filter_combo(ui, "Highest ISO", &isos, &iso_labels, &mut filter.max_iso);
+        if changed {
+          changed_filter = Some(filter);
+        }
+      });
+
+    self.show_filter_panel = open;
+    if let Some(file_type) = changed_file_type {
+      if !loaded_dir.set_file_type_filter(file_type, services) {
+        self.notifications.warning("No images match the filter");
+      }
+    }
+    if let Some(reject_filter) = changed_reject_filter {
+      if !loaded_dir.set_reject_filter(reject_filter, services) {
+        self.notifications.warning("No images match the filter");
+      }
+    }
+    if let Some(filter) = changed_filter {
+      if !loaded_dir.set_metadata_filter(filter, services) {
+        self.notifications.warning("No images match the filter");
+      }
+    }
+  }
+
+    // draws queued notifications as a stack of toasts in the top right corner, newest at the bottom
+  fn draw_notifications(&self, ui: &Ui) {
+    let now = Instant::now();
+    let scale = self.ui_scale;
+    let border_padding = 10.0 * scale; // distance between the window edge and the toasts
+    let backing_padding = 8.0 * scale; // distance between the toast backing box edge and its text
+    let severity_stripe_width = 3.0 * scale;
+    let toast_spacing = 5.0 * scale;
+
+    let draw_list = ui.get_window_draw_list();
+    let right = self.view_area_size.width as f32 - border_padding;
+    let mut top = border_padding;
+
+    for notification in self.notifications.iter() {
+      let opacity = notification.opacity(now);
+      let text_size = ui.calc_text_size(&notification.message);
+
+      let left = right - text_size[0] - backing_padding * 2.0 - severity_stripe_width;
+      let bot = top + text_size[1] + backing_padding * 2.0;
+
+      let backing_col = self.backing_col(0.8 * opacity);
+      draw_list.add_rect([left, top], [right, bot], backing_col).filled(true).build();
+
+      let mut stripe_col = notification.severity.color();
+      stripe_col[3] *= opacity;
+      draw_list.add_rect([left, top], [left + severity_stripe_width, bot], stripe_col).filled(true).build();
+
+      draw_list.add_text([left + severity_stripe_width + backing_padding, top + backing_padding], [1.0, 1.0, 1.0, opacity], &notification.message);
+
+      top = bot + toast_spacing;
+    }
+  }
+
+    // ctrl + scrolling zooms in and out around the cursor, which is also how some platforms report pinching on a trackpad.
+    // two-finger scrolling on a trackpad pans the zoomed in image, and otherwise swipes between images
+    // in the compare view, this applies to the image under the cursor, or to all of them while they are zoomed together
+  fn on_scroll(&mut self, delta: MouseScrollDelta) {
+    let targets = self.scroll_targets();
+    let Some(ref mut loaded_dir) = self.image_handling.loaded_dir else {
+      return;
+    };
+    let first_image = targets.first().and_then(|&(coll_idx, _, _)| loaded_dir.loaded_image(coll_idx));
+    let (has_image, zoomed) = (first_image.is_some(), first_image.is_some_and(|placed_image| placed_image.is_zoomed()));
+    let mut zoom_targets = |factor: f64| {
+      for &(coll_idx, ref size, anchor) in &targets {
+        if let Some(placed_image) = loaded_dir.loaded_image_mut(coll_idx) {
+          placed_image.zoom_at(factor, anchor, size);
+        }
+      }
+    };
+
+    match delta {
+      MouseScrollDelta::PixelDelta(delta) => {
+        let delta = delta.to_logical::<f64>(self.scale_factor);
+        if has_image && self.modifiers.ctrl() {
+          zoom_targets((delta.y * PIXEL_ZOOM_SPEED).exp());
+        } else if zoomed {
+          let now = Instant::now();
+          for &(coll_idx, _, _) in &targets {
+            if let Some(placed_image) = loaded_dir.loaded_image_mut(coll_idx) {
+              placed_image.pan_by(delta.x, delta.y, now);
+            }
+          }
+        } else {
+          self.pending_actions.extend(self.trackpad_swipe.scroll(delta, Instant::now()));
+        }
+      },
+      MouseScrollDelta::LineDelta(_, lines) if has_image && self.modifiers.ctrl() => zoom_targets(LINE_ZOOM_FACTOR.powf(lines as f64)),
+      _ => {}
+    }
+  }
+
+    // the images zooming and panning applies to, with the size of the area each one is placed in, and the cursor position within it
+  fn scroll_targets(&self)->Vec<(usize, LogicalSize<f64>, LogicalPosition<f64>)> {
+    let Some(ref loaded_dir) = self.image_handling.loaded_dir else {
+      return Vec::new();
+    };
+    let cells = self.compare_cells();
+    if cells.is_empty() {
+      return vec![(loaded_dir.current_collection_idx(), self.view_area_size, self.cursor_pos)];
+    }
+
+    let Some(&(hovered_idx, hovered_viewport)) = cells.iter().find(|(_, viewport)| viewport.contains(self.cursor_pos)) else {
+      return Vec::new();
+    };
+    let anchor = LogicalPosition::new(self.cursor_pos.x - hovered_viewport.origin.x, self.cursor_pos.y - hovered_viewport.origin.y);
+    cells.into_iter()
+      .filter(|&(coll_idx, _)| self.shared_compare_zoom || coll_idx == hovered_idx)
+      .map(|(coll_idx, viewport)| (coll_idx, viewport.size, anchor))
+      .collect()
+  }
+
+    // the images of the compare view and where they are shown, empty while no image is pinned
+  fn compare_cells(&self)->Vec<(usize, Viewport)> {
+    let images = self.image_handling.loaded_dir.as_ref().map_or_else(Vec::new, |loaded_dir| loaded_dir.compare_images());
+    let viewports = compare::layout(images.len(), &self.view_area_size);
+    images.into_iter().zip(viewports).collect()
+  }
+
+    // raw and jpeg files that are missing their other half, to notice an incomplete copy from a memory card before it is formatted
+  fn warn_about_incomplete_pairs(&mut self) {
+    let Some((jpegs_without_raw, raws_without_image)) = self.image_handling.loaded_dir.as_ref().and_then(|loaded_dir| loaded_dir.incomplete_pairs()) else {
+      return;
+    };
+
+    let mut parts = Vec::new();
+    if jpegs_without_raw > 0 {
+      parts.push(format!("{} JPEGs have no raw file, filter by it with E", jpegs_without_raw));
+    }
+    if !raws_without_image.is_empty() {
+      for raw_path in raws_without_image {
+        warn!("{} has no JPEG", raw_path.display());
+      }
+      parts.push(format!("{} raw files have no JPEG, see the log for which", raws_without_image.len()));
+    }
+    if !parts.is_empty() {
+      self.notifications.warning(parts.join(". "));
+    }
+  }
+
+    // the shown image for remote control, and the folder it is in. the folder is null while none is loaded
+  fn remote_status(&self)->serde_json::Value {
+    match self.image_handling.loaded_dir {
+      Some(ref loaded_dir) => serde_json::json!({
+        "folder": loaded_dir.path(),
+        "path": loaded_dir.current_path(),
+        "index": loaded_dir.current_collection_idx() + 1,
+        "count": loaded_dir.collection_image_count(),
+        "rating": loaded_dir.get_current_rating().to_u8() + 1,
+        "selected": loaded_dir.is_current_selected(),
+        "selection_count": loaded_dir.selection_count()
+      }),
+      None => serde_json::json!({ "folder": null })
+    }
+  }
+
+    // actions that work on the shown image or the folder do nothing while no folder is loaded
+  fn perform_action(&mut self, action: Action)->LoopSignal {
+      // escape ends a presentation as well
+    if self.presentation.is_some() {
+      match action {
+        Action::TogglePresentation | Action::PresentSelects | Action::ClearSelectionOrFilters => {
+          self.end_presentation();
+          return LoopSignal::Wait;
+        },
+        _ if !presentation::allows(action) => return LoopSignal::Wait,
+        _ => {}
+      }
+    }
+
+    if self.read_only && action.writes_files() {
+      self.notifications.warning("Read-only mode is on, nothing can be changed");
+      return LoopSignal::Wait;
+    }
+
+      // while striking out, X rejects instead of selecting
+    let action = if self.strike_out && action == Action::ToggleSelected { Action::ToggleRejected } else { action };
+    let services = &self.image_handling.services;
+    let wrap = self.presentation.is_some() && self.presentation_loop;
+    let export_options = self.export_options();
+    match (action, self.image_handling.loaded_dir.as_mut()) {
+      (Action::Quit, _) => return LoopSignal::Exit,
+      (Action::ToggleUi, _) => self.show_ui = !self.show_ui,
+        // the log shows without a folder loaded too, to see why loading failed
+      (Action::ToggleLogConsole, _) => self.show_log_console = !self.show_log_console,
+      (Action::TogglePerfHud, _) => self.show_perf_hud = !self.show_perf_hud,
+      (Action::OpenCommandPalette, _) => self.command_palette.open(),
+      (Action::ToggleUiScaleWindow, _) => self.show_ui_scale_window = !self.show_ui_scale_window,
+      (Action::ToggleBackgroundWindow, _) => self.show_background_window = !self.show_background_window,
+      (Action::ToggleExportWindow, _) => self.show_export_window = !self.show_export_window,
+      (Action::ToggleVerifyWindow, loaded_dir) => {
+        self.show_verify_window = !self.show_verify_window;
+        if self.show_verify_window && self.verify_source.is_empty() {
+          let manifest = loaded_dir.and_then(|loaded_dir| copy_verification::find_manifest(loaded_dir.path()));
+          if let Some(file_name) = manifest.as_ref().and_then(|manifest| manifest.file_name()) {
+            self.verify_source = file_name.to_string_lossy().into_owned();
+          }
+        }
+      },
+      (Action::ToggleLoaderThreadsWindow, _) => self.show_loader_threads_window = !self.show_loader_threads_window,
+      (Action::ToggleLoadSetWindow, _) => self.show_load_set_window = !self.show_load_set_window,
+      (Action::CycleDisplayMode, _) => {
+        self.display_mode = self.display_mode.next();
+        self.notifications.info(format!("Display: {}", self.display_mode.label()));
+      },
+      (Action::ToggleReadOnly, _) => {
+        self.read_only = !self.read_only;
+        self.image_handling.set_read_only(self.read_only);
+        let message = if self.read_only { "Read-only: ratings and file operations are off, and nothing is written to the folder" } else { "Stopped being read-only" };
+        self.notifications.info(message);
+      },
+      (Action::ToggleStrikeOut, loaded_dir) => {
+        self.strike_out = !self.strike_out;
+          // rejected images are hidden while striking out, and the filter is left to show all of them after
+        if let Some(loaded_dir) = loaded_dir {
+          loaded_dir.set_reject_filter(if self.strike_out { Some(false) } else { None }, services);
+        }
+        let message = if self.strike_out { "Striking out: X rejects and hides the image" } else { "Stopped striking out" };
+        self.notifications.info(message);
+      },
+      (Action::ToggleSharedCompareZoom, _) => {
+        self.shared_compare_zoom = !self.shared_compare_zoom;
+        let state = if self.shared_compare_zoom { "together" } else { "one at a time" };
+        self.notifications.info(format!("Zooming compared images {}", state));
+      },
+      (Action::CycleTransition, _) => {
+        self.transition_style = self.transition_style.next();
+        self.notifications.info(format!("Transition: {}", self.transition_style.label()));
+        let transition_style = self.transition_style;
+        self.save_preference("transition", |preferences| preferences.image_transition = transition_style);
+      },
+      (Action::TogglePowerSaver, _) => {
+        self.power_saver = !self.power_saver;
+        let state = if self.power_saver { "on" } else { "off" };
+        self.notifications.info(format!("Power saver {}", state));
+        let power_saver = self.power_saver;
+        self.save_preference("power saver", |preferences| preferences.power_saver = power_saver);
+      },
+      (Action::ToggleHighQualityDownscaling, _) => {
+        self.image_display.high_quality_downscaling = !self.image_display.high_quality_downscaling;
+        let state = if self.image_display.high_quality_downscaling { "on" } else { "off" };
+        self.notifications.info(format!("High quality downscaling {}", state));
+      },
+      (_, None) => {},
+      (Action::ShowPrevious(distance), Some(loaded_dir)) => loaded_dir.offset_current(-(distance as i32), wrap, services),
+      (Action::ShowNext(distance), Some(loaded_dir)) => loaded_dir.offset_current(distance as i32, wrap, services),
+      (Action::Rate(rating), Some(loaded_dir)) => {
+          // ratings apply to all selected images while there is a selection
+        let selection_count = loaded_dir.selection_count();
+        let save_res = if selection_count > 0 {
+          loaded_dir.set_selection_rating(rating)
+        } else {
+          loaded_dir.set_current_rating(rating)
+        };
+
+        match save_res {
+          Ok(()) if selection_count > 0 => self.notifications.info(format!("Rated {} selected images", selection_count)),
+          Ok(()) => {},
+          Err(error) => self.notifications.error(format!("Failed to save ratings: {}", error))
+        }
+      },
+      (Action::FilterByRating(rating), Some(loaded_dir)) => {
+        if !loaded_dir.set_rating_filter(Some(rating), services) {
+          self.notifications.warning("No images match the filter");
+        }
+      },
+      (Action::ToggleRejected, Some(loaded_dir)) => {
+        let was_rejected = loaded_dir.is_current_rejected();
+        match loaded_dir.toggle_current_rejected(self.strike_out, services) {
+          Ok(_) if was_rejected => self.notifications.info("Took back the rejection"),
+          Ok(_) => {},
+          Err(error) => self.notifications.error(format!("Failed to save the rejected images: {}", error))
+        }
+      },
+      (Action::ToggleSelected, Some(loaded_dir)) => loaded_dir.toggle_current_selected(),
+      (Action::SelectRange, Some(loaded_dir)) => loaded_dir.select_range_to_current(),
+      (Action::TogglePinned, Some(loaded_dir)) => {
+        if loaded_dir.toggle_current_pinned(services).is_none() {
+          self.notifications.warning(format!("Up to {} images can be compared, unpin one first", MAX_PINNED));
+        }
+      },
+      (Action::UnpinAll, Some(loaded_dir)) => loaded_dir.unpin_all(services),
+      (Action::ToggleSelect, Some(loaded_dir)) => {
+        match loaded_dir.toggle_current_select(services) {
+          Ok(true) => self.notifications.info(format!("Added to the selects, {} in total", loaded_dir.select_count())),
+          Ok(false) => self.notifications.info("Removed from the selects"),
+          Err(error) => self.notifications.error(format!("Failed to save the selects: {}", error))
+        }
+      },
+      (Action::ToggleSelectsOnly, Some(loaded_dir)) => {
+        let selects_only = !loaded_dir.get_selects_filter();
+        if !loaded_dir.set_selects_filter(selects_only, services) {
+          self.notifications.warning("No selects match the filters");
+        }
+      },
+      (Action::MoveSelect { earlier }, Some(loaded_dir)) => {
+        match loaded_dir.move_current_select(earlier, services) {
+          Ok(true) => {
+            let position = loaded_dir.current_select_position().map_or(0, |position| position + 1);
+            self.notifications.info(format!("Moved to select {} of {}", position, loaded_dir.select_count()));
+          },
+          Ok(false) => {},
+          Err(error) => self.notifications.error(format!("Failed to save the selects: {}", error))
+        }
+      },
+        // the images passing the filters, in the order of the selects while only those are shown
+      (Action::ExportArchive, Some(loaded_dir)) => {
+        if self.archive_export.is_some() {
+          self.notifications.warning("An archive is already being exported");
+        } else {
+          let folder = loaded_dir.path();
+          let folder_name = folder.file_name().map_or_else(|| "images".into(), |name| name.to_string_lossy()).into_owned();
+          let selects_only = loaded_dir.get_selects_filter();
+          let name = if selects_only { format!("{} selects", folder_name) } else { folder_name };
+          self.pending_export = Some(ExportPlan::new(ExportKind::Archive, folder, &name, loaded_dir.active_image_summaries(), selects_only, export_options));
+        }
+      },
+      (Action::ExportSelects, Some(loaded_dir)) => {
+        if self.folder_export.is_some() {
+          self.notifications.warning("Images are already being exported");
+        } else if loaded_dir.select_count() == 0 {
+          self.notifications.info("No selects to export, press J to add one");
+        } else {
+          self.pending_export = Some(ExportPlan::new(ExportKind::Folder, loaded_dir.path(), "selects", loaded_dir.select_summaries(), true, export_options));
+        }
+      },
+        // named after the rating filter, e.g. for proofs of the highly rated images
+      (Action::ExportToFolder, Some(loaded_dir)) => {
+        if self.folder_export.is_some() {
+          self.notifications.warning("Images are already being exported");
+        } else {
+          let name = loaded_dir.get_rating_filter().map_or_else(|| "export".to_string(), |rating| format!("rated {}", rating.name().to_lowercase()));
+          self.pending_export = Some(ExportPlan::new(ExportKind::Folder, loaded_dir.path(), &name, loaded_dir.active_image_summaries(), loaded_dir.get_selects_filter(), export_options));
+        }
+      },
+      (Action::ToggleBookmark, Some(loaded_dir)) => {
+        let message = if loaded_dir.toggle_current_bookmark() { "Bookmarked" } else { "Removed the bookmark" };
+        self.notifications.info(message);
+      },
+      (Action::ShowBookmark { forward }, Some(loaded_dir)) => {
+        match loaded_dir.show_next_bookmark(forward, services) {
+          Some((number, count)) => self.notifications.info(format!("Bookmark {}/{}", number, count)),
+          None if loaded_dir.bookmark_count() > 0 => self.notifications.info("No bookmarked images are shown"),
+          None => self.notifications.info("No bookmarks yet, press L to add one")
+        }
+      },
+      (Action::ClearSelectionOrFilters, Some(loaded_dir)) => {
+        if loaded_dir.selection_count() > 0 {
+          loaded_dir.clear_selection();
+        } else {
+          loaded_dir.clear_filters(services);
+        }
+      },
+      (Action::PlayPause, Some(loaded_dir)) => {
+          // a clip that played to the end starts over
+        let coll_idx = loaded_dir.current_collection_idx();
+        let path = loaded_dir.current_path();
+        if video::is_video(&path) {
+          match self.video_player {
+            Some((idx, ref mut player)) if idx == coll_idx && !player.is_finished() => player.toggle_pause(Instant::now()),
+            _ => self.video_player = Some((coll_idx, VideoPlayer::play(path)))
+          }
+        }
+      },
+        // rotations and flips are for images whose exif orientation is wrong
+      (Action::RotateCcw | Action::RotateCw | Action::Flip, Some(loaded_dir)) => {
+        let reorient: fn(ImageRotation)->ImageRotation = match action {
+          Action::RotateCcw => |rotation| rotation.rotated_ccw(),
+          Action::RotateCw => |rotation| rotation.rotated_cw(),
+          _ => |rotation| rotation.flipped()
+        };
+        if let Err(error) = loaded_dir.reorient_current(reorient, services) {
+          self.notifications.error(format!("Failed to save the image orientation: {}", error));
+        }
+      },
+      (Action::ResetZoom { all: false }, Some(loaded_dir)) => loaded_dir.reset_current_zoom(),
+      (Action::ResetZoom { all: true }, Some(loaded_dir)) => loaded_dir.reset_zooms(),
+      (Action::FindDuplicates, Some(loaded_dir)) => {
+        if loaded_dir.duplicate_groups().is_some() {
+          match loaded_dir.show_next_duplicate_group(services) {
+            Some((group_number, group_size)) => {
+              let group_count = loaded_dir.duplicate_groups().map_or(0, |groups| groups.len());
+              self.notifications.info(format!("Duplicate group {}/{}: {} images", group_number, group_count, group_size));
+            },
+            None => self.notifications.info("No duplicates found")
+          }
+        } else if loaded_dir.find_duplicates(services) {
+          self.notifications.info(format!("Looking for duplicates among {} images", loaded_dir.collection_image_count()));
+        } else {
+          self.notifications.info("Still looking for duplicates");
+        }
+      },
+      (Action::ToggleHotFolder, Some(loaded_dir)) => {
+        let watching = !loaded_dir.is_watching();
+        loaded_dir.set_watching(watching, services);
+        self.notifications.info(if watching { "Watching the folder for new images" } else { "Stopped watching the folder" });
+      },
+      (Action::ToggleFollowArrivals, Some(loaded_dir)) => {
+        let follow = !loaded_dir.follows_arrivals();
+        loaded_dir.set_follow_arrivals(follow);
+        self.notifications.info(if follow { "Showing new images as they arrive" } else { "Staying on the shown image when new ones arrive" });
+      },
+      (Action::OpenRawFile, Some(loaded_dir)) => {
+        let image_path = loaded_dir.current_path();
+        let path = match external_tools::find_raw_file(&image_path) {
+          Some(raw_path) => raw_path,
+          None => {
+            self.notifications.warning(format!("No raw file found for {}, opening it instead", image_path.display()));
+            image_path
+          }
+        };
+
+        if let Err(err) = platform::open_file(&path) {
+          self.notifications.error(format!("Couldn't open {}, error {}", path.display(), err));
+        }
+      },
+      (Action::Share, Some(loaded_dir)) => {
+        let path = loaded_dir.current_path();
+        if let Err(error) = share::share_file(self.framework.display.gl_window().window(), &path) {
+          self.notifications.error(format!("Couldn't share {}: {}", path.display(), error));
+        }
+      },
+      (Action::ShowOnMap, Some(loaded_dir)) => {
+        match loaded_dir.current_image().and_then(|placed_image| placed_image.image.location) {
+          Some(location) => {
+            if let Err(err) = platform::open_map(location.latitude, location.longitude) {
+              self.notifications.error(format!("Couldn't open the map, error {}", err));
+            }
+          },
+          None => self.notifications.warning("The shown image has no location")
+        }
+      },
+        // the image itself, at full resolution
+      (Action::CopyImage, Some(loaded_dir)) => {
+        let rotation = loaded_dir.current_image().map(|placed_image| placed_image.image.rotation);
+        self.image_copy = Some(ImageCopy::start(loaded_dir.current_path(), rotation, self.color_management));
+      },
+      (Action::CopyFileName | Action::CopyFilePath, Some(loaded_dir)) => {
+        let path = loaded_dir.current_path();
+        let text = if action == Action::CopyFilePath {
+          std::fs::canonicalize(&path).unwrap_or(path).to_string_lossy().into_owned()
+        } else {
+          path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+        };
+
+        let copy_res = match self.framework.clipboard {
+          Some(ref mut clipboard) => clipboard.set_text(text.clone()).map_err(|error| error.to_string()),
+          None => Err("The clipboard isn't available".to_string())
+        };
+        match copy_res {
+          Ok(()) => self.notifications.info(format!("Copied {}", text)),
+          Err(error) => self.notifications.error(format!("Couldn't copy {}: {}", text, error))
+        }
+      },
+      (Action::RetryOrReveal, Some(loaded_dir)) => {
+        if !loaded_dir.retry_current(services) {
+          let path = loaded_dir.current_path();
+          if let Err(err) = platform::reveal_file(&path) {
+            self.notifications.error(format!("Couldn't reveal file {}, error {}", path.display(), err));
+          }
+        }
+      },
+      (Action::RunExternalTool(idx), Some(loaded_dir)) => {
+        if let Some(tool) = self.external_tools.get(idx) {
+          let path = loaded_dir.current_path();
+          match tool.run(&path) {
+            Ok(()) => self.notifications.info(format!("{}: {}", tool.name, path.display())),
+            Err(error) => self.notifications.error(format!("Couldn't run {}: {}", tool.name, error))
+          }
+        }
+      },
+        // contact sheets of the images passing the filters
+      (Action::ExportContactSheets, Some(loaded_dir)) => {
+        if self.contact_sheet_export.is_some() {
+          self.notifications.warning("Contact sheets are already being exported");
+        } else {
+          loaded_dir.record_in_journal(&format!("Exported contact sheets of {} images", loaded_dir.active_image_count()));
+          self.contact_sheet_export = Some(ContactSheetExport::start(loaded_dir.path(), loaded_dir.active_image_summaries(), self.color_management));
+        }
+      },
+        // presentations end above, before the action is matched
+      (Action::TogglePresentation, Some(loaded_dir)) => {
+        let selection_only = loaded_dir.selection_count() > 0 && loaded_dir.set_selection_filter(true, services);
+        let narrowed_to = selection_only.then_some(PresentedImages::Selection);
+        self.presentation = Some(Presentation::start(self.framework.display.gl_window().window(), narrowed_to));
+      },
+        // a filter showing only the selects already is left on after the presentation
+      (Action::PresentSelects, Some(loaded_dir)) => {
+        if loaded_dir.get_selects_filter() {
+          self.presentation = Some(Presentation::start(self.framework.display.gl_window().window(), None));
+        } else if loaded_dir.set_selects_filter(true, services) {
+          loaded_dir.show_active_idx(0, services);
+          self.presentation = Some(Presentation::start(self.framework.display.gl_window().window(), Some(PresentedImages::Selects)));
+        } else {
+          self.notifications.info("No selects to present, press J to add one");
+        }
+      },
+        // saved once the frame is drawn
+      (Action::SaveView { with_ui }, Some(_)) => self.save_view = Some(with_ui),
+      (Action::ToggleStatisticsPanel, Some(_)) => self.show_statistics_panel = !self.show_statistics_panel,
+      (Action::ToggleFilterPanel, Some(_)) => self.show_filter_panel = !self.show_filter_panel,
+      (Action::ToggleFilmstrip, Some(_)) => {
+        self.show_filmstrip = !self.show_filmstrip;
+        if !self.show_filmstrip {
+          self.filmstrip_textures.clear(self.framework.renderer.textures());
+        }
+      }
+    }
+
+    LoopSignal::Wait
+  }
+
+  fn end_presentation(&mut self) {
+    if let Some(presentation) = self.presentation.take() {
+      if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+        match presentation.narrowed_to {
+          Some(PresentedImages::Selection) => { loaded_dir.set_selection_filter(false, &self.image_handling.services); },
+          Some(PresentedImages::Selects) => { loaded_dir.set_selects_filter(false, &self.image_handling.services); },
+          None => {}
+        }
+      }
+      presentation.end(self.framework.display.gl_window().window());
+    }
+  }
+
+    // renders the shown image as it is on screen into a file, with the ui on top if `overlay` is given. returns the path it was saved to
+  fn save_view(&mut self, overlay: Option<&DrawData>, now: Instant)->Result<PathBuf, String> {
+    let loaded_dir = self.image_handling.loaded_dir.as_ref().ok_or_else(|| "No folder is loaded".to_string())?;
+    let placed_image = loaded_dir.current_image().ok_or_else(|| "The shown image hasn't loaded yet".to_string())?;
+    let video_frame = self.video_player.as_ref().and_then(|(_, player)| player.texture());
+    let elapsed = now - self.animation_start;
+
+    let image_display = &mut self.image_display;
+    let renderer = &mut self.framework.renderer;
+    let bg_col = self.letterbox_col.unwrap_or(self.bg_col);
+    let (size, rgb) = view_export::render(&self.framework.display, |target| {
+      target.clear_color(bg_col[0], bg_col[1], bg_col[2], 1.0);
+      match video_frame {
+        Some(frame) => image_display.draw_video_frame(placed_image, frame, target),
+        None => image_display.draw_image(placed_image, elapsed, target)
+      }
+
+      match overlay {
+        Some(draw_data) => renderer.render(target, draw_data).map_err(|error| ViewExportError::RenderFailed(format!("{:?}", error))),
+        None => Ok(())
+      }
+    }).map_err(|error| error.to_string())?;
+
+    view_export::save(loaded_dir.path(), &loaded_dir.current_path(), size, &rgb).map_err(|error| error.to_string())
+  }
+}
+
+  // a combo box to pick one of `values` for a filter, or none of them to not filter by it
+fn filter_combo<T: Clone + PartialEq>(ui: &Ui, label: &str, values: &[T], value_labels: &[String], selected: &mut Option<T>)->bool {
+  let mut labels = vec!["Any"];
+  labels.extend(value_labels.iter().map(|label| label.as_str()));
+
+  let mut selected_idx = selected.as_ref()
+    .and_then(|selected| values.iter().position(|value| value == selected))
+    .map_or(0, |idx| idx + 1);
+  if ui.combo_simple_string(label, &mut selected_idx, &labels) {
+    *selected = selected_idx.checked_sub(1).map(|idx| values[idx].clone());
+    true
+  } else {
+    false
   }
 }
 
@@ -219,80 +1933,186 @@ impl Program for Fotoleine {
           WindowEvent::CloseRequested 
             => LoopSignal::Exit,
 
-            // On resize, need to use immediate redraw to update the visuals because a redraw request won't arrive until the resizing is done
-            // Input events need to trigger an immediate redraw, since otherwise, both e.g. a key down and a key up event can arrive in the same batch
-            //  In that case, a redraw request won't arrive until after both those events were processed, which means that imgui never sees the change from not pressed to pressed, effectively making it miss the input
-            //  This should probably be done to all inputs, including cursor movement. However, redrawing on every cursor move makes the app feel more frame-y
-          WindowEvent::Resized { .. } | 
-          WindowEvent::KeyboardInput { .. } | WindowEvent::MouseWheel { .. } | WindowEvent::MouseInput { .. } 
-            => LoopSignal::ImmediateRedraw,
-
-            // cursor moved not doing an instant redraw might mean that intermediate mouse positions are not detected on long blocking frames
-            // so certain hover states may not be detected. this is deemed acceptable though, since doing immediate redraws on mouse movement has a noticeable impact on UI smootheness
+            // frames for input are drawn once all waiting events are handled. the loop makes sure imgui sees every key press, see `support::run`
+          WindowEvent::Resized { .. } |
+          WindowEvent::KeyboardInput { .. } | WindowEvent::MouseWheel { .. } | WindowEvent::MouseInput { .. } |
           WindowEvent::Focused { .. } | WindowEvent::ScaleFactorChanged { .. } |
           WindowEvent::CursorMoved { .. } | WindowEvent::CursorEntered { .. } | WindowEvent::CursorLeft { .. }
-            => LoopSignal::RequestRedraw,          
+            => LoopSignal::RequestRedraw,
 
           _ => LoopSignal::Wait
         }
       },
-      Event::UserEvent(_) => LoopSignal::RequestRedraw,
       _ => LoopSignal::Wait
     };
 
-    match event {
-      Event::WindowEvent{event:win_event, .. } => {
-        match win_event {
-          WindowEvent::DroppedFile(path) => {
-            let load_res = self.image_handling.load_path(&path);
-            if let Err(load_error) = load_res {
-              println!("Couldn't load path {}: {}", path.display(), load_error);
+    if let Event::WindowEvent{event:win_event, .. } = event {
+      match win_event {
+        WindowEvent::DroppedFile(path) => self.image_handling.load_path(&path),
+        WindowEvent::ScaleFactorChanged{ scale_factor, .. } => {
+          self.scale_factor = *scale_factor;
+          // Updating view area etc should be handled by the subsequent Resized event
+        },
+          // pressing on the shown image and moving the cursor drags its file out of the window. the compare view places images within its cells, so nothing is dragged from it
+        WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+          let on_image = !self.ui_wants_mouse && self.compare_cells().is_empty() && self.image_handling.loaded_dir.as_ref()
+            .and_then(|loaded_dir| loaded_dir.current_image())
+            .is_some_and(|placed_image| placed_image.contains(self.cursor_pos));
+          match state {
+            ElementState::Pressed if on_image => self.file_drag.press(self.cursor_pos),
+            _ => self.file_drag.release()
+          }
+
+          match state {
+            ElementState::Pressed if !self.ui_wants_mouse => self.click_zones.press(self.cursor_pos),
+            ElementState::Pressed => {},
+            ElementState::Released => self.pending_actions.extend(self.click_zones.release(self.cursor_pos, &self.view_area_size))
+          }
+        },
+        WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => self.pending_actions.extend(mouse::button_action(*button)),
+        WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+        WindowEvent::MouseWheel { delta, .. } if !self.ui_wants_mouse => self.on_scroll(*delta),
+        WindowEvent::CursorMoved { position, .. } => {
+          self.cursor_pos = position.to_logical(self.scale_factor);
+          if self.file_drag.cursor_moved(self.cursor_pos) {
+            if let Some(ref loaded_dir) = self.image_handling.loaded_dir {
+              let path = loaded_dir.current_path();
+              let coll_idx = loaded_dir.current_collection_idx();
+              let raw_path = loaded_dir.raw_path(coll_idx).filter(|_| self.include_raw_files);
+              let thumbnail = loaded_dir.thumbnail(coll_idx).map(|thumbnail| (thumbnail, loaded_dir.orientation(coll_idx).unwrap_or(thumbnail.rotation())));
+              if let Err(error) = file_drag::start(self.framework.display.gl_window().window(), &path, raw_path, thumbnail) {
+                self.notifications.error(format!("Couldn't drag {}: {}", path.display(), error));
+              }
             }
+          }
+        },
+        WindowEvent::Resized(size) => {
+          let logical_size = size.to_logical(self.scale_factor);
+          self.view_area_size = logical_size;
+          self.image_display.set_display_size(&logical_size, self.scale_factor);
+          self.image_handling.set_display_size([size.width, size.height]);
+        },
+        _ => {}
+      }
+    }
+
+    loop_signal
+  }
+
+  fn on_user_event(&mut self, notification: Self::UserEvent)->LoopSignal {
+    let mut loop_signal = LoopSignal::RequestRedraw;
+
+    match notification {
+      LoadNotification::ImageLoaded(result) => self.image_handling.receive_image(*result),
+      LoadNotification::ImageHashed { hash, idx, token } => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          match loaded_dir.receive_hash(hash, idx, &token) {
+            Some(0) => self.notifications.info("No duplicates found"),
+            Some(group_count) => self.notifications.info(format!("Found {} groups of duplicates, press G to jump between them", group_count)),
+            None => {}
+          }
+        }
+      },
+      LoadNotification::ThumbnailMade { thumbnail, idx, token } => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          loaded_dir.receive_thumbnail(thumbnail, idx, &token);
+        }
+      },
+      LoadNotification::PreviewPrefetched { preview, idx, token } => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          loaded_dir.receive_prefetched_preview(preview, idx, &token);
+        }
+      },
+      LoadNotification::MetadataRead => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          loaded_dir.receive_metadata(&self.image_handling.services);
+        }
+      },
+      LoadNotification::IntegrityChecked => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          let damaged = if loaded_dir.receive_integrity() { loaded_dir.damaged_file_names() } else { Vec::new() };
+          match damaged.as_slice() {
+            [] => {},
+            [file_name] => self.notifications.warning(format!("{} is damaged", file_name)),
+            [file_name, others @ ..] => self.notifications.warning(format!("{} and {} other files are damaged", file_name, others.len()))
+          }
+        }
+      },
+      LoadNotification::ImagesArrived => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          let arrived_count = loaded_dir.receive_arrivals(&self.image_handling.services);
+          if arrived_count > 0 && !loaded_dir.follows_arrivals() {
+            self.notifications.info(format!("{} new images arrived", arrived_count));
+          }
+        }
+      },
+      LoadNotification::FolderScanProgress => {}, // shown in the frame this wakes up for
+      LoadNotification::FolderListed | LoadNotification::FolderScanned => {
+        let update = self.image_handling.receive_folder_scan();
+        if update.replaced {
+            // the textures are of the previous folder's images
+          self.filmstrip_textures.clear(self.framework.renderer.textures());
+            // and the collection indices too, the first image shown doesn't transition from anything
+          self.animation_idx = None;
+          self.transition = None;
+        }
+        match update.completed {
+          Some((path, Ok(()))) => {
+            let (image_count, broken_link_count) = self.image_handling.loaded_dir.as_ref()
+              .map_or((0, 0), |loaded_dir| (loaded_dir.collection_image_count(), loaded_dir.broken_link_count()));
+            self.notifications.info(format!("Loaded {} images from {}", image_count, path.display()));
+            if broken_link_count > 0 {
+              self.notifications.warning(format!("Skipped {} links to images that don't exist, see the log for which", broken_link_count));
+            }
+            self.warn_about_incomplete_pairs();
           },
-          WindowEvent::ScaleFactorChanged{ scale_factor, .. } => {
-            self.scale_factor = *scale_factor;
-            // Updating view area etc should be handled by the subsequent Resized event
+            // dropping something that isn't an image folder is a user mistake rather than a failure
+          Some((path, Err(load_error @ (DirLoadError::NotADirectory | DirLoadError::NoRelevantImages)))) => {
+            self.notifications.warning(format!("Couldn't load path {}: {}", path.display(), load_error));
           },
-          WindowEvent::Resized(size) => {
-            let logical_size = size.to_logical(self.scale_factor);
-            self.view_area_size = logical_size;
-            self.image_display.set_display_size(&logical_size);
+          Some((path, Err(load_error))) => {
+            self.notifications.error(format!("Couldn't load path {}: {}", path.display(), load_error));
           },
-          _ => {}
+          None => {}
         }
       },
-      Event::UserEvent(notification) => {
-        match notification {
-          LoadNotification::ImageLoaded => {
-            if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
-              let gl_ctx = self.framework.display.get_context();
-              let load_res = loaded_dir.receive_image(&self.image_handling.services, gl_ctx);
-              if let Err(error) = load_res {
-                println!("Error receiving image: {}", error);
-              }
-            } else {
-                //:todo: this could happen if an invalid path was loaded while a load was pending
-                // it's fine to discard the image in that case though
-              println!("Received load result, but loaded_dir does not exist!");
-            }
-          },
-          LoadNotification::LoadFailed => {
-            println!("Image loading failed!");
-            // :todo: set a flag and show this in the ui
-            // also, maybe send image id along with notification to see whether the failed load was on the image we showed,
-            // also to make decisions in loaded dir about re-requesting maybe
+      LoadNotification::LoadFailed(failure) => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          let message = format!("Couldn't load image {}: {}", failure.request.path.display(), failure.error);
+          if loaded_dir.receive_load_failure(*failure) {
+            self.notifications.error(message);
           }
+        } else {
+          warn!("Received load failure, but loaded_dir does not exist!");
         }
       },
-      _ => {}
-    };
+      LoadNotification::LoadDropped { idx, token } => {
+        if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
+          loaded_dir.receive_load_drop(idx, &token);
+        }
+      },
+      LoadNotification::ActionTriggered => {}, // performed with the other input in the frame this wakes up for
+        // performed right away, so that the reply shows the result
+      LoadNotification::RemoteRequest(request) => {
+        if let RemoteCommand::Perform(action) = request.command {
+          loop_signal = loop_signal.max(self.perform_action(action));
+        }
+        request.reply(self.remote_status());
+      }
+    }
 
     loop_signal
   }
 
   fn on_frame(&mut self, imgui: &mut Context)->LoopSignal {
+    let frame_start = Instant::now();
     let mut loop_signal = LoopSignal::Wait;
 
+    if self.rescale_ui {
+      self.font = self.framework.set_fonts(imgui, UI_FONT_DATA, UI_FONT_SIZE, self.ui_scale as f64);
+      self.rescale_ui = false;
+    }
+
     {
       let io = imgui.io_mut();
       let gl_window = self.framework.display.gl_window();
@@ -303,117 +2123,315 @@ impl Program for Fotoleine {
     }
     let ui = imgui.new_frame();
 
-    if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Q as _) && ui.io().key_super {
-      loop_signal = LoopSignal::Exit;
+    let mut actions = std::mem::take(&mut self.pending_actions);
+    actions.extend(actions::keyboard_actions(ui, &self.external_tools));
+    let (repeated_action, next_repeat_at) = self.key_repeat.update(actions::held_navigation(ui), frame_start);
+    actions.extend(repeated_action);
+    if let Some(next_repeat_at) = next_repeat_at {
+      loop_signal = loop_signal.max(LoopSignal::WaitUntil(next_repeat_at));
+    }
+    actions.extend(self.gamepads.take_actions());
+    if let Some(ref midi_control) = self.midi_control {
+      actions.extend(midi_control.take_actions());
+    }
+    for action in actions {
+      loop_signal = loop_signal.max(self.perform_action(action));
     }
 
+    let compare_cells = self.compare_cells();
     if let Some(ref mut loaded_dir) = self.image_handling.loaded_dir {
-      let offset_distance = if ui.io().key_shift {
-        10
+      let gl_ctx = self.framework.display.get_context();
+      let upload_start = Instant::now();
+      if let Err(error) = loaded_dir.process_uploads(&self.image_handling.services, gl_ctx, UPLOAD_PIXELS_PER_FRAME) {
+        self.notifications.error(format!("Error uploading image: {}", error));
+      }
+      self.upload_time = upload_start.elapsed();
+        // keep drawing frames until all received images are on the gpu
+      if loaded_dir.has_pending_uploads() {
+        loop_signal = loop_signal.max(LoopSignal::RequestRedraw);
+      }
+
+        // the compared images are placed within their cells
+      let placements = if compare_cells.is_empty() {
+        vec![(loaded_dir.current_collection_idx(), self.view_area_size)]
       } else {
-        1
+        compare_cells.iter().map(|&(coll_idx, viewport)| (coll_idx, viewport.size)).collect()
       };
+      for (coll_idx, area_size) in placements {
+        let Some(placed_image) = loaded_dir.loaded_image_mut(coll_idx) else {
+          continue;
+        };
+          // zooms and flung pans move on a bit every frame until they arrive
+        if placed_image.animate(frame_start) {
+          loop_signal = loop_signal.max(LoopSignal::RequestRedraw);
+        }
+        placed_image.place(&area_size, self.image_margin as f64 * 2.0, self.display_mode, self.scale_factor);
+          // images too large for a single texture upload the tiles that came into view
+        match placed_image.stream_tiles(&area_size, self.scale_factor, gl_ctx, UPLOAD_PIXELS_PER_FRAME) {
+          Ok(true) => loop_signal = loop_signal.max(LoopSignal::RequestRedraw),
+          Ok(false) => {},
+          Err(error) => self.notifications.error(format!("Error uploading image tiles: {}", error))
+        }
+      }
+      if let Some(pace_change_at) = loaded_dir.update_pace(&self.image_handling.services, frame_start) {
+        loop_signal = loop_signal.max(LoopSignal::WaitUntil(pace_change_at));
+      }
+      loaded_dir.load_full_resolution_if_needed(self.scale_factor, &self.image_handling.services);
+    }
 
-      if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::A as _) || ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Left as _) {
-        loaded_dir.offset_current(-offset_distance, &self.image_handling.services);
-      } else if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::D as _) || ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Right as _) {
-        loaded_dir.offset_current( offset_distance, &self.image_handling.services);
+    if let Some(ref image_copy) = self.image_copy {
+      match image_copy.poll() {
+        Some(result) => {
+          let copy_res = result.and_then(|image| {
+            let clipboard = self.framework.clipboard.as_mut().ok_or_else(|| "The clipboard isn't available".to_string())?;
+            let image_data = arboard::ImageData { width: image.size[0], height: image.size[1], bytes: image.rgba.into() };
+            clipboard.set_image(image_data).map_err(|error| error.to_string())
+          });
+
+          match copy_res {
+            Ok(()) => self.notifications.info(format!("Copied {}", image_copy.path.display())),
+            Err(error) => self.notifications.error(format!("Couldn't copy {}: {}", image_copy.path.display(), error))
+          }
+          self.image_copy = None;
+        },
+          // keep checking until the decode is done
+        None => loop_signal = loop_signal.max(LoopSignal::RequestRedraw)
       }
+    }
 
-      if let Some(ref mut placed_image) = loaded_dir.current_image_mut() {
-        placed_image.place_to_fit(&self.view_area_size, 0.0);
-      };
+    let now = Instant::now();
+    if let Some(ref export) = self.contact_sheet_export {
+      match export.poll() {
+        Some(result) => {
+          match result {
+            Ok(sheet_count) => self.notifications.info(format!("Saved {} contact sheets to {}", sheet_count, export.out_dir.display())),
+            Err(error) => self.notifications.error(format!("Couldn't export contact sheets: {}", error))
+          }
+          self.contact_sheet_export = None;
+        },
+        None => loop_signal = loop_signal.max(LoopSignal::WaitUntil(now + PROGRESS_UPDATE_INTERVAL))
+      }
+    }
+    if let Some(ref export) = self.folder_export {
+      match export.poll() {
+        Some(result) => {
+          match result {
+            Ok(0) => self.notifications.info(format!("Exported {} images to {}", export.image_count, export.out_dir.display())),
+            Ok(skipped_count) => self.notifications.warning(format!("Exported {} of {} images to {}, the others couldn't be read or written, see the log for why", export.image_count - skipped_count, export.image_count, export.out_dir.display())),
+            Err(error) => self.notifications.error(format!("Couldn't export the images: {}", error))
+          }
+          self.folder_export = None;
+        },
+        None => loop_signal = loop_signal.max(LoopSignal::WaitUntil(now + PROGRESS_UPDATE_INTERVAL))
+      }
+    }
+    if let Some(ref export) = self.archive_export {
+      match export.poll() {
+        Some(result) => {
+          match result {
+            Ok(0) => self.notifications.info(format!("Saved {} images to {}", export.image_count, export.out_path.display())),
+            Ok(skipped_count) => self.notifications.warning(format!("Saved {} of {} images to {}, the others couldn't be read", export.image_count - skipped_count, export.image_count, export.out_path.display())),
+            Err(error) => self.notifications.error(format!("Couldn't export the archive: {}", error))
+          }
+          self.archive_export = None;
+        },
+        None => loop_signal = loop_signal.max(LoopSignal::WaitUntil(now + PROGRESS_UPDATE_INTERVAL))
+      }
+    }
+    if let Some(ref verification) = self.copy_verification {
+      match verification.poll() {
+        Some(Ok(report)) => {
+          let summary = if report.is_complete_copy() {
+            format!("All {} files of the copy match", report.matched)
+          } else {
+            format!("{} of {} files of the copy don't match", verification.file_count - report.matched, verification.file_count)
+          };
+            // verifying only reads the files, so it works in read-only mode too, without the journal entry
+          if let (Some(ref loaded_dir), false) = (&self.image_handling.loaded_dir, self.read_only) {
+            loaded_dir.record_in_journal(&format!("Verified the copy: {}", summary));
+          }
+          if report.is_complete_copy() {
+            self.notifications.info(summary);
+          } else {
+            self.notifications.error(summary);
+            self.show_verify_window = true;
+          }
+          self.verification_report = Some(report);
+          self.copy_verification = None;
+        },
+        Some(Err(error)) => {
+          self.notifications.error(format!("Couldn't verify the copy: {}", error));
+          self.copy_verification = None;
+        },
+        None => loop_signal = loop_signal.max(LoopSignal::WaitUntil(now + PROGRESS_UPDATE_INTERVAL))
+      }
+    }
 
-      if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::O as _) {
-        let mut path = loaded_dir.current_path();
+    self.notifications.remove_expired(now);
 
-        let res = ["cr2", "cr3"].iter()
-          .map(|ext| {
-            path.set_extension(ext);
+      // the imgui frame is still started and ended while the ui is hidden, since keyboard input is read through it,
+      // but with nothing built into it there is nothing to render either
+    let ui_idle = self.ui_is_idle();
+    if !ui_idle {
+      self.build_ui(ui);
+    }
+    self.ui_wants_mouse = ui.io().want_capture_mouse;
+      // a command picked from the palette is performed in the next frame, and the fonts are rebuilt before it
+    if !self.pending_actions.is_empty() || self.rescale_ui {
+      loop_signal = loop_signal.max(LoopSignal::RequestRedraw);
+    }
 
-            Command::new("open")
-              .arg(path.as_os_str())
-              .output()
-          })
-          .reduce(Result::or)
-          .unwrap(); // We know we have 2 elements in the iterator
+    if let Some(update_at) = self.notifications.next_update(now) {
+      let notification_signal = if update_at <= now {
+        LoopSignal::RequestRedraw
+      } else {
+        LoopSignal::WaitUntil(update_at)
+      };
+      loop_signal = loop_signal.max(notification_signal);
+    }
 
-        // Note that if the file couldn't be found, it won't actually error - the command will still have run correctly,
-        // but just print out that the file doesn't exist. Keeping this error handling because might as well.
-        if let Err(err) = res {
-          println!("Couldn't open raw file for path {}, error {}", loaded_dir.current_path().with_extension("").display(), err);
-        }
-      }
+    self.framework.platform.prepare_render(ui, self.framework.display.gl_window().window());
+    let draw_data = imgui.render();
 
-      if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::R as _) {
-        let path = loaded_dir.current_path();
-        let open_res = Command::new("open")
-          .arg("-R") // reveal in finder
-          .arg(path.as_os_str())
-          .output();
+    let mut target = self.framework.display.draw();
+      // presentations show the image on black, like a projector would
+    let bg_col = if self.presentation.is_some() { [0.0, 0.0, 0.0] } else { self.letterbox_col.unwrap_or(self.bg_col) };
+    target.clear_color(bg_col[0], bg_col[1], bg_col[2], 1.0);
 
-        if let Err(err) = open_res {
-          println!("Couldn't reveal file {}, error {}", path.display(), err);
+    if let Some(ref loaded_dir) = self.image_handling.loaded_dir {
+        // animations start over whenever another image was shown in between
+      let coll_idx = loaded_dir.current_collection_idx();
+      if self.animation_idx != Some(coll_idx) {
+        if let Some(from_idx) = self.animation_idx {
+          let active_idxs = loaded_dir.active_collection_idxs();
+          let forward = active_idxs.iter().position(|&idx| idx == from_idx).is_none_or(|from_pos| from_pos < loaded_dir.current_active_idx());
+          self.transition = Transition::start(self.transition_style, from_idx, forward, now);
         }
+        self.animation_idx = Some(coll_idx);
+        self.animation_start = now;
       }
 
-      if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::U as _) {
-        self.show_ui = !self.show_ui;
+        // video playback stops when another image is shown
+      if self.video_player.as_ref().is_some_and(|(idx, _)| *idx != coll_idx) {
+        self.video_player = None;
       }
 
-      if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Escape as _) {
-        loaded_dir.set_rating_filter(None, &self.image_handling.services);
+      if let Some((_, ref mut player)) = self.video_player {
+        match player.update(self.framework.display.get_context(), now) {
+          Ok(Some(next_frame_at)) => loop_signal = loop_signal.max(LoopSignal::WaitUntil(next_frame_at)),
+          Ok(None) => {},
+          Err(error) => {
+            self.notifications.error(format!("Couldn't play video {}: {}", loaded_dir.current_path().display(), error));
+            self.video_player = None;
+          }
+        }
       }
+      let video_frame = self.video_player.as_ref().and_then(|(_, player)| player.texture());
 
-      if ui.io().key_super {
-        if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Key1 as _) {
-          loaded_dir.set_rating_filter(Some(Rating::Low), &self.image_handling.services);
-        } else if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Key2 as _) {
-          loaded_dir.set_rating_filter(Some(Rating::Medium), &self.image_handling.services);
-        } else if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Key3 as _) {
-          loaded_dir.set_rating_filter(Some(Rating::High), &self.image_handling.services);
+        // the outgoing image needs to be loaded still. videos start playing right away, without a transition
+      if self.transition.as_ref().is_some_and(|transition| transition.is_done(now) || video_frame.is_some()) {
+        self.transition = None;
+      }
+      let outgoing_image = self.transition.as_ref().and_then(|transition| loaded_dir.loaded_image(transition.from_idx));
+
+        // the compare view draws each image in its cell, without transitions or video playback
+      if !compare_cells.is_empty() {
+        let elapsed = now - self.animation_start;
+        for (coll_idx, viewport) in &compare_cells {
+          if let Some(placed_image) = loaded_dir.loaded_image(*coll_idx) {
+            self.image_display.draw_image_in(placed_image, elapsed, viewport, &mut target);
+          }
+        }
+      } else if let Some(ref placed_image) = loaded_dir.current_image() {
+        let elapsed = now - self.animation_start;
+        match (video_frame, &self.transition, outgoing_image) {
+          (Some(frame), _, _) => self.image_display.draw_video_frame(placed_image, frame, &mut target),
+          (None, Some(transition), Some(outgoing_image)) => {
+            let (outgoing_blend, incoming_blend) = transition.blends(now, self.view_area_size.width);
+              // the outgoing image stays on the frame it was at, animations restart for the incoming one
+            self.image_display.draw_image_blended(outgoing_image, Duration::ZERO, &outgoing_blend, &mut target);
+            self.image_display.draw_image_blended(placed_image, elapsed, &incoming_blend, &mut target);
+            loop_signal = loop_signal.max(LoopSignal::RequestRedraw);
+          },
+          (None, _, _) => self.image_display.draw_image(placed_image, elapsed, &mut target)
+        }
+
+          // only redraw for animations while they are shown
+        if let (_, Some(next_frame_in)) = placed_image.image.frame_at(elapsed) {
+          loop_signal = loop_signal.max(LoopSignal::WaitUntil(now + next_frame_in));
         }
-      } else {
-        if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Key1 as _) {
-          loaded_dir.set_current_rating(Rating::Low);
-        } else if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Key2 as _) {
-          loaded_dir.set_current_rating(Rating::Medium);
-        } else if ui.is_key_index_pressed_no_repeat(VirtualKeyCode::Key3 as _) {
-          loaded_dir.set_current_rating(Rating::High);
-        }  
       }
     }
 
-    self.build_ui(ui);
-
-    self.framework.platform.prepare_render(ui, self.framework.display.gl_window().window());
-    let draw_data = imgui.render();
-
-    let mut target = self.framework.display.draw();
-    target.clear_color(self.bg_col[0], self.bg_col[1], self.bg_col[2], 1.0);
+    if !ui_idle {
+      self.framework.renderer
+        .render(&mut target, draw_data)
+        .expect("Rendering failed");
+    }
+      // measured before the swap, which waits for vsync
+    self.frame_times.push(frame_start.elapsed());
+    target.finish().expect("Failed to swap buffers");
 
-    if let Some(ref loaded_dir) = self.image_handling.loaded_dir {
-      if let Some(ref placed_image) = loaded_dir.current_image() {
-        self.image_display.draw_image(placed_image, &mut target);
+    if let Some(with_ui) = self.save_view.take() {
+      let overlay = if with_ui { Some(draw_data) } else { None };
+      match self.save_view(overlay, now) {
+        Ok(path) => {
+          if let Some(ref loaded_dir) = self.image_handling.loaded_dir {
+            loaded_dir.record_in_journal(&format!("Saved a view to {}", path.display()));
+          }
+          self.notifications.info(format!("Saved the view to {}", path.display()));
+        },
+        Err(error) => self.notifications.error(format!("Couldn't save the view: {}", error))
       }
+        // the notification is drawn in the next frame
+      loop_signal = loop_signal.max(LoopSignal::RequestRedraw);
     }
 
-    self.framework.renderer
-      .render(&mut target, draw_data)
-      .expect("Rendering failed");
-    target.finish().expect("Failed to swap buffers");
-
     loop_signal
   }
 
+  fn frame_pacing(&self)->FramePacing {
+      // a cap of 0 means no cap, like none
+    let frame_cap = self.frame_cap.filter(|&cap| cap > 0);
+    let max_fps = if self.power_saver {
+      Some(frame_cap.map_or(POWER_SAVER_MAX_FPS, |frame_cap| frame_cap.min(POWER_SAVER_MAX_FPS)))
+    } else {
+      frame_cap
+    };
+    FramePacing { max_fps, power_saver: self.power_saver }
+  }
+
   fn on_shutdown(&mut self) {
+    self.image_handling.save_session();
+    self.image_handling.shutdown(WORKER_SHUTDOWN_TIMEOUT);
+
+      // the window opens where it was the next time. only saved if the preferences could be loaded, so the file isn't overwritten
+    if let Some(ref mut preferences) = self.loaded_preferences {
+      let geometry = WindowGeometry::of(self.framework.display.gl_window().window(), preferences.window.as_ref());
+      if geometry.is_some() {
+        preferences.window = geometry;
+        if let Err(error) = preferences.save() {
+          warn!("Couldn't save the window position: {}", error);
+        }
+      }
+    }
   }
 }
 
 fn main() {
-  let display_size = LogicalSize::new(1280.0, 720.0);
-  let (event_loop, mut imgui, framework) = init("Fotoleine", &display_size);
-  let fotoleine = Fotoleine::init(framework, &display_size, &mut imgui, &event_loop).expect("Couldn't initialize Fotoleine.");
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let read_only = match cli::run(&args) {
+    cli::Invocation::Viewer { read_only } => read_only,
+    cli::Invocation::Exit(exit_code) => std::process::exit(exit_code)
+  };
+
+  let log_history = logging::init();
+  crash::install_panic_hook();
+  let preferences_res = Preferences::load();
+  let window_geometry = preferences_res.as_ref().ok().and_then(|preferences| preferences.window.clone());
+  let (event_loop, mut imgui, framework) = init("Fotoleine", &DEFAULT_WINDOW_SIZE, window_geometry.as_ref());
+  let fotoleine = Fotoleine::init(framework, &mut imgui, &event_loop, log_history, preferences_res, read_only)
+    .unwrap_or_else(|error| crash::fatal(&format!("Couldn't initialize Fotoleine: {}", error)));
 
   run(event_loop, imgui, fotoleine);
 }
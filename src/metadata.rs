@@ -0,0 +1,289 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek};
+use std::path::Path;
+use exif::{Exif, In, Tag, Value};
+use serde::{Deserialize, Serialize};
+use log::warn;
+
+  // The exif details images can be filtered by
+#[derive(Debug, Clone, Default)]
+pub struct ImageMetadata {
+  pub camera: Option<CameraBody>,
+  pub lens: Option<String>,
+  pub iso: Option<u32>,
+  pub aperture: Option<Aperture>,
+  pub exposure_time: Option<ExposureTime>,
+  pub date: Option<String> // the day the image was taken, as YYYY-MM-DD
+}
+
+impl ImageMetadata {
+    // images without exif data, or without some of the tags, just have those details missing
+  pub fn read(path: &Path)->ImageMetadata {
+    let exif_data = match read_exif(path) {
+      Some(exif_data) => exif_data,
+      None => return ImageMetadata::default()
+    };
+
+    let exposure = ExposureSettings::read(&exif_data);
+    ImageMetadata {
+      camera: read_string(&exif_data, Tag::Model).map(|model| CameraBody { model, serial: read_string(&exif_data, Tag::BodySerialNumber) }),
+      lens: read_string(&exif_data, Tag::LensModel),
+      iso: exposure.iso,
+      aperture: exposure.aperture,
+      exposure_time: exposure.exposure_time,
+      date: read_date(&exif_data)
+    }
+  }
+}
+
+  // The serial number tells apart bodies of the same model, like those of two shooters at the same event
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CameraBody {
+  pub model: String,
+  pub serial: Option<String>
+}
+
+impl CameraBody {
+    // the serial number is only needed to tell apart several bodies of the same model among `cameras`
+  pub fn label(&self, cameras: &[CameraBody])->String {
+    match self.serial {
+      Some(ref serial) if cameras.iter().filter(|other| other.model == self.model).count() > 1 => format!("{} #{}", self.model, serial),
+      _ => self.model.clone()
+    }
+  }
+}
+
+  // The settings an image was taken with, which tell how much noise and blur to expect
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ExposureSettings {
+  pub iso: Option<u32>,
+  pub aperture: Option<Aperture>,
+  pub exposure_time: Option<ExposureTime>
+}
+
+impl ExposureSettings {
+  fn read(exif_data: &Exif)->ExposureSettings {
+    ExposureSettings {
+      iso: read_iso(exif_data),
+      aperture: read_rational(exif_data, Tag::FNumber).map(|(numerator, denominator)| Aperture(numerator as f64 / denominator as f64)),
+      exposure_time: read_rational(exif_data, Tag::ExposureTime).map(|(numerator, denominator)| ExposureTime { numerator, denominator })
+    }
+  }
+
+  pub fn is_empty(&self)->bool {
+    *self == ExposureSettings::default()
+  }
+}
+
+  // the settings that are known, like ISO 400  f/2.8  1/250s
+impl fmt::Display for ExposureSettings {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    let mut parts = Vec::new();
+    if let Some(iso) = self.iso {
+      parts.push(format!("ISO {}", iso));
+    }
+    if let Some(aperture) = self.aperture {
+      parts.push(aperture.to_string());
+    }
+    if let Some(exposure_time) = self.exposure_time {
+      parts.push(exposure_time.to_string());
+    }
+    write!(f, "{}", parts.join("  "))
+  }
+}
+
+  // The f-number the image was taken at
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aperture(pub f64);
+
+impl fmt::Display for Aperture {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+      // whole stops like f/8 are written without a decimal
+    let f_number = format!("{:.1}", self.0);
+    write!(f, "f/{}", f_number.trim_end_matches(".0"))
+  }
+}
+
+  // How long the shutter was open, in seconds, as the fraction cameras store it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ExposureTime {
+  pub numerator: u32,
+  pub denominator: u32
+}
+
+impl fmt::Display for ExposureTime {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    let seconds = self.numerator as f64 / self.denominator as f64;
+      // shorter exposures are written the way cameras show them, like 1/250s, even if stored as e.g. 10/2500
+    if seconds < 0.3 {
+      write!(f, "1/{:.0}s", 1.0 / seconds)
+    } else {
+      let seconds = format!("{:.1}", seconds);
+      write!(f, "{}s", seconds.trim_end_matches(".0"))
+    }
+  }
+}
+
+  // Restricts the images that are shown to those with matching metadata. None matches any value
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetadataFilter {
+  pub camera: Option<CameraBody>,
+  pub lens: Option<String>,
+  pub min_iso: Option<u32>,
+  pub max_iso: Option<u32>
+}
+
+impl MetadataFilter {
+  pub fn is_active(&self)->bool {
+    *self != MetadataFilter::default()
+  }
+
+    // images that are missing a detail the filter restricts don't match
+  pub fn matches(&self, metadata: &ImageMetadata)->bool {
+    let camera_matches = self.camera.is_none() || self.camera == metadata.camera;
+    let lens_matches = self.lens.is_none() || self.lens == metadata.lens;
+    let iso_matches = match metadata.iso {
+      Some(iso) => self.min_iso.is_none_or(|min_iso| iso >= min_iso) && self.max_iso.is_none_or(|max_iso| iso <= max_iso),
+      None => self.min_iso.is_none() && self.max_iso.is_none()
+    };
+    camera_matches && lens_matches && iso_matches
+  }
+}
+
+impl fmt::Display for MetadataFilter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    let mut parts = Vec::new();
+    if let Some(ref camera) = self.camera {
+      parts.push(camera.model.clone());
+    }
+    if let Some(ref lens) = self.lens {
+      parts.push(lens.clone());
+    }
+    match (self.min_iso, self.max_iso) {
+      (Some(min_iso), Some(max_iso)) => parts.push(format!("ISO {}-{}", min_iso, max_iso)),
+      (Some(min_iso), None) => parts.push(format!("ISO {}+", min_iso)),
+      (None, Some(max_iso)) => parts.push(format!("ISO up to {}", max_iso)),
+      (None, None) => {}
+    }
+    write!(f, "{}", parts.join(", "))
+  }
+}
+
+  // Where an image was taken, from its GPS exif tags. In degrees, north and east are positive
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GpsLocation {
+  pub latitude: f64,
+  pub longitude: f64
+}
+
+impl fmt::Display for GpsLocation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    let north_south = if self.latitude < 0.0 { "S" } else { "N" };
+    let east_west = if self.longitude < 0.0 { "W" } else { "E" };
+    write!(f, "{:.5}° {}, {:.5}° {}", self.latitude.abs(), north_south, self.longitude.abs(), east_west)
+  }
+}
+
+  // the location and exposure settings stored in the exif data of an image file that was read into memory. missing for images without
+  // exif data, or without the tags. the path is for reporting errors
+pub fn read_embedded(path: &Path, file: &[u8])->(Option<GpsLocation>, ExposureSettings) {
+  match parse_exif(path, &mut Cursor::new(file)) {
+    Some(exif_data) => (read_location(&exif_data), ExposureSettings::read(&exif_data)),
+    None => (None, ExposureSettings::default())
+  }
+}
+
+fn read_location(exif_data: &Exif)->Option<GpsLocation> {
+  let latitude = read_coordinate(exif_data, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+  let longitude = read_coordinate(exif_data, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+
+    // cameras without a gps fix can write zeroes
+  if latitude == 0.0 && longitude == 0.0 {
+    return None;
+  }
+  Some(GpsLocation { latitude, longitude })
+}
+
+fn read_exif(path: &Path)->Option<Exif> {
+  let file = File::open(path)
+    .map_err(|error| warn!("Couldn't open {} to read its metadata, {}", path.display(), error))
+    .ok()?;
+
+  parse_exif(path, &mut BufReader::new(&file))
+}
+
+fn parse_exif(path: &Path, reader: &mut (impl BufRead + Seek))->Option<Exif> {
+  match exif::Reader::new().read_from_container(reader) {
+    Ok(exif_data) => Some(exif_data),
+    Err(exif::Error::NotFound(_)) => None,
+    Err(error) => {
+      warn!("Couldn't read the metadata of {}, {}", path.display(), error);
+      None
+    }
+  }
+}
+
+  // a coordinate is stored as degrees, minutes and seconds, with a separate tag for the hemisphere
+fn read_coordinate(exif_data: &Exif, tag: Tag, ref_tag: Tag, negative_ref: u8)->Option<f64> {
+  let degrees = match exif_data.get_field(tag, In::PRIMARY)?.value {
+    Value::Rational(ref parts) if parts.len() == 3 => {
+      parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+    },
+    _ => return None
+  };
+
+  let is_negative = match exif_data.get_field(ref_tag, In::PRIMARY)?.value {
+    Value::Ascii(ref strings) => strings.first().and_then(|string| string.first()) == Some(&negative_ref),
+    _ => return None
+  };
+
+    // zero denominators are how some cameras mark a missing value
+  if !degrees.is_finite() {
+    return None;
+  }
+
+  Some(if is_negative { -degrees } else { degrees })
+}
+
+  // the first string of an ascii tag, without the padding some cameras add
+fn read_string(exif_data: &Exif, tag: Tag)->Option<String> {
+  match exif_data.get_field(tag, In::PRIMARY)?.value {
+    Value::Ascii(ref strings) => {
+      let string = String::from_utf8_lossy(strings.first()?);
+      let string = string.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+      if string.is_empty() { None } else { Some(string.to_string()) }
+    },
+    _ => None
+  }
+}
+
+  // the first value of a rational tag, as numerator and denominator. zero denominators are how some cameras mark a missing value
+fn read_rational(exif_data: &Exif, tag: Tag)->Option<(u32, u32)> {
+  match exif_data.get_field(tag, In::PRIMARY)?.value {
+    Value::Rational(ref values) => values.first().filter(|value| value.num > 0 && value.denom > 0).map(|value| (value.num, value.denom)),
+    _ => None
+  }
+}
+
+  // the sensitivity tag only holds 16 bits, so cameras store 65535 and put the actual value into another tag above that
+const SATURATED_ISO: u32 = u16::MAX as u32;
+
+fn read_iso(exif_data: &Exif)->Option<u32> {
+  let read_uint = |tag| exif_data.get_field(tag, In::PRIMARY).and_then(|field| field.value.get_uint(0));
+  match read_uint(Tag::PhotographicSensitivity)? {
+    SATURATED_ISO => read_uint(Tag::ISOSpeed).or_else(|| read_uint(Tag::RecommendedExposureIndex)).or(Some(SATURATED_ISO)),
+    iso => Some(iso)
+  }
+}
+
+  // exif dates look like 2019:05:04 13:02:01, in the camera's local time
+fn read_date(exif_data: &Exif)->Option<String> {
+  let date_time = read_string(exif_data, Tag::DateTimeOriginal).or_else(|| read_string(exif_data, Tag::DateTime))?;
+  let date = date_time.split(' ').next()?;
+  let parts: Vec<_> = date.split(':').collect();
+  match parts[..] {
+    [year, month, day] if parts.iter().all(|part| part.chars().all(|c| c.is_ascii_digit())) && year != "0000" => Some(format!("{}-{}-{}", year, month, day)),
+    _ => None
+  }
+}
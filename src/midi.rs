@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver};
+use midir::{MidiInput, MidiInputConnection};
+use glium::glutin::event_loop::EventLoopProxy;
+use serde::{Deserialize, Serialize};
+use log::{info, warn};
+use crate::actions::Action;
+use crate::image_handling::loader_pool::LoadNotification;
+
+const CLIENT_NAME: &str = "Fotoleine";
+
+  // A midi message bound to an action in the preferences, e.g.
+  //   message: note 36
+  //   action: next
+  // Messages are `note <number>` or `cc <number>` for control changes, on any channel
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MidiBindingConfig {
+  pub message: String,
+  pub action: String
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MidiMessage {
+  Note(u8),
+  ControlChange(u8)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MidiBinding {
+  message: MidiMessage,
+  action: Action
+}
+
+impl MidiBinding {
+  pub fn from_config(config: &MidiBindingConfig)->Result<MidiBinding, MidiBindingError> {
+    let invalid_message = || MidiBindingError::InvalidMessage(config.message.clone());
+    let (kind, number) = config.message.split_once(' ').ok_or_else(invalid_message)?;
+    let number = number.trim().parse::<u8>().ok().filter(|number| *number < 128).ok_or_else(invalid_message)?;
+    let message = match kind.to_lowercase().as_str() {
+      "note" => MidiMessage::Note(number),
+      "cc" => MidiMessage::ControlChange(number),
+      _ => return Err(invalid_message())
+    };
+
+    let action = Action::from_name(&config.action).ok_or_else(|| MidiBindingError::UnknownAction(config.action.clone()))?;
+    Ok(MidiBinding {
+      message,
+      action
+    })
+  }
+}
+
+  // Turns messages from midi controllers and control surfaces into actions, through the bindings from the preferences.
+  // Listens to all midi inputs that are connected when it is started. The inputs call back on a thread of their own, which wakes up the event loop
+pub struct MidiControl {
+  _connections: Vec<MidiInputConnection<()>>, // the inputs are closed when these are dropped
+  actions: Receiver<Action>
+}
+
+impl MidiControl {
+  pub fn start(bindings: Vec<MidiBinding>, event_loop_proxy: EventLoopProxy<LoadNotification>)->MidiControl {
+    let (sender, receiver) = mpsc::channel();
+    let mut connections = Vec::new();
+
+    let port_count = match MidiInput::new(CLIENT_NAME) {
+      Ok(input) => input.port_count(),
+      Err(error) => {
+        warn!("Midi isn't available: {}", error);
+        0
+      }
+    };
+
+      // connecting consumes the MidiInput, so every port gets one of its own
+    for port_idx in 0..port_count {
+      let input = match MidiInput::new(CLIENT_NAME) {
+        Ok(input) => input,
+        Err(error) => {
+          warn!("Midi isn't available: {}", error);
+          break;
+        }
+      };
+      let port = match input.ports().into_iter().nth(port_idx) {
+        Some(port) => port,
+        None => break // disconnected in the meantime
+      };
+      let port_name = input.port_name(&port).unwrap_or_else(|_| format!("midi input {}", port_idx + 1));
+
+      let bindings = bindings.clone();
+      let sender = sender.clone();
+      let event_loop_proxy = event_loop_proxy.clone();
+      let connection = input.connect(&port, CLIENT_NAME, move |_timestamp, bytes, _| {
+        let action = parse_message(bytes)
+          .and_then(|message| bindings.iter().find(|binding| binding.message == message))
+          .map(|binding| binding.action);
+
+        if let Some(action) = action {
+            // the app is shutting down once either end is gone, the connection is closed along with it
+          if sender.send(action).is_ok() {
+            let _ = event_loop_proxy.send_event(LoadNotification::ActionTriggered);
+          }
+        }
+      }, ());
+
+      match connection {
+        Ok(connection) => {
+          info!("Listening to midi input {}", port_name);
+          connections.push(connection);
+        },
+        Err(error) => warn!("Couldn't connect to midi input {}: {}", port_name, error)
+      }
+    }
+
+    MidiControl {
+      _connections: connections,
+      actions: receiver
+    }
+  }
+
+    // the actions of the messages received since the last call, in the order they were received
+  pub fn take_actions(&self)->Vec<Action> {
+    self.actions.try_iter().collect()
+  }
+}
+
+  // notes trigger when they are pressed, control changes when they change to a value other than 0, like buttons on control surfaces do.
+  // note ons with a velocity of 0 are note offs
+fn parse_message(bytes: &[u8])->Option<MidiMessage> {
+  match *bytes {
+    [status, number, value] if status & 0xF0 == 0x90 && value > 0 => Some(MidiMessage::Note(number)),
+    [status, number, value] if status & 0xF0 == 0xB0 && value > 0 => Some(MidiMessage::ControlChange(number)),
+    _ => None
+  }
+}
+
+#[derive(Debug)]
+pub enum MidiBindingError {
+  InvalidMessage(String),
+  UnknownAction(String)
+}
+
+impl fmt::Display for MidiBindingError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::MidiBindingError::*;
+    match self {
+      InvalidMessage(message) => write!(f, "\"{}\" isn't a midi message, use \"note <number>\" or \"cc <number>\"", message),
+      UnknownAction(action) => write!(f, "There is no action called \"{}\"", action),
+    }
+  }
+}
+
+impl Error for MidiBindingError {}
@@ -0,0 +1,63 @@
+use glium::glutin::dpi::{LogicalPosition, LogicalSize};
+use glium::glutin::event::MouseButton;
+use crate::actions::Action;
+
+  // how far the cursor may move between pressing and releasing the button for it to still be a click
+const CLICK_DISTANCE: f64 = 5.0;
+
+  // the back and forward buttons on the side of a mouse, which every platform numbers differently
+#[cfg(target_os = "windows")]
+const BACK_BUTTONS: [u16; 1] = [1];
+#[cfg(target_os = "windows")]
+const FORWARD_BUTTONS: [u16; 1] = [2];
+#[cfg(target_os = "macos")]
+const BACK_BUTTONS: [u16; 1] = [3];
+#[cfg(target_os = "macos")]
+const FORWARD_BUTTONS: [u16; 1] = [4];
+  // x11 buttons, then the evdev codes wayland reports
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const BACK_BUTTONS: [u16; 3] = [8, 0x113, 0x116];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const FORWARD_BUTTONS: [u16; 3] = [9, 0x114, 0x115];
+
+  // Tracks clicks of the left mouse button, to move to the previous image with clicks on the left third of the window and to the next one on the right third.
+  // Presses that move further, like dragging the image out of the window, aren't clicks
+pub struct ClickZones {
+  press_pos: Option<LogicalPosition<f64>>
+}
+
+impl ClickZones {
+  pub fn new()->ClickZones {
+    ClickZones {
+      press_pos: None
+    }
+  }
+
+  pub fn press(&mut self, pos: LogicalPosition<f64>) {
+    self.press_pos = Some(pos);
+  }
+
+    // returns the action of the zone clicked in, if the press was a click
+  pub fn release(&mut self, pos: LogicalPosition<f64>, view_size: &LogicalSize<f64>)->Option<Action> {
+    let press_pos = self.press_pos.take()?;
+    if (pos.x - press_pos.x).hypot(pos.y - press_pos.y) > CLICK_DISTANCE {
+      return None;
+    }
+
+    if pos.x < view_size.width / 3.0 {
+      Some(Action::ShowPrevious(1))
+    } else if pos.x > view_size.width * 2.0 / 3.0 {
+      Some(Action::ShowNext(1))
+    } else {
+      None
+    }
+  }
+}
+
+pub fn button_action(button: MouseButton)->Option<Action> {
+  match button {
+    MouseButton::Other(button) if BACK_BUTTONS.contains(&button) => Some(Action::ShowPrevious(1)),
+    MouseButton::Other(button) if FORWARD_BUTTONS.contains(&button) => Some(Action::ShowNext(1)),
+    _ => None
+  }
+}
@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use log::{log, Level};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+  Info,
+  Warning,
+  Error
+}
+
+impl Severity {
+    // how long a notification of this severity stays on screen
+  fn display_duration(&self)->Duration {
+    match self {
+      Severity::Info => Duration::from_secs(3),
+      Severity::Warning => Duration::from_secs(5),
+      Severity::Error => Duration::from_secs(8)
+    }
+  }
+
+  fn log_level(&self)->Level {
+    match self {
+      Severity::Info => Level::Info,
+      Severity::Warning => Level::Warn,
+      Severity::Error => Level::Error
+    }
+  }
+
+  pub fn color(&self)->[f32; 4] {
+    match self {
+      Severity::Info => [0.8, 0.8, 0.8, 1.0],
+      Severity::Warning => [0.95, 0.75, 0.2, 1.0],
+      Severity::Error => [0.95, 0.3, 0.25, 1.0]
+    }
+  }
+}
+
+pub struct Notification {
+  pub message: String,
+  pub severity: Severity,
+  expires_at: Instant
+}
+
+const FADE_DURATION: Duration = Duration::from_millis(500);
+
+impl Notification {
+    // how far along the notification is in fading out, 1.0 is fully visible and 0.0 is gone
+  pub fn opacity(&self, now: Instant)->f32 {
+    let remaining = self.expires_at.saturating_duration_since(now);
+    if remaining >= FADE_DURATION {
+      1.0
+    } else {
+      remaining.as_secs_f32() / FADE_DURATION.as_secs_f32()
+    }
+  }
+
+  fn fade_start(&self)->Instant {
+    self.expires_at - FADE_DURATION
+  }
+}
+
+  // Queue of transient messages shown to the user as toasts in the overlay
+pub struct Notifications {
+  queue: VecDeque<Notification>,
+  max_count: usize, // the oldest notifications are dropped once more than this many are queued
+}
+
+impl Notifications {
+  pub fn new(max_count: usize)->Notifications {
+    Notifications {
+      queue: VecDeque::with_capacity(max_count),
+      max_count
+    }
+  }
+
+  pub fn post<S: Into<String>>(&mut self, severity: Severity, message: S) {
+    let message = message.into();
+    log!(severity.log_level(), "{}", message);
+
+    let expires_at = Instant::now() + severity.display_duration();
+
+    self.queue.push_back(Notification {
+      message,
+      severity,
+      expires_at
+    });
+
+    while self.queue.len() > self.max_count {
+      self.queue.pop_front();
+    }
+  }
+
+  pub fn info<S: Into<String>>(&mut self, message: S) {
+    self.post(Severity::Info, message);
+  }
+
+  pub fn warning<S: Into<String>>(&mut self, message: S) {
+    self.post(Severity::Warning, message);
+  }
+
+  pub fn error<S: Into<String>>(&mut self, message: S) {
+    self.post(Severity::Error, message);
+  }
+
+  pub fn remove_expired(&mut self, now: Instant) {
+    self.queue.retain(|notification| notification.expires_at > now);
+  }
+
+  pub fn is_empty(&self)->bool {
+    self.queue.is_empty()
+  }
+
+    // oldest first
+  pub fn iter(&self)->impl Iterator<Item = &Notification> {
+    self.queue.iter()
+  }
+
+    // the next point in time at which the shown notifications change visually, if any are shown
+    // returns `now` if a notification is currently fading out, since that needs redraws every frame
+  pub fn next_update(&self, now: Instant)->Option<Instant> {
+    self.queue.iter()
+      .map(|notification| notification.fade_start().max(now))
+      .min()
+  }
+}
@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::fmt;
+use crate::image_handling::Rating;
+use crate::metadata::ImageMetadata;
+
+  // A line of details about the shown image, laid out by the user, like
+  //   {index}/{count} {filename} {iso} {f} {shutter}
+  // Each placeholder in braces is replaced with that detail of the image, the rest is shown as it is.
+  // Details the image doesn't have, like the settings of images without exif data, are left out, see `render`
+#[derive(Debug, Clone)]
+pub struct OverlayTemplate {
+  parts: Vec<TemplatePart>
+}
+
+#[derive(Debug, Clone)]
+enum TemplatePart {
+  Text(String),
+  Detail(Detail)
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Detail {
+  Index,
+  Count,
+  FileName,
+  Rating,
+  Iso,
+  Aperture,
+  Shutter,
+  Camera,
+  Lens,
+  Date
+}
+
+const DETAILS: [(&str, Detail); 10] = [
+  ("index", Detail::Index),
+  ("count", Detail::Count),
+  ("filename", Detail::FileName),
+  ("rating", Detail::Rating),
+  ("iso", Detail::Iso),
+  ("f", Detail::Aperture),
+  ("shutter", Detail::Shutter),
+  ("camera", Detail::Camera),
+  ("lens", Detail::Lens),
+  ("date", Detail::Date)
+];
+
+  // What the placeholders are filled in with. The metadata is None until the folder's metadata scan has read it
+pub struct OverlayDetails<'a> {
+  pub index: usize, // starting at 1
+  pub count: usize,
+  pub file_name: &'a str,
+  pub rating: Rating,
+  pub metadata: Option<&'a ImageMetadata>
+}
+
+impl OverlayTemplate {
+  pub fn parse(template: &str)->Result<OverlayTemplate, OverlayTemplateError> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+      if start > 0 {
+        parts.push(TemplatePart::Text(rest[..start].to_string()));
+      }
+      let end = rest[start..].find('}').ok_or(OverlayTemplateError::Unclosed)? + start;
+      let name = &rest[start + 1..end];
+      let detail = DETAILS.iter()
+        .find(|(detail_name, _)| *detail_name == name)
+        .map(|&(_, detail)| detail)
+        .ok_or_else(|| OverlayTemplateError::UnknownPlaceholder(name.to_string()))?;
+      parts.push(TemplatePart::Detail(detail));
+      rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+      parts.push(TemplatePart::Text(rest.to_string()));
+    }
+    Ok(OverlayTemplate { parts })
+  }
+
+    // the filled in line. a left out detail takes the text separating it from the part before it along, or the text after it
+    // if it comes first, so the rest keeps the spacing of the template. None if nothing is left
+  pub fn render(&self, details: &OverlayDetails)->Option<String> {
+    let mut kept: Vec<(String, bool)> = Vec::new(); // the parts to show, and whether each is text of the template
+    let mut drop_next_text = false;
+    for part in &self.parts {
+      match part {
+        TemplatePart::Text(_) if drop_next_text => drop_next_text = false,
+        TemplatePart::Text(text) => kept.push((text.clone(), true)),
+        TemplatePart::Detail(detail) => match detail_text(*detail, details) {
+          Some(text) => {
+            kept.push((text, false));
+            drop_next_text = false;
+          },
+          None if kept.len() >= 2 && kept.last().is_some_and(|&(_, is_text)| is_text) => {
+            kept.pop();
+          },
+          None => drop_next_text = true
+        }
+      }
+    }
+    let line: String = kept.into_iter().map(|(text, _)| text).collect();
+    let line = line.trim();
+    if line.is_empty() { None } else { Some(line.to_string()) }
+  }
+}
+
+fn detail_text(detail: Detail, details: &OverlayDetails)->Option<String> {
+  let metadata = details.metadata;
+  match detail {
+    Detail::Index => Some(details.index.to_string()),
+    Detail::Count => Some(details.count.to_string()),
+    Detail::FileName => Some(details.file_name.to_string()),
+    Detail::Rating => Some(details.rating.name().to_string()),
+    Detail::Iso => metadata?.iso.map(|iso| format!("ISO {}", iso)),
+    Detail::Aperture => metadata?.aperture.map(|aperture| aperture.to_string()),
+    Detail::Shutter => metadata?.exposure_time.map(|exposure_time| exposure_time.to_string()),
+    Detail::Camera => metadata?.camera.as_ref().map(|camera| camera.model.clone()),
+    Detail::Lens => metadata?.lens.clone(),
+    Detail::Date => metadata?.date.clone()
+  }
+}
+
+#[derive(Debug)]
+pub enum OverlayTemplateError {
+  Unclosed, // a { without a matching }
+  UnknownPlaceholder(String)
+}
+
+impl fmt::Display for OverlayTemplateError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::OverlayTemplateError::*;
+    match self {
+      Unclosed => write!(f, "A {{ isn't closed with a }}"),
+      UnknownPlaceholder(name) => {
+        let names: Vec<_> = DETAILS.iter().map(|(name, _)| format!("{{{}}}", name)).collect();
+        write!(f, "Unknown placeholder {{{}}}, the placeholders are {}", name, names.join(", "))
+      }
+    }
+  }
+}
+
+impl Error for OverlayTemplateError {}
@@ -0,0 +1,170 @@
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+  // Opens the file with the program the OS associates with it
+pub fn open_file(path: &Path)->io::Result<()> {
+  launch(open_command(path.as_os_str()))
+}
+
+  // Shows the location in the Maps app
+#[cfg(target_os = "macos")]
+pub fn open_map(latitude: f64, longitude: f64)->io::Result<()> {
+  launch(open_command(OsStr::new(&format!("https://maps.apple.com/?ll={},{}&q={},{}", latitude, longitude, latitude, longitude))))
+}
+
+  // Shows the location on Google Maps in the default browser
+#[cfg(not(target_os = "macos"))]
+pub fn open_map(latitude: f64, longitude: f64)->io::Result<()> {
+  launch(open_command(OsStr::new(&format!("https://www.google.com/maps?q={},{}", latitude, longitude))))
+}
+
+  // Whether the file is a placeholder of a file in cloud storage, like iCloud Drive or Dropbox, that isn't downloaded yet.
+  // the platform downloads it when it is read. macOS marks these as dataless
+#[cfg(target_os = "macos")]
+pub fn is_cloud_placeholder(metadata: &Metadata)->bool {
+  use std::os::macos::fs::MetadataExt;
+  const SF_DATALESS: u32 = 0x40000000;
+  metadata.st_flags() & SF_DATALESS != 0
+}
+
+  // Windows marks the placeholders of OneDrive, Dropbox and other cloud files as to be recalled, or as offline for older providers
+#[cfg(target_os = "windows")]
+pub fn is_cloud_placeholder(metadata: &Metadata)->bool {
+  use std::os::windows::fs::MetadataExt;
+  const FILE_ATTRIBUTE_OFFLINE: u32 = 0x1000;
+  const FILE_ATTRIBUTE_RECALL_ON_OPEN: u32 = 0x40000;
+  const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: u32 = 0x400000;
+  metadata.file_attributes() & (FILE_ATTRIBUTE_OFFLINE | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS) != 0
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn is_cloud_placeholder(_metadata: &Metadata)->bool {
+  false
+}
+
+  // Shows the file in the OS file manager
+pub fn reveal_file(path: &Path)->io::Result<()> {
+  launch(reveal_command(path))
+}
+
+#[cfg(target_os = "macos")]
+fn open_command(target: &OsStr)->Command {
+  let mut command = Command::new("open");
+  command.arg(target);
+  command
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_command(path: &Path)->Command {
+  let mut command = Command::new("open");
+  command.arg("-R").arg(path);
+  command
+}
+
+  // explorer opens files with their program and urls in the default browser. unlike cmd's start, it takes the target as it is,
+  // without treating characters like & or ^ in it as part of a command line
+#[cfg(target_os = "windows")]
+fn open_command(target: &OsStr)->Command {
+  let mut command = Command::new("explorer");
+  command.arg(target);
+  command
+}
+
+#[cfg(target_os = "windows")]
+fn reveal_command(path: &Path)->Command {
+  let mut select_arg = std::ffi::OsString::from("/select,");
+  select_arg.push(path);
+
+  let mut command = Command::new("explorer");
+  command.arg(select_arg);
+  command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn open_command(target: &OsStr)->Command {
+  let mut command = Command::new("xdg-open");
+  command.arg(target);
+  command
+}
+
+  // there is no common way to select a file in the file manager, so this opens the folder containing it
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal_command(path: &Path)->Command {
+  let mut command = Command::new("xdg-open");
+  command.arg(path.parent().unwrap_or(path));
+  command
+}
+
+  // Shows a modal error message outside of the app's window, which might not be drawing anymore. Returns once it is closed
+pub fn show_error_dialog(title: &str, message: &str)->io::Result<()> {
+  run(error_dialog_command(title, message))
+}
+
+  // the texts are passed as arguments of the script, which saves escaping them for applescript
+#[cfg(target_os = "macos")]
+fn error_dialog_command(title: &str, message: &str)->Command {
+  let mut command = Command::new("osascript");
+  command
+    .arg("-e").arg("on run argv")
+    .arg("-e").arg("display alert (item 1 of argv) message (item 2 of argv) as critical")
+    .arg("-e").arg("end run")
+    .arg(title).arg(message);
+  command
+}
+
+  // the texts are passed in environment variables, which saves escaping them for powershell
+#[cfg(target_os = "windows")]
+fn error_dialog_command(title: &str, message: &str)->Command {
+  let mut command = Command::new("powershell");
+  command
+    .arg("-NoProfile").arg("-Command")
+    .arg("Add-Type -AssemblyName PresentationFramework; [void][System.Windows.MessageBox]::Show($env:FOTOLEINE_MESSAGE, $env:FOTOLEINE_TITLE, 'OK', 'Error')")
+    .env("FOTOLEINE_TITLE", title)
+    .env("FOTOLEINE_MESSAGE", message);
+  command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn error_dialog_command(title: &str, message: &str)->Command {
+  let mut command = Command::new("zenity");
+  command.arg("--error").arg("--no-markup").arg("--title").arg(title).arg("--text").arg(message);
+  command
+}
+
+  // starts the command without waiting for it, since the program it hands the file to may take a while to start, or keep running until it is closed.
+  // it is waited for on another thread, which logs a failed exit status
+#[cfg(not(target_os = "windows"))]
+fn launch(mut command: Command)->io::Result<()> {
+  use std::thread;
+  use log::warn;
+  let mut child = command.spawn()?;
+  let program = command.get_program().to_string_lossy().into_owned();
+  thread::spawn(move || {
+    match child.wait() {
+      Ok(status) if !status.success() => warn!("{} failed ({})", program, status),
+      Ok(_) => {},
+      Err(error) => warn!("Couldn't wait for {}: {}", program, error)
+    }
+  });
+  Ok(())
+}
+
+  // explorer exits with an error status even when it did what was asked, so only failing to start it is an error
+#[cfg(target_os = "windows")]
+fn launch(mut command: Command)->io::Result<()> {
+  command.spawn().map(|_| ())
+}
+
+  // runs the command to completion, turning a failed exit status into an error with the command's error output
+fn run(mut command: Command)->io::Result<()> {
+  let output = command.output()?;
+  if output.status.success() {
+    Ok(())
+  } else {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(io::Error::other(format!("{} ({})", stderr.trim(), output.status)))
+  }
+}
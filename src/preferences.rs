@@ -0,0 +1,165 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::external_tools::ExternalToolConfig;
+use crate::midi::MidiBindingConfig;
+use crate::window_geometry::WindowGeometry;
+use crate::transition::TransitionStyle;
+use crate::image_handling::{RatingsFile, RatingsLocation, DEFAULT_RATINGS_FILE_NAME};
+use crate::image::TextureCompression;
+
+  // User settings, read from preferences.yaml in the platform's config directory.
+  // Settings missing from the file, or a missing file, use their defaults
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Preferences {
+  pub color_management: bool, // convert images with an embedded color profile to srgb
+  pub follow_symlinks: bool, // show the images that symbolic links in a folder point to. links are skipped otherwise
+  pub read_threads: usize, // how many image files are read at once. fewer suit spinning disks and network drives
+  pub memory_mapped_reads: bool, // map image files into memory instead of reading them, see `file_read::read_file`
+  pub texture_compression: TextureCompression, // how image textures are stored on the gpu
+  pub decode_threads: Option<usize>, // how many images are decoded at once. one per cpu core if None
+  pub buffer_zone_count: usize, // how far the shown image can move before the images around it load, see `ImageLoadingPolicy`
+  pub load_behind_count: usize, // images loaded behind and ahead of the shown one, beyond the buffer zone
+  pub load_ahead_count: usize,
+  pub external_tools: Vec<ExternalToolConfig>, // programs that can be run on the shown image with a key
+  pub midi_bindings: Vec<MidiBindingConfig>, // actions triggered by midi controllers
+  pub ui_scale: f32, // on top of the display's scale factor
+  pub background_color: [f32; 3], // srgb, behind the image and the overlay
+  pub image_margin: f32, // logical pixels kept free between fitted images and the window edges
+  pub letterbox_color: Option<[f32; 3]>, // srgb, for the area around the image. the background color if None
+  pub remote_control_port: Option<u16>, // local tcp port to accept remote control commands on. off if None
+  pub remote_control_token: Option<String>, // the secret every remote control request has to carry. the remote control stays off without one
+  pub window: Option<WindowGeometry>, // saved when Fotoleine quits
+  pub presentation_loop: bool, // whether presentations go around from the last image to the first
+  pub include_raw_files: bool, // whether dragging an image out of the window drags the raw file shot alongside it too
+  pub image_transition: TransitionStyle, // how the view changes from one image to the next
+  pub frame_cap: Option<u32>, // frames per second drawn at most, e.g. during zooms and animations. only limited by the display if None
+  pub power_saver: bool, // draw fewer frames, for browsing on battery
+  pub export_max_edge: Option<u32>, // pixels on the long edge jpegs are scaled down to when they are copied or archived. exported as they are if None
+  pub export_quality: u8, // of the scaled down jpegs, from 50 to 100
+  pub overlay_template: Option<String>, // a line of details about the shown image in the bottom left, see `OverlayTemplate`
+  pub ratings_location: RatingsLocation, // where the ratings of a folder are saved
+  pub ratings_file_name: String, // of the ratings file in the folder, or its hidden folder
+  pub ratings_library: bool, // collect the ratings of every folder opened in the platform's data directory
+}
+
+impl Default for Preferences {
+  fn default()->Preferences {
+    Preferences {
+      color_management: true,
+      follow_symlinks: true,
+      read_threads: 2,
+      memory_mapped_reads: false,
+      texture_compression: TextureCompression::Driver,
+      decode_threads: None,
+      buffer_zone_count: 2,
+      load_behind_count: 2,
+      load_ahead_count: 5,
+      external_tools: Vec::new(),
+      midi_bindings: Vec::new(),
+      ui_scale: 1.0,
+      background_color: [0.1, 0.1, 0.1],
+      image_margin: 0.0,
+      letterbox_color: None,
+      remote_control_port: None,
+      remote_control_token: None,
+      window: None,
+      presentation_loop: true,
+      include_raw_files: true,
+      image_transition: TransitionStyle::Cut,
+      frame_cap: None,
+      power_saver: false,
+      export_max_edge: None,
+      export_quality: 85,
+      overlay_template: None,
+      ratings_location: RatingsLocation::Folder,
+      ratings_file_name: DEFAULT_RATINGS_FILE_NAME.to_string(),
+      ratings_library: false
+    }
+  }
+}
+
+impl Preferences {
+  pub fn ratings_file(&self)->RatingsFile {
+    let name = if self.ratings_file_name.is_empty() { DEFAULT_RATINGS_FILE_NAME } else { &self.ratings_file_name };
+    RatingsFile { location: self.ratings_location, name: name.to_string(), library: self.ratings_library }
+  }
+
+  pub fn load()->Result<Preferences, PreferencesError> {
+    let path = match Preferences::path() {
+      Some(path) => path,
+      None => return Ok(Preferences::default())
+    };
+
+    if !path.exists() {
+      return Ok(Preferences::default());
+    }
+
+    let file = File::open(&path)?;
+    let preferences = serde_yaml::from_reader(file)?;
+    Ok(preferences)
+  }
+
+    // replaces the preferences file, for settings changed in the app
+  pub fn save(&self)->Result<(), PreferencesError> {
+    let path = Preferences::path().ok_or_else(|| io::Error::other("The platform has no config directory"))?;
+    let dir = path.parent().ok_or_else(|| io::Error::other("The preferences file has no folder"))?;
+    fs::create_dir_all(dir)?;
+
+    let s = serde_yaml::to_string(self).map_err(PreferencesError::SerializeFailed)?;
+    let mut tmp_file = tempfile::NamedTempFile::new_in(dir)?;
+    tmp_file.as_file_mut().write_all(s.as_bytes())?;
+    tmp_file.persist(&path).map_err(|error| error.error)?;
+    Ok(())
+  }
+
+    // the preferences file path, if the platform has a config directory
+  pub fn path()->Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("fotoleine").join("preferences.yaml"))
+  }
+}
+
+#[derive(Debug)]
+pub enum PreferencesError {
+  IoError(io::Error),
+  DeserializeError(serde_yaml::Error),
+  SerializeFailed(serde_yaml::Error)
+}
+
+impl fmt::Display for PreferencesError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::PreferencesError::*;
+    match self {
+      IoError(error) => write!(f, "Could not read the preferences file: {}", error),
+      DeserializeError(error) => write!(f, "Could not deserialize the preferences file: {}", error),
+      SerializeFailed(error) => write!(f, "Could not serialize the preferences: {}", error),
+    }
+  }
+}
+
+impl Error for PreferencesError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::PreferencesError::*;
+    match self {
+      IoError(error) => Some(error),
+      DeserializeError(error) => Some(error),
+      SerializeFailed(error) => Some(error)
+    }
+  }
+}
+
+impl From<io::Error> for PreferencesError {
+  fn from(error: io::Error)->Self {
+    PreferencesError::IoError(error)
+  }
+}
+
+impl From<serde_yaml::Error> for PreferencesError {
+  fn from(error: serde_yaml::Error)->Self {
+    PreferencesError::DeserializeError(error)
+  }
+}
@@ -0,0 +1,39 @@
+use glium::glutin::window::{Window, Fullscreen};
+use crate::actions::Action;
+
+  // Shows the images to someone else, like the selects to a client on the spot. The window fills the screen without borders,
+  // the cursor and the overlay are hidden, and only navigation works, so nothing gets rated or changed by accident.
+  // A selection made before starting narrows the presentation down to the selected images, and presenting the selects shows them in their order
+pub struct Presentation {
+  previous_fullscreen: Option<Fullscreen>, // to go back to once the presentation ends
+  pub narrowed_to: Option<PresentedImages> // the images the presentation filtered the shown ones down to, which are shown again once it ends
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresentedImages {
+  Selection,
+  Selects
+}
+
+impl Presentation {
+  pub fn start(window: &Window, narrowed_to: Option<PresentedImages>)->Presentation {
+    let previous_fullscreen = window.fullscreen();
+    window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+    window.set_cursor_visible(false);
+
+    Presentation {
+      previous_fullscreen,
+      narrowed_to
+    }
+  }
+
+  pub fn end(self, window: &Window) {
+    window.set_fullscreen(self.previous_fullscreen);
+    window.set_cursor_visible(true);
+  }
+}
+
+  // what can be done while presenting: moving between the images and playing clips, and ending the presentation
+pub fn allows(action: Action)->bool {
+  matches!(action, Action::ShowPrevious(_) | Action::ShowNext(_) | Action::PlayPause | Action::TogglePresentation | Action::PresentSelects | Action::ClearSelectionOrFilters | Action::Quit)
+}
@@ -0,0 +1,172 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+use glium::glutin::event_loop::EventLoopProxy;
+use serde_json::{json, Value};
+use log::{info, warn};
+use crate::actions::Action;
+use crate::image_handling::Rating;
+use crate::image_handling::loader_pool::LoadNotification;
+
+  // how long a connection waits for the app to handle a request before giving up on it
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+  // json-rpc error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const APP_ERROR: i64 = -32000;
+const UNAUTHORIZED: i64 = -32001;
+
+#[derive(Debug)]
+pub enum RemoteCommand {
+  Perform(Action),
+  Status // what is shown
+}
+
+  // A command from a remote control connection, handed to the event loop as a user event.
+  // The connection waits for the reply, so scripts can chain commands and see their effect
+#[derive(Debug)]
+pub struct RemoteRequest {
+  pub command: RemoteCommand,
+  reply: Sender<Value>
+}
+
+impl RemoteRequest {
+  pub fn reply(&self, result: Value) {
+      // the connection may have timed out and closed already
+    let _ = self.reply.send(result);
+  }
+}
+
+  // Lets scripts and other programs, like a Stream Deck plugin, drive the viewer through json-rpc 2.0 on a local tcp port.
+  // Requests and responses are one json object per line, e.g.
+  //   {"jsonrpc": "2.0", "id": 1, "method": "rate", "params": {"rating": 3}, "token": "..."}
+  // Only connections from the same machine are accepted. Web pages can reach local ports too, through the browser,
+  // so every request has to carry the token from the preferences, and connections that speak http are closed
+pub fn start(port: u16, token: String, event_loop_proxy: EventLoopProxy<LoadNotification>)->io::Result<()> {
+  let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+  info!("Listening for remote control on port {}", port);
+
+  let token: Arc<str> = token.into();
+  thread::spawn(move || {
+    for stream in listener.incoming() {
+      match stream {
+        Ok(stream) => {
+          let event_loop_proxy = event_loop_proxy.clone();
+          let token = Arc::clone(&token);
+          thread::spawn(move || {
+            if let Err(error) = serve(stream, &token, event_loop_proxy) {
+              warn!("Remote control connection failed: {}", error);
+            }
+          });
+        },
+        Err(error) => warn!("Couldn't accept a remote control connection: {}", error)
+      }
+    }
+  });
+
+  Ok(())
+}
+
+fn serve(stream: TcpStream, token: &str, event_loop_proxy: EventLoopProxy<LoadNotification>)->io::Result<()> {
+  let mut writer = stream.try_clone()?;
+  let mut first_line = true;
+  for line in BufReader::new(stream).lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    if first_line && is_http_request_line(&line) {
+      warn!("Closed a remote control connection that sent an http request");
+      return Ok(());
+    }
+    first_line = false;
+
+    let response = match serde_json::from_str::<Value>(&line) {
+      Ok(request) => handle(&request, token, &event_loop_proxy),
+      Err(error) => Some(error_response(Value::Null, PARSE_ERROR, &error.to_string()))
+    };
+
+      // notifications, requests without an id, don't get a response
+    if let Some(response) = response {
+      writeln!(writer, "{}", response)?;
+    }
+  }
+  Ok(())
+}
+
+  // like `POST / HTTP/1.1`, which is how a browser's request starts
+fn is_http_request_line(line: &str)->bool {
+  let mut words = line.split_whitespace();
+  let is_method = words.next().is_some_and(|method| method.chars().all(|c| c.is_ascii_uppercase()));
+  let has_target = words.next().is_some();
+  is_method && has_target && words.next().is_some_and(|version| version.starts_with("HTTP/"))
+}
+
+fn handle(request: &Value, token: &str, event_loop_proxy: &EventLoopProxy<LoadNotification>)->Option<Value> {
+  let id = request.get("id").cloned();
+  let respond = |result: Result<Value, (i64, String)>| {
+    let id = id.clone()?;
+    Some(match result {
+      Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+      Err((code, message)) => error_response(id, code, &message)
+    })
+  };
+
+  if request.get("token").and_then(Value::as_str) != Some(token) {
+      // answered like invalid requests, even without an id
+    return Some(error_response(id.unwrap_or(Value::Null), UNAUTHORIZED, "The request's token is missing or wrong"));
+  }
+
+  let method = match request.get("method").and_then(Value::as_str) {
+    Some(method) => method,
+      // invalid requests are answered even without an id, since there is no telling whether they were meant as notifications
+    None => return Some(error_response(id.unwrap_or(Value::Null), INVALID_REQUEST, "The request has no method"))
+  };
+  let params = request.get("params").unwrap_or(&Value::Null);
+
+  let command = match parse_command(method, params) {
+    Ok(command) => command,
+    Err(error) => return respond(Err(error))
+  };
+
+  let (reply_tx, reply_rx) = mpsc::channel();
+  let remote_request = RemoteRequest {
+    command,
+    reply: reply_tx
+  };
+  if event_loop_proxy.send_event(LoadNotification::RemoteRequest(remote_request)).is_err() {
+    return respond(Err((APP_ERROR, "Fotoleine is closing".to_string())));
+  }
+    // notifications get no response, so there is nothing to wait for
+  id.as_ref()?;
+
+  let result = reply_rx.recv_timeout(REPLY_TIMEOUT)
+    .map_err(|_| (APP_ERROR, "Fotoleine didn't respond in time".to_string()));
+  respond(result)
+}
+
+  // methods are the action names from the preferences, `rate` with a rating from 1 to 3, and `status`
+fn parse_command(method: &str, params: &Value)->Result<RemoteCommand, (i64, String)> {
+  match method {
+    "status" => Ok(RemoteCommand::Status),
+    "rate" => {
+      let rating = params.get("rating").and_then(Value::as_u64)
+        .filter(|rating| (1..=3).contains(rating))
+        .ok_or_else(|| (INVALID_PARAMS, "rate needs a rating from 1 to 3".to_string()))?;
+      Ok(RemoteCommand::Perform(Action::Rate(Rating::from_u8(rating as u8 - 1))))
+    },
+    _ => Action::from_name(method)
+      .map(RemoteCommand::Perform)
+      .ok_or_else(|| (METHOD_NOT_FOUND, format!("There is no method called \"{}\"", method)))
+  }
+}
+
+fn error_response(id: Value, code: i64, message: &str)->Value {
+  json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
@@ -0,0 +1,175 @@
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+  // SHA-256 as specified in FIPS 180-4, for comparing file copies to checksum manifests written by sha256sum and card offload tools.
+  // Data is added in pieces with `update`, so large files don't need to be read into memory at once
+pub struct Sha256 {
+  state: [u32; 8],
+  block: [u8; 64], // data waiting for a full block
+  block_len: usize,
+  total_len: u64 // bytes added so far
+}
+
+const INITIAL_STATE: [u32; 8] = [
+  0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+];
+
+const ROUND_CONSTANTS: [u32; 64] = [
+  0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+  0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+  0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+  0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+  0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+  0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+  0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+  0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+  // the digest of a file's contents, read in pieces
+pub fn hash_file(path: &Path)->Result<String, io::Error> {
+  let mut file = File::open(path)?;
+  let mut hasher = Sha256::new();
+  let mut buffer = vec![0u8; 1024 * 1024];
+  loop {
+    match file.read(&mut buffer) {
+      Ok(0) => break,
+      Ok(read) => hasher.update(&buffer[..read]),
+      Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+      Err(error) => return Err(error)
+    }
+  }
+  Ok(hasher.finish_hex())
+}
+
+impl Default for Sha256 {
+  fn default()->Sha256 {
+    Sha256::new()
+  }
+}
+
+impl Sha256 {
+  pub fn new()->Sha256 {
+    Sha256 {
+      state: INITIAL_STATE,
+      block: [0; 64],
+      block_len: 0,
+      total_len: 0
+    }
+  }
+
+  pub fn update(&mut self, mut data: &[u8]) {
+    self.total_len += data.len() as u64;
+    if self.block_len > 0 {
+      let taken = data.len().min(64 - self.block_len);
+      self.block[self.block_len..self.block_len + taken].copy_from_slice(&data[..taken]);
+      self.block_len += taken;
+      data = &data[taken..];
+      if self.block_len < 64 {
+        return;
+      }
+      let block = self.block;
+      self.compress(&block);
+      self.block_len = 0;
+    }
+
+    let mut blocks = data.chunks_exact(64);
+    for block in &mut blocks {
+      self.compress(block.try_into().unwrap());
+    }
+    let rest = blocks.remainder();
+    self.block[..rest.len()].copy_from_slice(rest);
+    self.block_len = rest.len();
+  }
+
+    // the digest as lowercase hex, like checksum manifests write it
+  pub fn finish_hex(mut self)->String {
+    let bit_len = self.total_len.wrapping_mul(8);
+      // a 1 bit, zeroes up to 8 bytes short of a block, then the length in bits
+    let padding_len = if self.block_len < 56 { 56 - self.block_len } else { 120 - self.block_len };
+    let mut padding = vec![0u8; padding_len];
+    padding[0] = 0x80;
+    self.update(&padding);
+    self.update(&bit_len.to_be_bytes());
+
+    self.state.iter().fold(String::with_capacity(64), |mut hex, word| {
+      let _ = write!(hex, "{:08x}", word);
+      hex
+    })
+  }
+
+  fn compress(&mut self, block: &[u8; 64]) {
+    let mut schedule = [0u32; 64];
+    for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+      *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    for i in 16..64 {
+      let s0 = schedule[i - 15].rotate_right(7) ^ schedule[i - 15].rotate_right(18) ^ (schedule[i - 15] >> 3);
+      let s1 = schedule[i - 2].rotate_right(17) ^ schedule[i - 2].rotate_right(19) ^ (schedule[i - 2] >> 10);
+      schedule[i] = schedule[i - 16].wrapping_add(s0).wrapping_add(schedule[i - 7]).wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+    for (constant, word) in ROUND_CONSTANTS.iter().zip(schedule.iter()) {
+      let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+      let choice = (e & f) ^ (!e & g);
+      let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(*constant).wrapping_add(*word);
+      let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+      let majority = (a & b) ^ (a & c) ^ (b & c);
+      let temp2 = s0.wrapping_add(majority);
+
+      h = g;
+      g = f;
+      f = e;
+      e = d.wrapping_add(temp1);
+      d = c;
+      c = b;
+      b = a;
+      a = temp1.wrapping_add(temp2);
+    }
+
+    for (state, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+      *state = state.wrapping_add(value);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Sha256;
+
+  fn digest(data: &[u8])->String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finish_hex()
+  }
+
+    // known answers from the NIST examples and for the lengths around the padding boundaries
+  #[test]
+  fn known_answers() {
+    let vectors: [(&[u8], &str); 6] = [
+      (b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+      (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+      (&[b'a'; 55], "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318"),
+      (&[b'a'; 56], "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a"),
+      (&[b'a'; 64], "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"),
+      (b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq", "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+    ];
+    for (data, expected) in vectors {
+      assert_eq!(digest(data), expected, "digest of {} bytes", data.len());
+    }
+  }
+
+    // a million times 'a', added in pieces that don't line up with the blocks
+  #[test]
+  fn multi_block_in_pieces() {
+    let data = vec![b'a'; 1_000_000];
+    let mut hasher = Sha256::new();
+    for piece in data.chunks(993) {
+      hasher.update(piece);
+    }
+    assert_eq!(hasher.finish_hex(), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+    assert_eq!(digest(&data), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+  }
+}
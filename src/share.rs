@@ -0,0 +1,94 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use glium::glutin::window::Window;
+
+  // Offers the image file to the operating system's share menu, to send it off with AirDrop, Messages, Mail and the other services it lists.
+  // Only macOS has a share menu apps can open, through NSSharingServicePicker. It opens from the middle of `window`, and is closed by the platform
+pub fn share_file(window: &Window, path: &Path)->Result<(), ShareError> {
+    // the services receiving the file need its absolute path
+  let path = fs::canonicalize(path)?;
+  show_share_menu(window, &path)
+}
+
+#[cfg(target_os = "macos")]
+fn show_share_menu(window: &Window, path: &Path)->Result<(), ShareError> {
+  use std::cell::Cell;
+  use cocoa::base::{id, nil};
+  use cocoa::foundation::{NSArray, NSPoint, NSRect, NSSize, NSString};
+  use glium::glutin::platform::macos::WindowExtMacOS;
+  use objc::{class, msg_send, sel, sel_impl};
+
+  const NS_RECT_EDGE_MIN_Y: u64 = 1; // below the anchor, window views aren't flipped
+
+  thread_local! {
+      // the picker doesn't keep itself alive while its menu is open, so the last one is kept until the next replaces it
+    static PICKER: Cell<id> = const { Cell::new(nil) };
+  }
+
+  let path = path.to_str().ok_or(ShareError::NonUnicodePath)?;
+  unsafe {
+    let ns_path = NSString::alloc(nil).init_str(path);
+    let url: id = msg_send![class!(NSURL), fileURLWithPath: ns_path];
+    let _: () = msg_send![ns_path, release];
+    let items = NSArray::arrayWithObject(nil, url);
+
+    let picker: id = msg_send![class!(NSSharingServicePicker), alloc];
+    let picker: id = msg_send![picker, initWithItems: items];
+    let previous = PICKER.with(|last_picker| last_picker.replace(picker));
+    if previous != nil {
+      let _: () = msg_send![previous, release];
+    }
+
+    let view = window.ns_view() as id;
+    let bounds: NSRect = msg_send![view, bounds];
+    let anchor = NSRect::new(NSPoint::new(bounds.size.width / 2.0, bounds.size.height / 2.0), NSSize::new(1.0, 1.0));
+    let _: () = msg_send![picker, showRelativeToRect: anchor ofView: view preferredEdge: NS_RECT_EDGE_MIN_Y];
+  }
+  Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn show_share_menu(_window: &Window, _path: &Path)->Result<(), ShareError> {
+  Err(ShareError::Unsupported)
+}
+
+#[derive(Debug)]
+pub enum ShareError {
+  PathError(io::Error),
+  #[cfg(target_os = "macos")]
+  NonUnicodePath, // file urls are made from strings
+  #[cfg(not(target_os = "macos"))]
+  Unsupported
+}
+
+impl fmt::Display for ShareError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::ShareError::*;
+    match self {
+      PathError(error) => write!(f, "Couldn't find the file: {}", error),
+      #[cfg(target_os = "macos")]
+      NonUnicodePath => write!(f, "The file's path isn't valid unicode"),
+      #[cfg(not(target_os = "macos"))]
+      Unsupported => write!(f, "Sharing is only supported on macOS"),
+    }
+  }
+}
+
+impl Error for ShareError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::ShareError::*;
+    match self {
+      PathError(error) => Some(error),
+      _ => None
+    }
+  }
+}
+
+impl From<io::Error> for ShareError {
+  fn from(error: io::Error)->Self {
+    ShareError::PathError(error)
+  }
+}
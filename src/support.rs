@@ -1,26 +1,77 @@
 use glium::glutin::ContextBuilder;
 use glium::glutin::window::{WindowBuilder};
 use glium::glutin::event_loop::{EventLoop, EventLoopBuilder, ControlFlow};
-use glium::glutin::event::Event;
+use glium::glutin::event::{Event, WindowEvent};
 use glium::glutin::dpi::LogicalSize;
 use glium::Display;
-use imgui::{Context, FontConfig, FontSource};
+use imgui::{Context, FontConfig, FontSource, FontId, Style};
 use imgui_glium_renderer::Renderer;
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
-use std::time::Instant;
+use arboard::Clipboard;
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+use log::warn;
+use crate::window_geometry::WindowGeometry;
 
 pub struct Framework {
   pub display: Display,
   pub platform: WinitPlatform,
   pub renderer: Renderer,
+  pub clipboard: Option<Clipboard>, // None if the platform's clipboard couldn't be accessed
+  base_style: Style // before scaling the ui
 }
 
-pub fn init<T>(title: &str, window_size: &LogicalSize<f64>) -> (EventLoop<T>, Context, Framework) {
+  // in logical pixels
+const DEFAULT_FONT_SIZE: f64 = 13.0;
+
+impl Framework {
+    // replaces the fonts with imgui's default font and `ui_font`, ttf data shown at `ui_font_size` logical pixels,
+    // both rendered at `ui_scale` times their size on top of the hidpi factor. the style's sizes are scaled to match.
+    // the font atlas is in use while a frame is built, so this has to be called before starting one
+  pub fn set_fonts(&mut self, imgui: &mut Context, ui_font: &'static [u8], ui_font_size: f64, ui_scale: f64)->FontId {
+    let font_scale = self.platform.hidpi_factor() * ui_scale;
+    imgui.fonts().clear();
+    add_default_font(imgui, font_scale);
+    let font = imgui.fonts().add_font(&[
+      FontSource::TtfData {
+        data: ui_font,
+        size_pixels: (ui_font_size * font_scale) as f32,
+        config: None,
+      }
+    ]);
+
+    self.renderer.reload_font_texture(imgui)
+      .expect("Couldn't reload font");
+
+    let mut style = self.base_style;
+    style.scale_all_sizes(ui_scale as f32);
+    *imgui.style_mut() = style;
+
+    font
+  }
+}
+
+fn add_default_font(imgui: &mut Context, font_scale: f64) {
+  imgui.fonts().add_font(&[
+    FontSource::DefaultFontData {
+      config: Some(FontConfig {
+        size_pixels: (DEFAULT_FONT_SIZE * font_scale) as f32,
+        ..FontConfig::default()
+      }),
+    }
+  ]);
+}
+
+  // the window opens at `geometry` if it is given, and otherwise at `window_size` wherever the platform places it
+pub fn init<T>(title: &str, window_size: &LogicalSize<f64>, geometry: Option<&WindowGeometry>) -> (EventLoop<T>, Context, Framework) {
   let event_loop = EventLoopBuilder::with_user_event().build();
   let context = ContextBuilder::new().with_vsync(true);
-  let builder = WindowBuilder::new()
+  let mut builder = WindowBuilder::new()
     .with_title(title.to_owned())
     .with_inner_size(window_size.clone());
+  if let Some(geometry) = geometry {
+    builder = geometry.apply(builder, event_loop.available_monitors());
+  }
   let display =
     Display::new(builder, context, &event_loop).expect("Failed to initialize display");
 
@@ -35,25 +86,23 @@ pub fn init<T>(title: &str, window_size: &LogicalSize<f64>) -> (EventLoop<T>, Co
   }
 
   let hidpi_factor = platform.hidpi_factor();
-  let font_size = (13.0 * hidpi_factor) as f32;
-  imgui.fonts().add_font(&[
-    FontSource::DefaultFontData {
-      config: Some(FontConfig {
-        size_pixels: font_size,
-        ..FontConfig::default()
-      }),
-    }
-  ]);
+  add_default_font(&mut imgui, hidpi_factor);
 
   imgui.io_mut().font_global_scale = (1.0 / hidpi_factor) as f32;
 
   let renderer =
     Renderer::init(&mut imgui, &display).expect("Failed to initialize renderer");
 
+  let clipboard = Clipboard::new()
+    .map_err(|error| warn!("Couldn't access the clipboard: {}", error))
+    .ok();
+
   let framework = Framework {
     display,
     platform,
-    renderer
+    renderer,
+    clipboard,
+    base_style: *imgui.style()
   };
 
   (event_loop, imgui, framework)
@@ -64,83 +113,205 @@ pub trait Program {
 
   fn framework(&self)->&Framework;
   fn framework_mut(&mut self)->&mut Framework;
-  fn on_event(&mut self, event: &Event<Self::UserEvent>)->LoopSignal;
+  fn on_event(&mut self, event: &Event<Self::UserEvent>)->LoopSignal; // every event except user events, see `on_user_event`
+  fn on_user_event(&mut self, event: Self::UserEvent)->LoopSignal; // handed over by value, so the program can take the results they carry
   fn on_frame(&mut self, imgui: &mut Context)->LoopSignal;
   fn on_shutdown(&mut self);
+  fn frame_pacing(&self)->FramePacing; // asked for on every event, so changes apply right away
 }
 
-  // The ordering determines "strength", lower signals are stronger and override weaker (higher up) signals
-  // I.e. Exit > Redraw > Wait
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
+  // Limits on how often the loop draws frames, to save power while nothing needs smooth motion
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FramePacing {
+  pub max_fps: Option<u32>, // frames are drawn at most this often, the rest wait for their turn. only vsync limits them if None
+  pub power_saver: bool // scrolling doesn't force frames early, see `forces_frame`
+}
+
+impl FramePacing {
+  fn min_frame_interval(&self)->Option<Duration> {
+    self.max_fps.filter(|&max_fps| max_fps > 0).map(|max_fps| Duration::from_secs(1) / max_fps)
+  }
+}
+
+  // What the program needs from the loop after an event or frame.
+  // The ordering determines "strength", stronger signals override weaker ones: Exit > RequestRedraw > WaitUntil > Wait
+  // Between two WaitUntil signals, the earlier one is stronger, so that no wakeup gets missed
+#[derive(PartialEq, Eq)]
 pub enum LoopSignal {
   Wait,
-  RequestRedraw,
-  ImmediateRedraw,
+  WaitUntil(Instant), // wait for events, but redraw at the given time at the latest
+  RequestRedraw, // draw a frame once the events that are waiting have been handled
   Exit
 }
 
+impl LoopSignal {
+  fn strength(&self)->u8 {
+    match self {
+      LoopSignal::Wait => 0,
+      LoopSignal::WaitUntil(_) => 1,
+      LoopSignal::RequestRedraw => 2,
+      LoopSignal::Exit => 3
+    }
+  }
+}
+
+impl Ord for LoopSignal {
+  fn cmp(&self, other: &Self)->Ordering {
+    match (self, other) {
+      (LoopSignal::WaitUntil(a), LoopSignal::WaitUntil(b)) => b.cmp(a),
+      _ => self.strength().cmp(&other.strength())
+    }
+  }
+}
+
+impl PartialOrd for LoopSignal {
+  fn partial_cmp(&self, other: &Self)->Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+  // Decides when frames are drawn. Events only mark the window as dirty, and all events that arrive together are handled
+  // before a single frame draws their effects. Only input imgui hasn't seen a frame for yet forces a frame early, see `is_input`
+struct FrameScheduler {
+  dirty: bool, // a frame should be drawn once the waiting events are handled
+  wake_at: Option<Instant>, // pending timed redraw, e.g. for the next frame of an animation
+  unseen_input: bool, // input arrived since the last frame
+  exit: bool
+}
+
+impl FrameScheduler {
+  fn new()->FrameScheduler {
+    FrameScheduler {
+      dirty: true, // so the first frame gets drawn
+      wake_at: None,
+      unseen_input: false,
+      exit: false
+    }
+  }
+
+  fn apply(&mut self, signal: LoopSignal) {
+    match signal {
+      LoopSignal::Wait => {},
+      LoopSignal::WaitUntil(instant) => self.wake_at = Some(self.wake_at.map_or(instant, |wake_at| wake_at.min(instant))),
+      LoopSignal::RequestRedraw => self.dirty = true,
+      LoopSignal::Exit => self.exit = true
+    }
+  }
+
+  fn control_flow(&self)->ControlFlow {
+    match (self.exit, self.wake_at) {
+      (true, _) => ControlFlow::Exit,
+      (false, Some(instant)) => ControlFlow::WaitUntil(instant),
+      (false, None) => ControlFlow::Wait
+    }
+  }
+}
+
+  // imgui only sees the state of keys and buttons at the start of each frame. if a key goes down and up again without a frame
+  // in between, the press is lost. so once an input event arrived, the next one is only handled after a frame was drawn for it.
+  // cursor movement isn't counted, since only its latest position matters
+fn is_input<T>(event: &Event<T>)->bool {
+  matches!(event, Event::WindowEvent { event: WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } | WindowEvent::ReceivedCharacter(_), .. })
+}
+
+  // whether input arriving before the last input had a frame draws that frame first. trackpads send scroll events
+  // at a high rate, which would draw a frame for nearly every one. scrolling changes no key or button state imgui could miss,
+  // so in power saver mode it waits for the frame of its batch instead
+fn forces_frame<T>(event: &Event<T>, pacing: &FramePacing)->bool {
+  is_input(event) && !(pacing.power_saver && matches!(event, Event::WindowEvent { event: WindowEvent::MouseWheel { .. }, .. }))
+}
+
 pub fn run<P:'static + Program>(event_loop: EventLoop<P::UserEvent>, mut imgui: Context, mut program: P)->! {
   let mut last_frame = Instant::now();
-  let mut first_redraw = false;
+  let mut scheduler = FrameScheduler::new();
 
   event_loop.run(move |event, _, control_flow| {
+      // user events go straight to the program, they are neither input nor of any use to imgui
+    let event = match event {
+      Event::UserEvent(user_event) => {
+        scheduler.apply(program.on_user_event(user_event));
+        *control_flow = scheduler.control_flow();
+        return;
+      },
+      event => event
+    };
+
+    let pacing = program.frame_pacing();
+    let input = is_input(&event);
+    if forces_frame(&event, &pacing) && scheduler.unseen_input {
+      draw_frame(&mut program, &mut imgui, &mut scheduler, &mut last_frame);
+    }
+
     {
       let framework = program.framework_mut();
       internal_handle_event(&mut imgui, &mut framework.platform, &framework.display, &event);
     }
 
-    let mut loop_signal = program.on_event(&event);
-    
-    let mut redraw_event = false;
+    let loop_signal = program.on_event(&event);
+    scheduler.apply(loop_signal);
+    if input {
+      scheduler.unseen_input = true;
+      scheduler.dirty = true;
+    }
+
     match event {
+      Event::NewEvents(_) if scheduler.wake_at.is_some_and(|wake_at| wake_at <= Instant::now()) => {
+        scheduler.wake_at = None;
+        scheduler.dirty = true;
+      },
+        // while the window is being resized, redraw requests only arrive once the resizing is done, so frames are drawn right away
+      Event::WindowEvent { event: WindowEvent::Resized(_), .. } => {
+        draw_frame(&mut program, &mut imgui, &mut scheduler, &mut last_frame);
+      },
+        // every event of this batch has been handled, one frame draws all their effects
+      Event::MainEventsCleared if scheduler.dirty => {
+        program.framework().display.gl_window().window().request_redraw();
+      },
       Event::RedrawRequested(_) => {
-        redraw_event = true;
+        match pacing.min_frame_interval().map(|interval| last_frame + interval) {
+            // too soon after the last frame for the frame cap, the scheduler wakes up for it once it is time
+          Some(frame_due_at) if Instant::now() < frame_due_at => {
+            scheduler.dirty = false;
+            scheduler.apply(LoopSignal::WaitUntil(frame_due_at));
+          },
+          _ => draw_frame(&mut program, &mut imgui, &mut scheduler, &mut last_frame)
+        }
       },
       Event::LoopDestroyed => {
         program.on_shutdown();
-      }
+      },
       _ => {}
     };
 
-    if redraw_event || loop_signal == LoopSignal::ImmediateRedraw {
-      {
-        let now = Instant::now();
-        imgui.io_mut().update_delta_time(now - last_frame);
-        last_frame = now;
-      }
-      
-      let frame_loop_signal = program.on_frame(&mut imgui);
-      loop_signal = loop_signal.max(frame_loop_signal);
-
-        // imgui doesn't react to some events on the same frame they arrive at, but rather one frame late
-        // E.g. if a mouse release arrives, the first frame rendered after that won't see its effects, only the second
-        // So for every event that arrives, we actually do two redraws, to be sure those events take effect
-        // Doing this through two requests is crucial for framerate, if we just did draw_ui twice here every frame would effectively be twice as long
-      if redraw_event && first_redraw {
-        let framework = program.framework();
-        first_redraw = false;
-        let gl_window = framework.display.gl_window();
-        let window = gl_window.window();
-        window.request_redraw();
-      }
-    }
-
-    *control_flow = match loop_signal {
-      LoopSignal::Wait => ControlFlow::Wait,
-      LoopSignal::RequestRedraw => {
-        let framework = program.framework();
-        let gl_window = framework.display.gl_window();
-        let window = gl_window.window();
-        window.request_redraw();
-        first_redraw = true;
-        ControlFlow::Wait
-      },
-      LoopSignal::ImmediateRedraw => ControlFlow::Wait,
-      LoopSignal::Exit => ControlFlow::Exit
-    };
+    *control_flow = scheduler.control_flow();
   });
 }
 
+fn draw_frame<P: Program>(program: &mut P, imgui: &mut Context, scheduler: &mut FrameScheduler, last_frame: &mut Instant) {
+  let now = Instant::now();
+  imgui.io_mut().update_delta_time(now - *last_frame);
+  *last_frame = now;
+
+    // imgui reacts to some input one frame late, e.g. a click on a button only registers in the frame after the release.
+    // so frames that handle input get one more after them
+  let follow_up = scheduler.unseen_input;
+  scheduler.dirty = false;
+  scheduler.unseen_input = false;
+    // every frame decides anew whether it needs a timed redraw
+  scheduler.wake_at = None;
+
+  scheduler.apply(program.on_frame(imgui));
+  if follow_up {
+    scheduler.dirty = true;
+  }
+
+    // requested here, since this frame may be drawn after the events of its batch were handled
+  if scheduler.dirty {
+    scheduler.dirty = false;
+    program.framework().display.gl_window().window().request_redraw();
+  }
+}
+
 fn internal_handle_event<T>(imgui:&mut Context, platform:&mut WinitPlatform, display:&Display, event:&Event<T>) {
   let gl_window = display.gl_window();
   let window = gl_window.window();
@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+use glium::glutin::dpi::LogicalPosition;
+use crate::actions::Action;
+
+  // how far two fingers have to move sideways to swipe to the next or previous image
+const SWIPE_DISTANCE: f64 = 120.0;
+  // scrolls further apart than this belong to separate swipes.
+  // the momentum scrolling after lifting the fingers follows right away, so it is part of the same swipe and doesn't move on again
+const SWIPE_GAP: Duration = Duration::from_millis(200);
+
+  // Tracks two-finger scrolling on a trackpad that isn't used to pan, to swipe between images.
+  // A swipe moves at most one image, however far it goes
+pub struct TrackpadSwipe {
+  distance: f64, // sideways, since the swipe started
+  swiped: bool, // whether this swipe moved to another image already
+  last_scroll: Option<Instant>
+}
+
+impl TrackpadSwipe {
+  pub fn new()->TrackpadSwipe {
+    TrackpadSwipe {
+      distance: 0.0,
+      swiped: false,
+      last_scroll: None
+    }
+  }
+
+    // returns the action a scroll by `delta` completes a swipe with, if any.
+    // content follows the fingers, so swiping left moves on to the next image like turning a page
+  pub fn scroll(&mut self, delta: LogicalPosition<f64>, now: Instant)->Option<Action> {
+    if self.last_scroll.is_none_or(|last_scroll| now - last_scroll > SWIPE_GAP) {
+      self.distance = 0.0;
+      self.swiped = false;
+    }
+    self.last_scroll = Some(now);
+
+      // mostly vertical scrolls aren't swipes
+    if self.swiped || delta.x.abs() < delta.y.abs() {
+      return None;
+    }
+
+    self.distance += delta.x;
+    if self.distance.abs() < SWIPE_DISTANCE {
+      return None;
+    }
+
+    self.swiped = true;
+    if self.distance < 0.0 {
+      Some(Action::ShowNext(1))
+    } else {
+      Some(Action::ShowPrevious(1))
+    }
+  }
+}
@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::image_display::ImageBlend;
+
+const TRANSITION_DURATION: Duration = Duration::from_millis(180);
+
+  // How the view changes from one image to the next
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionStyle {
+  Cut, // the next image replaces the previous one right away
+  Crossfade,
+  Slide // the images move sideways, like turning a page
+}
+
+impl TransitionStyle {
+  pub fn next(self)->TransitionStyle {
+    match self {
+      TransitionStyle::Cut => TransitionStyle::Crossfade,
+      TransitionStyle::Crossfade => TransitionStyle::Slide,
+      TransitionStyle::Slide => TransitionStyle::Cut
+    }
+  }
+
+  pub fn label(self)->&'static str {
+    match self {
+      TransitionStyle::Cut => "Cut",
+      TransitionStyle::Crossfade => "Crossfade",
+      TransitionStyle::Slide => "Slide"
+    }
+  }
+}
+
+  // The change from the image shown before to the one shown now, over a few frames.
+  // The outgoing image is drawn first and the incoming one blended over it
+pub struct Transition {
+  pub from_idx: usize, // collection index of the outgoing image
+  start: Instant,
+  style: TransitionStyle,
+  forward: bool // whether the incoming image is after the outgoing one, slides come in from the right then
+}
+
+impl Transition {
+    // None for cuts, which have nothing to draw
+  pub fn start(style: TransitionStyle, from_idx: usize, forward: bool, now: Instant)->Option<Transition> {
+    if style == TransitionStyle::Cut {
+      return None;
+    }
+    Some(Transition {
+      from_idx,
+      start: now,
+      style,
+      forward
+    })
+  }
+
+  pub fn is_done(&self, now: Instant)->bool {
+    now - self.start >= TRANSITION_DURATION
+  }
+
+    // how the outgoing and the incoming image are drawn at `now`, for a view `view_width` logical pixels wide
+  pub fn blends(&self, now: Instant, view_width: f64)->(ImageBlend, ImageBlend) {
+    let t = ((now - self.start).as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.0);
+    let eased = t * t * (3.0 - 2.0 * t); // smoothstep, so the change starts and ends gently
+
+    match self.style {
+      TransitionStyle::Cut => (ImageBlend { opacity: 0.0, ..ImageBlend::OPAQUE }, ImageBlend::OPAQUE),
+        // the outgoing image fades out as well, so where it is larger than the incoming one it doesn't linger until the end
+      TransitionStyle::Crossfade => (ImageBlend { opacity: 1.0 - eased, ..ImageBlend::OPAQUE }, ImageBlend { opacity: eased, ..ImageBlend::OPAQUE }),
+      TransitionStyle::Slide => {
+        let width = if self.forward { view_width as f32 } else { -view_width as f32 };
+        (ImageBlend { offset: [-width * eased, 0.0], ..ImageBlend::OPAQUE },
+         ImageBlend { offset: [width * (1.0 - eased), 0.0], ..ImageBlend::OPAQUE })
+      }
+    }
+  }
+}
@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+  // time for an eased value to cover about two thirds of the way to its target
+const EASE_TIME_CONSTANT: Duration = Duration::from_millis(60);
+  // time for a fling to lose about two thirds of its speed
+const FLING_TIME_CONSTANT: Duration = Duration::from_millis(325);
+  // flings slower than this, in logical pixels per second, stop
+const MIN_FLING_SPEED: f64 = 20.0;
+  // how far back the movements a fling starts with are measured over
+const VELOCITY_WINDOW: Duration = Duration::from_millis(100);
+  // a pan that got no movement for this long was let go of
+const RELEASE_GAP: Duration = Duration::from_millis(40);
+  // released pans slower than this, in logical pixels per second, stay where they were let go
+const MIN_RELEASE_SPEED: f64 = 200.0;
+
+  // Follows a target value smoothly, instead of jumping to it. Moves a fixed share of the remaining way per unit of time,
+  // so two tweens towards targets set at the same time stay in proportion along the way
+#[derive(Debug, Clone, Copy)]
+pub struct Tween {
+  value: f64,
+  target: f64
+}
+
+impl Tween {
+  pub fn new(value: f64)->Tween {
+    Tween { value, target: value }
+  }
+
+  pub fn value(&self)->f64 {
+    self.value
+  }
+
+  pub fn target(&self)->f64 {
+    self.target
+  }
+
+  pub fn set_target(&mut self, target: f64) {
+    self.target = target;
+  }
+
+    // moves the value and the target by the same amount, e.g. for a pan that should follow the fingers directly
+  pub fn shift(&mut self, delta: f64) {
+    self.value += delta;
+    self.target += delta;
+  }
+
+  pub fn jump(&mut self, value: f64) {
+    self.value = value;
+    self.target = value;
+  }
+
+    // the value and the target can have different limits, e.g. for a pan while the zoom changes
+  pub fn clamp(&mut self, min: f64, max: f64, target_min: f64, target_max: f64) {
+    self.value = self.value.clamp(min, max);
+    self.target = self.target.clamp(target_min, target_max);
+  }
+
+    // returns whether the value is still moving. values within `tolerance` of their target arrive at it
+  pub fn tick(&mut self, dt: Duration, tolerance: f64)->bool {
+    if (self.target - self.value).abs() <= tolerance {
+      self.value = self.target;
+      return false;
+    }
+    let share = 1.0 - (-dt.as_secs_f64() / EASE_TIME_CONSTANT.as_secs_f64()).exp();
+    self.value += (self.target - self.value) * share;
+    true
+  }
+}
+
+  // Keeps a pan going after the fingers left the trackpad, slowing it down until it stops.
+  // Platforms that keep sending scroll events after the fingers left, like MacOS, make their own momentum;
+  // their pans are only let go of once those events slowed down, so they aren't flung twice
+#[derive(Debug, Default)]
+pub struct Fling {
+  movements: VecDeque<(Instant, [f64; 2])>, // of the pan while it is held, in logical pixels
+  velocity: Option<[f64; 2]> // in logical pixels per second, while flinging
+}
+
+impl Fling {
+    // a movement of the held pan, which also stops a fling
+  pub fn push(&mut self, delta: [f64; 2], now: Instant) {
+    self.velocity = None;
+    while self.movements.front().is_some_and(|&(time, _)| now - time > VELOCITY_WINDOW) {
+      self.movements.pop_front();
+    }
+    self.movements.push_back((now, delta));
+  }
+
+  pub fn stop(&mut self) {
+    self.movements.clear();
+    self.velocity = None;
+  }
+
+  pub fn is_active(&self)->bool {
+    self.velocity.is_some() || !self.movements.is_empty()
+  }
+
+    // how far the pan moves over `dt` up to `now`. starts the fling once the pan was let go, if it moved fast enough
+  pub fn tick(&mut self, dt: Duration, now: Instant)->Option<[f64; 2]> {
+    if let Some(&(last_time, _)) = self.movements.back() {
+      if now - last_time < RELEASE_GAP {
+        return None;
+      }
+      self.velocity = self.release_velocity().filter(|velocity| velocity[0].hypot(velocity[1]) >= MIN_RELEASE_SPEED);
+      self.movements.clear();
+    }
+
+    let velocity = self.velocity?;
+      // the distance covered while the speed decays over `dt`
+    let decay = (-dt.as_secs_f64() / FLING_TIME_CONSTANT.as_secs_f64()).exp();
+    let distance_factor = FLING_TIME_CONSTANT.as_secs_f64() * (1.0 - decay);
+    let next_velocity = [velocity[0] * decay, velocity[1] * decay];
+    self.velocity = Some(next_velocity).filter(|velocity| velocity[0].hypot(velocity[1]) >= MIN_FLING_SPEED);
+    Some([velocity[0] * distance_factor, velocity[1] * distance_factor])
+  }
+
+    // the average velocity over the movements kept, the first one only marks when they started
+  fn release_velocity(&self)->Option<[f64; 2]> {
+    let (first_time, _) = *self.movements.front()?;
+    let (last_time, _) = *self.movements.back()?;
+    let span = (last_time - first_time).as_secs_f64();
+    if span <= 0.0 {
+      return None;
+    }
+    let distance = self.movements.iter().skip(1).fold([0.0, 0.0], |sum, &(_, delta)| [sum[0] + delta[0], sum[1] + delta[1]]);
+    Some([distance[0] / span, distance[1] / span])
+  }
+}
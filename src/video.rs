@@ -0,0 +1,374 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufReader};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use glium::{
+  Rect,
+  backend::Facade,
+  texture::{RawImage2d, SrgbTexture2d, TextureCreationError},
+};
+use openh264::decoder::{Decoder, DecodedYUV};
+use openh264::formats::YUVSource;
+use crate::worker_pool::CancelToken;
+
+  // how many decoded frames the decode thread may get ahead of playback
+const FRAME_BUFFER_COUNT: usize = 4;
+  // how often to check for a frame again when the decode thread falls behind playback
+const DECODE_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub fn is_video(path: &Path)->bool {
+  path.extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| {
+      let ext_lowercase = ext.to_lowercase();
+      ext_lowercase == "mp4" || ext_lowercase == "mov"
+    })
+}
+
+pub struct VideoFrame {
+  pub data: Vec<u8>, // rgb, 3 bytes per pixel
+  pub size: [usize; 2],
+  pub time: Duration // presentation time, from the start of the clip
+}
+
+  // Decodes the frames of the first h.264 track in an mp4 or mov file, in presentation order.
+  // Other codecs (hevc, prores) aren't supported
+pub struct VideoDecoder {
+  reader: mp4::Mp4Reader<BufReader<File>>,
+  track_id: u32,
+  next_sample: u32, // mp4 sample ids start at 1
+  sample_count: u32,
+  timescale: u32,
+  length_size: usize, // byte count of the nal unit length prefixes in samples
+  parameter_sets: Option<Vec<u8>>, // sps and pps in annex b format, fed to the decoder with the first sample
+  decoder: Decoder,
+    // frames come out of the decoder reordered into presentation order, which is assumed to have a constant frame rate.
+    // so decoded frames are timed by the durations of the samples fed in, in order
+  sample_durations: VecDeque<u32>,
+  frame_time: u64, // in timescale units
+  flushed_frames: Option<VecDeque<VideoFrame>> // frames still buffered in the decoder after the last sample, once flushed
+}
+
+impl VideoDecoder {
+  pub fn open(path: &Path)->Result<VideoDecoder, VideoError> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)?;
+
+    let track = reader.tracks().values()
+      .find(|track| track.media_type().is_ok_and(|media_type| media_type == mp4::MediaType::H264))
+      .ok_or(VideoError::NoH264Track)?;
+
+    let track_id = track.track_id();
+    let timescale = track.timescale();
+    let length_size = track.trak.mdia.minf.stbl.stsd.avc1.as_ref().map_or(4, |avc1| avc1.avcc.length_size_minus_one as usize + 1);
+
+    let mut parameter_sets = Vec::new();
+    for nal_unit in [track.sequence_parameter_set()?, track.picture_parameter_set()?] {
+      parameter_sets.extend_from_slice(&ANNEX_B_START_CODE);
+      parameter_sets.extend_from_slice(nal_unit);
+    }
+
+    let sample_count = reader.sample_count(track_id)?;
+
+    Ok(VideoDecoder {
+      reader,
+      track_id,
+      next_sample: 1,
+      sample_count,
+      timescale,
+      length_size,
+      parameter_sets: Some(parameter_sets),
+      decoder: Decoder::new()?,
+      sample_durations: VecDeque::new(),
+      frame_time: 0,
+      flushed_frames: None
+    })
+  }
+
+    // the next frame in presentation order, or None after the last one
+  pub fn next_frame(&mut self)->Result<Option<VideoFrame>, VideoError> {
+    while self.next_sample <= self.sample_count {
+      let sample = self.reader.read_sample(self.track_id, self.next_sample)?;
+      self.next_sample += 1;
+
+      let sample = match sample {
+        Some(sample) => sample,
+        None => continue
+      };
+      self.sample_durations.push_back(sample.duration);
+
+      let mut packet = self.parameter_sets.take().unwrap_or_default();
+      append_annex_b(&mut packet, &sample.bytes, self.length_size);
+
+      if let Some(yuv) = self.decoder.decode(&packet)? {
+        let (data, size) = to_rgb(&yuv);
+        let time = self.next_frame_time();
+        return Ok(Some(VideoFrame { data, size, time }));
+      }
+    }
+
+    if self.flushed_frames.is_none() {
+      let remaining: Vec<_> = self.decoder.flush_remaining()?.iter().map(to_rgb).collect();
+      let frames = remaining.into_iter()
+        .map(|(data, size)| VideoFrame { data, size, time: self.next_frame_time() })
+        .collect();
+      self.flushed_frames = Some(frames);
+    }
+
+    Ok(self.flushed_frames.as_mut().and_then(|frames| frames.pop_front()))
+  }
+
+  fn next_frame_time(&mut self)->Duration {
+    let time = Duration::from_secs_f64(self.frame_time as f64 / self.timescale.max(1) as f64);
+    self.frame_time += self.sample_durations.pop_front().unwrap_or(0) as u64;
+    time
+  }
+}
+
+const ANNEX_B_START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+  // mp4 samples store nal units with length prefixes, the decoder expects them separated by start codes
+fn append_annex_b(packet: &mut Vec<u8>, sample: &[u8], length_size: usize) {
+  let mut rest = sample;
+  while rest.len() > length_size {
+    let (length_bytes, after) = rest.split_at(length_size);
+    let length = length_bytes.iter().fold(0usize, |length, &byte| (length << 8) | byte as usize).min(after.len());
+    let (nal_unit, after) = after.split_at(length);
+
+    packet.extend_from_slice(&ANNEX_B_START_CODE);
+    packet.extend_from_slice(nal_unit);
+    rest = after;
+  }
+}
+
+fn to_rgb(yuv: &DecodedYUV)->(Vec<u8>, [usize; 2]) {
+  let (width, height) = yuv.dimensions();
+  let mut data = vec![0; width * height * 3];
+  yuv.write_rgb8(&mut data);
+  (data, [width, height])
+}
+
+  // Plays a clip by decoding it on a separate thread, which stays a few frames ahead of playback.
+  // Frames are uploaded into a single texture as they become due
+pub struct VideoPlayer {
+  frames: Receiver<Result<VideoFrame, VideoError>>,
+  token: CancelToken, // stops the decode thread
+  start: Instant, // when playback would have started, if it had never been paused
+  paused_at: Option<Instant>,
+  next_frame: Option<VideoFrame>, // decoded, but not due yet
+  texture: Option<SrgbTexture2d>,
+  finished: bool
+}
+
+impl VideoPlayer {
+  pub fn play(path: PathBuf)->VideoPlayer {
+    let (sender, receiver) = mpsc::sync_channel(FRAME_BUFFER_COUNT);
+    let token = CancelToken::new();
+
+    let thread_token = token.clone();
+    thread::spawn(move || decode_frames(&path, &sender, &thread_token));
+
+    VideoPlayer {
+      frames: receiver,
+      token,
+      start: Instant::now(),
+      paused_at: None,
+      next_frame: None,
+      texture: None,
+      finished: false
+    }
+  }
+
+  pub fn is_finished(&self)->bool {
+    self.finished
+  }
+
+  pub fn toggle_pause(&mut self, now: Instant) {
+    match self.paused_at.take() {
+      Some(paused_at) => self.start += now - paused_at,
+      None => self.paused_at = Some(now)
+    }
+  }
+
+    // the most recently shown frame, None until the first frame was decoded
+  pub fn texture(&self)->Option<&SrgbTexture2d> {
+    self.texture.as_ref()
+  }
+
+    // uploads the latest frame that is due, and returns when the next one is.
+    // returns None while paused, and once the clip has finished
+  pub fn update<F: Facade>(&mut self, gl_ctx: &F, now: Instant)->Result<Option<Instant>, VideoPlaybackError> {
+    if self.paused_at.is_some() || self.finished {
+      return Ok(None);
+    }
+
+    let mut due_frame = None;
+    loop {
+      let frame = match self.next_frame.take() {
+        Some(frame) => frame,
+        None => match self.frames.try_recv() {
+          Ok(frame) => frame?,
+          Err(TryRecvError::Empty) => break,
+          Err(TryRecvError::Disconnected) => {
+            self.finished = true;
+            break;
+          }
+        }
+      };
+
+        // skips frames that are overdue, if decoding or drawing can't keep up
+      if self.start + frame.time <= now {
+        due_frame = Some(frame);
+      } else {
+        self.next_frame = Some(frame);
+        break;
+      }
+    }
+
+    if let Some(frame) = due_frame {
+      self.show_frame(gl_ctx, frame)?;
+    }
+
+    Ok(match self.next_frame {
+      Some(ref frame) => Some(self.start + frame.time),
+      None if self.finished => None,
+      None => Some(now + DECODE_POLL_INTERVAL)
+    })
+  }
+
+  fn show_frame<F: Facade>(&mut self, gl_ctx: &F, frame: VideoFrame)->Result<(), TextureCreationError> {
+    let [width, height] = frame.size;
+    let raw_image = RawImage2d::from_raw_rgb(frame.data, (width as u32, height as u32));
+
+    match self.texture {
+      Some(ref texture) if texture.dimensions() == (width as u32, height as u32) => {
+        texture.write(Rect { left: 0, bottom: 0, width: width as u32, height: height as u32 }, raw_image);
+      },
+      _ => self.texture = Some(SrgbTexture2d::new(gl_ctx, raw_image)?)
+    }
+
+    Ok(())
+  }
+}
+
+impl Drop for VideoPlayer {
+  fn drop(&mut self) {
+    self.token.cancel();
+  }
+}
+
+fn decode_frames(path: &Path, sender: &SyncSender<Result<VideoFrame, VideoError>>, token: &CancelToken) {
+  let mut decoder = match VideoDecoder::open(path) {
+    Ok(decoder) => decoder,
+    Err(error) => {
+      let _ = sender.send(Err(error));
+      return;
+    }
+  };
+
+    // sending fails once the player was dropped
+  while !token.is_cancelled() {
+    match decoder.next_frame() {
+      Ok(Some(frame)) => if sender.send(Ok(frame)).is_err() { return },
+      Ok(None) => return,
+      Err(error) => {
+        let _ = sender.send(Err(error));
+        return;
+      }
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum VideoError {
+  IoError(io::Error),
+  ContainerError(mp4::Error),
+  DecodeError(openh264::Error),
+  NoH264Track
+}
+
+impl fmt::Display for VideoError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::VideoError::*;
+    match self {
+      IoError(error) => write!(f, "File read error: {}", error),
+      ContainerError(error) => write!(f, "Could not read the video container: {}", error),
+      DecodeError(error) => write!(f, "Could not decode the video: {}", error),
+      NoH264Track => write!(f, "The file has no H.264 video track"),
+    }
+  }
+}
+
+impl Error for VideoError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::VideoError::*;
+    match self {
+      IoError(error) => Some(error),
+      ContainerError(error) => Some(error),
+      DecodeError(error) => Some(error),
+      NoH264Track => None
+    }
+  }
+}
+
+impl From<io::Error> for VideoError {
+  fn from(error: io::Error)->Self {
+    VideoError::IoError(error)
+  }
+}
+
+impl From<mp4::Error> for VideoError {
+  fn from(error: mp4::Error)->Self {
+    VideoError::ContainerError(error)
+  }
+}
+
+impl From<openh264::Error> for VideoError {
+  fn from(error: openh264::Error)->Self {
+    VideoError::DecodeError(error)
+  }
+}
+
+#[derive(Debug)]
+pub enum VideoPlaybackError {
+  Decode(VideoError),
+  Upload(TextureCreationError)
+}
+
+impl fmt::Display for VideoPlaybackError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::VideoPlaybackError::*;
+    match self {
+      Decode(error) => write!(f, "{}", error),
+      Upload(error) => write!(f, "Could not upload the video frame: {}", error),
+    }
+  }
+}
+
+impl Error for VideoPlaybackError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::VideoPlaybackError::*;
+    match self {
+      Decode(error) => Some(error),
+      Upload(error) => Some(error)
+    }
+  }
+}
+
+impl From<VideoError> for VideoPlaybackError {
+  fn from(error: VideoError)->Self {
+    VideoPlaybackError::Decode(error)
+  }
+}
+
+impl From<TextureCreationError> for VideoPlaybackError {
+  fn from(error: TextureCreationError)->Self {
+    VideoPlaybackError::Upload(error)
+  }
+}
@@ -0,0 +1,94 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use glium::{Display, framebuffer::SimpleFrameBuffer, texture::{RawImage2d, SrgbTexture2d}};
+use crate::image::{self, ImageLoadError};
+
+const JPEG_QUALITY: f32 = 95.0; // high, since the ui's text shows jpeg artifacts easily
+
+  // Renders the view into an offscreen framebuffer at the window's resolution, with `draw` drawing into it the way it draws into a frame.
+  // Returns the size and rgb pixels of the rendered view
+pub fn render<F>(display: &Display, draw: F)->Result<([usize; 2], Vec<u8>), ViewExportError>
+  where F: FnOnce(&mut SimpleFrameBuffer)->Result<(), ViewExportError> {
+
+  let (width, height) = display.get_framebuffer_dimensions();
+  let texture = SrgbTexture2d::empty(display, width, height).map_err(|error| ViewExportError::RenderFailed(error.to_string()))?;
+  {
+    let mut framebuffer = SimpleFrameBuffer::new(display, &texture).map_err(|error| ViewExportError::RenderFailed(error.to_string()))?;
+    draw(&mut framebuffer)?;
+  }
+
+    // gl returns the rows bottom to top
+  let pixels: RawImage2d<u8> = texture.read();
+  let size = [pixels.width as usize, pixels.height as usize];
+  let mut rgb = Vec::with_capacity(size[0] * size[1] * 3);
+  for row in pixels.data.chunks_exact(size[0] * 4).rev() {
+    for pixel in row.chunks_exact(4) {
+      rgb.extend_from_slice(&pixel[..3]);
+    }
+  }
+  Ok((size, rgb))
+}
+
+  // saves the view of the image at `image_path` as a jpeg in a views folder inside `folder`. Earlier views of the same image are kept.
+  // returns the path it was saved to
+pub fn save(folder: &Path, image_path: &Path, size: [usize; 2], rgb: &[u8])->Result<PathBuf, ViewExportError> {
+  let jpeg = image::encode_rgb_jpeg(size, rgb, JPEG_QUALITY)?;
+
+  let out_dir = folder.join("views");
+  fs::create_dir_all(&out_dir)?;
+
+  let stem = image_path.file_stem().map_or_else(|| "image".into(), |stem| stem.to_string_lossy());
+  let mut out_path = out_dir.join(format!("{}_view.jpg", stem));
+  let mut number = 2;
+  while out_path.exists() {
+    out_path = out_dir.join(format!("{}_view_{}.jpg", stem, number));
+    number += 1;
+  }
+
+  fs::write(&out_path, jpeg)?;
+  Ok(out_path)
+}
+
+#[derive(Debug)]
+pub enum ViewExportError {
+  RenderFailed(String),
+  EncodeError(ImageLoadError),
+  IoError(io::Error)
+}
+
+impl fmt::Display for ViewExportError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::ViewExportError::*;
+    match self {
+      RenderFailed(error) => write!(f, "Couldn't render the view: {}", error),
+      EncodeError(error) => write!(f, "Couldn't encode the view: {}", error),
+      IoError(error) => write!(f, "Couldn't save the view: {}", error),
+    }
+  }
+}
+
+impl Error for ViewExportError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::ViewExportError::*;
+    match self {
+      RenderFailed(_) => None,
+      EncodeError(error) => Some(error),
+      IoError(error) => Some(error),
+    }
+  }
+}
+
+impl From<ImageLoadError> for ViewExportError {
+  fn from(error: ImageLoadError)->Self {
+    ViewExportError::EncodeError(error)
+  }
+}
+
+impl From<io::Error> for ViewExportError {
+  fn from(error: io::Error)->Self {
+    ViewExportError::IoError(error)
+  }
+}
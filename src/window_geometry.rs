@@ -0,0 +1,69 @@
+use glium::glutin::window::{Window, WindowBuilder};
+use glium::glutin::monitor::MonitorHandle;
+use glium::glutin::dpi::{LogicalSize, PhysicalPosition};
+use serde::{Deserialize, Serialize};
+
+  // the size of the window the first time Fotoleine opens
+pub const DEFAULT_WINDOW_SIZE: LogicalSize<f64> = LogicalSize::new(1280.0, 720.0);
+  // saved sizes smaller than this, e.g. from a window minimized while quitting, are ignored
+const MIN_WINDOW_SIZE: f64 = 200.0;
+
+  // Where the window was when Fotoleine last quit, to open it there again. Stored in the preferences
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WindowGeometry {
+  pub monitor: Option<String>, // name of the monitor the window was on
+  pub x: i32, // outer position in physical pixels, in desktop coordinates
+  pub y: i32,
+  pub width: f64, // inner size in logical pixels
+  pub height: f64,
+  pub maximized: bool
+}
+
+impl WindowGeometry {
+    // the geometry of `window`. maximized windows keep the size and position from `previous`, so they go back to those once they are restored.
+    // None if the platform doesn't report where the window is, like wayland
+  pub fn of(window: &Window, previous: Option<&WindowGeometry>)->Option<WindowGeometry> {
+    let maximized = window.is_maximized();
+    if maximized {
+      if let Some(previous) = previous {
+        return Some(WindowGeometry { maximized, ..previous.clone() });
+      }
+    }
+
+    let position = window.outer_position().ok()?;
+    let size: LogicalSize<f64> = window.inner_size().to_logical(window.scale_factor());
+    Some(WindowGeometry {
+      monitor: window.current_monitor().and_then(|monitor| monitor.name()),
+      x: position.x,
+      y: position.y,
+      width: size.width,
+      height: size.height,
+      maximized
+    })
+  }
+
+    // places the window where it was. the position is only used if the monitor it was on is still connected and the window's corner is still on it,
+    // otherwise the platform places the window, so that it doesn't open out of sight after unplugging a display
+  pub fn apply(&self, builder: WindowBuilder, monitors: impl Iterator<Item=MonitorHandle>)->WindowBuilder {
+    let mut builder = builder.with_maximized(self.maximized);
+    if self.width >= MIN_WINDOW_SIZE && self.height >= MIN_WINDOW_SIZE {
+      builder = builder.with_inner_size(LogicalSize::new(self.width, self.height));
+    }
+
+    let position = PhysicalPosition::new(self.x, self.y);
+    let on_monitor = monitors
+      .filter(|monitor| self.monitor.is_none() || monitor.name() == self.monitor)
+      .any(|monitor| contains(&monitor, position));
+    if on_monitor {
+      builder = builder.with_position(position);
+    }
+    builder
+  }
+}
+
+fn contains(monitor: &MonitorHandle, position: PhysicalPosition<i32>)->bool {
+  let origin = monitor.position();
+  let size = monitor.size();
+  position.x >= origin.x && position.x < origin.x + size.width as i32 &&
+  position.y >= origin.y && position.y < origin.y + size.height as i32
+}
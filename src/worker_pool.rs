@@ -1,83 +1,473 @@
-use std::sync::mpsc::{Sender, Receiver, channel};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard, Condvar, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, AtomicU64, Ordering};
+use std::collections::BinaryHeap;
+use std::cmp;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use std::error::Error;
+use std::fmt;
+use log::{debug, warn};
+
 pub struct WorkerPool<W: Worker + 'static + Send> {
-  pub output: Receiver<W::Output>,
-  worker_threads: Vec<Option<JoinHandle<()>>>,
-  task_sender: Sender<TaskMessage<W>>
+  worker_threads: Vec<WorkerThread>, // the first one is reserved, if the pool has a reserved worker
+  retired_threads: Vec<JoinHandle<()>>, // of workers removed by `resize` or `shutdown`, finishing their last task
+  spawn_thread: Box<dyn Fn(usize, Arc<AtomicBool>, bool)->JoinHandle<()>>, // takes the worker's id, its retire flag and whether it is reserved
+  next_worker_id: usize,
+  tasks: Arc<SharedTaskQueue<W::Input>>,
+  timings: Arc<TaskTimings>,
+  pending_count: Arc<AtomicUsize> // tasks submitted that didn't finish executing yet
 }
 
+  // The result of every task is handed to the pool's `on_result` callback along with the task's input, on the thread of the worker that executed it.
+  // So a failed task can be told apart by its input, e.g. which image couldn't be loaded
 pub trait Worker {
   type Input: 'static + Send;
   type Output: 'static + Send;
+  type Error: 'static + Send;
+
+    // parts of the input may be taken out of it, the callback gets what is left
+  fn execute(&mut self, input: &mut Self::Input)->Result<Self::Output, Self::Error>;
+}
+
+  // how long dropping a pool that wasn't shut down waits for its workers, see `WorkerPool::shutdown`
+const DROP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+  // Tasks with lower priority values are executed first
+pub type Priority = usize;
+
+struct QueuedTask<I> {
+  priority: Priority,
+  seq: u64, // submission order, so that tasks of equal priority execute first-in first-out
+  input: I
+}
 
-  fn execute(&mut self, input: Self::Input, output: &Sender<Self::Output>);
+  // BinaryHeap is a max-heap, so the ordering is reversed to pop the lowest priority value, and then the oldest task first
+impl<I> Ord for QueuedTask<I> {
+  fn cmp(&self, other: &Self)->cmp::Ordering {
+    self.priority.cmp(&other.priority)
+      .then(self.seq.cmp(&other.seq))
+      .reverse()
+  }
 }
 
-enum TaskMessage<W: Worker> {
-  NewTask(W::Input),
-  Terminate
+impl<I> PartialOrd for QueuedTask<I> {
+  fn partial_cmp(&self, other: &Self)->Option<cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<I> PartialEq for QueuedTask<I> {
+  fn eq(&self, other: &Self)->bool {
+    self.cmp(other) == cmp::Ordering::Equal
+  }
+}
+
+impl<I> Eq for QueuedTask<I> {}
+
+struct TaskQueue<I> {
+  tasks: BinaryHeap<QueuedTask<I>>,
+  next_seq: u64,
+  max_queued: Option<usize>, // see `WorkerPool::with_queue_limit`
+  reserved_max_priority: Option<Priority>, // see `WorkerPool::reserve_worker`
+  terminate: bool
+}
+
+struct WorkerThread {
+  handle: JoinHandle<()>,
+  retire: Arc<AtomicBool> // tells the worker to stop once it finished its task
+}
+
+struct SharedTaskQueue<I> {
+  queue: Mutex<TaskQueue<I>>,
+  task_available: Condvar
+}
+
+impl<I> SharedTaskQueue<I> {
+    // the queue stays consistent even if a thread panicked while holding the lock, so a poisoned lock is used all the same
+  fn lock(&self)->MutexGuard<'_, TaskQueue<I>> {
+    self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+  // Updated by the workers around every task they execute
+#[derive(Default)]
+struct TaskTimings {
+  busy_count: AtomicUsize, // workers executing a task right now
+  completed_count: AtomicU64,
+  total_nanos: AtomicU64, // time spent executing the completed tasks
+  last_nanos: AtomicU64 // of the most recently completed task
+}
+
+  // A snapshot of what the pool is doing, for profiling
+#[derive(Debug, Clone, Copy)]
+pub struct PoolStats {
+  pub queued_count: usize, // tasks waiting for a worker
+  pub busy_count: usize,
+  pub worker_count: usize,
+  pub completed_count: u64,
+  pub average_task_time: Duration, // zero before the first task completed
+  pub last_task_time: Duration
 }
 
 impl<W: Worker + 'static + Send> WorkerPool<W> {
-  pub fn new<F>(n_workers: usize, spawn_worker: F)->WorkerPool<W>
-    where F: Fn(usize)->W {
+  pub fn new<F, R>(n_workers: usize, spawn_worker: F, on_result: R)->WorkerPool<W>
+    where F: Fn(usize)->W + 'static, R: Fn(W::Input, Result<W::Output, W::Error>) + Clone + Send + 'static {
+
+    WorkerPool::with_queue_limit(n_workers, None, spawn_worker, on_result)
+  }
+
+    // once more than `max_queued` tasks are waiting for a worker, submitting another one drops the task that would execute last.
+    // the dropped task is returned by `submit`, so that the submitter can tell it won't get a result for it
+  pub fn with_queue_limit<F, R>(n_workers: usize, max_queued: Option<usize>, spawn_worker: F, on_result: R)->WorkerPool<W>
+    where F: Fn(usize)->W + 'static, R: Fn(W::Input, Result<W::Output, W::Error>) + Clone + Send + 'static {
 
     assert!(n_workers > 0);
 
-    let (task_tx, task_rx) = channel();
-    let base_task_receiver = Arc::new(Mutex::new(task_rx));
+    let tasks = Arc::new(SharedTaskQueue {
+      queue: Mutex::new(TaskQueue {
+        tasks: BinaryHeap::new(),
+        next_seq: 0,
+        max_queued,
+        reserved_max_priority: None,
+        terminate: false
+      }),
+      task_available: Condvar::new()
+    });
 
-    let (output_tx, output_rx) = channel();
+    let timings = Arc::new(TaskTimings::default());
+    let pending_count = Arc::new(AtomicUsize::new(0));
 
-    let worker_threads: Vec<_> = (0..n_workers).map(|id| {
+      // kept for `resize`, to start workers later on
+    let spawn_thread = {
+      let tasks = Arc::clone(&tasks);
+      let timings = Arc::clone(&timings);
+      let pending_count = Arc::clone(&pending_count);
+      move |id: usize, retire: Arc<AtomicBool>, reserved: bool| {
         let mut worker = spawn_worker(id);
-        let output = output_tx.clone();
-        let task_receiver = Arc::clone(&base_task_receiver);
+        let on_result = on_result.clone();
+        let tasks = Arc::clone(&tasks);
+        let timings = Arc::clone(&timings);
+        let pending_count = Arc::clone(&pending_count);
 
-        Some(thread::spawn(move || {
+        thread::spawn(move || {
           loop {
-            let task_message = task_receiver.lock().expect("Error when locking the job mutex").recv().expect("Error when getting new job."); //:todo: error handling
+            let next_input = {
+              let mut queue = tasks.lock();
+              loop {
+                if queue.terminate || retire.load(Ordering::Relaxed) {
+                  break None;
+                }
+                  // the task with the lowest priority value is on top, so a reserved worker can take none if it can't take that one
+                let reserved_max_priority = queue.reserved_max_priority.filter(|_| reserved);
+                if queue.tasks.peek().is_some_and(|task| reserved_max_priority.is_none_or(|max_priority| task.priority <= max_priority)) {
+                  break queue.tasks.pop().map(|task| task.input);
+                }
+                queue = tasks.task_available.wait(queue).unwrap_or_else(PoisonError::into_inner);
+              }
+            };
 
-            match task_message {
-              TaskMessage::NewTask(input) => {
-                thread::sleep(std::time::Duration::from_millis(1000));
-                worker.execute(input, &output);
+            match next_input {
+              Some(mut input) => {
+                timings.busy_count.fetch_add(1, Ordering::Relaxed);
+                let start = Instant::now();
+                let result = worker.execute(&mut input);
+                let nanos = start.elapsed().as_nanos() as u64;
+                timings.total_nanos.fetch_add(nanos, Ordering::Relaxed);
+                timings.last_nanos.store(nanos, Ordering::Relaxed);
+                timings.completed_count.fetch_add(1, Ordering::Relaxed);
+                timings.busy_count.fetch_sub(1, Ordering::Relaxed);
+                  // the task only counts as done once its result is handed on, so the pool isn't idle before that
+                on_result(input, result);
+                pending_count.fetch_sub(1, Ordering::Relaxed);
               },
-              TaskMessage::Terminate => {
+              None => {
                 break;
               }
             }
           }
-        }))
-      }).collect();
+        })
+      }
+    };
 
-    WorkerPool {
-      output: output_rx,
-      worker_threads,
-      task_sender: task_tx
+    let mut pool = WorkerPool {
+      worker_threads: Vec::new(),
+      retired_threads: Vec::new(),
+      spawn_thread: Box::new(spawn_thread),
+      next_worker_id: 0,
+      tasks,
+      timings,
+      pending_count
+    };
+    pool.resize(n_workers);
+    pool
+  }
+
+  pub fn set_queue_limit(&self, max_queued: Option<usize>) {
+    self.tasks.lock().max_queued = max_queued;
+  }
+
+    // keeps the first worker for tasks with a priority value of at most `max_priority`, so that they never wait for other tasks to finish.
+    // the pool keeps at least one other worker
+  pub fn reserve_worker(&mut self, max_priority: Priority) {
+    self.tasks.lock().reserved_max_priority = Some(max_priority);
+    self.resize(self.worker_threads.len());
+  }
+
+    // starts or stops workers until the pool has `n_workers`, or the least it keeps. workers that are stopped finish their task first.
+    // the reserved worker, if there is one, is never stopped
+  pub fn resize(&mut self, n_workers: usize) {
+    if self.tasks.lock().terminate {
+      return;
+    }
+
+    let has_reserved = self.tasks.lock().reserved_max_priority.is_some();
+    let n_workers = n_workers.max(1 + has_reserved as usize);
+    while self.worker_threads.len() < n_workers {
+      let retire = Arc::new(AtomicBool::new(false));
+      let reserved = self.worker_threads.is_empty();
+      let handle = (self.spawn_thread)(self.next_worker_id, Arc::clone(&retire), reserved);
+      self.next_worker_id += 1;
+      self.worker_threads.push(WorkerThread { handle, retire });
+    }
+    if self.worker_threads.len() > n_workers {
+      for worker_thread in self.worker_threads.drain(n_workers..) {
+        worker_thread.retire.store(true, Ordering::Relaxed);
+        self.retired_threads.push(worker_thread.handle);
+      }
+      self.tasks.task_available.notify_all();
     }
+      // finished threads are detached, rather than joined on the main thread
+    self.retired_threads.retain(|handle| !handle.is_finished());
   }
 
-  pub fn submit(&self, input: W::Input) {
-    self.task_sender.send(TaskMessage::NewTask(input)).expect("Couldn't send input task.");
+    // stops the workers once they finished the tasks they are executing. the tasks still queued are dropped.
+    // waits for the workers until `deadline`, the ones still busy after it are left to finish on their own.
+    // the pool takes no more tasks after this, and dropping it does nothing more
+  pub fn shutdown(&mut self, deadline: Instant)->Result<(), ShutdownError> {
+    {
+      let mut queue = self.tasks.lock();
+      if queue.terminate {
+        return Ok(());
+      }
+
+      debug!("Notifying all workers of termination");
+      queue.terminate = true;
+      let dropped_count = queue.tasks.len();
+      queue.tasks.clear();
+      self.pending_count.fetch_sub(dropped_count, Ordering::Relaxed);
+    }
+    self.tasks.task_available.notify_all();
+    self.retired_threads.extend(self.worker_threads.drain(..).map(|worker_thread| worker_thread.handle));
+
+    debug!("Joining on all workers");
+      // JoinHandle::join can't time out, so only workers that finished are joined
+    let mut error = ShutdownError::default();
+    loop {
+      let (finished, running) = std::mem::take(&mut self.retired_threads).into_iter().partition::<Vec<_>, _>(|handle| handle.is_finished());
+      self.retired_threads = running;
+      error.panicked_count += finished.into_iter().map(JoinHandle::join).filter(Result::is_err).count();
+
+      if self.retired_threads.is_empty() {
+        break;
+      }
+      if Instant::now() >= deadline {
+        error.timed_out_count = self.retired_threads.drain(..).count();
+        break;
+      }
+      thread::sleep(SHUTDOWN_POLL_INTERVAL);
+    }
+
+    if error.panicked_count == 0 && error.timed_out_count == 0 {
+      Ok(())
+    } else {
+      Err(error)
+    }
+  }
+
+  pub fn stats(&self)->PoolStats {
+    let queued_count = self.tasks.lock().tasks.len();
+    let completed_count = self.timings.completed_count.load(Ordering::Relaxed);
+    let total_nanos = self.timings.total_nanos.load(Ordering::Relaxed);
+    PoolStats {
+      queued_count,
+      busy_count: self.timings.busy_count.load(Ordering::Relaxed),
+      worker_count: self.worker_threads.len(),
+      completed_count,
+      average_task_time: Duration::from_nanos(total_nanos.checked_div(completed_count).unwrap_or(0)),
+      last_task_time: Duration::from_nanos(self.timings.last_nanos.load(Ordering::Relaxed))
+    }
+  }
+
+    // a handle for other threads to check whether the pool has work
+  pub fn activity(&self)->PoolActivity {
+    PoolActivity(vec![Arc::clone(&self.pending_count)])
+  }
+
+    // a handle for other threads to submit tasks, e.g. the workers of a pool that feeds this one
+  pub fn submitter(&self)->TaskSubmitter<W::Input> {
+    TaskSubmitter {
+      tasks: Arc::clone(&self.tasks),
+      pending_count: Arc::clone(&self.pending_count)
+    }
+  }
+
+    // returns the task that was dropped to make room, if the queue is limited and full. that can be `input` itself,
+    // which is also returned after the pool shut down
+  pub fn submit(&self, input: W::Input, priority: Priority)->Option<W::Input> {
+    submit(&self.tasks, &self.pending_count, input, priority)
+  }
+
+    // assigns new priorities to all tasks that haven't started executing yet.
+    // tasks for which `prioritize` returns None are removed from the queue. tasks may be changed to match their new priority
+  pub fn reprioritize<F>(&self, mut prioritize: F)
+    where F: FnMut(&mut W::Input)->Option<Priority> {
+
+    let mut queue = self.tasks.lock();
+    let tasks = std::mem::take(&mut queue.tasks).into_vec();
+    let task_count = tasks.len();
+    queue.tasks = tasks.into_iter()
+      .filter_map(|mut task| {
+        prioritize(&mut task.input).map(|priority| {
+          task.priority = priority;
+          task
+        })
+      })
+      .collect();
+    self.pending_count.fetch_sub(task_count - queue.tasks.len(), Ordering::Relaxed);
+      // tasks that became top priority can now be taken by the reserved worker
+    self.tasks.task_available.notify_all();
+  }
+}
+
+fn submit<I>(tasks: &SharedTaskQueue<I>, pending_count: &AtomicUsize, input: I, priority: Priority)->Option<I> {
+  let mut queue = tasks.lock();
+  if queue.terminate {
+    return Some(input);
+  }
+  pending_count.fetch_add(1, Ordering::Relaxed);
+  let seq = queue.next_seq;
+  queue.next_seq += 1;
+  queue.tasks.push(QueuedTask {
+    priority,
+    seq,
+    input
+  });
+    // a reserved worker may not take the task, so it can't be the only one woken
+  if queue.reserved_max_priority.is_some() {
+    tasks.task_available.notify_all();
+  } else {
+    tasks.task_available.notify_one();
+  }
+
+  if queue.max_queued.is_none_or(|max_queued| queue.tasks.len() <= max_queued) {
+    return None;
+  }
+    // the smallest task by the heap's ordering is the one that would execute last
+  let mut queued = std::mem::take(&mut queue.tasks).into_vec();
+  let last_idx = queued.iter().enumerate().min_by(|(_, a), (_, b)| a.cmp(b)).map(|(idx, _)| idx)?;
+  let dropped = queued.swap_remove(last_idx);
+  queue.tasks = queued.into();
+  pending_count.fetch_sub(1, Ordering::Relaxed);
+  Some(dropped.input)
+}
+
+  // Submits tasks to a pool from other threads. Tasks submitted after the pool was dropped are never executed
+pub struct TaskSubmitter<I> {
+  tasks: Arc<SharedTaskQueue<I>>,
+  pending_count: Arc<AtomicUsize>
+}
+
+impl<I> TaskSubmitter<I> {
+    // see `WorkerPool::submit`
+  pub fn submit(&self, input: I, priority: Priority)->Option<I> {
+    submit(&self.tasks, &self.pending_count, input, priority)
+  }
+}
+
+impl<I> Clone for TaskSubmitter<I> {
+  fn clone(&self)->Self {
+    TaskSubmitter {
+      tasks: Arc::clone(&self.tasks),
+      pending_count: Arc::clone(&self.pending_count)
+    }
   }
 }
 
 impl<W: Worker + 'static + Send> Drop for WorkerPool<W> {
   fn drop(&mut self) {
-    println!("Notifying all workers of termination");
-
-    for _ in &mut self.worker_threads {
-      self.task_sender.send(TaskMessage::Terminate).expect("Couldn't send terminate to worker");
+    if let Err(error) = self.shutdown(Instant::now() + DROP_SHUTDOWN_TIMEOUT) {
+      warn!("Worker pool: {}", error);
     }
+  }
+}
 
-    println!("Joining on all workers");
+  // Workers that didn't stop cleanly when their pool shut down
+#[derive(Debug, Default)]
+pub struct ShutdownError {
+  pub panicked_count: usize, // workers whose thread panicked at some point
+  pub timed_out_count: usize // workers still executing a task at the deadline
+}
 
-    for handle in &mut self.worker_threads {
-      if let Some(handle) = handle.take() {
-        handle.join().expect("Couldn't join on thread.");
-      }
+impl ShutdownError {
+    // the errors of shutting down two pools, as one
+  pub fn combine(a: Result<(), ShutdownError>, b: Result<(), ShutdownError>)->Result<(), ShutdownError> {
+    match (a, b) {
+      (Ok(()), Ok(())) => Ok(()),
+      (Err(error), Ok(())) | (Ok(()), Err(error)) => Err(error),
+      (Err(a), Err(b)) => Err(ShutdownError {
+        panicked_count: a.panicked_count + b.panicked_count,
+        timed_out_count: a.timed_out_count + b.timed_out_count
+      })
     }
   }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for ShutdownError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    write!(f, "{} workers had panicked, {} workers were still busy at the shutdown deadline", self.panicked_count, self.timed_out_count)
+  }
+}
+
+impl Error for ShutdownError {}
+
+  // Whether pools have tasks queued or executing, readable from other threads.
+  // Lets background work give way to pools whose results are waited for
+#[derive(Clone)]
+pub struct PoolActivity(Vec<Arc<AtomicUsize>>); // the pending task counts of the pools
+
+impl PoolActivity {
+    // idle while both this and `other` are
+  pub fn and(mut self, other: PoolActivity)->PoolActivity {
+    self.0.extend(other.0);
+    self
+  }
+
+  pub fn is_idle(&self)->bool {
+    self.0.iter().all(|pending_count| pending_count.load(Ordering::Relaxed) == 0)
+  }
+}
+
+  // Shared flag that lets the submitter of a task tell the worker that its result is no longer needed.
+  // Workers are expected to check it before starting expensive work.
+  // Clones refer to the same flag, so a token can also be used to identify which submission a result belongs to.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+  pub fn new()->CancelToken {
+    CancelToken(Arc::new(AtomicBool::new(false)))
+  }
+
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::Relaxed);
+  }
+
+  pub fn is_cancelled(&self)->bool {
+    self.0.load(Ordering::Relaxed)
+  }
+
+  pub fn same_as(&self, other: &CancelToken)->bool {
+    Arc::ptr_eq(&self.0, &other.0)
+  }
+}
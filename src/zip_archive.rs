@@ -0,0 +1,185 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const VERSION: u16 = 20; // 2.0, the first version with folders and deflate, which every unzip tool reads
+const UTF8_NAMES_FLAG: u16 = 1 << 11;
+const STORED_METHOD: u16 = 0;
+
+  // Writes a zip archive of files stored as they are, without compressing them. Images are compressed already,
+  // so deflating them again would take long for next to no gain. The archive is limited to what zip files without
+  // the zip64 extension can hold, 4 GiB and 65535 files
+pub struct ZipWriter<W: Write> {
+  out: W,
+  offset: u64, // bytes written so far
+  entries: Vec<CentralEntry>
+}
+
+struct CentralEntry {
+  name: String,
+  crc: u32,
+  size: u32,
+  modified: (u16, u16), // dos time and date
+  header_offset: u32
+}
+
+impl<W: Write> ZipWriter<W> {
+  pub fn new(out: W)->ZipWriter<W> {
+    ZipWriter {
+      out,
+      offset: 0,
+      entries: Vec::new()
+    }
+  }
+
+    // adds a file named `name`, which can contain forward slashes for folders inside the archive
+  pub fn add_file(&mut self, name: &str, data: &[u8], modified: SystemTime)->Result<(), ZipError> {
+    if self.entries.len() == u16::MAX as usize {
+      return Err(ZipError::TooManyFiles);
+    }
+    let size = u32::try_from(data.len()).map_err(|_| ZipError::TooLarge)?;
+    let header_offset = u32::try_from(self.offset).map_err(|_| ZipError::TooLarge)?;
+    let entry = CentralEntry {
+      name: name.to_string(),
+      crc: crc32fast::hash(data),
+      size,
+      modified: dos_date_time(modified),
+      header_offset
+    };
+
+    let mut header = Vec::with_capacity(30 + entry.name.len());
+    put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+    put_u16(&mut header, VERSION);
+    put_u16(&mut header, UTF8_NAMES_FLAG);
+    put_u16(&mut header, STORED_METHOD);
+    put_u16(&mut header, entry.modified.0);
+    put_u16(&mut header, entry.modified.1);
+    put_u32(&mut header, entry.crc);
+    put_u32(&mut header, entry.size); // compressed size, the same when stored
+    put_u32(&mut header, entry.size);
+    put_u16(&mut header, entry.name.len() as u16);
+    put_u16(&mut header, 0); // extra field length
+    header.extend_from_slice(entry.name.as_bytes());
+
+    self.write(&header)?;
+    self.write(data)?;
+    self.entries.push(entry);
+    Ok(())
+  }
+
+    // writes the directory of the files that lists them for unzip tools, and returns the output
+  pub fn finish(mut self)->Result<W, ZipError> {
+    let directory_offset = u32::try_from(self.offset).map_err(|_| ZipError::TooLarge)?;
+    let mut directory = Vec::new();
+    for entry in &self.entries {
+      put_u32(&mut directory, CENTRAL_HEADER_SIGNATURE);
+      put_u16(&mut directory, VERSION); // made by
+      put_u16(&mut directory, VERSION); // needed to extract
+      put_u16(&mut directory, UTF8_NAMES_FLAG);
+      put_u16(&mut directory, STORED_METHOD);
+      put_u16(&mut directory, entry.modified.0);
+      put_u16(&mut directory, entry.modified.1);
+      put_u32(&mut directory, entry.crc);
+      put_u32(&mut directory, entry.size);
+      put_u32(&mut directory, entry.size);
+      put_u16(&mut directory, entry.name.len() as u16);
+      put_u16(&mut directory, 0); // extra field length
+      put_u16(&mut directory, 0); // comment length
+      put_u16(&mut directory, 0); // disk number
+      put_u16(&mut directory, 0); // internal attributes
+      put_u32(&mut directory, 0); // external attributes
+      put_u32(&mut directory, entry.header_offset);
+      directory.extend_from_slice(entry.name.as_bytes());
+    }
+    let directory_size = u32::try_from(directory.len()).map_err(|_| ZipError::TooLarge)?;
+    u32::try_from(self.offset + directory.len() as u64).map_err(|_| ZipError::TooLarge)?;
+
+    let entry_count = self.entries.len() as u16;
+    put_u32(&mut directory, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    put_u16(&mut directory, 0); // this disk
+    put_u16(&mut directory, 0); // the disk the directory starts on
+    put_u16(&mut directory, entry_count); // on this disk
+    put_u16(&mut directory, entry_count);
+    put_u32(&mut directory, directory_size);
+    put_u32(&mut directory, directory_offset);
+    put_u16(&mut directory, 0); // comment length
+
+    self.write(&directory)?;
+    self.out.flush()?;
+    Ok(self.out)
+  }
+
+  fn write(&mut self, bytes: &[u8])->io::Result<()> {
+    self.out.write_all(bytes)?;
+    self.offset += bytes.len() as u64;
+    Ok(())
+  }
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+  out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+  out.extend_from_slice(&value.to_le_bytes());
+}
+
+  // the time and date fields of zip files, in utc since zip files don't store a time zone. dos dates start in 1980, earlier times are clamped to it
+fn dos_date_time(time: SystemTime)->(u16, u16) {
+  let earliest = (0, (1 << 5) | 1); // 1980-01-01 00:00
+  if time < SystemTime::UNIX_EPOCH {
+    return earliest;
+  }
+
+    // formatted as 2024-05-17T13:45:12Z
+  let formatted = humantime::format_rfc3339_seconds(time).to_string();
+  let field = |range: std::ops::Range<usize>| formatted.get(range).and_then(|digits| digits.parse::<u16>().ok()).unwrap_or(0);
+  let (year, month, day) = (field(0..4), field(5..7), field(8..10));
+  let (hour, minute, second) = (field(11..13), field(14..16), field(17..19));
+  if year < 1980 {
+    return earliest;
+  }
+
+  let dos_time = (hour << 11) | (minute << 5) | (second / 2);
+  let dos_date = ((year - 1980).min(127) << 9) | (month << 5) | day;
+  (dos_time, dos_date)
+}
+
+#[derive(Debug)]
+pub enum ZipError {
+  Write(io::Error),
+  TooLarge, // over the 4 GiB zip files without zip64 can hold
+  TooManyFiles
+}
+
+impl fmt::Display for ZipError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+    use self::ZipError::*;
+    match self {
+      Write(error) => write!(f, "Could not write the archive: {}", error),
+      TooLarge => write!(f, "The archive would be larger than 4 GiB, the most a zip file can hold"),
+      TooManyFiles => write!(f, "The archive would have more than {} files, the most a zip file can hold", u16::MAX),
+    }
+  }
+}
+
+impl Error for ZipError {
+  fn source(&self)->Option<&(dyn Error + 'static)> {
+    use self::ZipError::*;
+    match self {
+      Write(error) => Some(error),
+      TooLarge => None,
+      TooManyFiles => None
+    }
+  }
+}
+
+impl From<io::Error> for ZipError {
+  fn from(error: io::Error)->Self {
+    ZipError::Write(error)
+  }
+}